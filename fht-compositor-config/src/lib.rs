@@ -13,6 +13,7 @@ use fht_animation::AnimationCurve;
 use regex::Regex;
 use serde::de::Unexpected;
 use serde::{Deserialize, Deserializer};
+use smithay::backend::allocator::Fourcc;
 use smithay::backend::input::MouseButton as SmithayMouseButton;
 use smithay::input::keyboard::{
     keysyms, xkb, Keysym, ModifiersState as SmithayModifiersState, XkbConfig,
@@ -66,12 +67,15 @@ pub struct Config {
     pub mousebinds: HashMap<MousePattern, MouseAction>,
     pub input: Input,
     pub general: General,
+    pub workspaces: Vec<WorkspaceDefaults>,
     pub cursor: Cursor,
     pub decorations: Decorations,
     pub animations: Animations,
+    pub zoom: Zoom,
     pub rules: Vec<WindowRule>,
     pub layer_rules: Vec<LayerRule>,
     pub outputs: HashMap<String, Output>,
+    pub backend: Backend,
     pub debug: Debug,
 }
 
@@ -87,12 +91,15 @@ impl Default for Config {
             mousebinds: Default::default(),
             input: Default::default(),
             general: Default::default(),
+            workspaces: Default::default(),
             cursor: Default::default(),
             decorations: Default::default(),
             animations: Default::default(),
+            zoom: Default::default(),
             rules: Default::default(),
             layer_rules: Default::default(),
             outputs: HashMap::new(),
+            backend: Default::default(),
             debug: Default::default(),
         }
     }
@@ -119,6 +126,34 @@ impl From<SmithayModifiersState> for ModifiersState {
     }
 }
 
+impl<'de> Deserialize<'de> for ModifiersState {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // Same syntax as the modifiers of a key pattern, for example Super-Ctrl
+        let raw = String::deserialize(deserializer)?;
+        let mut modifiers = ModifiersState::default();
+        for part in raw.split('-') {
+            match part.trim() {
+                "Super" | "Mod" | "Logo" | "Meta" | "M" => modifiers.logo = true,
+                "Shift" | "S" => modifiers.shift = true,
+                "Alt" | "A" => modifiers.alt = true,
+                "Ctrl" | "Control" | "C" => modifiers.ctrl = true,
+                "AltGr" => modifiers.alt_gr = true,
+                x => {
+                    return Err(<D::Error as serde::de::Error>::invalid_value(
+                        Unexpected::Str(x),
+                        &"Modifier",
+                    ))
+                }
+            }
+        }
+
+        Ok(modifiers)
+    }
+}
+
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct KeyPattern(pub ModifiersState, pub Keysym);
 
@@ -215,6 +250,10 @@ pub enum SimpleKeyAction {
     FullscreenFocusedWindow,
     FloatFocusedWindow,
     CenterFloatingWindow,
+    RaiseFloatingWindow,
+    LowerFloatingWindow,
+    ToggleRaiseFloatingWindow,
+    PinFocusedWindow,
     FocusNextWindow,
     FocusPreviousWindow,
     SwapWithNextWindow,
@@ -224,41 +263,226 @@ pub enum SimpleKeyAction {
     FocusNextWorkspace,
     FocusPreviousWorkspace,
     CloseFocusedWindow,
+    ToggleMouseKeys,
+    CycleTabletOutput,
+    ToggleDebugOverlay,
+    ToggleFrameTimingsCapture,
+    ToggleProfiling,
+    ResetZoom,
+    RotateSplit,
+    EqualizeSplits,
+    ConsumeIntoColumn,
+    ExpelFromColumn,
+    TabWithNextWindow,
+    UntabFocusedWindow,
+    FocusNextTab,
+    FocusPreviousTab,
+    ToggleStackedContainer,
+    MarkInsertionPoint,
     None,
 }
-#[derive(Debug, Clone, Deserialize)]
-#[serde(rename_all = "kebab-case", deny_unknown_fields)]
-#[serde(tag = "action", content = "arg")]
-pub enum ComplexKeyAction {
-    // Also include simple key actions here, since complex key action format will also be used
-    // soon to include additional attributes for simple key actions (repeat,
-    // allow-while-locked, etc...)
-    Quit,
-    ReloadConfig,
-    SelectNextLayout,
-    SelectPreviousLayout,
-    MaximizeFocusedWindow,
-    FullscreenFocusedWindow,
-    FloatFocusedWindow,
-    CenterFloatingWindow,
-    MoveFloatingWindow([i32; 2]),
-    ResizeFloatingWindow([i32; 2]),
-    FocusNextWindow,
-    FocusPreviousWindow,
-    SwapWithNextWindow,
-    SwapWithPreviousWindow,
-    FocusNextOutput,
-    FocusPreviousOutput,
-    FocusNextWorkspace,
-    FocusPreviousWorkspace,
-    CloseFocusedWindow,
-    None,
-    RunCommand(String),
-    ChangeMwfact(f64),
-    ChangeNmaster(i32),
-    ChangeWindowProportion(f64),
-    FocusWorkspace(usize),
-    SendToWorkspace(usize),
+/// Information about a bindable action, for external tools to list what can be bound.
+#[derive(Debug, Clone)]
+pub struct ActionInfo {
+    /// The name of the action, as written in the configuration.
+    pub name: String,
+    /// The types of the action arguments, if any.
+    pub args: Vec<&'static str>,
+    /// The documentation of the action.
+    pub doc: String,
+}
+
+// Define an action enum while keeping its variants and documentation around, so that the list
+// of actions given to external tools can never get out of sync with what we actually parse.
+macro_rules! described_actions {
+    (
+        $(#[$attr:meta])*
+        pub enum $name:ident {
+            $(
+                $(#[doc = $doc:literal])*
+                $variant:ident $(($($arg:ty),+))?,
+            )*
+        }
+    ) => {
+        $(#[$attr])*
+        pub enum $name {
+            $(
+                $(#[doc = $doc])*
+                $variant $(($($arg),+))?,
+            )*
+        }
+
+        impl $name {
+            /// Get information about every variant of this action.
+            pub fn infos() -> Vec<ActionInfo> {
+                vec![$(
+                    ActionInfo {
+                        name: kebab_case(stringify!($variant)),
+                        args: vec![$($(stringify!($arg)),+)?],
+                        doc: [$($doc),*]
+                            .iter()
+                            .map(|line: &&str| line.trim())
+                            .collect::<Vec<_>>()
+                            .join("\n"),
+                    },
+                )*]
+            }
+        }
+    };
+}
+
+fn kebab_case(name: &str) -> String {
+    let mut ret = String::with_capacity(name.len() + 4);
+    for (idx, char) in name.chars().enumerate() {
+        if char.is_ascii_uppercase() && idx != 0 {
+            ret.push('-');
+        }
+        ret.push(char.to_ascii_lowercase());
+    }
+    ret
+}
+
+described_actions! {
+    #[derive(Debug, Clone, Deserialize)]
+    #[serde(rename_all = "kebab-case", deny_unknown_fields)]
+    #[serde(tag = "action", content = "arg")]
+    pub enum ComplexKeyAction {
+        // Also include simple key actions here, since complex key action format will also be used
+        // soon to include additional attributes for simple key actions (repeat,
+        // allow-while-locked, etc...)
+        /// Exit the compositor.
+        Quit,
+        /// Reload the configuration file.
+        ReloadConfig,
+        /// Select the next layout of the active workspace.
+        SelectNextLayout,
+        /// Select the previous layout of the active workspace.
+        SelectPreviousLayout,
+        /// Toggle the maximized state of the focused window.
+        MaximizeFocusedWindow,
+        /// Toggle the fullscreen state of the focused window.
+        FullscreenFocusedWindow,
+        /// Toggle the floating state of the focused window.
+        FloatFocusedWindow,
+        /// Center the focused window on its output, if it is floating.
+        CenterFloatingWindow,
+        /// Raise the focused floating window above the other floating windows.
+        RaiseFloatingWindow,
+        /// Lower the focused floating window below the other floating windows.
+        LowerFloatingWindow,
+        /// Raise the focused floating window, or lower it if it is already on top.
+        ToggleRaiseFloatingWindow,
+        /// Toggle the pinned state of the focused window. A pinned window follows its output's
+        /// active workspace when switching workspaces.
+        PinFocusedWindow,
+        /// Move the focused floating window by `[dx, dy]` logical pixels.
+        MoveFloatingWindow([i32; 2]),
+        /// Resize the focused floating window by `[dw, dh]` logical pixels.
+        ResizeFloatingWindow([i32; 2]),
+        /// Focus the next window of the active workspace.
+        FocusNextWindow,
+        /// Focus the previous window of the active workspace.
+        FocusPreviousWindow,
+        /// Swap the focused window with the next window of the active workspace.
+        SwapWithNextWindow,
+        /// Swap the focused window with the previous window of the active workspace.
+        SwapWithPreviousWindow,
+        /// Focus the next output.
+        FocusNextOutput,
+        /// Focus the previous output.
+        FocusPreviousOutput,
+        /// Focus the next workspace of the active output.
+        FocusNextWorkspace,
+        /// Focus the previous workspace of the active output.
+        FocusPreviousWorkspace,
+        /// Ask the focused window to close.
+        CloseFocusedWindow,
+        /// Toggle mouse keys, to control the pointer with the keyboard.
+        ToggleMouseKeys,
+        /// Map the tablets to the next output.
+        CycleTabletOutput,
+        /// Toggle the debug overlay, displaying rendering statistics and damage on each output.
+        ToggleDebugOverlay,
+        /// Start or stop capturing the timings of every rendered frame to a CSV file inside the
+        /// runtime directory.
+        ToggleFrameTimingsCapture,
+        /// Pause or resume sending profiling data to Tracy, when built with the
+        /// `profile-with-tracy` feature.
+        ToggleProfiling,
+        /// Zoom out of the active output.
+        ResetZoom,
+        /// Rotate the split containing the focused window by 90 degrees, with the `bsp` layout.
+        RotateSplit,
+        /// Give the same size to every window of the split containing the focused window, with
+        /// the `bsp` layout.
+        EqualizeSplits,
+        /// Move the focused window to the bottom of the column on its left, with the `scrolling`
+        /// layout.
+        ConsumeIntoColumn,
+        /// Move the focused window out of its column, into a new column on its right, with the
+        /// `scrolling` layout.
+        ExpelFromColumn,
+        /// Add the next tiled window as a tab of the focused window, grouping them in a tabbed
+        /// container taking a single slot of the layout.
+        TabWithNextWindow,
+        /// Remove the focused window from its tabbed container.
+        UntabFocusedWindow,
+        /// Focus the next tab of the focused tabbed container.
+        FocusNextTab,
+        /// Focus the previous tab of the focused tabbed container.
+        FocusPreviousTab,
+        /// Switch the focused container between tabbed and stacked. A stacked container displays
+        /// the titles of its windows as a vertical list of title bars.
+        ToggleStackedContainer,
+        /// Mark the focused window as the insertion point of the active workspace, or remove the
+        /// mark if it's already there. New windows get inserted after it with the `after-marked`
+        /// insert window strategy.
+        MarkInsertionPoint,
+        /// Do nothing, useful to disable a default key-binding.
+        None,
+        /// Run a command through `/bin/sh -c`.
+        RunCommand(String),
+        /// Change the master width factor of the active workspace by a delta.
+        ///
+        /// With the bsp layout, this resizes the focused window inside its split instead.
+        ChangeMwfact(f64),
+        /// Change the number of master windows of the active workspace by a delta.
+        ChangeNmaster(i32),
+        /// Change the inner gaps of the active workspace by a delta, until the configuration gets
+        /// reloaded.
+        ChangeInnerGaps(i32),
+        /// Change the outer gaps of the active workspace by a delta, until the configuration gets
+        /// reloaded.
+        ChangeOuterGaps(i32),
+        /// Change the inner gaps of every workspace by a delta, until the configuration gets
+        /// reloaded.
+        ChangeAllInnerGaps(i32),
+        /// Change the outer gaps of every workspace by a delta, until the configuration gets
+        /// reloaded.
+        ChangeAllOuterGaps(i32),
+        /// Change the proportion of the focused window by a delta.
+        ChangeWindowProportion(f64),
+        /// Change the opacity of the focused window by a delta.
+        ChangeWindowOpacity(f32),
+        /// Focus a workspace of the active output, indexed from zero.
+        FocusWorkspace(usize),
+        /// Send the focused window to a workspace of the active output, indexed from zero.
+        SendToWorkspace(usize),
+        /// Focus a workspace on an output, without focusing the output itself.
+        FocusWorkspaceOnOutput(String, usize),
+        /// Toggle a color filter on the active output. Enabling a filter replaces the previous
+        /// one.
+        ToggleColorFilter(ColorFilter),
+        /// Change the zoom level of the active output by a delta, magnifying the area around the
+        /// pointer. A zoom level of 1.0 means no zoom.
+        ChangeZoom(f64),
+        /// Set the direction of the split made by the next window opened on the active workspace,
+        /// with the `bsp` layout.
+        SetSplitDirection(SplitDirection),
+        /// Scroll the view of the active workspace by a delta in logical pixels, with the
+        /// `scrolling` layout.
+        ScrollView(i32),
+    }
 }
 
 #[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq, Hash)]
@@ -346,11 +570,15 @@ impl<'de> Deserialize<'de> for MousePattern {
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
-#[serde(rename_all = "kebab-case", deny_unknown_fields)]
-pub enum MouseAction {
-    SwapTile,
-    ResizeTile,
+described_actions! {
+    #[derive(Debug, Clone, Deserialize)]
+    #[serde(rename_all = "kebab-case", deny_unknown_fields)]
+    pub enum MouseAction {
+        /// Move the window under the pointer, swapping it with another tile when dropped.
+        SwapTile,
+        /// Resize the window under the pointer.
+        ResizeTile,
+    }
 }
 
 #[derive(Debug, Default, Clone, Deserialize)]
@@ -358,7 +586,110 @@ pub enum MouseAction {
 pub struct Input {
     pub keyboard: Keyboard,
     pub mouse: Mouse,
+    pub mouse_keys: MouseKeys,
+    pub on_screen_keyboard: OnScreenKeyboard,
+    pub tablet: Tablet,
+    pub touch: Touch,
     pub per_device: HashMap<String, PerDeviceInput>,
+    // Let virtual input devices (wayvnc, wtype, remote desktop sessions...) trigger mouse and key
+    // bindings. Otherwise, injected input always goes straight to the focused client.
+    pub virtual_input_bindings: bool,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Tablet {
+    // The output to map tablets to. If unset, the first output is used.
+    // It can be changed at runtime with the `cycle-tablet-output` key action.
+    pub output: Option<String>,
+    // The area of the tablet to use, as [x, y, width, height], each from 0.0 to 1.0.
+    pub area: Option<[f64; 4]>,
+    // Reduce the used area of the tablet to match the aspect ratio of the output, so that drawing
+    // a circle on the tablet draws a circle on the output.
+    pub keep_aspect_ratio: bool,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Touch {
+    // The output to map touchscreens to. If unset, the first output is used.
+    pub output: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct OnScreenKeyboard {
+    // Commands to run when a window supporting text input gains or loses keyboard focus, for
+    // on-screen keyboards that don't implement the input method protocol.
+    pub show_command: Option<String>,
+    pub hide_command: Option<String>,
+    // Move the focused floating window up so that it's not covered by the on-screen keyboard.
+    pub pan_floating_windows: bool,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub enum MouseKeysAction {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    LeftClick,
+    MiddleClick,
+    RightClick,
+}
+
+fn default_mouse_keys_bindings() -> HashMap<KeyPattern, MouseKeysAction> {
+    let key = |keysym: u32| KeyPattern(ModifiersState::default(), keysym.into());
+    HashMap::from_iter([
+        (key(keysyms::KEY_KP_Up), MouseKeysAction::MoveUp),
+        (key(keysyms::KEY_KP_Down), MouseKeysAction::MoveDown),
+        (key(keysyms::KEY_KP_Left), MouseKeysAction::MoveLeft),
+        (key(keysyms::KEY_KP_Right), MouseKeysAction::MoveRight),
+        (key(keysyms::KEY_KP_Begin), MouseKeysAction::LeftClick),
+        (key(keysyms::KEY_KP_Delete), MouseKeysAction::MiddleClick),
+        (key(keysyms::KEY_KP_Insert), MouseKeysAction::RightClick),
+    ])
+}
+
+const fn default_mouse_keys_initial_speed() -> f64 {
+    2.0
+}
+
+const fn default_mouse_keys_max_speed() -> f64 {
+    20.0
+}
+
+const fn default_mouse_keys_acceleration() -> f64 {
+    0.5
+}
+
+// Mouse keys, a keybind layer to move the pointer and click from the keyboard. It gets enabled
+// and disabled with the `toggle-mouse-keys` key action.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct MouseKeys {
+    #[serde(default = "default_mouse_keys_bindings")]
+    pub bindings: HashMap<KeyPattern, MouseKeysAction>,
+    // Pointer speed when starting to move, in logical pixels every 10 milliseconds.
+    #[serde(default = "default_mouse_keys_initial_speed")]
+    pub initial_speed: f64,
+    #[serde(default = "default_mouse_keys_max_speed")]
+    pub max_speed: f64,
+    // How much the speed increases every 10 milliseconds while moving.
+    #[serde(default = "default_mouse_keys_acceleration")]
+    pub acceleration: f64,
+}
+
+impl Default for MouseKeys {
+    fn default() -> Self {
+        Self {
+            bindings: default_mouse_keys_bindings(),
+            initial_speed: default_mouse_keys_initial_speed(),
+            max_speed: default_mouse_keys_max_speed(),
+            acceleration: default_mouse_keys_acceleration(),
+        }
+    }
 }
 
 fn default_keyboard_layout() -> String {
@@ -373,6 +704,16 @@ const fn default_repeat_delay() -> i32 {
     250
 }
 
+fn default_shortcuts_inhibit_escape() -> KeyPattern {
+    KeyPattern(
+        ModifiersState {
+            logo: true,
+            ..Default::default()
+        },
+        keysyms::KEY_Escape.into(),
+    )
+}
+
 #[derive(Debug, Clone, Deserialize)]
 #[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
 pub struct Keyboard {
@@ -386,6 +727,10 @@ pub struct Keyboard {
     pub repeat_delay: i32,
     #[serde(default = "default_repeat_rate")]
     pub repeat_rate: i32,
+    // Key pattern that is never forwarded to a client inhibiting the compositor keybinds (with
+    // keyboard-shortcuts-inhibit-v1), and toggles the inhibition instead.
+    #[serde(default = "default_shortcuts_inhibit_escape")]
+    pub shortcuts_inhibit_escape: KeyPattern,
 }
 
 impl Default for Keyboard {
@@ -400,6 +745,7 @@ impl Default for Keyboard {
 
             repeat_delay: default_repeat_delay(),
             repeat_rate: default_repeat_rate(),
+            shortcuts_inhibit_escape: default_shortcuts_inhibit_escape(),
         }
     }
 }
@@ -480,15 +826,75 @@ impl Into<ClickMethod> for ClickMethodDef {
     }
 }
 
+/// A custom pointer acceleration curve, applied by the compositor on unaccelerated motion.
+///
+/// The curve is made of `[speed, multiplier]` points, where speed is in device units per
+/// millisecond. The multiplier is linearly interpolated between the points, and clamped to the
+/// first/last point outside of them. A single point gives a flat curve with a multiplier.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccelerationCurve(Vec<[f64; 2]>);
+
+impl<'de> Deserialize<'de> for AccelerationCurve {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut points = Vec::<[f64; 2]>::deserialize(deserializer)?;
+        if points.is_empty() {
+            return Err(<D::Error as serde::de::Error>::invalid_length(
+                0,
+                &"at least one [speed, multiplier] point",
+            ));
+        }
+        if let Some([speed, multiplier]) = points.iter().find(|[speed, multiplier]| {
+            !speed.is_finite() || !multiplier.is_finite() || *speed < 0.0
+        }) {
+            return Err(<D::Error as serde::de::Error>::invalid_value(
+                Unexpected::Other(&format!("[{speed}, {multiplier}]")),
+                &"a positive speed and a finite multiplier",
+            ));
+        }
+
+        points.sort_by(|a, b| a[0].total_cmp(&b[0]));
+        Ok(Self(points))
+    }
+}
+
+impl AccelerationCurve {
+    /// Get the multiplier to apply to the pointer motion at this speed.
+    pub fn multiplier(&self, speed: f64) -> f64 {
+        let points = &self.0;
+        let first = points[0];
+        let last = points[points.len() - 1];
+        if speed <= first[0] {
+            return first[1];
+        }
+        if speed >= last[0] {
+            return last[1];
+        }
+
+        let idx = points.partition_point(|point| point[0] <= speed);
+        let ([x0, y0], [x1, y1]) = (points[idx - 1], points[idx]);
+        if x1 == x0 {
+            return y1;
+        }
+        y0 + (y1 - y0) * (speed - x0) / (x1 - x0)
+    }
+}
+
 #[derive(Default, Debug, Clone, Deserialize)]
 #[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
 pub struct Mouse {
     pub acceleration_profile: Option<AccelProfileDef>,
     pub acceleration_speed: Option<f64>,
+    // When set, libinput acceleration is disabled and this curve is used instead.
+    pub acceleration_curve: Option<AccelerationCurve>,
     pub left_handed: Option<bool>,
     pub scroll_method: Option<ScrollMethodDef>,
     pub scroll_button_lock: Option<bool>,
     pub scroll_button: Option<MouseButton>,
+    // Multiplier applied to the scroll amount of the device.
+    pub scroll_factor: Option<f64>,
+    // Convert high-resolution wheel scrolling into discrete wheel steps, for clients that
+    // misbehave with smooth scrolling.
+    pub discrete_scroll: Option<bool>,
     pub click_method: Option<ClickMethodDef>,
     pub natural_scrolling: Option<bool>,
     pub middle_button_emulation: Option<bool>,
@@ -533,9 +939,23 @@ pub struct General {
     pub focus_new_windows: bool,
     #[serde(default = "default_false")]
     pub focus_follows_mouse: bool,
+    // Whether clicking on a window raises it above the others.
+    #[serde(default = "default_true")]
+    pub click_to_raise: bool,
     pub insert_window_strategy: InsertWindowStrategy,
+    // What gets keyboard focus after switching to another workspace.
+    pub workspace_switch_focus: WorkspaceSwitchFocus,
+    // Briefly show the workspace number in the middle of the output after switching to it.
+    #[serde(default = "default_false")]
+    pub workspace_osd: bool,
+    // A command to run after switching workspaces, for example to play a sound.
+    // FHT_OUTPUT and FHT_WORKSPACE are set in its environment, the latter starting from 1.
+    pub workspace_switch_command: Option<String>,
     #[serde(default = "default_layouts")]
     pub layouts: Vec<WorkspaceLayout>,
+    // The shared library computing the geometries of the windows with the plugin layout. It gets
+    // loaded again when reloading the configuration.
+    pub layout_plugin: Option<PathBuf>,
     #[serde(default = "default_nmaster")]
     pub nmaster: usize,
     #[serde(default = "default_mwfact")]
@@ -552,8 +972,13 @@ impl Default for General {
             cursor_warps: true,
             focus_new_windows: true,
             focus_follows_mouse: false,
+            click_to_raise: true,
             insert_window_strategy: InsertWindowStrategy::default(),
+            workspace_switch_focus: WorkspaceSwitchFocus::default(),
+            workspace_osd: false,
+            workspace_switch_command: None,
             layouts: default_layouts(),
+            layout_plugin: None,
             nmaster: 1,
             mwfact: 0.5,
             outer_gaps: default_gaps(),
@@ -562,15 +987,55 @@ impl Default for General {
     }
 }
 
+// The layout settings a workspace starts with, instead of the ones from the general section.
+#[derive(Default, Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct WorkspaceDefaults {
+    // The index of the workspace, starting from zero.
+    pub index: usize,
+    // Only apply to the workspace of this output, takes precedence over the defaults without an
+    // output.
+    pub output: Option<String>,
+    pub layouts: Option<Vec<WorkspaceLayout>>,
+    pub nmaster: Option<usize>,
+    pub mwfact: Option<f64>,
+}
+
 #[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub enum WorkspaceLayout {
     Tile,
     BottomStack,
     CenteredMaster,
+    // Each window splits the area left by the previous one in two, alternating between vertical
+    // and horizontal splits.
+    Dwindle,
+    // Every window takes the whole work area, and only the focused one is visible.
+    Monocle,
+    // Windows are placed in columns on an infinite horizontal strip, each column taking mwfact of
+    // the work area width. The view follows the focused window.
+    Scrolling,
+    // Manual tiling: each new window splits the node of the focused window in two, in the
+    // direction chosen with the `set-split-direction` key action.
+    Bsp,
+    // The geometries of the windows get computed by another process over IPC, see the `commit-layout`
+    // IPC request. Falls back to the tile layout until it answers.
+    External,
+    // The geometries of the windows get computed by the shared library at `layout-plugin`. Falls
+    // back to the tile layout if it can't be loaded.
+    Plugin,
     Floating,
 }
 
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub enum SplitDirection {
+    // The windows get placed side by side.
+    Horizontal,
+    // The windows get placed one above the other.
+    Vertical,
+}
+
 #[derive(Debug, Default, Clone, Copy, Deserialize)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub enum InsertWindowStrategy {
@@ -578,6 +1043,23 @@ pub enum InsertWindowStrategy {
     EndOfSlaveStack,
     ReplaceMaster,
     AfterFocused,
+    // After the window marked with the `mark-insertion-point` key action, or at the end of the
+    // slave stack if no window is marked.
+    AfterMarked,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub enum WorkspaceSwitchFocus {
+    // The window that was last focused on the workspace.
+    #[default]
+    LastFocused,
+    // The master window of the workspace.
+    Master,
+    // The window under the pointer.
+    UnderCursor,
+    // Nothing, the keyboard focus gets cleared.
+    None,
 }
 
 fn default_cursor_theme() -> String {
@@ -617,7 +1099,11 @@ pub struct Decorations {
     pub border: Border,
     pub shadow: Shadow,
     pub blur: Blur,
+    pub dim_inactive: DimInactive,
     pub decoration_mode: DecorationMode,
+    // Ask clients to not draw client-side decorations, both through xdg-decoration and through
+    // toolkit environment variables for spawned commands.
+    pub prefer_no_csd: bool,
 }
 
 impl Default for Decorations {
@@ -626,7 +1112,9 @@ impl Default for Decorations {
             border: Default::default(),
             shadow: Default::default(),
             blur: Default::default(),
+            dim_inactive: Default::default(),
             decoration_mode: DecorationMode::default(),
+            prefer_no_csd: false,
         }
     }
 }
@@ -644,6 +1132,8 @@ const fn default_radius() -> f32 {
 pub struct Border {
     pub focused_color: Color,
     pub normal_color: Color,
+    // Color of windows that requested attention (activation) without being allowed to take focus.
+    pub urgent_color: Color,
     #[serde(default = "default_thickness")]
     pub thickness: i32,
     #[serde(default = "default_radius")]
@@ -659,6 +1149,7 @@ impl Default for Border {
                 angle: 0.0,
             },
             normal_color: Color::Solid(csscolorparser::parse("#222230").unwrap().to_array()),
+            urgent_color: Color::Solid(csscolorparser::parse("#c94f6d").unwrap().to_array()),
             thickness: default_thickness(),
             radius: default_radius(),
         }
@@ -683,8 +1174,13 @@ pub struct Shadow {
         default = "default_shadow_color"
     )]
     pub color: [f32; 4],
+    // The color used for unfocused windows, defaults to `color`.
+    #[serde(deserialize_with = "deserialize_color_maybe")]
+    pub inactive_color: Option<[f32; 4]>,
     #[serde(default = "default_shadow_sigma")]
     pub sigma: f32,
+    // How much the shadow is moved from the window, as [x, y] in logical pixels.
+    pub offset: [i32; 2],
 }
 
 impl Shadow {
@@ -692,7 +1188,9 @@ impl Shadow {
         let mut ret = *self;
         ret.disable = overrides.disable.unwrap_or(ret.disable);
         ret.color = overrides.color.unwrap_or(ret.color);
+        ret.inactive_color = overrides.inactive_color.or(ret.inactive_color);
         ret.sigma = overrides.sigma.unwrap_or(ret.sigma);
+        ret.offset = overrides.offset.unwrap_or(ret.offset);
 
         ret
     }
@@ -704,7 +1202,9 @@ impl Default for Shadow {
             disable: false,
             floating_only: true,
             color: default_shadow_color(),
+            inactive_color: None,
             sigma: default_shadow_sigma(),
+            offset: [0, 0],
         }
     }
 }
@@ -771,6 +1271,33 @@ impl Blur {
     }
 }
 
+const fn default_dim_inactive_duration() -> Duration {
+    Duration::from_millis(150)
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct DimInactive {
+    // How much to darken unfocused windows, from 0.0 (no dimming) to 1.0 (fully black).
+    pub strength: f32,
+    // The duration of the dimming animation when the focus changes.
+    #[serde(
+        default = "default_dim_inactive_duration",
+        serialize_with = "serialize_duration",
+        deserialize_with = "deserialize_duration_millis"
+    )]
+    pub duration: Duration,
+}
+
+impl Default for DimInactive {
+    fn default() -> Self {
+        Self {
+            strength: 0.0,
+            duration: default_dim_inactive_duration(),
+        }
+    }
+}
+
 #[derive(Default, Debug, Clone, Copy, Deserialize, PartialEq)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub enum DecorationMode {
@@ -791,6 +1318,9 @@ impl Border {
         if let Some(normal_color) = &overrides.normal_color {
             ret.normal_color = *normal_color;
         }
+        if let Some(urgent_color) = &overrides.urgent_color {
+            ret.urgent_color = *urgent_color;
+        }
         if let Some(thickness) = &overrides.thickness {
             ret.thickness = *thickness;
         }
@@ -850,12 +1380,19 @@ pub struct Animations {
     pub workspace_switch: WorkspaceSwitchAnimation,
     pub window_open_close: WindowOpenCloseAnimation,
     pub window_geometry: WindowGeometryAnimation,
+    pub border_color: BorderColorAnimation,
+    pub opacity: OpacityAnimation,
+    pub screen_fade: ScreenFadeAnimation,
 }
 
 const fn default_workspace_switch_animation_duration() -> Duration {
     Duration::from_millis(350)
 }
 
+const fn default_workspace_switch_stagger_delay() -> Duration {
+    Duration::from_millis(40)
+}
+
 fn default_workspace_switch_curve() -> AnimationCurve {
     fht_animation::SpringCurve::new(1.0, false, 0.85, 1.0, 600.0, Some(0.0001)).into()
 }
@@ -866,6 +1403,14 @@ pub struct WorkspaceSwitchAnimation {
     #[serde(default = "default_false")]
     pub disable: bool,
     pub direction: WorkspaceSwitchAnimationDirection,
+    pub style: WorkspaceSwitchAnimationStyle,
+    // The delay between each window when using the staggered style.
+    #[serde(
+        default = "default_workspace_switch_stagger_delay",
+        serialize_with = "serialize_duration",
+        deserialize_with = "deserialize_duration_millis"
+    )]
+    pub stagger_delay: Duration,
     #[serde(default = "default_workspace_switch_curve")]
     pub curve: AnimationCurve,
     #[serde(
@@ -883,6 +1428,8 @@ impl Default for WorkspaceSwitchAnimation {
             curve: default_workspace_switch_curve(),
             duration: default_workspace_switch_animation_duration(),
             direction: WorkspaceSwitchAnimationDirection::Horizontal,
+            style: WorkspaceSwitchAnimationStyle::Slide,
+            stagger_delay: default_workspace_switch_stagger_delay(),
         }
     }
 }
@@ -895,6 +1442,16 @@ pub enum WorkspaceSwitchAnimationDirection {
     Vertical,
 }
 
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub enum WorkspaceSwitchAnimationStyle {
+    // Slide the whole workspace.
+    #[default]
+    Slide,
+    // Fade and slide each window of the workspace individually, one after the other.
+    Stagger,
+}
+
 const fn default_window_animation_duration() -> Duration {
     Duration::from_millis(300)
 }
@@ -953,6 +1510,158 @@ impl Default for WindowGeometryAnimation {
     }
 }
 
+const fn default_border_color_duration() -> Duration {
+    Duration::from_millis(200)
+}
+
+fn default_border_color_curve() -> AnimationCurve {
+    AnimationCurve::Simple(fht_animation::curve::Easing::EaseOutCubic)
+}
+
+// The transition between the focused and normal border colors when the focus changes.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct BorderColorAnimation {
+    #[serde(default = "default_false")]
+    pub disable: bool,
+    #[serde(default = "default_border_color_curve")]
+    pub curve: AnimationCurve,
+    #[serde(
+        default = "default_border_color_duration",
+        serialize_with = "serialize_duration",
+        deserialize_with = "deserialize_duration_millis"
+    )]
+    pub duration: Duration,
+}
+
+impl Default for BorderColorAnimation {
+    fn default() -> Self {
+        Self {
+            disable: false,
+            curve: default_border_color_curve(),
+            duration: default_border_color_duration(),
+        }
+    }
+}
+
+const fn default_opacity_duration() -> Duration {
+    Duration::from_millis(150)
+}
+
+fn default_opacity_curve() -> AnimationCurve {
+    AnimationCurve::Simple(fht_animation::curve::Easing::EaseOutCubic)
+}
+
+// The transition of the window opacity when the focus changes (with `inactive-opacity`) or when
+// the opacity gets changed with the `change-window-opacity` key action.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct OpacityAnimation {
+    #[serde(default = "default_false")]
+    pub disable: bool,
+    #[serde(default = "default_opacity_curve")]
+    pub curve: AnimationCurve,
+    #[serde(
+        default = "default_opacity_duration",
+        serialize_with = "serialize_duration",
+        deserialize_with = "deserialize_duration_millis"
+    )]
+    pub duration: Duration,
+}
+
+impl Default for OpacityAnimation {
+    fn default() -> Self {
+        Self {
+            disable: false,
+            curve: default_opacity_curve(),
+            duration: default_opacity_duration(),
+        }
+    }
+}
+
+const fn default_screen_fade_duration() -> Duration {
+    Duration::from_millis(300)
+}
+
+fn default_screen_fade_curve() -> AnimationCurve {
+    AnimationCurve::Simple(fht_animation::curve::Easing::EaseOutCubic)
+}
+
+// The fade from and to black of whole outputs, when the compositor starts and quits, and when the
+// session gets locked and unlocked.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ScreenFadeAnimation {
+    #[serde(default = "default_false")]
+    pub disable: bool,
+    #[serde(default = "default_screen_fade_curve")]
+    pub curve: AnimationCurve,
+    #[serde(
+        default = "default_screen_fade_duration",
+        serialize_with = "serialize_duration",
+        deserialize_with = "deserialize_duration_millis"
+    )]
+    pub duration: Duration,
+}
+
+impl Default for ScreenFadeAnimation {
+    fn default() -> Self {
+        Self {
+            disable: false,
+            curve: default_screen_fade_curve(),
+            duration: default_screen_fade_duration(),
+        }
+    }
+}
+
+const fn default_zoom_scroll_step() -> f64 {
+    0.25
+}
+
+const fn default_zoom_max_level() -> f64 {
+    8.0
+}
+
+const fn default_zoom_duration() -> Duration {
+    Duration::from_millis(200)
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Zoom {
+    // Hold these modifiers while scrolling to zoom in and out of the active output, for example
+    // "Super-Ctrl". Scrolling does not zoom if not set.
+    pub scroll_modifiers: Option<ModifiersState>,
+    // How much the zoom level changes with each wheel step, touchpad scrolling gets accumulated.
+    #[serde(default = "default_zoom_scroll_step")]
+    pub scroll_step: f64,
+    // The highest zoom level, 1.0 being no zoom at all.
+    #[serde(default = "default_zoom_max_level")]
+    pub max_level: f64,
+    // Move the zoomed area to the focused window when the keyboard focus changes, instead of only
+    // following the pointer.
+    pub follow_focus: bool,
+    // The duration of the animation when the zoom level changes.
+    #[serde(
+        default = "default_zoom_duration",
+        serialize_with = "serialize_duration",
+        deserialize_with = "deserialize_duration_millis"
+    )]
+    pub duration: Duration,
+}
+
+impl Default for Zoom {
+    fn default() -> Self {
+        Self {
+            scroll_modifiers: None,
+            scroll_step: default_zoom_scroll_step(),
+            max_level: default_zoom_max_level(),
+            follow_focus: false,
+            duration: default_zoom_duration(),
+        }
+    }
+}
+
 fn deserialize_regexes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Regex>, D::Error> {
     let patterns = Vec::<String>::deserialize(deserializer)?;
     let mut regexes = vec![];
@@ -979,6 +1688,9 @@ pub struct WindowRule {
     pub on_output: Option<String>,
     pub on_workspace: Option<usize>,
     pub is_focused: Option<bool>,
+    // Also apply the rule to existing windows when the configuration gets reloaded, including the
+    // rules that normally only apply when a window opens (floating, workspace, etc.)
+    pub retroactive: bool,
     // Rules to apply
     pub open_on_output: Option<String>,
     pub open_on_workspace: Option<usize>,
@@ -987,11 +1699,20 @@ pub struct WindowRule {
     pub shadow: ShadowOverrides,
     pub proportion: Option<f64>,
     pub opacity: Option<f32>,
+    // The opacity of the window when it is not focused, defaults to `opacity`.
+    pub inactive_opacity: Option<f32>,
     pub decoration_mode: Option<DecorationMode>,
     pub maximized: Option<bool>,
     pub fullscreen: Option<bool>,
     pub floating: Option<bool>,
     pub centered: Option<bool>, // only effective if floating == Some(true)
+    pub allow_tearing: Option<bool>,
+    // Switch to this keyboard layout while the window is focused. It must be one of the layouts
+    // specified in `input.keyboard.layout`
+    pub keyboard_layout: Option<String>,
+    // Snap the window size to multiples of [width, height] when resizing or tiling it, for example
+    // the cell size of a terminal. xdg-shell has no way for clients to give this themselves.
+    pub size_increments: Option<[i32; 2]>,
 }
 
 // NOTE: For layer shells we by default disable blur and shadow
@@ -1025,6 +1746,8 @@ pub struct LayerRule {
     pub shadow: ShadowOverrides,
     pub opacity: Option<f32>,
     pub corner_radius: Option<f32>,
+    // Mark the layer as an on-screen keyboard, windows will not get covered by it.
+    pub on_screen_keyboard: Option<bool>,
 }
 
 #[derive(Default, Debug, Clone, Copy, Deserialize)]
@@ -1032,6 +1755,7 @@ pub struct LayerRule {
 pub struct BorderOverrides {
     pub focused_color: Option<Color>,
     pub normal_color: Option<Color>,
+    pub urgent_color: Option<Color>,
     pub thickness: Option<i32>,
     pub radius: Option<f32>,
 }
@@ -1044,6 +1768,9 @@ impl BorderOverrides {
         if let Some(normal_color) = other.normal_color {
             self.normal_color = Some(normal_color);
         }
+        if let Some(urgent_color) = other.urgent_color {
+            self.urgent_color = Some(urgent_color);
+        }
         if let Some(thickness) = other.thickness {
             self.thickness = Some(thickness);
         }
@@ -1093,7 +1820,10 @@ pub struct ShadowOverrides {
     pub disable: Option<bool>,
     #[serde(deserialize_with = "deserialize_color_maybe")]
     pub color: Option<[f32; 4]>,
+    #[serde(deserialize_with = "deserialize_color_maybe")]
+    pub inactive_color: Option<[f32; 4]>,
     pub sigma: Option<f32>,
+    pub offset: Option<[i32; 2]>,
 }
 
 impl ShadowOverrides {
@@ -1104,9 +1834,15 @@ impl ShadowOverrides {
         if let Some(color) = other.color {
             self.color = Some(color);
         }
+        if let Some(inactive_color) = other.inactive_color {
+            self.inactive_color = Some(inactive_color);
+        }
         if let Some(sigma) = other.sigma {
             self.sigma = Some(sigma);
         }
+        if let Some(offset) = other.offset {
+            self.offset = Some(offset);
+        }
 
         self
     }
@@ -1178,6 +1914,34 @@ impl Into<SmithayTransform> for OutputTransform {
     }
 }
 
+#[derive(Default, Debug, Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub enum VrrMode {
+    /// Never enable variable refresh rate.
+    #[default]
+    Off,
+    /// Always enable variable refresh rate.
+    On,
+    /// Only enable variable refresh rate when a fullscreen window displays a game or video, as
+    /// advertised with `wp-content-type-v1`
+    OnDemand,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub enum ColorFilter {
+    /// Display everything in shades of gray.
+    Grayscale,
+    /// Invert all the colors.
+    Invert,
+    /// Shift colors to be easier to tell apart with protanopia (no red cones).
+    Protanopia,
+    /// Shift colors to be easier to tell apart with deuteranopia (no green cones).
+    Deuteranopia,
+    /// Shift colors to be easier to tell apart with tritanopia (no blue cones).
+    Tritanopia,
+}
+
 #[derive(Default, Debug, Clone, Deserialize, PartialEq)]
 #[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
 pub struct Output {
@@ -1189,6 +1953,115 @@ pub struct Output {
     pub transform: Option<OutputTransform>,
     pub scale: Option<i32>,
     pub position: Option<[i32; 2]>,
+    pub vrr: VrrMode,
+    // Treat this output as a non-desktop display (for example a VR headset), even if its EDID does
+    // not advertise it. Non-desktop outputs are kept out of the layout and offered for leasing
+    // through drm-lease-v1 instead.
+    pub leasable: bool,
+    // The workspace index (starting from zero) this output shows when it gets added.
+    pub default_workspace: Option<usize>,
+    // Start rendering at most this many milliseconds before the next presentation, instead of
+    // right away, to reduce latency.
+    pub max_render_time: Option<u16>,
+    // ICC profile whose calibration curves (vcgt tag) get loaded into the output gamma ramps.
+    // Only the calibration curves are used, there is no color space conversion.
+    pub icc_profile: Option<PathBuf>,
+    // A custom modeline, takes precedence over the configured mode.
+    pub modeline: Option<Modeline>,
+    // A color filter applied to everything displayed on the output. It can also be toggled at
+    // runtime with the `toggle-color-filter` key action.
+    pub color_filter: Option<ColorFilter>,
+}
+
+/// A custom output mode, with the same syntax as X11 modelines (without the `Modeline "name"`
+/// prefix), for example `173.00 1920 2048 2248 2576 1080 1083 1088 1120 -hsync +vsync`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Modeline {
+    /// The pixel clock, in MHz.
+    pub clock: f64,
+    pub hdisplay: u16,
+    pub hsync_start: u16,
+    pub hsync_end: u16,
+    pub htotal: u16,
+    pub vdisplay: u16,
+    pub vsync_start: u16,
+    pub vsync_end: u16,
+    pub vtotal: u16,
+    pub hsync_positive: bool,
+    pub vsync_positive: bool,
+    pub interlace: bool,
+}
+
+impl<'de> Deserialize<'de> for Modeline {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let raw = String::deserialize(deserializer)?;
+        let invalid = || {
+            <D::Error as serde::de::Error>::invalid_value(
+                Unexpected::Str(&raw),
+                &"{clock} {hdisplay} {hsync-start} {hsync-end} {htotal} {vdisplay} {vsync-start} \
+                  {vsync-end} {vtotal} [flags]",
+            )
+        };
+
+        let mut parts = raw.split_whitespace();
+        let clock = parts
+            .next()
+            .and_then(|clock| clock.parse::<f64>().ok())
+            .filter(|clock| *clock > 0.0)
+            .ok_or_else(invalid)?;
+        let mut timings = [0u16; 8];
+        for timing in &mut timings {
+            *timing = parts
+                .next()
+                .and_then(|timing| timing.parse().ok())
+                .ok_or_else(invalid)?;
+        }
+        let [hdisplay, hsync_start, hsync_end, htotal, vdisplay, vsync_start, vsync_end, vtotal] =
+            timings;
+        if !(hdisplay <= hsync_start && hsync_start <= hsync_end && hsync_end <= htotal)
+            || !(vdisplay <= vsync_start && vsync_start <= vsync_end && vsync_end <= vtotal)
+        {
+            return Err(invalid());
+        }
+
+        let mut modeline = Self {
+            clock,
+            hdisplay,
+            hsync_start,
+            hsync_end,
+            htotal,
+            vdisplay,
+            vsync_start,
+            vsync_end,
+            vtotal,
+            // Same defaults as the CVT modes we generate.
+            hsync_positive: false,
+            vsync_positive: true,
+            interlace: false,
+        };
+        for flag in parts {
+            match flag.to_lowercase().as_str() {
+                "+hsync" => modeline.hsync_positive = true,
+                "-hsync" => modeline.hsync_positive = false,
+                "+vsync" => modeline.vsync_positive = true,
+                "-vsync" => modeline.vsync_positive = false,
+                "interlace" => modeline.interlace = true,
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(modeline)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Backend {
+    // The render node of the GPU used for rendering. Takes precedence over `debug.render-node`.
+    pub render_node: Option<std::path::PathBuf>,
 }
 
 fn default_disable_10bit() -> bool {
@@ -1205,6 +2078,13 @@ fn default_disable_overlay_planes() -> bool {
         .unwrap_or(false)
 }
 
+fn default_disable_direct_scanout() -> bool {
+    std::env::var("FHTC_DISABLE_DIRECT_SCANOUT")
+        .ok()
+        .and_then(|str| str.parse::<bool>().ok())
+        .unwrap_or(false)
+}
+
 fn default_render_node() -> Option<std::path::PathBuf> {
     std::env::var("FHTC_RENDER_NODE")
         .ok()
@@ -1218,12 +2098,27 @@ pub struct Debug {
     pub disable_10bit: bool,
     #[serde(default = "default_disable_overlay_planes")]
     pub disable_overlay_planes: bool,
+    #[serde(default = "default_disable_direct_scanout")]
+    pub disable_direct_scanout: bool,
     #[serde(default = "default_render_node")]
     pub render_node: Option<std::path::PathBuf>,
     pub draw_damage: bool,
     pub draw_opaque_regions: bool,
     pub debug_overlay: bool,
     pub tile_debug_overlay: bool,
+    // DMA-BUF formats (as fourcc codes, for example "AR30") that should not be advertised to
+    // clients.
+    #[serde(deserialize_with = "deserialize_fourccs")]
+    pub disallowed_dmabuf_formats: Vec<Fourcc>,
+    // DMA-BUF format modifiers that should not be advertised to clients.
+    pub disallowed_dmabuf_modifiers: Vec<u64>,
+    // Do not advertise Intel CCS (compression control surface) modifiers to clients.
+    pub disable_ccs_modifiers: bool,
+    // Only use linear buffers for screencasts, some consumers can't handle tiled buffers.
+    pub screencast_force_linear: bool,
+    // Create an additional seat without any input device, to test how clients and the compositor
+    // deal with multiple seats.
+    pub extra_test_seat: bool,
 }
 
 impl Default for Debug {
@@ -1231,15 +2126,39 @@ impl Default for Debug {
         Self {
             disable_10bit: default_disable_10bit(),
             disable_overlay_planes: default_disable_overlay_planes(),
+            disable_direct_scanout: default_disable_direct_scanout(),
             render_node: default_render_node(),
             draw_damage: false,
             draw_opaque_regions: false,
             debug_overlay: false,
             tile_debug_overlay: false,
+            disallowed_dmabuf_formats: vec![],
+            disallowed_dmabuf_modifiers: vec![],
+            disable_ccs_modifiers: false,
+            screencast_force_linear: false,
+            extra_test_seat: false,
         }
     }
 }
 
+fn deserialize_fourccs<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Fourcc>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .into_iter()
+        .map(|raw| {
+            let invalid = || {
+                <D::Error as serde::de::Error>::invalid_value(
+                    Unexpected::Str(&raw),
+                    &"a four character DRM format code",
+                )
+            };
+            let bytes: [u8; 4] = raw.as_bytes().try_into().map_err(|_| invalid())?;
+            Fourcc::try_from(u32::from_le_bytes(bytes)).map_err(|_| invalid())
+        })
+        .collect()
+}
+
 fn get_xdg_path() -> Result<path::PathBuf, xdg::BaseDirectoriesError> {
     xdg::BaseDirectories::new()
         .map(|base_directories| base_directories.get_config_file("fht/compositor.toml"))
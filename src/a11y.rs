@@ -0,0 +1,361 @@
+//! Accessibility event bridge.
+//!
+//! This is a first step toward accessibility support: the compositor does not expose a full
+//! AT-SPI accessible tree. It exports an application root object, with the focused window as its
+//! only child, and emits AT-SPI focus events on the accessibility bus when the focused window
+//! changes, so that screen readers (like Orca) can at least track focus.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context;
+use smithay::reexports::calloop::{self, LoopHandle};
+use zbus::names::BusName;
+use zvariant::{OwnedObjectPath, OwnedValue, Value};
+
+use crate::focus_target::KeyboardFocusTarget;
+use crate::state::{Fht, State};
+use crate::window::{Window, WindowId};
+
+/// Path of the application root object, the one AT-SPI registers with the registry.
+const ROOT_PATH: &str = "/org/a11y/atspi/accessible/root";
+/// Path AT-SPI uses to refer to no object.
+const NULL_PATH: &str = "/org/a11y/atspi/null";
+
+/// AT-SPI role of the root object, `ATSPI_ROLE_APPLICATION`.
+const ROLE_APPLICATION: u32 = 75;
+/// AT-SPI role of a top level window, `ATSPI_ROLE_FRAME`.
+const ROLE_FRAME: u32 = 23;
+
+/// AT-SPI states of the focused window: active, enabled, focusable, focused, sensitive, showing
+/// and visible.
+const FOCUSED_WINDOW_STATES: u32 =
+    (1 << 1) | (1 << 8) | (1 << 11) | (1 << 12) | (1 << 24) | (1 << 25) | (1 << 30);
+
+/// A reference to an accessible object, its bus name and its path.
+type ObjectRef = (String, OwnedObjectPath);
+
+pub struct A11yBridge {
+    /// Connection to the accessibility bus, not the session bus.
+    connection: zbus::blocking::Connection,
+    /// Our unique name on the accessibility bus.
+    bus_name: String,
+    /// The path of the focused window object, the only child of the root object.
+    focused_path: Arc<Mutex<Option<OwnedObjectPath>>>,
+    /// The last window we sent focus events for.
+    focused: Option<WindowId>,
+}
+
+impl A11yBridge {
+    /// Start connecting to the accessibility bus.
+    ///
+    /// Looking up and connecting to the accessibility bus happens on the executor of the session
+    /// connection, the bridge gets set in [`Fht::a11y`] once it's done.
+    pub fn start(
+        session_connection: &zbus::blocking::Connection,
+        loop_handle: &LoopHandle<'static, State>,
+    ) -> anyhow::Result<()> {
+        let (to_compositor, from_bus) = calloop::channel::channel::<A11yBridge>();
+        loop_handle
+            .insert_source(from_bus, |event, _, state| {
+                let calloop::channel::Event::Msg(bridge) = event else {
+                    return;
+                };
+                state.fht.a11y = Some(bridge);
+            })
+            .map_err(|err| {
+                anyhow::anyhow!("Failed to insert accessibility bridge source! {err}")
+            })?;
+
+        let connection = session_connection.inner().clone();
+        let connect = async move {
+            match Self::connect(&connection).await {
+                Ok(bridge) => {
+                    let _ = to_compositor.send(bridge);
+                }
+                Err(err) => warn!(?err, "Failed to start accessibility bridge"),
+            }
+        };
+        session_connection
+            .inner()
+            .executor()
+            .spawn(connect, "a11y-connect")
+            .detach();
+
+        Ok(())
+    }
+
+    /// Connect to the accessibility bus, using the address given by `org.a11y.Bus`, and register
+    /// our root object with the AT-SPI registry.
+    async fn connect(session_connection: &zbus::Connection) -> anyhow::Result<Self> {
+        let reply = session_connection
+            .call_method(
+                Some("org.a11y.Bus"),
+                "/org/a11y/bus",
+                Some("org.a11y.Bus"),
+                "GetAddress",
+                &(),
+            )
+            .await
+            .context("Failed to get accessibility bus address")?;
+        let address: String = reply
+            .body()
+            .deserialize()
+            .context("Invalid accessibility bus address")?;
+        let connection = zbus::connection::Builder::address(address.as_str())?
+            .build()
+            .await
+            .context("Failed to connect to accessibility bus")?;
+        let bus_name = connection.unique_name().unwrap().to_string();
+
+        let focused_path = Arc::new(Mutex::new(None));
+        let root = Accessible {
+            bus_name: bus_name.clone(),
+            name: String::from("fht-compositor"),
+            role: ROLE_APPLICATION,
+            states: 0,
+            parent: (String::new(), null_path()),
+            children: Arc::clone(&focused_path),
+        };
+        connection
+            .object_server()
+            .at(root_path(), root)
+            .await
+            .context("Failed to export accessibility root object")?;
+
+        let reply = connection
+            .call_method(
+                Some("org.a11y.atspi.Registry"),
+                ROOT_PATH,
+                Some("org.a11y.atspi.Socket"),
+                "Embed",
+                &((bus_name.as_str(), root_path()),),
+            )
+            .await
+            .context("Failed to register with the accessibility registry")?;
+        let parent: ObjectRef = reply
+            .body()
+            .deserialize()
+            .context("Invalid accessibility registry reply")?;
+        let root = connection
+            .object_server()
+            .interface::<_, Accessible>(ROOT_PATH)
+            .await?;
+        root.get_mut().await.parent = parent;
+
+        Ok(Self {
+            connection: connection.into(),
+            bus_name,
+            focused_path,
+            focused: None,
+        })
+    }
+
+    /// Export the focused window object and emit focus events if the focused window changed.
+    fn set_focused_window(&mut self, window: Option<&Window>) {
+        let id = window.map(Window::id);
+        if id == self.focused {
+            return;
+        }
+        self.focused = id;
+
+        let object_server = self.connection.object_server();
+        let previous_path = self.focused_path.lock().unwrap().take();
+        if let Some(previous_path) = previous_path {
+            if let Err(err) = object_server.remove::<Accessible, _>(&previous_path) {
+                warn!(?err, "Failed to remove accessible window object");
+            }
+        }
+        let Some(window) = window else {
+            return;
+        };
+
+        let path = format!("/org/a11y/atspi/accessible/window_{}", window.id().get());
+        let path = OwnedObjectPath::try_from(path).unwrap();
+        let title = window.title().unwrap_or_default();
+        let accessible = Accessible {
+            bus_name: self.bus_name.clone(),
+            name: title.clone(),
+            role: ROLE_FRAME,
+            states: FOCUSED_WINDOW_STATES,
+            parent: (self.bus_name.clone(), root_path()),
+            children: Arc::new(Mutex::new(None)),
+        };
+        if let Err(err) = object_server.at(&path, accessible) {
+            warn!(?err, "Failed to export accessible window object");
+            return;
+        }
+        *self.focused_path.lock().unwrap() = Some(path.clone());
+
+        let mut properties: HashMap<&str, OwnedValue> = HashMap::new();
+        properties.insert("role", OwnedValue::from(ROLE_FRAME));
+        if let Some(app_id) = window.app_id() {
+            if let Ok(app_id) = OwnedValue::try_from(Value::from(app_id)) {
+                properties.insert("app-id", app_id);
+            }
+        }
+
+        // AT-SPI events are (kind, detail1, detail2, any_data, properties)
+        let events = [
+            (
+                "org.a11y.atspi.Event.Window",
+                "Activate",
+                ("", 0i32, 0i32, Value::from(title.as_str())),
+            ),
+            (
+                "org.a11y.atspi.Event.Object",
+                "StateChanged",
+                ("focused", 1i32, 0i32, Value::from(0i32)),
+            ),
+        ];
+        for (interface, member, (kind, detail1, detail2, any_data)) in events {
+            if let Err(err) = self.connection.emit_signal(
+                None::<BusName<'_>>,
+                path.as_str(),
+                interface,
+                member,
+                &(kind, detail1, detail2, any_data, &properties),
+            ) {
+                warn!(?err, "Failed to emit accessibility event");
+            }
+        }
+    }
+}
+
+fn root_path() -> OwnedObjectPath {
+    OwnedObjectPath::try_from(ROOT_PATH).unwrap()
+}
+
+fn null_path() -> OwnedObjectPath {
+    OwnedObjectPath::try_from(NULL_PATH).unwrap()
+}
+
+/// An object of our accessible tree, either the root or the focused window.
+struct Accessible {
+    bus_name: String,
+    name: String,
+    role: u32,
+    /// The first half of the AT-SPI state set, the states we use all fit in it.
+    states: u32,
+    parent: ObjectRef,
+    /// The only child of this object, if any.
+    children: Arc<Mutex<Option<OwnedObjectPath>>>,
+}
+
+impl Accessible {
+    fn children(&self) -> Vec<ObjectRef> {
+        let child = self.children.lock().unwrap().clone();
+        child
+            .map(|path| (self.bus_name.clone(), path))
+            .into_iter()
+            .collect()
+    }
+}
+
+#[zbus::interface(name = "org.a11y.atspi.Accessible")]
+impl Accessible {
+    #[zbus(property)]
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    #[zbus(property)]
+    fn description(&self) -> String {
+        String::new()
+    }
+
+    #[zbus(property)]
+    fn parent(&self) -> ObjectRef {
+        self.parent.clone()
+    }
+
+    #[zbus(property)]
+    fn child_count(&self) -> i32 {
+        self.children().len() as i32
+    }
+
+    #[zbus(property)]
+    fn locale(&self) -> String {
+        String::new()
+    }
+
+    #[zbus(property)]
+    fn accessible_id(&self) -> String {
+        String::new()
+    }
+
+    fn get_child_at_index(&self, index: i32) -> ObjectRef {
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| self.children().into_iter().nth(index))
+            .unwrap_or_else(|| (String::new(), null_path()))
+    }
+
+    fn get_children(&self) -> Vec<ObjectRef> {
+        self.children()
+    }
+
+    fn get_index_in_parent(&self) -> i32 {
+        // The root has no index, and windows are the only child of the root.
+        if self.role == ROLE_APPLICATION {
+            -1
+        } else {
+            0
+        }
+    }
+
+    fn get_relation_set(&self) -> Vec<(u32, Vec<ObjectRef>)> {
+        vec![]
+    }
+
+    fn get_role(&self) -> u32 {
+        self.role
+    }
+
+    fn get_role_name(&self) -> String {
+        let name = if self.role == ROLE_APPLICATION {
+            "application"
+        } else {
+            "frame"
+        };
+        String::from(name)
+    }
+
+    fn get_localized_role_name(&self) -> String {
+        self.get_role_name()
+    }
+
+    fn get_state(&self) -> Vec<u32> {
+        vec![self.states, 0]
+    }
+
+    fn get_attributes(&self) -> HashMap<String, String> {
+        HashMap::new()
+    }
+
+    fn get_application(&self) -> ObjectRef {
+        (self.bus_name.clone(), root_path())
+    }
+
+    fn get_interfaces(&self) -> Vec<String> {
+        vec![String::from("org.a11y.atspi.Accessible")]
+    }
+}
+
+impl Fht {
+    /// Send accessibility focus events if the focused window changed.
+    pub fn refresh_a11y_focus(&mut self) {
+        crate::profile_function!();
+        if self.a11y.is_none() {
+            return;
+        }
+        let focused_window = match self.seat.get_keyboard().unwrap().current_focus() {
+            Some(KeyboardFocusTarget::Window(window)) => Some(window),
+            _ => None,
+        };
+        self.a11y
+            .as_mut()
+            .unwrap()
+            .set_focused_window(focused_window.as_ref());
+    }
+}
@@ -0,0 +1,346 @@
+//! Headless backend.
+//!
+//! Renders virtual outputs offscreen, without any DRM device or parent compositor. This is useful
+//! for remote sessions (for example with wayvnc, that uses the wlr-screencopy protocol) and for
+//! automated testing.
+//!
+//! The virtual outputs are named `HEADLESS-<n>`, and are created from the `[outputs.HEADLESS-<n>]`
+//! sections of the configuration. If there are none, a single 1920x1080@60 output gets created.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::Context;
+use smithay::backend::allocator::dmabuf::Dmabuf;
+use smithay::backend::allocator::Fourcc;
+use smithay::backend::egl::{EGLContext, EGLDevice, EGLDisplay};
+use smithay::backend::renderer::damage::{Error as OutputDamageTrackerError, OutputDamageTracker};
+use smithay::backend::renderer::gles::GlesTexture;
+use smithay::backend::renderer::glow::GlowRenderer;
+use smithay::backend::renderer::{Bind, ImportDma, ImportMemWl, Offscreen};
+use smithay::desktop::utils::OutputPresentationFeedback;
+use smithay::output::{Mode, Output, PhysicalProperties, Subpixel};
+use smithay::reexports::calloop::timer::{TimeoutAction, Timer};
+use smithay::reexports::wayland_protocols::wp::presentation_time::server::wp_presentation_feedback;
+use smithay::reexports::wayland_server::backend::GlobalId;
+use smithay::utils::{Monotonic, Size};
+use smithay::wayland::dmabuf::{DmabufFeedbackBuilder, DmabufGlobal, ImportNotifier};
+use smithay::wayland::presentation::Refresh;
+
+use crate::frame_clock::FrameClock;
+use crate::output::RedrawState;
+use crate::renderer::blur::EffectsFramebuffers;
+use crate::state::{Fht, State};
+use crate::utils::get_monotonic_time;
+
+/// The prefix of the virtual output names.
+const OUTPUT_NAME_PREFIX: &str = "HEADLESS-";
+
+pub struct HeadlessData {
+    renderer: GlowRenderer,
+    outputs: HashMap<Output, HeadlessOutput>,
+    _dmabuf_global: DmabufGlobal,
+}
+
+struct HeadlessOutput {
+    /// The texture we render the output into.
+    texture: GlesTexture,
+    damage_tracker: OutputDamageTracker,
+    /// The presentation feedback of the last rendered frame, sent when the frame timer fires.
+    presentation_feedback: Option<OutputPresentationFeedback>,
+    _global: GlobalId,
+}
+
+impl HeadlessData {
+    pub fn new(fht: &mut Fht) -> anyhow::Result<Self> {
+        // Prefer hardware devices, but software ones (llvmpipe) work just fine when there is no
+        // GPU, for example inside virtual machines.
+        let device = EGLDevice::enumerate()
+            .context("Failed to enumerate EGL devices")?
+            .max_by_key(|device| {
+                !device
+                    .extensions()
+                    .iter()
+                    .any(|ext| ext == "EGL_MESA_device_software")
+            })
+            .context("No EGL device available")?;
+        let render_node = device.try_get_render_node().ok().flatten();
+
+        let display = unsafe { EGLDisplay::new(device) }.context("Failed to create EGL display")?;
+        let context = EGLContext::new(&display).context("Failed to create EGL context")?;
+        let mut renderer =
+            unsafe { GlowRenderer::new(context) }.context("Failed to create renderer")?;
+        crate::renderer::init(&mut renderer);
+
+        let dmabuf_formats =
+            crate::renderer::dmabuf::filter_formats(&renderer.dmabuf_formats(), &fht.config.debug);
+        let dmabuf_global = match render_node
+            .map(|node| DmabufFeedbackBuilder::new(node.dev_id(), dmabuf_formats.clone()).build())
+        {
+            Some(Ok(default_feedback)) => fht
+                .dmabuf_state
+                .create_global_with_default_feedback::<State>(
+                    &fht.display_handle,
+                    &default_feedback,
+                ),
+            // Without a render node, fallback to dmabuf v3
+            _ => fht
+                .dmabuf_state
+                .create_global::<State>(&fht.display_handle, dmabuf_formats),
+        };
+        fht.shm_state.update_formats(renderer.shm_formats());
+
+        let mut data = Self {
+            renderer,
+            outputs: HashMap::new(),
+            _dmabuf_global: dmabuf_global,
+        };
+
+        let mut output_names: Vec<_> = fht
+            .config
+            .outputs
+            .iter()
+            .filter(|(name, config)| name.starts_with(OUTPUT_NAME_PREFIX) && !config.disable)
+            .map(|(name, _)| name.clone())
+            .collect();
+        output_names.sort();
+        if output_names.is_empty() {
+            output_names.push(format!("{OUTPUT_NAME_PREFIX}1"));
+        }
+
+        for name in output_names {
+            data.add_output(fht, name)?;
+        }
+
+        Ok(data)
+    }
+
+    fn add_output(&mut self, fht: &mut Fht, name: String) -> anyhow::Result<()> {
+        let output_config = fht.config.outputs.get(&name);
+        let (width, height, refresh) = output_config
+            .and_then(|config| config.mode)
+            .unwrap_or((1920, 1080, None));
+        let mode = validate_mode(Mode {
+            size: (width as i32, height as i32).into(),
+            refresh: (refresh.unwrap_or(60.0) * 1000.0).round() as i32,
+        });
+
+        let output = Output::new(
+            name,
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: String::from("fht-compositor"),
+                model: String::from("headless"),
+            },
+        );
+        output.add_mode(mode);
+        output.set_preferred(mode);
+        output.change_current_state(
+            Some(mode),
+            output_config
+                .and_then(|config| config.transform)
+                .map(Into::into),
+            output_config
+                .and_then(|config| config.scale)
+                .map(smithay::output::Scale::Integer),
+            None,
+        );
+        let global = output.create_global::<State>(&fht.display_handle);
+
+        let texture = self.create_texture(mode)?;
+        EffectsFramebuffers::init_for_output(&output, &mut self.renderer);
+        self.outputs.insert(
+            output.clone(),
+            HeadlessOutput {
+                texture,
+                damage_tracker: OutputDamageTracker::from_output(&output),
+                presentation_feedback: None,
+                _global: global,
+            },
+        );
+
+        fht.add_output(output, Some(refresh_interval(mode)));
+        Ok(())
+    }
+
+    fn create_texture(&mut self, mode: Mode) -> anyhow::Result<GlesTexture> {
+        Offscreen::<GlesTexture>::create_buffer(
+            &mut self.renderer,
+            Fourcc::Abgr8888,
+            Size::from((mode.size.w, mode.size.h)),
+        )
+        .context("Failed to create output texture")
+    }
+
+    pub fn render(
+        &mut self,
+        fht: &mut Fht,
+        output: &Output,
+        target_presentation_time: Duration,
+    ) -> anyhow::Result<bool> {
+        crate::profile_function!();
+        let Some(headless_output) = self.outputs.get_mut(output) else {
+            anyhow::bail!("Tried to render a non-headless output");
+        };
+
+        let mut output_elements_result = fht.output_elements(&mut self.renderer, output);
+        fht.draw_debug_elements(
+            &mut self.renderer,
+            output,
+            &mut output_elements_result.elements,
+        );
+        let mut res = {
+            let mut fb = self.renderer.bind(&mut headless_output.texture)?;
+            // We always render in the same texture, so it is always one frame old.
+            headless_output
+                .damage_tracker
+                .render_output(
+                    &mut self.renderer,
+                    &mut fb,
+                    1,
+                    &output_elements_result.elements,
+                    [0.1, 0.1, 0.1, 1.0],
+                )
+                .map_err(|err| match err {
+                    OutputDamageTrackerError::Rendering(err) => err,
+                    _ => unreachable!(),
+                })?
+        };
+        output_elements_result.merge_offscreen_states(&mut res.states);
+
+        fht.update_primary_scanout_output(output, &res.states);
+        fht.record_debug_element_states(output, &res.states);
+        fht.render_screencopy_without_damage(output, &mut self.renderer, &output_elements_result);
+
+        let has_damage = res.damage.is_some();
+        if has_damage {
+            headless_output.presentation_feedback =
+                Some(fht.take_presentation_feedback(output, &res.states));
+            fht.render_screencopy_with_damage(output, &mut self.renderer, &output_elements_result);
+        }
+
+        let output_state = fht.output_state.get_mut(output).unwrap();
+        let new_state = RedrawState::WaitingForVblank { queued: false };
+        match std::mem::replace(&mut output_state.redraw_state, new_state) {
+            RedrawState::Queued => (),
+            _ => unreachable!(),
+        }
+        output_state.current_frame_sequence = output_state.current_frame_sequence.wrapping_add(1);
+
+        // There's no display to wait for, pretend we present at the output refresh rate.
+        let mut duration = target_presentation_time.saturating_sub(get_monotonic_time());
+        if duration.is_zero() {
+            duration += output_state
+                .frame_clock
+                .refresh_interval()
+                .expect("headless backend should not have unknown refresh interval");
+        }
+        let output = output.clone();
+        fht.loop_handle
+            .insert_source(Timer::from_duration(duration), move |_, _, state| {
+                state.backend.headless().on_vblank(&mut state.fht, &output);
+                TimeoutAction::Drop
+            })
+            .map_err(|err| anyhow::anyhow!("Failed to insert frame timer: {err}"))?;
+
+        Ok(has_damage)
+    }
+
+    fn on_vblank(&mut self, fht: &mut Fht, output: &Output) {
+        crate::profile_function!();
+        let Some(output_state) = fht.output_state.get_mut(output) else {
+            return;
+        };
+        let redraw_queued =
+            match std::mem::replace(&mut output_state.redraw_state, RedrawState::Idle) {
+                RedrawState::WaitingForVblank { queued } => queued,
+                _ => unreachable!(),
+            };
+
+        let now = get_monotonic_time();
+        if let Some(mut presentation_feedback) = self
+            .outputs
+            .get_mut(output)
+            .and_then(|headless_output| headless_output.presentation_feedback.take())
+        {
+            let refresh = output_state
+                .frame_clock
+                .refresh_interval()
+                .unwrap_or(Duration::ZERO);
+            presentation_feedback.presented::<_, Monotonic>(
+                now,
+                Refresh::fixed(refresh),
+                0,
+                wp_presentation_feedback::Kind::empty(),
+            );
+        }
+        output_state.frame_clock.present(now);
+
+        if redraw_queued || output_state.animations_running {
+            fht.queue_redraw(output);
+        } else {
+            fht.send_frames(output);
+        }
+    }
+
+    pub fn dmabuf_imported(&mut self, dmabuf: &Dmabuf, notifier: ImportNotifier) {
+        crate::profile_function!();
+        if self.renderer.import_dmabuf(dmabuf, None).is_ok() {
+            let _ = notifier.successful::<State>();
+        } else {
+            notifier.failed();
+        }
+    }
+
+    pub fn set_output_mode(
+        &mut self,
+        fht: &mut Fht,
+        output: &Output,
+        mode: Mode,
+    ) -> anyhow::Result<()> {
+        let mode = validate_mode(mode);
+        if output.current_mode() == Some(mode) {
+            return Ok(());
+        }
+
+        let texture = self.create_texture(mode)?;
+        let Some(headless_output) = self.outputs.get_mut(output) else {
+            anyhow::bail!("Tried to set the mode of a non-headless output");
+        };
+
+        output.add_mode(mode);
+        output.change_current_state(Some(mode), None, None, None);
+        headless_output.texture = texture;
+        headless_output.damage_tracker = OutputDamageTracker::from_output(output);
+        if let Err(err) = EffectsFramebuffers::update_for_output(output, &mut self.renderer) {
+            error!(?err, "Failed to update output effects framebuffers");
+        }
+
+        let output_state = fht.output_state.get_mut(output).unwrap();
+        output_state.frame_clock = FrameClock::new(Some(refresh_interval(mode)));
+
+        Ok(())
+    }
+
+    pub fn renderer(&mut self) -> &mut GlowRenderer {
+        &mut self.renderer
+    }
+}
+
+/// Fall back to 60Hz if the refresh rate of `mode` is invalid, since there's no display to
+/// reject it.
+fn validate_mode(mut mode: Mode) -> Mode {
+    if mode.refresh <= 0 {
+        warn!(
+            refresh = mode.refresh,
+            "Invalid headless output refresh rate, using 60Hz"
+        );
+        mode.refresh = 60_000;
+    }
+    mode
+}
+
+fn refresh_interval(mode: Mode) -> Duration {
+    Duration::from_secs_f64(1_000f64 / mode.refresh as f64)
+}
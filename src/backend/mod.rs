@@ -5,6 +5,8 @@ use smithay::output::Output;
 
 use crate::state::Fht;
 
+#[cfg(feature = "headless-backend")]
+pub mod headless;
 #[cfg(feature = "udev-backend")]
 pub mod udev;
 #[cfg(feature = "winit-backend")]
@@ -15,6 +17,8 @@ pub enum Backend {
     Winit(winit::WinitData),
     #[cfg(feature = "udev-backend")]
     Udev(udev::UdevData),
+    #[cfg(feature = "headless-backend")]
+    Headless(headless::HeadlessData),
 }
 
 #[cfg(feature = "winit-backend")]
@@ -31,6 +35,13 @@ impl From<udev::UdevData> for Backend {
     }
 }
 
+#[cfg(feature = "headless-backend")]
+impl From<headless::HeadlessData> for Backend {
+    fn from(value: headless::HeadlessData) -> Self {
+        Self::Headless(value)
+    }
+}
+
 impl Backend {
     #[cfg(feature = "winit-backend")]
     pub fn winit(&mut self) -> &mut winit::WinitData {
@@ -50,6 +61,15 @@ impl Backend {
         unreachable!("Tried to get udev backend data on non-udev backend")
     }
 
+    #[cfg(feature = "headless-backend")]
+    pub fn headless(&mut self) -> &mut headless::HeadlessData {
+        #[allow(irrefutable_let_patterns)]
+        if let Self::Headless(data) = self {
+            return data;
+        }
+        unreachable!("Tried to get headless backend data on non-headless backend")
+    }
+
     pub fn render(
         &mut self,
         #[allow(unused)] fht: &mut Fht,
@@ -63,6 +83,9 @@ impl Backend {
             #[cfg(feature = "udev-backend")]
             #[allow(irrefutable_let_patterns)]
             Self::Udev(data) => data.render(fht, output, target_presentation_time),
+            #[cfg(feature = "headless-backend")]
+            #[allow(irrefutable_let_patterns)]
+            Self::Headless(data) => data.render(fht, output, target_presentation_time),
             #[allow(unreachable_patterns)]
             _ => unreachable!(),
         }
@@ -86,11 +109,48 @@ impl Backend {
                 use crate::renderer::AsGlowRenderer;
                 f(renderer.glow_renderer_mut())
             }
+            #[cfg(feature = "headless-backend")]
+            #[allow(irrefutable_let_patterns)]
+            Self::Headless(data) => f(data.renderer()),
             #[allow(unreachable_patterns)]
             _ => unreachable!(),
         }
     }
 
+    pub fn reload_dmabuf_formats(&mut self, #[allow(unused)] fht: &mut Fht) {
+        match self {
+            #[cfg(feature = "winit-backend")]
+            #[allow(irrefutable_let_patterns)]
+            // The nested backend is only used for testing, restrictions apply on restart.
+            Self::Winit(_) => (),
+            #[cfg(feature = "udev-backend")]
+            #[allow(irrefutable_let_patterns)]
+            Self::Udev(data) => data.reload_dmabuf_formats(fht),
+            #[cfg(feature = "headless-backend")]
+            #[allow(irrefutable_let_patterns)]
+            Self::Headless(_) => (),
+        }
+    }
+
+    pub fn set_render_node(
+        &mut self,
+        #[allow(unused)] fht: &mut Fht,
+        #[allow(unused)] path: &std::path::Path,
+    ) -> anyhow::Result<()> {
+        match self {
+            #[cfg(feature = "winit-backend")]
+            #[allow(irrefutable_let_patterns)]
+            // We render with whatever the parent compositor gave us.
+            Self::Winit(_) => anyhow::bail!("Can't switch render node under winit"),
+            #[cfg(feature = "udev-backend")]
+            #[allow(irrefutable_let_patterns)]
+            Self::Udev(data) => data.set_render_node(fht, path),
+            #[cfg(feature = "headless-backend")]
+            #[allow(irrefutable_let_patterns)]
+            Self::Headless(_) => anyhow::bail!("Can't switch render node on headless"),
+        }
+    }
+
     pub fn set_output_mode(
         &mut self,
         fht: &mut Fht,
@@ -105,6 +165,9 @@ impl Backend {
             #[cfg(feature = "udev-backend")]
             #[allow(irrefutable_let_patterns)]
             Self::Udev(data) => data.set_output_mode(fht, output, mode),
+            #[cfg(feature = "headless-backend")]
+            #[allow(irrefutable_let_patterns)]
+            Self::Headless(data) => data.set_output_mode(fht, output, mode),
         }
     }
 }
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use std::io;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use anyhow::Context as _;
@@ -8,7 +8,7 @@ use libc::dev_t;
 use smithay::backend::allocator::dmabuf::Dmabuf;
 use smithay::backend::allocator::format::FormatSet;
 use smithay::backend::allocator::gbm::GbmAllocator;
-use smithay::backend::allocator::Fourcc;
+use smithay::backend::allocator::{Buffer as _, Fourcc};
 use smithay::backend::drm::compositor::{FrameFlags, PrimaryPlaneElement, RenderFrameError};
 use smithay::backend::drm::output::{DrmOutput, DrmOutputManager, DrmOutputRenderElements};
 use smithay::backend::drm::{
@@ -18,16 +18,13 @@ use smithay::backend::drm::{
 use smithay::backend::egl::{EGLDevice, EGLDisplay};
 use smithay::backend::input::InputEvent;
 use smithay::backend::libinput::{LibinputInputBackend, LibinputSessionInterface};
-use smithay::backend::renderer::damage::{Error as OutputDamageTrackerError, OutputDamageTracker};
-use smithay::backend::renderer::element::solid::SolidColorRenderElement;
-use smithay::backend::renderer::element::{Element, Id, Kind};
+use smithay::backend::renderer::damage::Error as OutputDamageTrackerError;
 use smithay::backend::renderer::glow::GlowRenderer;
 use smithay::backend::renderer::multigpu::gbm::GbmGlesBackend;
 use smithay::backend::renderer::multigpu::{
     Error as MultiError, GpuManager, MultiFrame, MultiRenderer, MultiTexture, MultiTextureMapping,
 };
-use smithay::backend::renderer::utils::CommitCounter;
-use smithay::backend::renderer::{Color32F, ImportDma, ImportEgl, ImportMemWl};
+use smithay::backend::renderer::{ImportDma, ImportEgl, ImportMemWl};
 use smithay::backend::session::libseat::LibSeatSession;
 use smithay::backend::session::{Event as SessionEvent, Session};
 use smithay::backend::udev::{self, UdevBackend, UdevEvent};
@@ -46,24 +43,23 @@ use smithay::reexports::drm::{self, Device as _};
 use smithay::reexports::gbm::{BufferObjectFlags, Device as GbmDevice};
 use smithay::reexports::input::{DeviceCapability, Libinput};
 use smithay::reexports::rustix::fs::OFlags;
+use smithay::reexports::wayland_protocols::wp::content_type::v1::server::wp_content_type_v1::Type as ContentType;
 use smithay::reexports::wayland_protocols::wp::linux_dmabuf::zv1::server::zwp_linux_dmabuf_feedback_v1;
 use smithay::reexports::wayland_protocols::wp::presentation_time::server::wp_presentation_feedback;
 use smithay::reexports::wayland_server::backend::GlobalId;
 use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
-use smithay::utils::{DeviceFd, Monotonic, Scale};
+use smithay::utils::{DeviceFd, Monotonic};
 use smithay::wayland::dmabuf::{DmabufFeedbackBuilder, DmabufGlobal, ImportNotifier};
 use smithay::wayland::drm_lease::{DrmLease, DrmLeaseState};
 use smithay::wayland::drm_syncobj::{supports_syncobj_eventfd, DrmSyncobjState};
-use smithay::wayland::pointer_gestures::PointerGesturesState;
 use smithay::wayland::presentation::Refresh;
-use smithay::wayland::relative_pointer::RelativePointerManagerState;
 use smithay_drm_extras::display_info;
 use smithay_drm_extras::drm_scanner::{DrmScanEvent, DrmScanner};
 
 use crate::frame_clock::FrameClock;
 use crate::output::{OutputSerial, RedrawState};
 use crate::renderer::blur::EffectsFramebuffers;
-use crate::renderer::{AsGlowRenderer, DebugRenderElement, FhtRenderElement, FhtRenderer};
+use crate::renderer::{AsGlowRenderer, FhtRenderElement, FhtRenderer};
 use crate::state::{Fht, State, SurfaceDmabufFeedback};
 use crate::utils::get_monotonic_time;
 
@@ -190,7 +186,7 @@ impl UdevData {
             .insert_source(libinput_backend, move |mut event, _, state| {
                 if let InputEvent::DeviceAdded { device } = &mut event {
                     if device.has_capability(DeviceCapability::Keyboard) {
-                        let led_state = state.fht.keyboard.led_state();
+                        let led_state = state.fht.seat.get_keyboard().unwrap().led_state();
                         device.led_update(led_state.into());
                     }
 
@@ -242,6 +238,24 @@ impl UdevData {
                         if let Err(err) = device.drm_output_manager.device_mut().reset_state() {
                             warn!(?err, "Failed to reset drm surface state");
                         }
+                        // Whoever had the DRM device while we were paused could have changed the
+                        // gamma ramps.
+                        for (crtc, surface) in &device.surfaces {
+                            let Some(path) = &surface.icc_profile else {
+                                continue;
+                            };
+                            if let Err(err) = set_calibration_curves(
+                                device.drm_output_manager.device(),
+                                *crtc,
+                                Some(path),
+                            ) {
+                                warn!(
+                                    ?err,
+                                    output = surface.output.name(),
+                                    "Failed to apply ICC profile"
+                                );
+                            }
+                        }
                     }
 
                     state.fht.queue_redraw_all();
@@ -253,23 +267,23 @@ impl UdevData {
 
         let gpu_manager = GpuManager::new(gpu_manager).expect("Failed to initialize GPU manager!");
 
-        let (primary_gpu, primary_node) = if let Some(user_path) = &state.config.debug.render_node {
-            let primary_gpu = DrmNode::from_path(user_path)
-                .unwrap_or_else(|_| {
-                    panic!(
-                        "Please make sure that {} is a valid DRM node!",
-                        user_path.display()
-                    )
-                })
-                .node_with_type(NodeType::Render)
-                .expect("Please make sure that {user_path} is a render node!")
-                .expect("Please make sure that {user_path} is a render node!");
-            let primary_node = primary_gpu
-                .node_with_type(NodeType::Primary)
-                .expect("Unable to get primary node from primary gpu node!")
-                .expect("Unable to get primary node from primary gpu node!");
-
-            (primary_gpu, primary_node)
+        let configured_render_node = state.config.backend.render_node.as_ref();
+        let configured_render_node =
+            configured_render_node.or(state.config.debug.render_node.as_ref());
+        let configured_nodes =
+            configured_render_node.and_then(|user_path| match configured_gpu_nodes(user_path) {
+                Ok(nodes) => Some(nodes),
+                Err(err) => {
+                    warn!(
+                        ?err,
+                        path = %user_path.display(),
+                        "Invalid render node, falling back to the default one"
+                    );
+                    None
+                }
+            });
+        let (primary_gpu, primary_node) = if let Some(nodes) = configured_nodes {
+            nodes
         } else {
             let primary_node = udev::primary_gpu(&seat_name)
                 .unwrap()
@@ -316,27 +330,21 @@ impl UdevData {
                 keyboard_config.repeat_delay,
                 keyboard_config.repeat_rate,
             );
-            let keyboard = match res {
-                Ok(k) => k,
-                Err(err) => {
-                    error!(?err, "Failed to add keyboard! Falling back to defaults");
-                    new_seat
-                        .add_keyboard(
-                            XkbConfig::default(),
-                            keyboard_config.repeat_delay,
-                            keyboard_config.repeat_rate,
-                        )
-                        .expect("The keyboard is not keyboarding")
-                }
-            };
-            let pointer = new_seat.add_pointer();
+            if let Err(err) = res {
+                error!(?err, "Failed to add keyboard! Falling back to defaults");
+                new_seat
+                    .add_keyboard(
+                        XkbConfig::default(),
+                        keyboard_config.repeat_delay,
+                        keyboard_config.repeat_rate,
+                    )
+                    .expect("The keyboard is not keyboarding");
+            }
+            new_seat.add_pointer();
+            new_seat.add_touch();
 
             state.seat = new_seat;
-            state.keyboard = keyboard;
-            state.pointer = pointer;
         }
-        RelativePointerManagerState::new::<State>(&state.display_handle);
-        PointerGesturesState::new::<State>(&state.display_handle);
 
         for (device_id, path) in udev_dispatcher.as_source_ref().device_list() {
             if let Err(err) = data.device_added(device_id, path, state) {
@@ -352,18 +360,168 @@ impl UdevData {
         Ok(data)
     }
 
-    pub fn dmabuf_imported(&mut self, dmabuf: Dmabuf, notifier: ImportNotifier) {
-        if self
-            .gpu_manager
-            .single_renderer(&self.primary_gpu)
-            .and_then(|mut renderer| renderer.import_dmabuf(&dmabuf, None))
-            .is_ok()
-        {
-            dmabuf.set_node(self.primary_gpu);
-            let _ = notifier.successful::<State>();
-        } else {
+    pub fn dmabuf_imported(
+        &mut self,
+        dmabuf: Dmabuf,
+        notifier: ImportNotifier,
+        config: &fht_compositor_config::Debug,
+    ) {
+        if !crate::renderer::dmabuf::is_format_allowed(dmabuf.format(), config) {
+            // Clients should only use formats we advertised, but some just don't care.
+            debug!(format = ?dmabuf.format(), "Refusing to import disallowed dmabuf format");
             notifier.failed();
+            return;
+        }
+
+        // Clients can render on any GPU, for example games running on the discrete GPU of hybrid
+        // laptops, and their buffers might not be importable on the primary GPU. Try it first
+        // since it's the one rendering, the GPU manager takes care of copying buffers from the
+        // node they got imported on.
+        let mut render_nodes = vec![self.primary_gpu];
+        for device in self.devices.values() {
+            if !render_nodes.contains(&device.render_node) {
+                render_nodes.push(device.render_node);
+            }
+        }
+
+        for node in render_nodes {
+            if self
+                .gpu_manager
+                .single_renderer(&node)
+                .and_then(|mut renderer| renderer.import_dmabuf(&dmabuf, None))
+                .is_ok()
+            {
+                dmabuf.set_node(node);
+                let _ = notifier.successful::<State>();
+                return;
+            }
+        }
+
+        notifier.failed();
+    }
+
+    /// Re-create the dmabuf global and surface feedbacks after the DMA-BUF restrictions changed.
+    ///
+    /// The old global is first disabled, then destroyed after some time, to give clients a chance
+    /// to bind the new global and renegotiate their buffers.
+    pub fn reload_dmabuf_formats(&mut self, fht: &mut Fht) {
+        crate::profile_function!();
+        let Some(old_global) = self.dmabuf_global.take() else {
+            // We did not add our primary node yet.
+            return;
+        };
+
+        let mut renderer = match self.gpu_manager.single_renderer(&self.primary_gpu) {
+            Ok(renderer) => renderer,
+            Err(err) => {
+                warn!(
+                    ?err,
+                    "Failed to get primary renderer to reload dmabuf formats"
+                );
+                self.dmabuf_global = Some(old_global);
+                return;
+            }
+        };
+        let dmabuf_formats =
+            crate::renderer::dmabuf::filter_formats(&renderer.dmabuf_formats(), &fht.config.debug);
+        drop(renderer);
+        let default_feedback =
+            match DmabufFeedbackBuilder::new(self.primary_gpu.dev_id(), dmabuf_formats).build() {
+                Ok(feedback) => feedback,
+                Err(err) => {
+                    warn!(?err, "Failed to create dmabuf feedback");
+                    self.dmabuf_global = Some(old_global);
+                    return;
+                }
+            };
+
+        let global = fht
+            .dmabuf_state
+            .create_global_with_default_feedback::<State>(&fht.display_handle, &default_feedback);
+        self.dmabuf_global = Some(global);
+        fht.dmabuf_state.disable_global::<State>(&old_global);
+        fht.loop_handle
+            .insert_source(
+                Timer::from_duration(Duration::from_secs(10)),
+                move |_time, _, state| {
+                    state
+                        .fht
+                        .dmabuf_state
+                        .destroy_global::<State>(&state.fht.display_handle, old_global.clone());
+                    TimeoutAction::Drop
+                },
+            )
+            .expect("Failed to insert dmabuf global removal timer!");
+
+        for device in self.devices.values_mut() {
+            for surface in device.surfaces.values_mut() {
+                surface.dmabuf_feedback = surface.drm_output.with_compositor(|compositor| {
+                    get_surface_dmabuf_feedback(
+                        self.primary_gpu,
+                        surface.render_node,
+                        &mut self.gpu_manager,
+                        compositor.surface(),
+                        &fht.config.debug,
+                    )
+                });
+            }
         }
+
+        // Send the new feedbacks to the clients.
+        fht.queue_redraw_all();
+    }
+
+    /// Switch the primary GPU, that renders every output, to this render node.
+    ///
+    /// The GPU must already be in use by the compositor. Client buffers get imported again on the
+    /// new GPU when needed.
+    pub fn set_render_node(&mut self, fht: &mut Fht, path: &Path) -> anyhow::Result<()> {
+        let render_node = DrmNode::from_path(path)
+            .with_context(|| format!("{} is not a valid DRM node", path.display()))?
+            .node_with_type(NodeType::Render)
+            .context("DRM node has no render node")?
+            .context("Failed to get render node")?;
+        if render_node == self.primary_gpu {
+            return Ok(());
+        }
+
+        let Some(primary_node) = self
+            .devices
+            .iter()
+            .find(|(_, device)| device.render_node == render_node)
+            .map(|(device_node, _)| *device_node)
+        else {
+            anyhow::bail!("{} is not a GPU used by the compositor", path.display());
+        };
+
+        let mut renderer = self
+            .gpu_manager
+            .single_renderer(&render_node)
+            .context("Failed to create renderer")?;
+        crate::renderer::init(renderer.glow_renderer_mut());
+        fht.shm_state.update_formats(renderer.shm_formats());
+        // Our effect buffers are textures, they must live on the GPU that renders.
+        for output in fht.space.outputs() {
+            if let Err(err) = EffectsFramebuffers::update_for_output(output, &mut renderer) {
+                warn!(
+                    ?err,
+                    output = output.name(),
+                    "Failed to update effect buffers"
+                );
+            }
+        }
+        drop(renderer);
+        // The closing tiles are textures too, and since they only live for the duration of their
+        // animation, drop them instead. The egui elements notice the renderer change by themselves.
+        fht.space.discard_closing_tiles();
+
+        info!(?render_node, "Switching primary GPU");
+        self.primary_gpu = render_node;
+        self.primary_node = primary_node;
+        // Clients should now allocate their buffers on the new GPU.
+        self.reload_dmabuf_formats(fht);
+
+        Ok(())
     }
 
     // Early import this [`WlSurface`] to the [`GpuManager`]
@@ -467,7 +625,10 @@ impl UdevData {
             }
 
             // Init dmabuf support with format list from our primary gpu
-            let dmabuf_formats = renderer.dmabuf_formats();
+            let dmabuf_formats = crate::renderer::dmabuf::filter_formats(
+                &renderer.dmabuf_formats(),
+                &fht.config.debug,
+            );
             let default_feedback = DmabufFeedbackBuilder::new(device_node.dev_id(), dmabuf_formats)
                 .build()
                 .context("Failed to create dmabuf feedback")?;
@@ -489,6 +650,7 @@ impl UdevData {
                                 surface.render_node,
                                 &mut self.gpu_manager,
                                 compositor.surface(),
+                                &fht.config.debug,
                             )
                         })
                     });
@@ -641,17 +803,13 @@ impl UdevData {
         debug!(?crtc, ?output_name, "Trying to setup connector");
         let drm_device = device.drm_output_manager.device();
 
-        let non_desktop = match get_property_val(drm_device, connector.handle(), "non-desktop") {
-            Ok((ty, val)) => ty.convert_value(val).as_boolean().unwrap_or(false),
-            Err(err) => {
-                warn!(
-                    ?crtc,
-                    ?err,
-                    "Failed to get non-desktop property for connector, defaulting to false."
-                );
-                false
-            }
-        };
+        // The kernel detects most headsets from their EDID, but let the user force it.
+        let non_desktop = is_non_desktop(drm_device, connector.handle())
+            || fht
+                .config
+                .outputs
+                .get(&output_name)
+                .is_some_and(|config| config.leasable);
 
         let info = display_info::for_connector(drm_device, connector.handle());
         let make = info
@@ -707,6 +865,9 @@ impl UdevData {
                     get_matching_mode(modes, width, height, refresh).unwrap_or(requested_mode);
                 custom_mode = get_custom_mode(width, height, refresh);
             }
+            if let Some(modeline) = &output_config.modeline {
+                custom_mode = get_modeline_mode(modeline);
+            }
 
             if let Some(transform) = output_config.transform {
                 new_transform = Some(transform.into());
@@ -771,11 +932,12 @@ impl UdevData {
             .context("failed to query crtc planes")?;
 
         // Using an overlay plane on a nvidia card breaks
-        if driver
-            .name()
-            .to_string_lossy()
-            .to_lowercase()
-            .contains("nvidia")
+        if fht.config.debug.disable_overlay_planes
+            || driver
+                .name()
+                .to_string_lossy()
+                .to_lowercase()
+                .contains("nvidia")
             || driver
                 .description()
                 .to_string_lossy()
@@ -861,9 +1023,23 @@ impl UdevData {
                 device.render_node,
                 &mut self.gpu_manager,
                 compositor.surface(),
+                &fht.config.debug,
             )
         });
 
+        let icc_profile = fht
+            .config
+            .outputs
+            .get(&output_name)
+            .and_then(|config| config.icc_profile.clone());
+        if let Some(path) = &icc_profile {
+            if let Err(err) =
+                set_calibration_curves(device.drm_output_manager.device(), crtc, Some(path))
+            {
+                warn!(?err, ?output_name, "Failed to apply ICC profile");
+            }
+        }
+
         let surface = Surface {
             render_node: device.render_node,
             connector: connector.handle(),
@@ -871,6 +1047,8 @@ impl UdevData {
             output_global,
             drm_output,
             dmabuf_feedback,
+            vrr_enabled: false,
+            icc_profile,
         };
 
         fht.queue_redraw(&surface.output);
@@ -986,20 +1164,38 @@ impl UdevData {
         };
 
         let mut output_elements_result = fht.output_elements(&mut renderer, output);
-
-        // To render damage we just use solid color elements,
-        if fht.config.debug.draw_damage {
-            let state = fht.output_state.get_mut(output).unwrap();
-            draw_damage(
-                output,
-                &mut state.debug_damage_tracker,
-                &mut output_elements_result.elements,
-            );
+        fht.draw_debug_elements(&mut renderer, output, &mut output_elements_result.elements);
+
+        let vrr = fht.output_wants_vrr(output);
+        if vrr != surface.vrr_enabled {
+            if let Err(err) = surface
+                .drm_output
+                .with_compositor(|compositor| compositor.use_vrr(vrr))
+            {
+                warn!(?err, output = output.name(), "Failed to toggle VRR");
+            }
+            // Even if we failed, don't retry on every frame.
+            surface.vrr_enabled = vrr;
         }
 
-        if fht.config.debug.draw_opaque_regions {
-            let scale = output.current_scale().integer_scale() as f64;
-            draw_opaque_regions(&mut output_elements_result.elements, scale.into());
+        // Fullscreen windows that opted into tearing get asynchronous page flips.
+        let mut frame_flags = FrameFlags::DEFAULT;
+        if fht.output_allows_tearing(output) {
+            frame_flags |= FrameFlags::ALLOW_TEARING;
+        }
+        // Fullscreen games and videos should be scanned out directly when possible, even if they
+        // don't exactly match the output mode.
+        if matches!(
+            fht.output_fullscreen_content_type(output),
+            Some(ContentType::Game | ContentType::Video)
+        ) {
+            frame_flags |= FrameFlags::ALLOW_PRIMARY_PLANE_SCANOUT_ANY;
+        }
+        if fht.config.debug.disable_direct_scanout {
+            frame_flags.remove(
+                FrameFlags::ALLOW_PRIMARY_PLANE_SCANOUT
+                    | FrameFlags::ALLOW_PRIMARY_PLANE_SCANOUT_ANY,
+            );
         }
 
         // Renderand check for damage.
@@ -1010,7 +1206,7 @@ impl UdevData {
                 &output_elements_result.elements,
                 [0.1, 0.1, 0.1, 1.0],
                 // TODO: Add debug options to allow to change this?
-                FrameFlags::DEFAULT,
+                frame_flags,
             )
             .map_err(|err| match err {
                 RenderFrameError::PrepareFrame(err) => SwapBuffersError::from(err),
@@ -1050,7 +1246,8 @@ impl UdevData {
                     },
                 };
             }
-            Ok(res) => {
+            Ok(mut res) => {
+                output_elements_result.merge_offscreen_states(&mut res.states);
                 if res.needs_sync() {
                     if let PrimaryPlaneElement::Swapchain(element) = &res.primary_element {
                         crate::profile_scope!("SyncPoint::wait");
@@ -1061,6 +1258,7 @@ impl UdevData {
                 }
 
                 fht.update_primary_scanout_output(output, &res.states);
+                fht.record_debug_element_states(output, &res.states);
                 if let Some(dmabuf_feedback) = surface.dmabuf_feedback.as_ref() {
                     fht.send_dmabuf_feedbacks(output, dmabuf_feedback, &res.states);
                 }
@@ -1102,7 +1300,9 @@ impl UdevData {
                             output_state.current_frame_sequence =
                                 output_state.current_frame_sequence.wrapping_add(1);
                             // Also notify tracy of a new frame.
-                            tracy_client::Client::running().unwrap().frame_mark();
+                            if crate::profiling::is_profiling() {
+                                tracy_client::Client::running().unwrap().frame_mark();
+                            }
 
                             // Damage also means screencast.
                             #[cfg(feature = "xdg-screencast-portal")]
@@ -1149,6 +1349,7 @@ impl UdevData {
             RedrawState::Idle => unreachable!(),
             RedrawState::Queued => (),
             RedrawState::WaitingForVblank { .. } => unreachable!(),
+            RedrawState::WaitingForRenderDeadline { .. } => unreachable!(),
             RedrawState::WaitingForEstimatedVblankTimer { token, .. } => {
                 output_state.redraw_state = RedrawState::WaitingForEstimatedVblankTimer {
                     token,
@@ -1353,15 +1554,30 @@ impl UdevData {
                     continue;
                 };
 
-                if output_config.disable {
+                if output_config.disable || output_config.leasable {
                     fht.output_management_manager_state
                         .set_head_enabled::<State>(&surface.output, false);
                     to_disable.push((node, connector.clone(), crtc));
+                    if output_config.leasable && !output_config.disable {
+                        // Reconnect it as a non-desktop connector to offer it for leasing.
+                        to_enable.push((node, connector.clone(), crtc));
+                    }
                     continue;
                 }
                 fht.output_management_manager_state
                     .set_head_enabled::<State>(&surface.output, true);
 
+                if surface.icc_profile != output_config.icc_profile {
+                    if let Err(err) = set_calibration_curves(
+                        device.drm_output_manager.device(),
+                        crtc,
+                        output_config.icc_profile.as_deref(),
+                    ) {
+                        warn!(?err, ?output_name, "Failed to apply ICC profile");
+                    }
+                    surface.icc_profile = output_config.icc_profile.clone();
+                }
+
                 // Sometimes DRM connectors can have custom modes.
                 // ---
                 // The user specifies one, for example 1920x1080@165 and we build a new DrmMode out
@@ -1376,6 +1592,9 @@ impl UdevData {
                         get_matching_mode(modes, width, height, refresh).unwrap_or(requested_mode);
                     custom_mode = get_custom_mode(width, height, refresh);
                 }
+                if let Some(modeline) = &output_config.modeline {
+                    custom_mode = get_modeline_mode(modeline);
+                }
 
                 let new_mode = custom_mode.unwrap_or(requested_mode);
 
@@ -1435,6 +1654,34 @@ impl UdevData {
                 fht.output_resized(&surface.output);
             }
 
+            for &(handle, crtc) in &device.non_desktop_connectors {
+                let Some(connector) = device.drm_scanner.connectors().get(&handle) else {
+                    continue;
+                };
+                let output_name = format!(
+                    "{}-{}",
+                    connector.interface().as_str(),
+                    connector.interface_id()
+                );
+                let output_config = fht
+                    .config
+                    .outputs
+                    .get(&output_name)
+                    .cloned()
+                    .unwrap_or_default();
+                if output_config.leasable
+                    || is_non_desktop(device.drm_output_manager.device(), handle)
+                {
+                    continue;
+                }
+
+                // The user stopped forcing it as leasable, give it back to the desktop.
+                to_disable.push((node, connector.clone(), crtc));
+                if !output_config.disable {
+                    to_enable.push((node, connector.clone(), crtc));
+                }
+            }
+
             for (connector, crtc) in device.drm_scanner.crtcs() {
                 if connector.state() != connector::State::Connected {
                     continue;
@@ -1616,6 +1863,9 @@ pub struct Surface {
         DrmDeviceFd,
     >,
     dmabuf_feedback: Option<SurfaceDmabufFeedback>,
+    vrr_enabled: bool,
+    /// The ICC profile whose calibration curves are loaded in the CRTC gamma ramps.
+    icc_profile: Option<PathBuf>,
 }
 
 fn get_surface_dmabuf_feedback(
@@ -1623,9 +1873,12 @@ fn get_surface_dmabuf_feedback(
     render_node: DrmNode,
     gpus: &mut GpuManager<GbmGlesBackend<GlowRenderer, DrmDeviceFd>>,
     surface: &DrmSurface,
+    config: &fht_compositor_config::Debug,
 ) -> Option<SurfaceDmabufFeedback> {
     let primary_formats = gpus.single_renderer(&primary_gpu).ok()?.dmabuf_formats();
+    let primary_formats = crate::renderer::dmabuf::filter_formats(&primary_formats, config);
     let render_formats = gpus.single_renderer(&render_node).ok()?.dmabuf_formats();
+    let render_formats = crate::renderer::dmabuf::filter_formats(&render_formats, config);
 
     let all_render_formats = primary_formats
         .iter()
@@ -1635,6 +1888,15 @@ fn get_surface_dmabuf_feedback(
 
     let planes = surface.planes().clone();
 
+    // Fullscreen windows can be scanned out on the primary plane, so we advertise formats that
+    // it supports in priority.
+    let primary_plane_formats = surface
+        .plane_info()
+        .formats
+        .intersection(&all_render_formats)
+        .copied()
+        .collect::<FormatSet>();
+
     // We limit the scan-out tranche to formats we can also render from
     // so that there is always a fallback render path available in case
     // the supplied buffer can not be scanned out directly
@@ -1657,11 +1919,22 @@ fn get_surface_dmabuf_feedback(
         .unwrap();
 
     let scanout_feedback = builder
+        .clone()
         .add_preference_tranche(
             surface.device_fd().dev_id().unwrap(),
             Some(zwp_linux_dmabuf_feedback_v1::TrancheFlags::Scanout),
             planes_formats,
         )
+        .add_preference_tranche(render_node.dev_id(), None, render_formats.clone())
+        .build()
+        .unwrap();
+
+    let fullscreen_feedback = builder
+        .add_preference_tranche(
+            surface.device_fd().dev_id().unwrap(),
+            Some(zwp_linux_dmabuf_feedback_v1::TrancheFlags::Scanout),
+            primary_plane_formats,
+        )
         .add_preference_tranche(render_node.dev_id(), None, render_formats)
         .build()
         .unwrap();
@@ -1669,89 +1942,58 @@ fn get_surface_dmabuf_feedback(
     Some(SurfaceDmabufFeedback {
         render_feedback,
         scanout_feedback,
+        fullscreen_feedback,
     })
 }
 
-const DAMAGE_COLOR: Color32F = Color32F::new(0.3, 0.0, 0.0, 0.3);
-const OPAQUE_REGION_COLOR: Color32F = Color32F::new(0.0, 0.0, 0.3, 0.3);
-const SEMITRANSPARENT_COLOR: Color32F = Color32F::new(0.0, 0.3, 0.0, 0.3);
-
-fn draw_damage<R: FhtRenderer>(
-    output: &Output,
-    dt: &mut Option<OutputDamageTracker>,
-    elements: &mut Vec<FhtRenderElement<R>>,
-) {
-    let dt = dt.get_or_insert_with(|| OutputDamageTracker::from_output(output));
-    let Ok((Some(damage), _)) = dt.damage_output(1, elements) else {
-        return;
+/// Load the calibration curves of an ICC profile into the gamma ramps of a CRTC.
+///
+/// Without a profile, the gamma ramps are reset to be linear.
+fn set_calibration_curves(
+    device: &impl drm::control::Device,
+    crtc: CrtcHandle,
+    icc_profile: Option<&Path>,
+) -> anyhow::Result<()> {
+    let size = device
+        .get_crtc(crtc)
+        .context("Failed to get CRTC info")?
+        .gamma_length() as usize;
+    anyhow::ensure!(size > 0, "CRTC does not support gamma ramps");
+
+    let [red, green, blue] = match icc_profile {
+        Some(path) => crate::utils::icc::load_calibration_curves(path, size)?,
+        None => crate::utils::icc::linear_ramps(size),
     };
-
-    for damage_rect in damage {
-        let damage_element: DebugRenderElement = SolidColorRenderElement::new(
-            Id::new(),
-            *damage_rect,
-            CommitCounter::default(),
-            DAMAGE_COLOR,
-            Kind::Unspecified,
-        )
-        .into();
-        elements.insert(0, damage_element.into())
-    }
+    device
+        .set_gamma(crtc, &red, &green, &blue)
+        .context("Failed to set gamma ramps")
 }
 
-pub fn draw_opaque_regions<R: FhtRenderer>(
-    elements: &mut Vec<FhtRenderElement<R>>,
-    scale: Scale<f64>,
-) {
-    crate::profile_function!();
-
-    let mut i = 0;
-    while i < elements.len() {
-        let elem = &elements[i];
-        i += 1;
-
-        // HACK
-        if format!("{elem:?}").contains("ExtraDamage") {
-            continue;
-        }
-
-        let geo = elem.geometry(scale);
-        let mut opaque = elem.opaque_regions(scale).to_vec();
-
-        for rect in &mut opaque {
-            rect.loc += geo.loc;
-        }
-
-        let semitransparent = geo.subtract_rects(opaque.iter().copied());
-
-        for rect in opaque {
-            let color = SolidColorRenderElement::new(
-                Id::new(),
-                rect,
-                CommitCounter::default(),
-                OPAQUE_REGION_COLOR,
-                Kind::Unspecified,
-            );
-            elements.insert(
-                i - 1,
-                FhtRenderElement::Debug(DebugRenderElement::Solid(color)),
-            );
-            i += 1;
-        }
+/// Get the render and primary nodes of the GPU at `path`, from the configuration.
+fn configured_gpu_nodes(path: &Path) -> anyhow::Result<(DrmNode, DrmNode)> {
+    let node = DrmNode::from_path(path).context("Not a valid DRM node")?;
+    let render_node = node
+        .node_with_type(NodeType::Render)
+        .context("DRM node has no render node")?
+        .context("Failed to get render node")?;
+    let primary_node = node
+        .node_with_type(NodeType::Primary)
+        .context("DRM node has no primary node")?
+        .context("Failed to get primary node")?;
+    Ok((render_node, primary_node))
+}
 
-        for rect in semitransparent {
-            let color = SolidColorRenderElement::new(
-                Id::new(),
-                rect,
-                CommitCounter::default(),
-                SEMITRANSPARENT_COLOR,
-                Kind::Unspecified,
-            );
-            elements.insert(
-                i - 1,
-                FhtRenderElement::Debug(DebugRenderElement::Solid(color)),
+/// Whether the kernel marked this connector as a non-desktop display, like a VR headset.
+fn is_non_desktop(device: &impl drm::control::Device, connector: connector::Handle) -> bool {
+    match get_property_val(device, connector, "non-desktop") {
+        Ok((ty, val)) => ty.convert_value(val).as_boolean().unwrap_or(false),
+        Err(err) => {
+            warn!(
+                ?connector,
+                ?err,
+                "Failed to get non-desktop property for connector, defaulting to false."
             );
-            i += 1;
+            false
         }
     }
 }
@@ -1889,12 +2131,7 @@ fn get_custom_mode(width: u16, height: u16, refresh: Option<f64>) -> Option<drm:
     let hsync_end = hsync_start + timing.h_sync;
     let vsync_end = vsync_start + timing.v_sync;
 
-    let name = unsafe {
-        let mut name = format!("{width}x{height}@{}", refresh.unwrap_or(60.0)).into_bytes();
-        name.resize(32, ' ' as u8);
-        let name = &*(name.as_slice() as *const [u8] as *const [i8]);
-        name.try_into().ok()?
-    };
+    let name = mode_name(format!("{width}x{height}@{}", refresh.unwrap_or(60.0)))?;
     let mode_info = drm_ffi::drm_mode_modeinfo {
         clock: (timing.act_pixel_freq * 1000.).round() as u32,
         hdisplay: width,
@@ -1915,3 +2152,56 @@ fn get_custom_mode(width: u16, height: u16, refresh: Option<f64>) -> Option<drm:
 
     Some(mode_info.into())
 }
+
+fn get_modeline_mode(modeline: &fht_compositor_config::Modeline) -> Option<drm::control::Mode> {
+    let clock = (modeline.clock * 1000.).round() as u32; // in kHz
+    let refresh = clock as f64 * 1000. / (modeline.htotal as f64 * modeline.vtotal as f64);
+    let name = mode_name(format!(
+        "{}x{}@{refresh:.2}",
+        modeline.hdisplay, modeline.vdisplay
+    ))?;
+
+    let mut flags = if modeline.hsync_positive {
+        drm_ffi::DRM_MODE_FLAG_PHSYNC
+    } else {
+        drm_ffi::DRM_MODE_FLAG_NHSYNC
+    };
+    flags |= if modeline.vsync_positive {
+        drm_ffi::DRM_MODE_FLAG_PVSYNC
+    } else {
+        drm_ffi::DRM_MODE_FLAG_NVSYNC
+    };
+    if modeline.interlace {
+        flags |= drm_ffi::DRM_MODE_FLAG_INTERLACE;
+    }
+
+    let mode_info = drm_ffi::drm_mode_modeinfo {
+        clock,
+        hdisplay: modeline.hdisplay,
+        hsync_start: modeline.hsync_start,
+        hsync_end: modeline.hsync_end,
+        htotal: modeline.htotal,
+        hskew: 0,
+        vdisplay: modeline.vdisplay,
+        vsync_start: modeline.vsync_start,
+        vsync_end: modeline.vsync_end,
+        vtotal: modeline.vtotal,
+        vscan: 0,
+        vrefresh: refresh.round() as u32,
+        flags,
+        type_: drm_ffi::DRM_MODE_TYPE_USERDEF,
+        name,
+    };
+
+    Some(mode_info.into())
+}
+
+fn mode_name(name: String) -> Option<[std::ffi::c_char; 32]> {
+    let mut name = name.into_bytes();
+    name.resize(32, b' ');
+    let name = name
+        .into_iter()
+        .map(|b| b as std::ffi::c_char)
+        .collect::<Vec<_>>();
+    name.try_into().ok()
+}
@@ -17,7 +17,6 @@ use smithay::wayland::dmabuf::{
 };
 
 use crate::output::RedrawState;
-use crate::renderer::OutputElementsResult;
 use crate::state::{Fht, State};
 
 pub struct WinitData {
@@ -116,7 +115,10 @@ impl WinitData {
 
         let dmabuf_default_feedback = match render_node {
             Ok(Some(node)) => {
-                let dmabuf_formats = backend.renderer().dmabuf_formats();
+                let dmabuf_formats = crate::renderer::dmabuf::filter_formats(
+                    &backend.renderer().dmabuf_formats(),
+                    &fht.config.debug,
+                );
                 let dmabuf_default_feedback =
                     DmabufFeedbackBuilder::new(node.dev_id(), dmabuf_formats)
                         .build()
@@ -145,7 +147,10 @@ impl WinitData {
                     );
                 (dmabuf_global, Some(default_feedback))
             } else {
-                let dmabuf_formats = backend.renderer().dmabuf_formats();
+                let dmabuf_formats = crate::renderer::dmabuf::filter_formats(
+                    &backend.renderer().dmabuf_formats(),
+                    &fht.config.debug,
+                );
                 let dmabuf_global = fht
                     .dmabuf_state
                     .create_global::<State>(&fht.display_handle, dmabuf_formats);
@@ -177,10 +182,20 @@ impl WinitData {
 
         let age = self.backend.buffer_age().unwrap_or(1);
         let res = self.backend.bind().and_then(|(renderer, mut fb)| {
-            let OutputElementsResult { ref elements, .. } =
-                fht.output_elements(renderer, &self.output);
+            let mut output_elements_result = fht.output_elements(renderer, &self.output);
+            fht.draw_debug_elements(renderer, &self.output, &mut output_elements_result.elements);
             self.damage_tracker
-                .render_output(renderer, &mut fb, age, elements, [0.1, 0.1, 0.1, 1.0])
+                .render_output(
+                    renderer,
+                    &mut fb,
+                    age,
+                    &output_elements_result.elements,
+                    [0.1, 0.1, 0.1, 1.0],
+                )
+                .map(|mut res| {
+                    output_elements_result.merge_offscreen_states(&mut res.states);
+                    res
+                })
                 .map_err(|err| match err {
                     OutputDamageTrackerError::Rendering(err) => err.into(),
                     _ => unreachable!(),
@@ -188,6 +203,7 @@ impl WinitData {
         })?;
 
         fht.update_primary_scanout_output(&self.output, &res.states);
+        fht.record_debug_element_states(&self.output, &res.states);
         // FIXME: Screencopy rendering
         // fht.render_screencopy_without_damage(&self.output, renderer, output_elements_result);
 
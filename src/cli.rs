@@ -18,9 +18,19 @@ pub struct Cli {
     /// What backend should the compositor start with?
     #[arg(short, long, value_name = "BACKEND")]
     pub backend: Option<BackendType>,
+    /// Run without any display, rendering to virtual outputs. Same as `--backend headless`.
+    #[arg(long, conflicts_with = "backend")]
+    #[cfg(feature = "headless-backend")]
+    pub headless: bool,
     /// The configuration path to use.
     #[arg(short, long, value_name = "PATH")]
     pub config_path: Option<PathBuf>,
+    /// The name of the Wayland socket to listen on, picked automatically if not set.
+    #[arg(long, value_name = "NAME")]
+    pub socket_name: Option<String>,
+    /// The path of the IPC socket to listen on.
+    #[arg(long, value_name = "PATH")]
+    pub ipc_socket: Option<PathBuf>,
     /// Whether to run `uwsm` to finalize the compositor environment.
     #[arg(long)]
     #[cfg(feature = "uwsm")]
@@ -29,12 +39,20 @@ pub struct Cli {
     pub command: Option<Command>,
 }
 
-#[derive(Debug, Clone, Copy, clap::Subcommand)]
+#[derive(Debug, Clone, clap::Subcommand)]
 pub enum Command {
     /// Check the compositor configuration for any errors.
     CheckConfiguration,
     /// Generate shell completions for shell
     GenerateCompletions { shell: clap_complete::Shell },
+    /// Send a request to the running compositor.
+    Ipc {
+        /// The path of the IPC socket, instead of reading it from `FHTC_SOCKET_PATH`.
+        #[arg(long, value_name = "PATH")]
+        socket: Option<PathBuf>,
+        #[command(subcommand)]
+        request: crate::ipc::Request,
+    },
 }
 
 #[derive(Debug, Clone, Copy, clap::ValueEnum)]
@@ -45,6 +63,9 @@ pub enum BackendType {
     #[cfg(feature = "udev-backend")]
     /// Use the Udev backend, using a libseat session.
     Udev,
+    #[cfg(feature = "headless-backend")]
+    /// Use the headless backend, rendering to virtual outputs.
+    Headless,
 }
 
 fn get_version_string() -> String {
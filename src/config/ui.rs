@@ -209,6 +209,7 @@ impl ConfigUi {
                 scale,
                 1.0,
                 Point::default(),
+                output.refresh_interval(),
                 |ctx| ui(ctx, &self.state),
             )
             .inspect_err(|err| warn!(?err, "Failed to render egui for config ui"))
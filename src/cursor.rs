@@ -1,6 +1,6 @@
 use std::cell::{Ref, RefCell};
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::fs::File;
 use std::io::Read;
@@ -27,6 +27,8 @@ use crate::renderer::FhtRenderer;
 pub struct CursorThemeManager {
     // Image cache is keyed by icon type and cursor scale.
     cursor_image_cache: RefCell<HashMap<(i32, CursorIcon), Image>>,
+    // Cursor icons that the theme doesn't have, to avoid looking them up every frame.
+    missing_cursor_icons: RefCell<HashSet<CursorIcon>>,
     image_status: CursorImageStatus,
     cursor_theme: CursorTheme,
     config: Cursor,
@@ -38,6 +40,7 @@ impl CursorThemeManager {
 
         Self {
             cursor_image_cache: RefCell::new(HashMap::default()),
+            missing_cursor_icons: RefCell::new(HashSet::default()),
             image_status: CursorImageStatus::default_named(),
             cursor_theme,
             config,
@@ -50,6 +53,7 @@ impl CursorThemeManager {
             self.config = new_config;
             self.cursor_theme = CursorTheme::load(&self.config.name);
             self.cursor_image_cache.borrow_mut().clear();
+            self.missing_cursor_icons.borrow_mut().clear();
         }
     }
 
@@ -67,23 +71,28 @@ impl CursorThemeManager {
         cursor_scale: i32,
     ) -> Result<Ref<'_, Image>, Error> {
         crate::profile_function!();
+        if self.missing_cursor_icons.borrow().contains(&cursor_icon) {
+            return Err(Error::NoCursorIcon(cursor_icon));
+        }
+
         if let Entry::Vacant(entry) = self
             .cursor_image_cache
             .borrow_mut()
             .entry((cursor_scale, cursor_icon))
         {
-            let icon_path = self
-                .cursor_theme
-                .load_icon(cursor_icon.name())
-                .or_else(|| {
-                    for alt_name in cursor_icon.alt_names() {
-                        if let Some(icon) = self.cursor_theme.load_icon(alt_name) {
-                            return Some(icon);
-                        }
+            let icon_path = self.cursor_theme.load_icon(cursor_icon.name()).or_else(|| {
+                for alt_name in cursor_icon.alt_names() {
+                    if let Some(icon) = self.cursor_theme.load_icon(alt_name) {
+                        return Some(icon);
                     }
-                    None
-                })
-                .ok_or(Error::NoCursorIcon(cursor_icon))?;
+                }
+                None
+            });
+            let Some(icon_path) = icon_path else {
+                warn!(?cursor_icon, "Cursor theme does not have cursor icon");
+                self.missing_cursor_icons.borrow_mut().insert(cursor_icon);
+                return Err(Error::NoCursorIcon(cursor_icon));
+            };
 
             let mut cursor_file = File::open(icon_path)?;
             let mut cursor_file_data = Vec::new();
@@ -173,7 +182,15 @@ impl CursorThemeManager {
                 )
             }
             CursorImageStatus::Named(cursor_icon) => {
-                let Ok(cursor_image) = self.load_cursor_image(cursor_icon, scale) else {
+                // Clients can request any cursor shape using cursor-shape-v1, but not every theme
+                // provides all of them. Fallback to the default cursor of the theme first.
+                let cursor_image = self.load_cursor_image(cursor_icon, scale).or_else(|err| {
+                    if cursor_icon == CursorIcon::Default {
+                        return Err(err);
+                    }
+                    self.load_cursor_image(CursorIcon::Default, scale)
+                });
+                let Ok(cursor_image) = cursor_image else {
                     return self.render_with_fallback_cursor_data(renderer, location, alpha);
                 };
                 let frame = cursor_image.get_frame(time);
@@ -7,6 +7,7 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use smithay::backend::allocator::Fourcc;
 use smithay::backend::renderer::element::texture::{TextureRenderBuffer, TextureRenderElement};
@@ -20,8 +21,18 @@ use crate::renderer::texture_element::FhtTextureElement;
 
 pub struct EguiElement {
     size: Size<i32, Logical>,
-    ctx: egui::Context,
-    render_buffer: Arc<Mutex<Option<(i32, TextureRenderBuffer<GlesTexture>)>>>,
+    ctx: RefCell<egui::Context>,
+    /// The texture we render into, along with the scale and the ID of the renderer it got created
+    /// with.
+    render_buffer: Arc<Mutex<Option<(i32, usize, TextureRenderBuffer<GlesTexture>)>>>,
+}
+
+impl std::fmt::Debug for EguiElement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EguiElement")
+            .field("size", &self.size)
+            .finish_non_exhaustive()
+    }
 }
 
 impl EguiElement {
@@ -33,8 +44,8 @@ impl EguiElement {
         }
     }
 
-    pub fn ctx(&self) -> &egui::Context {
-        &self.ctx
+    pub fn ctx(&self) -> egui::Context {
+        self.ctx.borrow().clone()
     }
 
     pub fn render(
@@ -43,6 +54,7 @@ impl EguiElement {
         scale: i32,
         alpha: f32,
         location: Point<i32, Physical>,
+        refresh_interval: Option<Duration>,
         ui: impl FnMut(&egui::Context),
     ) -> Result<EguiRenderElement, GlesError> {
         let size = self.size.to_physical(scale);
@@ -79,10 +91,20 @@ impl EguiElement {
             .unwrap();
         let painter = &mut *RefCell::borrow_mut(&painter);
 
+        let renderer_id = renderer.id();
         let render_buffer = &mut *self.render_buffer.lock().unwrap();
-        let _ = render_buffer.take_if(|(s, _)| *s != scale);
+        if render_buffer
+            .as_ref()
+            .is_some_and(|(_, id, _)| *id != renderer_id)
+        {
+            // The renderer changed, for example when switching the primary GPU. Our texture lives
+            // on the old GPU, and so do the textures egui uploaded to the old painter. egui only
+            // uploads its textures once, start over with a new context to get them again.
+            *self.ctx.borrow_mut() = egui::Context::default();
+        }
+        let _ = render_buffer.take_if(|(s, id, _)| *s != scale || *id != renderer_id);
         let render_buffer = match render_buffer.as_mut() {
-            Some((_, render_buffer)) => render_buffer,
+            Some((_, _, render_buffer)) => render_buffer,
             None => {
                 let render_texture: GlesTexture = renderer
                     .create_buffer(Fourcc::Abgr8888, buffer_size)
@@ -99,8 +121,8 @@ impl EguiElement {
                     None,
                 );
 
-                let render_buffer = render_buffer.insert((scale, texture_buffer));
-                &mut render_buffer.1
+                let render_buffer = render_buffer.insert((scale, renderer_id, texture_buffer));
+                &mut render_buffer.2
             }
         };
 
@@ -117,15 +139,17 @@ impl EguiElement {
                 min: egui::pos2(0.0, 0.0),
                 max: egui::pos2(size.w as f32, size.h as f32),
             }),
-            predicted_dt: 1.0 / 60.0,
+            // egui animations should advance at the cadence of the output we render for.
+            predicted_dt: refresh_interval.map_or(1.0 / 60.0, |interval| interval.as_secs_f32()),
             focused: true,
             ..Default::default()
         };
+        let ctx = self.ctx();
         let egui::FullOutput {
             shapes,
             textures_delta,
             ..
-        } = self.ctx.run(input.clone(), ui);
+        } = ctx.run(input.clone(), ui);
 
         render_buffer.render().draw(|texture| {
             {
@@ -136,7 +160,7 @@ impl EguiElement {
                 painter.paint_and_update_textures(
                     [size.w as u32, size.h as u32],
                     scale as f32,
-                    &self.ctx.tessellate(shapes, scale as f32),
+                    &ctx.tessellate(shapes, scale as f32),
                     &textures_delta,
                 );
             };
@@ -145,7 +169,7 @@ impl EguiElement {
             //
             // If we want the best way I would bee to access egui::Memory::visible_windows, but its
             // gated behind a pub(crate), and all the funtions needed to reproduce it are too...
-            let egui::Rect { min, max } = self.ctx().used_rect();
+            let egui::Rect { min, max } = ctx.used_rect();
             let used_rect = Rectangle::<i32, Logical>::from_extremities(
                 (min.x.round() as i32, min.y.round() as i32),
                 (max.x.round() as i32, max.y.round() as i32),
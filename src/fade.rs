@@ -0,0 +1,162 @@
+//! Whole-output fade transitions.
+//!
+//! Each output has its own [`Fade`], stored inside its [`OutputState`](crate::output::OutputState),
+//! that draws a black overlay above everything displayed on the output, except the cursor.
+//!
+//! Outputs fade in from black when they get added (so when the compositor starts), and when the
+//! session gets locked or unlocked. They fade out to black before locking the session, and before
+//! quitting the compositor.
+
+use std::time::Duration;
+
+use fht_animation::Animation;
+use smithay::backend::renderer::element::solid::{SolidColorBuffer, SolidColorRenderElement};
+use smithay::backend::renderer::element::Kind;
+use smithay::backend::renderer::Color32F;
+use smithay::output::Output;
+use smithay::reexports::calloop::timer::{TimeoutAction, Timer};
+use smithay::utils::Point;
+
+use crate::output::OutputExt;
+use crate::space::AnimationConfig;
+use crate::state::Fht;
+
+#[derive(Debug)]
+pub struct Fade {
+    /// The opacity of the black overlay, or the opacity we are animating to.
+    opacity: f64,
+    /// The animation of the overlay opacity, if any.
+    animation: Option<Animation<f64>>,
+    buffer: SolidColorBuffer,
+}
+
+impl Fade {
+    /// Create a new [`Fade`], not displaying anything.
+    pub fn new() -> Self {
+        Self {
+            opacity: 0.0,
+            animation: None,
+            buffer: SolidColorBuffer::new((0, 0), Color32F::TRANSPARENT),
+        }
+    }
+
+    /// Fade from black to the output contents.
+    pub fn fade_in(&mut self, config: Option<&AnimationConfig>) {
+        self.animate(1.0, 0.0, config);
+    }
+
+    /// Fade the output contents to black.
+    pub fn fade_out(&mut self, config: Option<&AnimationConfig>) {
+        self.animate(self.current_opacity(), 1.0, config);
+    }
+
+    fn animate(&mut self, from: f64, to: f64, config: Option<&AnimationConfig>) {
+        self.opacity = to;
+        self.animation =
+            config.map(|config| Animation::new(from, to, config.duration).with_curve(config.curve));
+    }
+
+    fn current_opacity(&self) -> f64 {
+        self.animation
+            .as_ref()
+            .map_or(self.opacity, |animation| *animation.value())
+    }
+
+    /// Whether this [`Fade`] is animating.
+    pub fn is_animating(&self) -> bool {
+        self.animation.is_some()
+    }
+
+    /// Stop the animation of this [`Fade`], jumping to its end.
+    pub fn finish(&mut self) {
+        self.animation = None;
+    }
+
+    /// Advance the animations for this [`Fade`].
+    pub fn advance_animations(&mut self, target_presentation_time: Duration) -> bool {
+        let _ = self.animation.take_if(|a| a.is_finished());
+        if let Some(animation) = &mut self.animation {
+            animation.tick(target_presentation_time);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Render the black overlay of this [`Fade`], if it's visible.
+    pub fn render(&mut self, output: &Output, scale: i32) -> Option<SolidColorRenderElement> {
+        let opacity = self.current_opacity().clamp(0.0, 1.0) as f32;
+        if opacity == 0.0 {
+            return None;
+        }
+
+        self.buffer.update(
+            output.geometry().size,
+            Color32F::new(0.0, 0.0, 0.0, opacity),
+        );
+        Some(SolidColorRenderElement::from_buffer(
+            &self.buffer,
+            Point::default(),
+            scale,
+            1.0,
+            Kind::Unspecified,
+        ))
+    }
+}
+
+impl Fht {
+    /// Get the animation to use for output fades, if enabled.
+    pub fn screen_fade_animation(&self) -> Option<AnimationConfig> {
+        let config = &self.config.animations.screen_fade;
+        AnimationConfig::new(
+            config.duration,
+            config.curve,
+            !self.config.animations.disable && !config.disable,
+        )
+    }
+
+    /// Fade in all the outputs from black.
+    pub fn fade_in_outputs(&mut self) {
+        let animation = self.screen_fade_animation();
+        for output in self.space.outputs().cloned().collect::<Vec<_>>() {
+            if let Some(output_state) = self.output_state.get_mut(&output) {
+                output_state.fade.fade_in(animation.as_ref());
+            }
+            self.queue_redraw(&output);
+        }
+    }
+
+    /// Fade out all the outputs to black.
+    pub fn fade_out_outputs(&mut self) {
+        let animation = self.screen_fade_animation();
+        for output in self.space.outputs().cloned().collect::<Vec<_>>() {
+            if let Some(output_state) = self.output_state.get_mut(&output) {
+                output_state.fade.fade_out(animation.as_ref());
+            }
+            self.queue_redraw(&output);
+        }
+    }
+
+    /// Quit the compositor, after fading out all the outputs.
+    pub fn quit(&mut self) {
+        let Some(animation) = self.screen_fade_animation() else {
+            self.stop = true;
+            return;
+        };
+
+        self.fade_out_outputs();
+        // Use a timer instead of waiting for the animations to finish, since they only advance
+        // when outputs get redrawn, and they might not be (for example, if the session is
+        // inactive)
+        if let Err(err) = self.loop_handle.insert_source(
+            Timer::from_duration(animation.duration),
+            |_, _, state| {
+                state.fht.stop = true;
+                TimeoutAction::Drop
+            },
+        ) {
+            warn!(?err, "Failed to insert quit timer");
+            self.stop = true;
+        }
+    }
+}
@@ -15,8 +15,10 @@ use smithay::input::Seat;
 use smithay::reexports::wayland_server::backend::ObjectId;
 use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
 use smithay::utils::{IsAlive, Serial};
+use smithay::wayland::compositor::with_states;
 use smithay::wayland::seat::WaylandFocus;
 use smithay::wayland::session_lock::LockSurface;
+use smithay::wayland::shell::wlr_layer::{KeyboardInteractivity, Layer, LayerSurfaceCachedState};
 
 use crate::state::State;
 use crate::window::Window;
@@ -84,6 +86,49 @@ impl IsAlive for KeyboardFocusTarget {
     }
 }
 
+impl KeyboardFocusTarget {
+    /// Whether this is a top or overlay layer surface with exclusive keyboard interactivity.
+    pub fn is_exclusive_layer(&self) -> bool {
+        let Self::LayerSurface(layer) = self else {
+            return false;
+        };
+        let data = with_states(layer.wl_surface(), |states| {
+            *states
+                .cached_state
+                .get::<LayerSurfaceCachedState>()
+                .current()
+        });
+        data.keyboard_interactivity == KeyboardInteractivity::Exclusive
+            && matches!(data.layer, Layer::Top | Layer::Overlay)
+    }
+}
+
+/// The keyboard focus targets to restore once exclusive layer surfaces go away.
+///
+/// When an exclusive layer surface (a launcher, a lock prompt...) takes the keyboard focus, the
+/// previous focus target gets pushed here, so that we can give focus back to the exact same
+/// target (including popups) instead of the active window.
+#[derive(Debug, Default)]
+pub struct FocusRestoreStack(Vec<KeyboardFocusTarget>);
+
+impl FocusRestoreStack {
+    /// Remember a focus target to restore later.
+    pub fn push(&mut self, target: KeyboardFocusTarget) {
+        self.0.retain(|t| *t != target);
+        self.0.push(target);
+    }
+
+    /// Take the most recently pushed focus target that is still alive.
+    pub fn pop(&mut self) -> Option<KeyboardFocusTarget> {
+        std::iter::from_fn(|| self.0.pop()).find(IsAlive::alive)
+    }
+
+    /// Forget about all the focus targets.
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
 impl KeyboardTarget<State> for KeyboardFocusTarget {
     fn enter(
         &self,
@@ -94,6 +139,8 @@ impl KeyboardTarget<State> for KeyboardFocusTarget {
     ) {
         match self {
             Self::Window(w) => {
+                // The window got the attention it wanted.
+                w.set_urgent(false);
                 KeyboardTarget::enter(w.toplevel().wl_surface(), seat, data, keys, serial)
             }
             Self::LayerSurface(l) => {
@@ -0,0 +1,47 @@
+use std::time::Duration;
+
+use smithay::desktop::utils::surface_primary_scanout_output;
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::wayland::compositor::with_states;
+
+use crate::delegate_commit_timing;
+use crate::state::{Fht, State};
+
+impl Fht {
+    /// Get when a commit targeting `timestamp` should be applied.
+    ///
+    /// We hold the commit until the refresh cycle before the first presentation at or after
+    /// `timestamp` on the primary output of the `surface`, so that it gets rendered just in time.
+    /// Without a primary output, or one with a known refresh rate, we wait for `timestamp`.
+    pub fn commit_timing_release_time(&self, surface: &WlSurface, timestamp: Duration) -> Duration {
+        let Some(output) = with_states(surface, |states| {
+            surface_primary_scanout_output(surface, states)
+        }) else {
+            return timestamp;
+        };
+        let Some(frame_clock) = self
+            .output_state
+            .get(&output)
+            .map(|state| &state.frame_clock)
+        else {
+            return timestamp;
+        };
+        let Some(refresh_interval) = frame_clock.refresh_interval() else {
+            return timestamp;
+        };
+
+        let next_presentation_time = frame_clock.next_presentation_time();
+        if timestamp <= next_presentation_time {
+            // The next refresh cycle already satisfies the client, apply the commit now.
+            return Duration::ZERO;
+        }
+
+        let cycles = (timestamp - next_presentation_time)
+            .as_nanos()
+            .div_ceil(refresh_interval.as_nanos());
+        let target_presentation_time = next_presentation_time + refresh_interval * cycles as u32;
+        target_presentation_time - refresh_interval
+    }
+}
+
+delegate_commit_timing!(State);
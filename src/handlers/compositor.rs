@@ -1,11 +1,14 @@
-use fht_compositor_config::DecorationMode;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
 use smithay::backend::renderer::utils::{on_commit_buffer_handler, with_renderer_surface_state};
 use smithay::delegate_compositor;
+use smithay::desktop::utils::surface_primary_scanout_output;
 use smithay::desktop::{find_popup_root_surface, PopupKind};
 use smithay::output::Output;
+use smithay::reexports::calloop::timer::{TimeoutAction, Timer};
 use smithay::reexports::calloop::Interest;
 use smithay::reexports::wayland_protocols::wp::content_type::v1::server::wp_content_type_v1;
-use smithay::reexports::wayland_protocols::xdg::decoration::zv1::server::zxdg_toplevel_decoration_v1;
 use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
 use smithay::reexports::wayland_server::Resource;
 use smithay::utils::Rectangle;
@@ -21,8 +24,63 @@ use smithay::wayland::shell::xdg::{
     XdgToplevelSurfaceRoleAttributes,
 };
 
+use super::xdg_activation::ACTIVATION_TIMEOUT;
+use super::xdg_decoration::resolve_decoration_mode;
+use crate::protocols::commit_timing::{commit_timing_pre_commit, CommitTimingBlocker};
+use crate::protocols::fifo::{fifo_pre_commit, FifoBarrier};
 use crate::state::{Fht, ResolvedWindowRules, State, UnmappedWindow};
-use crate::utils::RectCenterExt;
+use crate::utils::{get_monotonic_time, RectCenterExt};
+
+// Same as the frame callback throttle, see Fht::send_frames
+const FIFO_BARRIER_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The timeout of the FIFO barrier a surface commit is blocked on.
+///
+/// Each surface has at most one timer for it, that gets pushed back by each blocked commit.
+#[derive(Default)]
+struct FifoBarrierTimeout {
+    /// The barrier to clear once its deadline is reached.
+    pending: Option<(FifoBarrier, Instant)>,
+    /// Whether the timer of the surface is in the event loop.
+    timer_scheduled: bool,
+}
+
+/// Clear the FIFO barrier of `surface` if its timeout is reached.
+///
+/// Returns the deadline to reschedule the timer at, if the barrier got pushed back.
+fn fifo_barrier_timeout(state: &mut State, surface: &WlSurface) -> Option<Instant> {
+    let barrier = with_states(surface, |states| {
+        let mut timeout = states
+            .data_map
+            .get::<Mutex<FifoBarrierTimeout>>()
+            .unwrap()
+            .lock()
+            .unwrap();
+        match &timeout.pending {
+            Some((_, deadline)) if *deadline > Instant::now() => Err(*deadline),
+            _ => {
+                timeout.timer_scheduled = false;
+                Ok(timeout.pending.take().map(|(barrier, _)| barrier))
+            }
+        }
+    });
+
+    match barrier {
+        Err(deadline) => Some(deadline),
+        Ok(barrier) => {
+            if let Some(barrier) = barrier.filter(|barrier| !barrier.is_cleared()) {
+                barrier.clear();
+                if let Some(client) = surface.client() {
+                    let dh = state.fht.display_handle.clone();
+                    state
+                        .client_compositor_state(&client)
+                        .blocker_cleared(state, &dh);
+                }
+            }
+            None
+        }
+    }
+}
 
 fn has_render_buffer(surface: &WlSurface) -> bool {
     // If there's no renderer surface data, just assume the surface didn't even get recognized by
@@ -57,6 +115,7 @@ impl State {
                 };
                 window.on_commit();
                 window.refresh();
+                self.fht.attach_launch_snapshot(&window);
 
                 let mut output = self.fht.space.active_output().clone();
                 let (mut workspace_id, mut workspace_idx) = {
@@ -64,6 +123,18 @@ impl State {
                     (workspace.id(), workspace.index())
                 };
 
+                // Windows launched with an activation token open where the token got created, for
+                // example on the output of the launcher that spawned them.
+                if let Some(workspace) = self
+                    .fht
+                    .activation_token_workspace(&window)
+                    .and_then(|id| self.fht.space.workspace_mut_for_id(id))
+                {
+                    workspace_id = workspace.id();
+                    workspace_idx = workspace.index();
+                    output = workspace.output().clone();
+                }
+
                 // Prefer parent workspace and output when matching
                 if let Some(parent_workspace) =
                     window.toplevel().parent().and_then(|parent_surface| {
@@ -85,6 +156,33 @@ impl State {
                     false, // we are still unmapped
                 );
 
+                // Clients can ask to be fullscreened on a specific output before being mapped, but
+                // window rules can still deny it.
+                if rules.fullscreen != Some(false) && window.fullscreen() {
+                    if let Some(mon) = window
+                        .requested_fullscreen_output()
+                        .and_then(|requested| self.fht.space.monitor_mut_for_output(&requested))
+                    {
+                        workspace_id = mon.active_workspace().id();
+                        output = mon.output().clone();
+                    }
+                }
+
+                // Windows coming back from a previous compositor instance get their placement
+                // back, unless the rules say otherwise.
+                if rules.open_on_output.is_none() && rules.open_on_workspace.is_none() {
+                    if let Some(saved) = self.fht.session.take_placement(&window) {
+                        if let Some(mon) = self
+                            .fht
+                            .output_named(&saved.output)
+                            .and_then(|output| self.fht.space.monitor_mut_for_output(&output))
+                        {
+                            workspace_id = mon.workspace_by_index(saved.workspace.min(8)).id();
+                            output = mon.output().clone();
+                        }
+                    }
+                }
+
                 if let Some(named_output) = rules
                     .open_on_output
                     .as_ref()
@@ -101,33 +199,14 @@ impl State {
                 let decoration_mode = rules
                     .decoration_mode
                     .unwrap_or(self.fht.config.decorations.decoration_mode);
-                window.toplevel().with_pending_state(|state| {
-                    // Prefer* == Set server side if client didn't specify anything.
-                    // Force* == set regardless of what the client set.
-                    match decoration_mode {
-                        DecorationMode::ClientPreference => {
-                            // Whatever the client has specified.
-                        }
-                        DecorationMode::PreferServerSide => {
-                            let _ = state
-                                .decoration_mode
-                                .get_or_insert(zxdg_toplevel_decoration_v1::Mode::ServerSide);
-                        }
-                        DecorationMode::PreferClientSide => {
-                            let _ = state
-                                .decoration_mode
-                                .get_or_insert(zxdg_toplevel_decoration_v1::Mode::ClientSide);
-                        }
-                        DecorationMode::ForceServerSide => {
-                            state.decoration_mode =
-                                Some(zxdg_toplevel_decoration_v1::Mode::ServerSide);
-                        }
-                        DecorationMode::ForceClientSide => {
-                            state.decoration_mode =
-                                Some(zxdg_toplevel_decoration_v1::Mode::ClientSide);
-                        }
-                    }
-                });
+                let decoration_mode = resolve_decoration_mode(
+                    decoration_mode,
+                    window.requested_decoration_mode(),
+                    self.fht.config.decorations.prefer_no_csd,
+                );
+                window
+                    .toplevel()
+                    .with_pending_state(|state| state.decoration_mode = decoration_mode);
 
                 // Check whether the toplevel asked for fullscreen/maximized on creation.
                 // This can override checking for other values
@@ -183,6 +262,10 @@ impl State {
                 // If the parent is floating, the child shall be too.
                 let parent_floating = parent.as_ref().is_some_and(|w| !w.tiled());
 
+                // Toplevels dragged out of another window (for example browser tabs) follow the
+                // pointer, see src/handlers/toplevel_drag.rs
+                let is_dragged = self.fht.is_toplevel_dragged(&window);
+
                 let is_modal = with_states(surface, |data| {
                     let state = data
                         .data_map
@@ -200,7 +283,8 @@ impl State {
                         || has_parent
                         || has_fixed_size
                         || has_content_type
-                        || parent_floating);
+                        || parent_floating
+                        || is_dragged);
 
                 if let Some(floating) = rules.floating {
                     window.request_tiled(!floating);
@@ -213,11 +297,12 @@ impl State {
                     }
                 } else if default_floating {
                     rules.floating = Some(true);
-                    if has_parent {
+                    // Dragged windows don't get centered, their position follows the pointer.
+                    if has_parent && !is_dragged {
                         // We need to center around the parent if it exists.
                         // For example OBS child window.
                         rules.centered_in_parent = Some(true);
-                    } else {
+                    } else if !is_dragged {
                         // Otherwise center in the workspace.
                         rules.centered = Some(true);
                     }
@@ -279,16 +364,35 @@ impl State {
                 window.size(),
             );
 
+            // Windows presenting a valid activation token that we gave out when spawning their
+            // command always get focus, even on another workspace.
+            let has_activation_token = window
+                .launch_snapshot()
+                .and_then(|snapshot| {
+                    self.fht
+                        .xdg_activation_state
+                        .data_for_token(&snapshot.activation_token)
+                        .map(|data| data.timestamp.elapsed() < ACTIVATION_TIMEOUT)
+                })
+                .unwrap_or(false);
+            if has_activation_token {
+                self.fht.space.activate_window(&window, true, true);
+            }
+
             let is_active = self.fht.space.active_workspace_id() == workspace_id;
-            let should_focus = self.fht.config.general.focus_new_windows && is_active;
+            let should_focus =
+                (self.fht.config.general.focus_new_windows && is_active) || has_activation_token;
 
             if should_focus {
                 let center = window_geometry.center();
+                // Don't warp the pointer away from the drag-and-drop operation.
+                let is_dragged = self.fht.is_toplevel_dragged(&window);
                 self.fht.loop_handle.insert_idle(move |state| {
-                    if state.fht.config.general.cursor_warps {
-                        state.move_pointer(center.to_f64());
+                    let seat = state.fht.seat.clone();
+                    if state.fht.config.general.cursor_warps && !is_dragged {
+                        state.move_pointer(&seat, center.to_f64());
                     }
-                    state.set_keyboard_focus(Some(window));
+                    state.set_keyboard_focus(&seat, Some(window));
                 });
             }
 
@@ -404,6 +508,86 @@ impl CompositorHandler for State {
                     }
                 }
             }
+
+            // wp-fifo-v1: Block the commit until the barrier it waits for gets cleared, which
+            // happens when the surface gets its next frame callback. If the surface is not
+            // displayed anywhere, the barrier would never clear, so don't bother.
+            if let Some(barrier) = fifo_pre_commit(surface) {
+                let displayed = with_states(surface, |states| {
+                    surface_primary_scanout_output(surface, states).is_some()
+                });
+                if displayed {
+                    // The surface can still get hidden while blocked, in this case make sure the
+                    // client does not stay blocked forever.
+                    let deadline = Instant::now() + FIFO_BARRIER_TIMEOUT;
+                    let schedule_timer = with_states(surface, |states| {
+                        states
+                            .data_map
+                            .insert_if_missing_threadsafe(Mutex::<FifoBarrierTimeout>::default);
+                        let mut timeout = states
+                            .data_map
+                            .get::<Mutex<FifoBarrierTimeout>>()
+                            .unwrap()
+                            .lock()
+                            .unwrap();
+                        timeout.pending = Some((barrier.clone(), deadline));
+                        !std::mem::replace(&mut timeout.timer_scheduled, true)
+                    });
+
+                    let mut blocked = true;
+                    if schedule_timer {
+                        let weak_surface = surface.downgrade();
+                        let res = state.fht.loop_handle.insert_source(
+                            Timer::from_deadline(deadline),
+                            move |_, _, state| {
+                                let Ok(surface) = weak_surface.upgrade() else {
+                                    return TimeoutAction::Drop;
+                                };
+                                match fifo_barrier_timeout(state, &surface) {
+                                    Some(deadline) => TimeoutAction::ToInstant(deadline),
+                                    None => TimeoutAction::Drop,
+                                }
+                            },
+                        );
+                        if res.is_err() {
+                            with_states(surface, |states| {
+                                let timeout = states.data_map.get::<Mutex<FifoBarrierTimeout>>();
+                                *timeout.unwrap().lock().unwrap() = FifoBarrierTimeout::default();
+                            });
+                            blocked = false;
+                        }
+                    }
+                    if blocked {
+                        add_blocker(surface, barrier);
+                    }
+                }
+            }
+
+            // wp-commit-timing-v1: Hold the commit until the refresh cycle that presents at or
+            // after the requested timestamp.
+            if let Some(timestamp) = commit_timing_pre_commit(surface) {
+                let release_time = state.fht.commit_timing_release_time(surface, timestamp);
+                let now = get_monotonic_time();
+                if release_time > now {
+                    let blocker = CommitTimingBlocker::default();
+                    let timer_blocker = blocker.clone();
+                    let client = surface.client().unwrap();
+                    let res = state.fht.loop_handle.insert_source(
+                        Timer::from_duration(release_time - now),
+                        move |_, _, state| {
+                            timer_blocker.release();
+                            let dh = state.fht.display_handle.clone();
+                            state
+                                .client_compositor_state(&client)
+                                .blocker_cleared(state, &dh);
+                            TimeoutAction::Drop
+                        },
+                    );
+                    if res.is_ok() {
+                        add_blocker(surface, blocker);
+                    }
+                }
+            }
         });
     }
 
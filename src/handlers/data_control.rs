@@ -1,14 +1,20 @@
-use smithay::delegate_data_control;
-use smithay::wayland::selection::wlr_data_control::DataControlHandler;
+use smithay::wayland::selection::{ext_data_control, wlr_data_control};
+use smithay::{delegate_data_control, delegate_ext_data_control};
 
 use crate::state::State;
 
-impl DataControlHandler for State {
-    fn data_control_state(
-        &self,
-    ) -> &smithay::wayland::selection::wlr_data_control::DataControlState {
+impl wlr_data_control::DataControlHandler for State {
+    fn data_control_state(&self) -> &wlr_data_control::DataControlState {
         &self.fht.data_control_state
     }
 }
 
 delegate_data_control!(State);
+
+impl ext_data_control::DataControlHandler for State {
+    fn data_control_state(&self) -> &ext_data_control::DataControlState {
+        &self.fht.ext_data_control_state
+    }
+}
+
+delegate_ext_data_control!(State);
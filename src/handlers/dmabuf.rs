@@ -21,7 +21,14 @@ impl DmabufHandler for State {
             crate::backend::Backend::Winit(ref mut data) => data.dmabuf_imported(&dmabuf, notifier),
             #[cfg(feature = "udev-backend")]
             #[allow(irrefutable_let_patterns)]
-            crate::backend::Backend::Udev(ref mut data) => data.dmabuf_imported(dmabuf, notifier),
+            crate::backend::Backend::Udev(ref mut data) => {
+                data.dmabuf_imported(dmabuf, notifier, &self.fht.config.debug)
+            }
+            #[cfg(feature = "headless-backend")]
+            #[allow(irrefutable_let_patterns)]
+            crate::backend::Backend::Headless(ref mut data) => {
+                data.dmabuf_imported(&dmabuf, notifier)
+            }
             _ => unreachable!(),
         };
     }
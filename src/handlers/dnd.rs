@@ -7,20 +7,26 @@ use crate::state::State;
 impl ClientDndGrabHandler for State {
     fn started(
         &mut self,
-        _source: Option<WlDataSource>,
+        source: Option<WlDataSource>,
         icon: Option<WlSurface>,
         _seat: smithay::input::Seat<Self>,
     ) {
         self.fht.dnd_icon = icon;
+        self.fht.dnd_source = source;
     }
 
     fn dropped(
         &mut self,
         _target: Option<WlSurface>,
         _validated: bool,
-        _seat: smithay::input::Seat<Self>,
+        seat: smithay::input::Seat<Self>,
     ) {
         self.fht.dnd_icon = None;
+        if let Some(window) = self.fht.finish_toplevel_drag() {
+            // The dragged toplevel stays where it got dropped, and gets focused.
+            self.fht.space.activate_window(&window, true, true);
+            self.set_keyboard_focus(&seat, Some(window));
+        }
     }
 }
 
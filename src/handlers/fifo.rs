@@ -0,0 +1,4 @@
+use crate::delegate_fifo;
+use crate::state::State;
+
+delegate_fifo!(State);
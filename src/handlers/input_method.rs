@@ -1,11 +1,23 @@
 use smithay::delegate_input_method_manager;
-use smithay::desktop::{PopupKind, PopupManager};
+use smithay::desktop::{layer_map_for_output, PopupKind, PopupManager, WindowSurfaceType};
 use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
-use smithay::utils::Rectangle;
+use smithay::utils::{Logical, Rectangle};
 use smithay::wayland::input_method::{InputMethodHandler, PopupSurface};
 
 use crate::state::State;
 
+impl State {
+    /// Redraw the output where the parent of this input method popup is visible.
+    fn queue_redraw_for_input_method_popup(&mut self, surface: &PopupSurface) {
+        let Some(parent) = surface.get_parent().map(|parent| parent.surface.clone()) else {
+            return;
+        };
+        if let Some(output) = self.fht.visible_output_for_surface(&parent).cloned() {
+            self.fht.queue_redraw(&output);
+        }
+    }
+}
+
 impl InputMethodHandler for State {
     fn new_popup(&mut self, surface: PopupSurface) {
         if let Err(err) = self.fht.popups.track_popup(PopupKind::from(surface)) {
@@ -13,19 +25,33 @@ impl InputMethodHandler for State {
         }
     }
 
-    fn popup_repositioned(&mut self, _: PopupSurface) {}
+    fn popup_repositioned(&mut self, surface: PopupSurface) {
+        // The text input cursor rectangle changed, the popup should follow it.
+        self.queue_redraw_for_input_method_popup(&surface);
+    }
 
     fn dismiss_popup(&mut self, surface: PopupSurface) {
+        self.queue_redraw_for_input_method_popup(&surface);
         if let Some(parent) = surface.get_parent().map(|parent| parent.surface.clone()) {
             let _ = PopupManager::dismiss_popup(&parent, &PopupKind::from(surface));
         }
     }
 
-    fn parent_geometry(&self, parent: &WlSurface) -> Rectangle<i32, smithay::utils::Logical> {
+    fn parent_geometry(&self, parent: &WlSurface) -> Rectangle<i32, Logical> {
+        if let Some(window) = self.fht.space.find_window(parent) {
+            return Rectangle::new(window.render_offset(), window.size());
+        }
+
+        // Launchers and other layer-shells can also take text input.
         self.fht
             .space
-            .find_window(parent)
-            .map(|w| Rectangle::new(w.render_offset(), w.size()))
+            .outputs()
+            .find_map(|output| {
+                let layer_map = layer_map_for_output(output);
+                let layer = layer_map.layer_for_surface(parent, WindowSurfaceType::TOPLEVEL)?;
+                let geometry = layer_map.layer_geometry(layer)?;
+                Some(Rectangle::from_size(geometry.size))
+            })
             .unwrap_or_default()
     }
 }
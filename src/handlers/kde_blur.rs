@@ -0,0 +1,4 @@
+use crate::delegate_kde_blur;
+use crate::state::State;
+
+delegate_kde_blur!(State);
@@ -1,5 +1,6 @@
 mod alpha_modifiers;
 mod buffer;
+mod commit_timing;
 mod compositor;
 mod content_type;
 mod cursor_shape;
@@ -11,10 +12,12 @@ mod dnd;
 mod drm_lease;
 #[cfg(feature = "udev-backend")]
 mod drm_syncobj;
+mod fifo;
 mod foreign_toplevel_list;
 mod fractional_scale;
 mod idle_inhibit;
 mod input_method;
+mod kde_blur;
 mod keyboard_shortcuts_inhibit;
 mod layer_shell;
 mod output;
@@ -31,9 +34,12 @@ mod selection;
 pub mod session_lock;
 mod shm;
 mod single_pixel_buffer;
+mod tearing_control;
+mod toplevel_drag;
 mod viewporter;
-mod virtual_keyboard;
-mod xdg_activation;
+pub mod virtual_keyboard;
+mod virtual_pointer;
+pub mod xdg_activation;
 mod xdg_decoration;
 mod xdg_dialog;
 mod xdg_foreign;
@@ -3,6 +3,7 @@ use std::collections::HashMap;
 use smithay::output::{self, Mode, Output};
 
 use crate::delegate_output_management;
+use crate::output::OutputExt;
 use crate::protocols::output_management::{
     OutputConfiguration, OutputManagementHandler, OutputManagementManagerState,
 };
@@ -57,8 +58,7 @@ impl OutputManagementHandler for State {
                 }
             }
 
-            output.change_current_state(
-                None,
+            output.change_logical_state(
                 transform,
                 scale.map(|scale| output::Scale::Integer(scale.round() as i32)),
                 position,
@@ -1,4 +1,4 @@
-use smithay::input::keyboard::LedState;
+use smithay::input::keyboard::{Layout, LedState};
 use smithay::input::pointer::CursorImageStatus;
 use smithay::input::{Seat, SeatHandler, SeatState};
 use smithay::reexports::input::DeviceCapability;
@@ -37,9 +37,31 @@ impl SeatHandler for State {
         let client = wl_surface.and_then(|s| dh.get_client(s.id()).ok());
         set_data_device_focus(dh, seat, client.clone());
         set_primary_focus(dh, seat, client);
+
+        if *seat != self.fht.seat {
+            return;
+        }
+
+        if let Some(KeyboardFocusTarget::Window(window)) = focused {
+            self.fht.zoom_follow_window(window);
+        }
+        // Windows get dimmed when they lose the keyboard focus.
+        self.fht.queue_redraw_all();
+
+        // We are still inside the keyboard focus change here, and we can't access the XKB state
+        // until it's done.
+        let seat = seat.clone();
+        self.fht
+            .loop_handle
+            .insert_idle(move |state| state.update_window_keyboard_layout(&seat));
     }
 
-    fn led_state_changed(&mut self, _seat: &Seat<Self>, led_state: LedState) {
+    fn led_state_changed(&mut self, seat: &Seat<Self>, led_state: LedState) {
+        if *seat != self.fht.seat {
+            // Our input devices only belong to the primary seat.
+            return;
+        }
+
         let keyboards = self
             .fht
             .devices
@@ -52,8 +74,68 @@ impl SeatHandler for State {
         }
     }
 
-    fn cursor_image(&mut self, _seat: &Seat<Self>, image: CursorImageStatus) {
+    fn cursor_image(&mut self, seat: &Seat<Self>, image: CursorImageStatus) {
+        if *seat != self.fht.seat {
+            // We only draw the cursor of the primary seat.
+            return;
+        }
+
         self.fht.cursor_theme_manager.set_image_status(image);
+        // The cursor is only drawn on the active output.
+        let output = self.fht.space.active_output().clone();
+        self.fht.queue_redraw(&output);
+    }
+}
+
+impl State {
+    /// Apply the keyboard layout window rule of the keyboard focus.
+    ///
+    /// When the focus changes to a window without a layout rule, the layout that was active
+    /// before gets restored.
+    fn update_window_keyboard_layout(&mut self, seat: &Seat<State>) {
+        let keyboard = seat.get_keyboard().unwrap();
+        let layout_name = match keyboard.current_focus() {
+            Some(KeyboardFocusTarget::Window(window)) => window.rules().keyboard_layout.clone(),
+            // Popups belong to a window, keep using its layout.
+            Some(KeyboardFocusTarget::Popup(_)) => return,
+            _ => None,
+        };
+
+        let layout_idx = layout_name.and_then(|name| {
+            let idx = self
+                .fht
+                .config
+                .input
+                .keyboard
+                .layout
+                .split(',')
+                .position(|layout| layout.trim() == name);
+            if idx.is_none() {
+                warn!(
+                    ?name,
+                    "Keyboard layout from window rule is not in input.keyboard.layout"
+                );
+            }
+            idx
+        });
+
+        match layout_idx {
+            Some(idx) => {
+                let previous = keyboard.with_xkb_state(self, |mut context| {
+                    let previous = context.xkb().lock().unwrap().active_layout();
+                    context.set_layout(Layout(idx as u32));
+                    previous
+                });
+                // Only remember the layout from before the first window with a rule, not the one
+                // from the previous window rule.
+                self.fht.keyboard_layout_to_restore.get_or_insert(previous);
+            }
+            None => {
+                if let Some(previous) = self.fht.keyboard_layout_to_restore.take() {
+                    keyboard.with_xkb_state(self, |mut context| context.set_layout(previous));
+                }
+            }
+        }
     }
 }
 
@@ -9,6 +9,12 @@ use crate::state::{ClientState, State};
 
 impl SecurityContextHandler for State {
     fn context_created(&mut self, source: SecurityContextListenerSource, context: SecurityContext) {
+        debug!(
+            sandbox_engine = ?context.sandbox_engine,
+            app_id = ?context.app_id,
+            instance_id = ?context.instance_id,
+            "New security context"
+        );
         self.fht
             .loop_handle
             .insert_source(source, move |client_stream, _, state| {
@@ -6,6 +6,7 @@ use smithay::backend::renderer::element::Kind;
 use smithay::backend::renderer::Color32F;
 use smithay::delegate_session_lock;
 use smithay::output::Output;
+use smithay::reexports::calloop::timer::{TimeoutAction, Timer};
 use smithay::reexports::wayland_server::protocol::wl_output::WlOutput;
 use smithay::utils::Point;
 use smithay::wayland::compositor::{send_surface_state, with_states};
@@ -32,6 +33,31 @@ impl SessionLockHandler for State {
 
     fn lock(&mut self, locker: session_lock::SessionLocker) {
         self.fht.lock_state = LockState::Pending(locker);
+        // We only draw the lock backdrop once the outputs are black, see session_lock_elements
+        self.fht.fade_out_outputs();
+        let Some(animation) = self.fht.screen_fade_animation() else {
+            return;
+        };
+
+        // The fade animations only advance when outputs get redrawn, finish them with a timer to
+        // make sure we don't delay the lock confirmation.
+        if let Err(err) = self.fht.loop_handle.insert_source(
+            Timer::from_duration(animation.duration),
+            |_, _, state| {
+                if matches!(state.fht.lock_state, LockState::Pending(_)) {
+                    for output_state in state.fht.output_state.values_mut() {
+                        output_state.fade.finish();
+                    }
+                    state.fht.queue_redraw_all();
+                }
+                TimeoutAction::Drop
+            },
+        ) {
+            warn!(?err, "Failed to insert lock fade timer");
+            for output_state in self.fht.output_state.values_mut() {
+                output_state.fade.finish();
+            }
+        }
     }
 
     fn unlock(&mut self) {
@@ -43,9 +69,11 @@ impl SessionLockHandler for State {
             output_state.lock_backdrop = None;
             let _ = output_state.lock_surface.take();
         }
+        self.fht.fade_in_outputs();
         // Reset focus
         let active_window = self.fht.space.active_window();
-        self.set_keyboard_focus(active_window);
+        let seat = self.fht.seat.clone();
+        self.set_keyboard_focus(&seat, active_window);
     }
 
     fn new_surface(&mut self, lock_surface: LockSurface, wl_output: WlOutput) {
@@ -76,7 +104,8 @@ impl SessionLockHandler for State {
 
         if output == *self.fht.space.active_output() {
             // Focus the newly placed lock surface.
-            self.set_keyboard_focus(Some(lock_surface));
+            let seat = self.fht.seat.clone();
+            self.set_keyboard_focus(&seat, Some(lock_surface));
         }
     }
 }
@@ -100,6 +129,10 @@ impl Fht {
         }
 
         let output_state = self.output_state.get_mut(output).unwrap();
+        if matches!(self.lock_state, LockState::Pending(_)) && output_state.fade.is_animating() {
+            // Keep displaying the output contents while fading out to black.
+            return elements;
+        }
 
         if let Some(lock_surface) = output_state.lock_surface.as_ref() {
             elements.extend(render_elements_from_surface_tree(
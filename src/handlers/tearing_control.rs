@@ -0,0 +1,4 @@
+use crate::delegate_tearing_control;
+use crate::state::State;
+
+delegate_tearing_control!(State);
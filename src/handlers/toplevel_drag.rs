@@ -0,0 +1,102 @@
+use smithay::reexports::wayland_protocols::xdg::shell::server::xdg_toplevel::XdgToplevel;
+use smithay::utils::{Logical, Point};
+
+use crate::delegate_toplevel_drag;
+use crate::output::OutputExt;
+use crate::protocols::toplevel_drag::{ToplevelDragHandler, ToplevelDragManagerState};
+use crate::state::{Fht, State};
+use crate::window::Window;
+
+impl ToplevelDragHandler for State {
+    fn toplevel_drag_state(&mut self) -> &mut ToplevelDragManagerState {
+        &mut self.fht.toplevel_drag_state
+    }
+}
+
+delegate_toplevel_drag!(State);
+
+impl Fht {
+    /// Get the toplevel attached to the ongoing drag-and-drop operation, if any.
+    fn dragged_toplevel(&self) -> Option<(XdgToplevel, Point<i32, Logical>)> {
+        let source = self.dnd_source.as_ref()?;
+        self.toplevel_drag_state.attached_toplevel(source)
+    }
+
+    /// Whether this [`Window`] is attached to the ongoing drag-and-drop operation.
+    pub fn is_toplevel_dragged(&self, window: &Window) -> bool {
+        self.dragged_toplevel()
+            .is_some_and(|(toplevel, _)| *window.toplevel().xdg_toplevel() == toplevel)
+    }
+
+    /// Make the toplevel attached to the ongoing drag-and-drop operation follow the pointer.
+    ///
+    /// The dragged window is always floating, and gets moved to the output under the pointer.
+    pub fn refresh_toplevel_drag(&mut self) {
+        crate::profile_function!();
+        let Some((toplevel, offset)) = self.dragged_toplevel() else {
+            return;
+        };
+        let Some(window) = self
+            .space
+            .windows()
+            .find(|window| *window.toplevel().xdg_toplevel() == toplevel)
+            .cloned()
+        else {
+            // The toplevel is not mapped yet, we will pick it up once it is.
+            return;
+        };
+
+        if self.dragged_window.as_ref() != Some(&window) {
+            if let Some(previous) = self.dragged_window.replace(window.clone()) {
+                previous.set_dragged(false);
+            }
+            window.set_dragged(true);
+        }
+
+        let pointer_location = self.seat.get_pointer().unwrap().current_location();
+        let Some(output) = self
+            .space
+            .outputs()
+            .find(|output| output.geometry().to_f64().contains(pointer_location))
+            .cloned()
+        else {
+            return;
+        };
+
+        let monitor = self.space.monitor_for_output(&output).unwrap();
+        if !monitor.active_workspace().windows().any(|w| *w == window) {
+            self.space.move_window_to_output(&window, &output, false);
+        }
+
+        let monitor = self.space.monitor_mut_for_output(&output).unwrap();
+        let workspace = monitor.active_workspace_mut();
+        let was_tiled = window.tiled();
+        if was_tiled {
+            window.request_tiled(false);
+        }
+
+        if let Some(tile) = workspace.tiles_mut().find(|tile| *tile.window() == window) {
+            let location = pointer_location.to_i32_round()
+                - output.current_location()
+                - offset
+                - tile.window_loc();
+            tile.set_location(location, false);
+        }
+
+        if was_tiled {
+            workspace.arrange_tiles(true);
+        }
+
+        self.queue_redraw(&output);
+    }
+
+    /// Finish the ongoing drag-and-drop operation, leaving the dragged toplevel where it is.
+    ///
+    /// Returns the window that was dragged, if any.
+    pub fn finish_toplevel_drag(&mut self) -> Option<Window> {
+        self.dnd_source = None;
+        let window = self.dragged_window.take()?;
+        window.set_dragged(false);
+        Some(window)
+    }
+}
@@ -1 +1,177 @@
-smithay::delegate_virtual_keyboard_manager!(crate::state::State);
+//! Virtual keyboards, implemented by smithay.
+//!
+//! Smithay sends the keys of virtual keyboards straight to the focused client, with the keymap of
+//! the virtual keyboard. We sit in front of it to keep track of that keymap, so that injected keys
+//! can trigger key bindings with `input.virtual-input-bindings`.
+
+use fht_compositor_config::KeyPattern;
+use smithay::input::keyboard::{xkb, FilterResult, ModifiersState};
+use smithay::reexports::wayland_protocols_misc::zwp_virtual_keyboard_v1::server::zwp_virtual_keyboard_manager_v1::ZwpVirtualKeyboardManagerV1;
+use smithay::reexports::wayland_protocols_misc::zwp_virtual_keyboard_v1::server::zwp_virtual_keyboard_v1::{
+    self, ZwpVirtualKeyboardV1,
+};
+use smithay::reexports::wayland_server::backend::ClientId;
+use smithay::reexports::wayland_server::protocol::wl_keyboard::KeyState;
+use smithay::reexports::wayland_server::{
+    delegate_dispatch, delegate_global_dispatch, Client, DataInit, Dispatch, DisplayHandle,
+    Resource,
+};
+use smithay::wayland::virtual_keyboard::{
+    VirtualKeyboardManagerGlobalData, VirtualKeyboardManagerState, VirtualKeyboardUserData,
+};
+
+use crate::state::State;
+
+/// The keymap of a virtual keyboard, along with its modifiers.
+pub struct VirtualKeyboardKeymap {
+    keymap: xkb::Keymap,
+    state: xkb::State,
+}
+
+impl VirtualKeyboardKeymap {
+    /// Get the key pattern of this key, the same way we do for physical keyboards.
+    fn key_pattern(&self, keycode: xkb::Keycode) -> Option<KeyPattern> {
+        let layout = self.state.key_get_layout(keycode);
+        let keysym = *self
+            .keymap
+            .key_get_syms_by_level(keycode, layout, 0)
+            .first()?;
+
+        let active = |name: &str| {
+            self.state
+                .mod_name_is_active(name, xkb::STATE_MODS_EFFECTIVE)
+        };
+        let modifiers = ModifiersState {
+            ctrl: active(xkb::MOD_NAME_CTRL),
+            alt: active(xkb::MOD_NAME_ALT),
+            shift: active(xkb::MOD_NAME_SHIFT),
+            logo: active(xkb::MOD_NAME_LOGO),
+            iso_level3_shift: active(xkb::MOD_NAME_ISO_LEVEL3_SHIFT),
+            ..Default::default()
+        };
+
+        Some(KeyPattern(modifiers.into(), keysym))
+    }
+}
+
+impl Dispatch<ZwpVirtualKeyboardV1, VirtualKeyboardUserData<State>> for State {
+    fn request(
+        state: &mut Self,
+        client: &Client,
+        resource: &ZwpVirtualKeyboardV1,
+        request: zwp_virtual_keyboard_v1::Request,
+        data: &VirtualKeyboardUserData<State>,
+        dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match &request {
+            zwp_virtual_keyboard_v1::Request::Keymap { fd, size, .. } => {
+                // Smithay validates the keymap and reports errors to the client.
+                let keymap = fd.try_clone().ok().and_then(|fd| {
+                    let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+                    // SAFETY: The fd comes from the client and is only read.
+                    unsafe {
+                        xkb::Keymap::new_from_fd(
+                            &context,
+                            fd,
+                            *size as usize,
+                            xkb::KEYMAP_FORMAT_TEXT_V1,
+                            xkb::KEYMAP_COMPILE_NO_FLAGS,
+                        )
+                    }
+                    .ok()
+                    .flatten()
+                });
+                match keymap {
+                    Some(keymap) => {
+                        let xkb_state = xkb::State::new(&keymap);
+                        state.fht.virtual_keyboards.insert(
+                            resource.id(),
+                            VirtualKeyboardKeymap {
+                                keymap,
+                                state: xkb_state,
+                            },
+                        );
+                    }
+                    None => {
+                        state.fht.virtual_keyboards.remove(&resource.id());
+                    }
+                }
+            }
+            zwp_virtual_keyboard_v1::Request::Modifiers {
+                mods_depressed,
+                mods_latched,
+                mods_locked,
+                group,
+            } => {
+                if let Some(keymap) = state.fht.virtual_keyboards.get_mut(&resource.id()) {
+                    keymap.state.update_mask(
+                        *mods_depressed,
+                        *mods_latched,
+                        *mods_locked,
+                        0,
+                        0,
+                        *group,
+                    );
+                }
+            }
+            zwp_virtual_keyboard_v1::Request::Key {
+                key,
+                state: key_state,
+                ..
+            } => {
+                // Virtual keyboard keycodes are evdev keycodes, xkb ones are offset by 8.
+                let Some(keycode) = key.checked_add(8).map(xkb::Keycode::new) else {
+                    return;
+                };
+                let key_pattern = state
+                    .fht
+                    .virtual_keyboards
+                    .get(&resource.id())
+                    .and_then(|keymap| keymap.key_pattern(keycode));
+                if let Some(key_pattern) = key_pattern {
+                    let pressed = *key_state == u32::from(KeyState::Pressed);
+                    if let FilterResult::Intercept(action) =
+                        state.filter_injected_key(key_pattern, pressed)
+                    {
+                        if let Some(action) = action {
+                            let seat = state.fht.seat.clone();
+                            state.process_key_action(&seat, action, key_pattern);
+                        }
+                        return;
+                    }
+                }
+            }
+            _ => (),
+        }
+
+        <VirtualKeyboardManagerState as Dispatch<
+            ZwpVirtualKeyboardV1,
+            VirtualKeyboardUserData<State>,
+            State,
+        >>::request(state, client, resource, request, data, dhandle, data_init);
+    }
+
+    fn destroyed(
+        state: &mut Self,
+        client: ClientId,
+        resource: &ZwpVirtualKeyboardV1,
+        data: &VirtualKeyboardUserData<State>,
+    ) {
+        state.fht.virtual_keyboards.remove(&resource.id());
+        <VirtualKeyboardManagerState as Dispatch<
+            ZwpVirtualKeyboardV1,
+            VirtualKeyboardUserData<State>,
+            State,
+        >>::destroyed(state, client, resource, data);
+    }
+}
+
+// The virtual keyboard objects are handled above, this is the rest of
+// smithay::delegate_virtual_keyboard_manager.
+delegate_global_dispatch!(State: [
+    ZwpVirtualKeyboardManagerV1: VirtualKeyboardManagerGlobalData
+] => VirtualKeyboardManagerState);
+delegate_dispatch!(State: [
+    ZwpVirtualKeyboardManagerV1: ()
+] => VirtualKeyboardManagerState);
@@ -0,0 +1,97 @@
+use fht_compositor_config::MouseButton;
+use smithay::backend::input::ButtonState;
+use smithay::input::pointer::ButtonEvent;
+use smithay::utils::{Point, SERIAL_COUNTER};
+
+use crate::delegate_virtual_pointer;
+use crate::output::OutputExt;
+use crate::protocols::virtual_pointer::{VirtualPointerEvent, VirtualPointerHandler};
+use crate::state::State;
+
+/// Get the [`MouseButton`] of an evdev button code, see `linux/input-event-codes.h`
+fn mouse_button(button: u32) -> Option<MouseButton> {
+    match button {
+        0x110 => Some(MouseButton::Left),
+        0x111 => Some(MouseButton::Right),
+        0x112 => Some(MouseButton::Middle),
+        0x113 => Some(MouseButton::Back),
+        0x114 => Some(MouseButton::Forward),
+        _ => None,
+    }
+}
+
+impl VirtualPointerHandler for State {
+    fn virtual_pointer_event(&mut self, event: VirtualPointerEvent) {
+        crate::profile_function!();
+        // Virtual pointers always drive the seat of our input devices.
+        let seat = self.fht.seat.clone();
+        match event {
+            VirtualPointerEvent::Motion { delta, .. } => {
+                let location = seat.get_pointer().unwrap().current_location() + delta;
+                self.move_injected_pointer(&seat, location);
+            }
+            VirtualPointerEvent::MotionAbsolute {
+                position, output, ..
+            } => {
+                let output = output.unwrap_or_else(|| self.fht.space.active_output().clone());
+                let geometry = output.geometry().to_f64();
+                let location = geometry.loc
+                    + Point::from((position.x * geometry.size.w, position.y * geometry.size.h));
+                self.move_injected_pointer(&seat, location);
+            }
+            VirtualPointerEvent::Button {
+                time,
+                button,
+                state,
+            } => {
+                let serial = SERIAL_COUNTER.next_serial();
+                let pointer = seat.get_pointer().unwrap();
+                if state == ButtonState::Pressed
+                    && !pointer.is_grabbed()
+                    && !self.has_active_layer()
+                {
+                    self.update_keyboard_focus(&seat, true);
+
+                    if let Some(mouse_button) = self
+                        .fht
+                        .config
+                        .input
+                        .virtual_input_bindings
+                        .then(|| mouse_button(button))
+                        .flatten()
+                    {
+                        let mouse_pattern = fht_compositor_config::MousePattern(
+                            seat.get_keyboard().unwrap().modifier_state().into(),
+                            mouse_button,
+                        );
+                        if let Some(action) =
+                            self.fht.config.mousebinds.get(&mouse_pattern).cloned()
+                        {
+                            self.process_mouse_action(&seat, button, action, serial);
+                        }
+                    }
+                }
+
+                pointer.button(
+                    self,
+                    &ButtonEvent {
+                        button,
+                        state,
+                        serial,
+                        time,
+                    },
+                );
+            }
+            VirtualPointerEvent::Axis(frame) => {
+                let pointer = seat.get_pointer().unwrap();
+                pointer.axis(self, frame);
+            }
+            VirtualPointerEvent::Frame => {
+                let pointer = seat.get_pointer().unwrap();
+                pointer.frame(self);
+            }
+        }
+    }
+}
+
+delegate_virtual_pointer!(State);
@@ -1,12 +1,47 @@
 use smithay::delegate_xdg_activation;
+use smithay::desktop::{layer_map_for_output, WindowSurfaceType};
 use smithay::input::Seat;
 use smithay::reexports::wayland_server::protocol::wl_surface;
 use smithay::wayland::xdg_activation::{self, XdgActivationHandler};
 
-use crate::state::State;
+use crate::space::WorkspaceId;
+use crate::state::{Fht, State};
 
 pub const ACTIVATION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
 
+/// Where an activation token got created from.
+///
+/// Windows launched with this token get opened there, unless window rules say otherwise.
+#[derive(Debug, Clone, Copy)]
+pub struct ActivationTokenContext {
+    pub workspace_id: WorkspaceId,
+}
+
+impl Fht {
+    /// Get the workspace where the surface requesting an activation token is.
+    fn activation_token_context(
+        &self,
+        surface: &wl_surface::WlSurface,
+    ) -> Option<ActivationTokenContext> {
+        if let Some((_, workspace)) = self.space.find_window_and_workspace(surface) {
+            return Some(ActivationTokenContext {
+                workspace_id: workspace.id(),
+            });
+        }
+
+        // Launchers are usually layer-shells, use the active workspace of their output.
+        let output = self.space.outputs().find(|output| {
+            layer_map_for_output(output)
+                .layer_for_surface(surface, WindowSurfaceType::ALL)
+                .is_some()
+        })?;
+        let monitor = self.space.monitor_for_output(output)?;
+        Some(ActivationTokenContext {
+            workspace_id: monitor.active_workspace().id(),
+        })
+    }
+}
+
 impl XdgActivationHandler for State {
     fn activation_state(&mut self) -> &mut xdg_activation::XdgActivationState {
         &mut self.fht.xdg_activation_state
@@ -17,36 +52,57 @@ impl XdgActivationHandler for State {
         _token: xdg_activation::XdgActivationToken,
         data: xdg_activation::XdgActivationTokenData,
     ) -> bool {
-        if let Some((serial, seat)) = data.serial {
-            Seat::from_resource(&seat).as_ref() == Some(&self.fht.seat)
-                && self
-                    .fht
-                    .keyboard
-                    .last_enter()
-                    .map(|le| serial.is_no_older_than(&le))
-                    .unwrap_or(false)
-        } else {
-            false
+        if let Some(context) = data
+            .surface
+            .as_ref()
+            .and_then(|surface| self.fht.activation_token_context(surface))
+        {
+            data.user_data.insert_if_missing(|| context);
         }
+
+        // We accept every token, and check whether the client is allowed to steal focus when it
+        // actually requests activation. Windows that are not allowed get marked urgent instead.
+        true
     }
 
     fn request_activation(
         &mut self,
-        _token: xdg_activation::XdgActivationToken,
+        token: xdg_activation::XdgActivationToken,
         token_data: xdg_activation::XdgActivationTokenData,
         surface: wl_surface::WlSurface,
     ) {
-        if token_data.timestamp.elapsed() < ACTIVATION_TIMEOUT {
-            let mut output = None;
-            if let Some((window, workspace)) = self.fht.space.find_window_and_workspace(&surface) {
-                output = Some(workspace.output().clone());
-                self.fht.space.activate_window(&window, true);
-            }
+        let Some((window, workspace)) = self.fht.space.find_window_and_workspace(&surface) else {
+            return;
+        };
+        let output = workspace.output().clone();
 
-            if let Some(ref output) = output {
-                self.fht.queue_redraw(output);
+        // If this token was given to a command we spawned, we now know where this window came from.
+        let snapshot = self.fht.launch_snapshot_for_token(&token);
+        let spawned_by_us = snapshot.is_some();
+        if let Some(snapshot) = snapshot {
+            if window.launch_snapshot().is_none() {
+                window.set_launch_snapshot(snapshot);
             }
         }
+
+        // Only tokens created by us, or by a client that had keyboard focus when creating it
+        // allow to take focus.
+        let has_focus_permission = spawned_by_us
+            || token_data.serial.is_some_and(|(serial, seat)| {
+                Seat::<State>::from_resource(&seat)
+                    .and_then(|seat| seat.get_keyboard())
+                    .and_then(|keyboard| keyboard.last_enter())
+                    .is_some_and(|le| serial.is_no_older_than(&le))
+            });
+
+        if has_focus_permission && token_data.timestamp.elapsed() < ACTIVATION_TIMEOUT {
+            self.fht.space.activate_window(&window, true, true);
+        } else if self.fht.space.active_window().as_ref() != Some(&window) {
+            debug!(app_id = ?window.app_id(), "Marking window as urgent");
+            window.set_urgent(true);
+        }
+
+        self.fht.queue_redraw(&output);
     }
 }
 
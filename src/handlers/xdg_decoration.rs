@@ -1,21 +1,80 @@
-use smithay::{
-    delegate_xdg_decoration,
-    reexports::wayland_protocols::xdg::decoration::zv1::server::zxdg_toplevel_decoration_v1::Mode as DecorationMode, wayland::shell::xdg::{ToplevelSurface, decoration::XdgDecorationHandler},
-};
+use fht_compositor_config::DecorationMode;
+use smithay::delegate_xdg_decoration;
+use smithay::reexports::wayland_protocols::xdg::decoration::zv1::server::zxdg_toplevel_decoration_v1::Mode;
+use smithay::wayland::shell::xdg::decoration::XdgDecorationHandler;
+use smithay::wayland::shell::xdg::ToplevelSurface;
 
 use crate::state::State;
+use crate::window::Window;
 
-// NOTE: Based on CONFIG.decoration.allow_csd, this will only (and forcefully) set either server
-// side deco or client side deco
-// This file only exists to adversite the protocol to clients that support it. The decoration mode
-// is set when mapping windows (see [`Fht::prepare_map_window`](../shell/mod.rs))
+/// Resolve the decoration mode of a toplevel from the configured [`DecorationMode`] and the mode
+/// the client requested, if any.
+///
+/// - Prefer* sets the preferred mode if the client didn't request anything.
+/// - Force* sets the mode regardless of what the client requested.
+///
+/// With `prefer_no_csd`, clients that didn't request anything are told to use server-side
+/// decorations even when following the client preference.
+pub fn resolve_decoration_mode(
+    configured: DecorationMode,
+    requested: Option<Mode>,
+    prefer_no_csd: bool,
+) -> Option<Mode> {
+    match configured {
+        DecorationMode::ClientPreference => requested.or(prefer_no_csd.then_some(Mode::ServerSide)),
+        DecorationMode::PreferServerSide => requested.or(Some(Mode::ServerSide)),
+        DecorationMode::PreferClientSide => requested.or(Some(Mode::ClientSide)),
+        DecorationMode::ForceServerSide => Some(Mode::ServerSide),
+        DecorationMode::ForceClientSide => Some(Mode::ClientSide),
+    }
+}
+
+impl State {
+    fn window_for_toplevel(&self, toplevel: &ToplevelSurface) -> Option<Window> {
+        let surface = toplevel.wl_surface();
+        self.fht
+            .space
+            .find_window(surface)
+            .or_else(|| self.fht.unmapped_window(surface))
+    }
+
+    /// Update the decoration mode of this toplevel after the client changed its preference.
+    fn update_decoration_mode(&mut self, toplevel: &ToplevelSurface, requested: Option<Mode>) {
+        let window = self.window_for_toplevel(toplevel);
+        if let Some(window) = &window {
+            window.set_requested_decoration_mode(requested);
+        }
+
+        // Window rules are not resolved yet for unmapped windows, but the decoration mode gets
+        // resolved again with them when sending the initial configure.
+        let configured = window
+            .and_then(|window| window.rules().decoration_mode)
+            .unwrap_or(self.fht.config.decorations.decoration_mode);
+        let mode = resolve_decoration_mode(
+            configured,
+            requested,
+            self.fht.config.decorations.prefer_no_csd,
+        );
+        toplevel.with_pending_state(|state| state.decoration_mode = mode);
+
+        if toplevel.is_initial_configure_sent() {
+            toplevel.send_pending_configure();
+        }
+    }
+}
 
 impl XdgDecorationHandler for State {
-    fn new_decoration(&mut self, _toplevel: ToplevelSurface) {}
+    fn new_decoration(&mut self, toplevel: ToplevelSurface) {
+        self.update_decoration_mode(&toplevel, None);
+    }
 
-    fn request_mode(&mut self, _toplevel: ToplevelSurface, _mode: DecorationMode) {}
+    fn request_mode(&mut self, toplevel: ToplevelSurface, mode: Mode) {
+        self.update_decoration_mode(&toplevel, Some(mode));
+    }
 
-    fn unset_mode(&mut self, _toplevel: ToplevelSurface) {}
+    fn unset_mode(&mut self, toplevel: ToplevelSurface) {
+        self.update_decoration_mode(&toplevel, None);
+    }
 }
 
 delegate_xdg_decoration!(State);
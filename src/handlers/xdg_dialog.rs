@@ -1,47 +1,103 @@
 use smithay::delegate_xdg_dialog;
 use smithay::utils::Rectangle;
+use smithay::wayland::seat::WaylandFocus;
 use smithay::wayland::shell::xdg::dialog::XdgDialogHandler;
 use smithay::wayland::shell::xdg::ToplevelSurface;
 
+use crate::focus_target::KeyboardFocusTarget;
 use crate::output::OutputExt;
-use crate::state::State;
+use crate::state::{Fht, State};
 use crate::utils::RectCenterExt;
+use crate::window::Window;
 
 impl XdgDialogHandler for State {
     fn modal_changed(&mut self, toplevel: ToplevelSurface, is_modal: bool) {
+        if !is_modal {
+            // I mean, we kinda don't care if its not.
+            return;
+        }
+
+        if !self.center_toplevel_on_parent(&toplevel) {
+            warn!("Received modal_changed for unmapped toplevel");
+        }
+    }
+}
+
+impl State {
+    /// Float this toplevel and center it on its parent, raising it above the other windows.
+    ///
+    /// Returns `false` if the toplevel is not mapped.
+    pub fn center_toplevel_on_parent(&mut self, toplevel: &ToplevelSurface) -> bool {
         let Some(workspace) = self
             .fht
             .space
             .workspace_mut_for_window_surface(toplevel.wl_surface())
         else {
-            warn!("Received modal_changed for unmapped toplevel");
-            return;
+            return false;
         };
         let output_rect = Rectangle::from_size(workspace.output().geometry().size);
 
-        if !is_modal {
-            // I mean, we kinda don't care if its not.
-            return;
-        }
+        // Dialogs are centered on their parent, if it's on the same workspace.
+        let parent_geometry = toplevel.parent().and_then(|parent_surface| {
+            workspace
+                .tiles()
+                .find(|tile| tile.window().wl_surface().as_deref() == Some(&parent_surface))
+                .map(|tile| tile.geometry())
+        });
 
         let tile = workspace
             .tiles_mut()
-            .find(|tile| *tile.window().toplevel() == toplevel)
+            .find(|tile| tile.window().toplevel() == toplevel)
             .unwrap();
-        tile.window().request_tiled(false);
+        let window = tile.window().clone();
+        window.request_tiled(false);
         // Ask the toplevel to set its own size according to whatever it likes.
         // For modals/dialogs it should set whatever needed size.
-        tile.window().reset_size();
-        tile.window().send_configure();
+        window.reset_size();
+        window.send_configure();
 
         // Now center the tile.
         let tile_size = tile.size();
-        let loc = output_rect.center() - tile_size.to_f64().downscale(2.0).to_i32_round();
+        let center = parent_geometry
+            .filter(|geometry| output_rect.contains_rect(*geometry))
+            .unwrap_or(output_rect)
+            .center();
+        let loc = center - tile_size.to_f64().downscale(2.0).to_i32_round();
         tile.set_location(loc, !self.fht.config.animations.disable);
 
-        // Now re-arrange in case the modal window was tiled.
+        workspace.raise_window(&window);
+        // Now re-arrange in case the dialog window was tiled.
         workspace.arrange_tiles(!self.fht.config.animations.disable);
+
+        // If the parent was focused, the dialog takes over.
+        let seat = self.fht.seat.clone();
+        let parent_focused = seat
+            .get_keyboard()
+            .unwrap()
+            .current_focus()
+            .is_some_and(|focus| match focus {
+                KeyboardFocusTarget::Window(focused) => window
+                    .toplevel()
+                    .parent()
+                    .is_some_and(|parent| focused.wl_surface().as_deref() == Some(&parent)),
+                _ => false,
+            });
+        if parent_focused {
+            self.set_keyboard_focus(&seat, Some(window));
+        }
+
+        true
     }
 }
 
 delegate_xdg_dialog!(State);
+
+impl Fht {
+    /// Get the modal dialog of this [`Window`], if any.
+    pub fn modal_dialog_of(&self, window: &Window) -> Option<Window> {
+        self.space
+            .windows()
+            .find(|dialog| dialog.is_modal_dialog_of(window))
+            .cloned()
+    }
+}
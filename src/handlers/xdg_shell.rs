@@ -82,8 +82,11 @@ impl XdgShellHandler for State {
         }
     }
 
-    fn move_request(&mut self, surface: ToplevelSurface, _: wl_seat::WlSeat, serial: Serial) {
-        let pointer = self.fht.pointer.clone();
+    fn move_request(&mut self, surface: ToplevelSurface, seat: wl_seat::WlSeat, serial: Serial) {
+        let Some(pointer) = Seat::<State>::from_resource(&seat).and_then(|seat| seat.get_pointer())
+        else {
+            return;
+        };
         let mut grab_start_data = None;
 
         pointer.with_grab(|grab_serial, grab| {
@@ -129,11 +132,14 @@ impl XdgShellHandler for State {
     fn resize_request(
         &mut self,
         surface: ToplevelSurface,
-        _seat: wl_seat::WlSeat,
+        seat: wl_seat::WlSeat,
         serial: Serial,
         edges: xdg_toplevel::ResizeEdge,
     ) {
-        let pointer = self.fht.pointer.clone();
+        let Some(pointer) = Seat::<State>::from_resource(&seat).and_then(|seat| seat.get_pointer())
+        else {
+            return;
+        };
         let mut grab_start_data = None;
 
         pointer.with_grab(|grab_serial, grab| {
@@ -242,10 +248,14 @@ impl XdgShellHandler for State {
                 ) {
                     window.request_maximized(true);
                 }
+            } else if let Some(window) = self.fht.unmapped_window(wl_surface) {
+                // Window rules get to decide on the initial configure, see
+                // src/handlers/compositor.rs
+                window.request_maximized(true);
             }
         }
 
-        toplevel.send_configure();
+        send_configure_if_initial_sent(&toplevel);
     }
 
     fn unmaximize_request(&mut self, toplevel: ToplevelSurface) {
@@ -256,9 +266,11 @@ impl XdgShellHandler for State {
         {
             window.request_maximized(false);
             ws.arrange_tiles(true);
+        } else if let Some(window) = self.fht.unmapped_window(toplevel.wl_surface()) {
+            window.request_maximized(false);
         }
 
-        toplevel.send_configure();
+        send_configure_if_initial_sent(&toplevel);
     }
 
     fn fullscreen_request(
@@ -283,19 +295,28 @@ impl XdgShellHandler for State {
                 if !self.fht.space.fullscreen_window(&window, true) {
                     window.request_fullscreen(false);
                 }
+            } else if let Some(window) = self.fht.unmapped_window(wl_surface) {
+                // Window rules get to decide on the initial configure, see
+                // src/handlers/compositor.rs
+                let requested = wl_output.as_ref().and_then(Output::from_resource);
+                window.set_requested_fullscreen_output(requested);
+                window.request_fullscreen(true);
             }
         }
 
-        surface.send_configure();
+        send_configure_if_initial_sent(&surface);
     }
 
     fn unfullscreen_request(&mut self, surface: ToplevelSurface) {
         if let Some(window) = self.fht.space.find_window(surface.wl_surface()) {
             // NOTE: Workspaces take care of unfullscreening and arranging
             window.request_fullscreen(false);
+        } else if let Some(window) = self.fht.unmapped_window(surface.wl_surface()) {
+            window.set_requested_fullscreen_output(None);
+            window.request_fullscreen(false);
         }
 
-        surface.send_configure();
+        send_configure_if_initial_sent(&surface);
     }
 
     fn title_changed(&mut self, surface: ToplevelSurface) {
@@ -312,6 +333,67 @@ impl XdgShellHandler for State {
         }
     }
 
+    fn parent_changed(&mut self, surface: ToplevelSurface) {
+        // The parent can get set after the initial configure, for example through xdg-foreign,
+        // where portals attach their dialogs to the window of the requesting application.
+        let parent_workspace_id = surface.parent().and_then(|parent_surface| {
+            let (_, workspace) = self.fht.space.find_window_and_workspace(&parent_surface)?;
+            Some(workspace.id())
+        });
+
+        if let Some(unmapped) = self
+            .fht
+            .unmapped_windows
+            .iter_mut()
+            .find(|unmapped| *unmapped.window().toplevel() == surface)
+        {
+            // Unconfigured windows will check their parent when getting their initial configure.
+            let UnmappedWindow::Configured {
+                window,
+                workspace_id,
+            } = unmapped
+            else {
+                return;
+            };
+
+            let mut rules = window.rules().clone();
+            match parent_workspace_id {
+                Some(parent_workspace_id) if rules.floating != Some(false) => {
+                    if rules.open_on_output.is_none() && rules.open_on_workspace.is_none() {
+                        *workspace_id = parent_workspace_id;
+                    }
+                    rules.floating = Some(true);
+                    rules.centered = None;
+                    rules.centered_in_parent = Some(true);
+                    window.request_tiled(false);
+                    window.reset_size();
+                }
+                _ => {
+                    // Centering in the parent requires one.
+                    if rules.centered_in_parent.take().is_some() {
+                        rules.centered = Some(true);
+                    }
+                }
+            }
+            window.set_rules(rules);
+            window.send_pending_configure();
+            return;
+        }
+
+        let Some(window) = self.fht.space.find_window(surface.wl_surface()) else {
+            return;
+        };
+        let same_workspace = parent_workspace_id.is_some_and(|parent_workspace_id| {
+            self.fht
+                .space
+                .workspace_for_window(&window)
+                .is_some_and(|workspace| workspace.id() == parent_workspace_id)
+        });
+        if same_workspace && window.rules().floating != Some(false) {
+            self.center_toplevel_on_parent(&surface);
+        }
+    }
+
     fn reposition_request(
         &mut self,
         surface: PopupSurface,
@@ -328,6 +410,16 @@ impl XdgShellHandler for State {
     }
 }
 
+/// Send a configure to this toplevel, unless it did not receive its initial configure yet.
+///
+/// The initial configure is sent once the toplevel got configured with window rules, see
+/// src/handlers/compositor.rs
+fn send_configure_if_initial_sent(toplevel: &ToplevelSurface) {
+    if toplevel.is_initial_configure_sent() {
+        toplevel.send_configure();
+    }
+}
+
 pub(super) fn add_window_pre_commit_hook(window: &Window) {
     // The workspace tile api is not responsible for actually starting the close animations, we are
     // the ones that should do this.
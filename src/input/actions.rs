@@ -1,9 +1,13 @@
 use std::sync::Arc;
 use std::time::Duration;
 
-use fht_compositor_config::{KeyPattern, MouseAction, WorkspaceLayout};
+use fht_compositor_config::{
+    ColorFilter, KeyPattern, MouseAction, SplitDirection, WorkspaceLayout, WorkspaceSwitchFocus,
+};
 use smithay::desktop::WindowSurfaceType;
 use smithay::input::pointer::{self, CursorIcon, CursorImageStatus, Focus};
+use smithay::input::Seat;
+use smithay::output::Output;
 use smithay::reexports::calloop::timer::{TimeoutAction, Timer};
 use smithay::utils::{Point, Rectangle, Serial};
 use smithay::wayland::seat::WaylandFocus;
@@ -28,11 +32,32 @@ pub enum KeyActionType {
     SelectPreviousLayout,
     ChangeMwfact(f64),
     ChangeNmaster(i32),
+    ChangeInnerGaps(i32),
+    ChangeOuterGaps(i32),
+    ChangeAllInnerGaps(i32),
+    ChangeAllOuterGaps(i32),
+    SetSplitDirection(SplitDirection),
+    RotateSplit,
+    EqualizeSplits,
+    ConsumeIntoColumn,
+    ExpelFromColumn,
+    ScrollView(i32),
+    TabWithNextWindow,
+    UntabFocusedWindow,
+    FocusNextTab,
+    FocusPreviousTab,
+    ToggleStackedContainer,
+    MarkInsertionPoint,
     ChangeProportion(f64),
+    ChangeOpacity(f32),
     MaximizeFocusedWindow,
     FullscreenFocusedWindow,
     FloatFocusedWindow,
     CenterFloatingWindow,
+    RaiseFloatingWindow,
+    LowerFloatingWindow,
+    ToggleRaiseFloatingWindow,
+    PinFocusedWindow,
     MoveFloatingWindow([i32; 2]),
     ResizeFloatingWindow([i32; 2]),
     FocusNextWindow,
@@ -43,9 +68,18 @@ pub enum KeyActionType {
     FocusPreviousOutput,
     CloseFocusedWindow,
     FocusWorkspace(usize),
+    FocusWorkspaceOnOutput(String, usize),
+    ToggleColorFilter(ColorFilter),
     SendFocusedWindowToWorkspace(usize),
     FocusNextWorkspace,
     FocusPreviousWorkspace,
+    ToggleMouseKeys,
+    CycleTabletOutput,
+    ToggleDebugOverlay,
+    ToggleFrameTimingsCapture,
+    ToggleProfiling,
+    ChangeZoom(f64),
+    ResetZoom,
     None,
 }
 
@@ -110,6 +144,18 @@ impl From<fht_compositor_config::KeyActionDesc> for KeyAction {
                     fht_compositor_config::SimpleKeyAction::CenterFloatingWindow => {
                         KeyActionType::CenterFloatingWindow
                     }
+                    fht_compositor_config::SimpleKeyAction::RaiseFloatingWindow => {
+                        KeyActionType::RaiseFloatingWindow
+                    }
+                    fht_compositor_config::SimpleKeyAction::LowerFloatingWindow => {
+                        KeyActionType::LowerFloatingWindow
+                    }
+                    fht_compositor_config::SimpleKeyAction::ToggleRaiseFloatingWindow => {
+                        KeyActionType::ToggleRaiseFloatingWindow
+                    }
+                    fht_compositor_config::SimpleKeyAction::PinFocusedWindow => {
+                        KeyActionType::PinFocusedWindow
+                    }
                     fht_compositor_config::SimpleKeyAction::FocusPreviousWindow => {
                         KeyActionType::FocusPreviousWindow
                     }
@@ -134,6 +180,52 @@ impl From<fht_compositor_config::KeyActionDesc> for KeyAction {
                     fht_compositor_config::SimpleKeyAction::FocusPreviousWorkspace => {
                         KeyActionType::FocusPreviousWorkspace
                     }
+                    fht_compositor_config::SimpleKeyAction::ToggleMouseKeys => {
+                        KeyActionType::ToggleMouseKeys
+                    }
+                    fht_compositor_config::SimpleKeyAction::CycleTabletOutput => {
+                        KeyActionType::CycleTabletOutput
+                    }
+                    fht_compositor_config::SimpleKeyAction::ToggleDebugOverlay => {
+                        KeyActionType::ToggleDebugOverlay
+                    }
+                    fht_compositor_config::SimpleKeyAction::ToggleFrameTimingsCapture => {
+                        KeyActionType::ToggleFrameTimingsCapture
+                    }
+                    fht_compositor_config::SimpleKeyAction::ToggleProfiling => {
+                        KeyActionType::ToggleProfiling
+                    }
+                    fht_compositor_config::SimpleKeyAction::ResetZoom => KeyActionType::ResetZoom,
+                    fht_compositor_config::SimpleKeyAction::RotateSplit => {
+                        KeyActionType::RotateSplit
+                    }
+                    fht_compositor_config::SimpleKeyAction::EqualizeSplits => {
+                        KeyActionType::EqualizeSplits
+                    }
+                    fht_compositor_config::SimpleKeyAction::ConsumeIntoColumn => {
+                        KeyActionType::ConsumeIntoColumn
+                    }
+                    fht_compositor_config::SimpleKeyAction::ExpelFromColumn => {
+                        KeyActionType::ExpelFromColumn
+                    }
+                    fht_compositor_config::SimpleKeyAction::TabWithNextWindow => {
+                        KeyActionType::TabWithNextWindow
+                    }
+                    fht_compositor_config::SimpleKeyAction::UntabFocusedWindow => {
+                        KeyActionType::UntabFocusedWindow
+                    }
+                    fht_compositor_config::SimpleKeyAction::FocusNextTab => {
+                        KeyActionType::FocusNextTab
+                    }
+                    fht_compositor_config::SimpleKeyAction::FocusPreviousTab => {
+                        KeyActionType::FocusPreviousTab
+                    }
+                    fht_compositor_config::SimpleKeyAction::ToggleStackedContainer => {
+                        KeyActionType::ToggleStackedContainer
+                    }
+                    fht_compositor_config::SimpleKeyAction::MarkInsertionPoint => {
+                        KeyActionType::MarkInsertionPoint
+                    }
                     fht_compositor_config::SimpleKeyAction::None => KeyActionType::None,
                 };
             }
@@ -167,6 +259,18 @@ impl From<fht_compositor_config::KeyActionDesc> for KeyAction {
                     fht_compositor_config::ComplexKeyAction::CenterFloatingWindow => {
                         KeyActionType::CenterFloatingWindow
                     }
+                    fht_compositor_config::ComplexKeyAction::RaiseFloatingWindow => {
+                        KeyActionType::RaiseFloatingWindow
+                    }
+                    fht_compositor_config::ComplexKeyAction::LowerFloatingWindow => {
+                        KeyActionType::LowerFloatingWindow
+                    }
+                    fht_compositor_config::ComplexKeyAction::ToggleRaiseFloatingWindow => {
+                        KeyActionType::ToggleRaiseFloatingWindow
+                    }
+                    fht_compositor_config::ComplexKeyAction::PinFocusedWindow => {
+                        KeyActionType::PinFocusedWindow
+                    }
                     fht_compositor_config::ComplexKeyAction::MoveFloatingWindow(change) => {
                         KeyActionType::MoveFloatingWindow(change)
                     }
@@ -200,6 +304,52 @@ impl From<fht_compositor_config::KeyActionDesc> for KeyAction {
                     fht_compositor_config::ComplexKeyAction::CloseFocusedWindow => {
                         KeyActionType::CloseFocusedWindow
                     }
+                    fht_compositor_config::ComplexKeyAction::ToggleMouseKeys => {
+                        KeyActionType::ToggleMouseKeys
+                    }
+                    fht_compositor_config::ComplexKeyAction::CycleTabletOutput => {
+                        KeyActionType::CycleTabletOutput
+                    }
+                    fht_compositor_config::ComplexKeyAction::ToggleDebugOverlay => {
+                        KeyActionType::ToggleDebugOverlay
+                    }
+                    fht_compositor_config::ComplexKeyAction::ToggleFrameTimingsCapture => {
+                        KeyActionType::ToggleFrameTimingsCapture
+                    }
+                    fht_compositor_config::ComplexKeyAction::ToggleProfiling => {
+                        KeyActionType::ToggleProfiling
+                    }
+                    fht_compositor_config::ComplexKeyAction::ResetZoom => KeyActionType::ResetZoom,
+                    fht_compositor_config::ComplexKeyAction::RotateSplit => {
+                        KeyActionType::RotateSplit
+                    }
+                    fht_compositor_config::ComplexKeyAction::EqualizeSplits => {
+                        KeyActionType::EqualizeSplits
+                    }
+                    fht_compositor_config::ComplexKeyAction::ConsumeIntoColumn => {
+                        KeyActionType::ConsumeIntoColumn
+                    }
+                    fht_compositor_config::ComplexKeyAction::ExpelFromColumn => {
+                        KeyActionType::ExpelFromColumn
+                    }
+                    fht_compositor_config::ComplexKeyAction::TabWithNextWindow => {
+                        KeyActionType::TabWithNextWindow
+                    }
+                    fht_compositor_config::ComplexKeyAction::UntabFocusedWindow => {
+                        KeyActionType::UntabFocusedWindow
+                    }
+                    fht_compositor_config::ComplexKeyAction::FocusNextTab => {
+                        KeyActionType::FocusNextTab
+                    }
+                    fht_compositor_config::ComplexKeyAction::FocusPreviousTab => {
+                        KeyActionType::FocusPreviousTab
+                    }
+                    fht_compositor_config::ComplexKeyAction::ToggleStackedContainer => {
+                        KeyActionType::ToggleStackedContainer
+                    }
+                    fht_compositor_config::ComplexKeyAction::MarkInsertionPoint => {
+                        KeyActionType::MarkInsertionPoint
+                    }
                     fht_compositor_config::ComplexKeyAction::None => KeyActionType::None,
                     fht_compositor_config::ComplexKeyAction::RunCommand(cmd) => {
                         KeyActionType::RunCommand(cmd)
@@ -210,15 +360,46 @@ impl From<fht_compositor_config::KeyActionDesc> for KeyAction {
                     fht_compositor_config::ComplexKeyAction::ChangeNmaster(delta) => {
                         KeyActionType::ChangeNmaster(delta)
                     }
+                    fht_compositor_config::ComplexKeyAction::ChangeInnerGaps(delta) => {
+                        KeyActionType::ChangeInnerGaps(delta)
+                    }
+                    fht_compositor_config::ComplexKeyAction::ChangeOuterGaps(delta) => {
+                        KeyActionType::ChangeOuterGaps(delta)
+                    }
+                    fht_compositor_config::ComplexKeyAction::ChangeAllInnerGaps(delta) => {
+                        KeyActionType::ChangeAllInnerGaps(delta)
+                    }
+                    fht_compositor_config::ComplexKeyAction::ChangeAllOuterGaps(delta) => {
+                        KeyActionType::ChangeAllOuterGaps(delta)
+                    }
+                    fht_compositor_config::ComplexKeyAction::SetSplitDirection(direction) => {
+                        KeyActionType::SetSplitDirection(direction)
+                    }
+                    fht_compositor_config::ComplexKeyAction::ScrollView(delta) => {
+                        KeyActionType::ScrollView(delta)
+                    }
                     fht_compositor_config::ComplexKeyAction::ChangeWindowProportion(delta) => {
                         KeyActionType::ChangeProportion(delta)
                     }
+                    fht_compositor_config::ComplexKeyAction::ChangeWindowOpacity(delta) => {
+                        KeyActionType::ChangeOpacity(delta)
+                    }
                     fht_compositor_config::ComplexKeyAction::FocusWorkspace(idx) => {
                         KeyActionType::FocusWorkspace(idx)
                     }
                     fht_compositor_config::ComplexKeyAction::SendToWorkspace(idx) => {
                         KeyActionType::SendFocusedWindowToWorkspace(idx)
                     }
+                    fht_compositor_config::ComplexKeyAction::FocusWorkspaceOnOutput(
+                        output,
+                        idx,
+                    ) => KeyActionType::FocusWorkspaceOnOutput(output, idx),
+                    fht_compositor_config::ComplexKeyAction::ToggleColorFilter(filter) => {
+                        KeyActionType::ToggleColorFilter(filter)
+                    }
+                    fht_compositor_config::ComplexKeyAction::ChangeZoom(delta) => {
+                        KeyActionType::ChangeZoom(delta)
+                    }
                 };
             }
         }
@@ -232,7 +413,12 @@ impl From<fht_compositor_config::KeyActionDesc> for KeyAction {
 }
 
 impl State {
-    pub fn process_key_action(&mut self, action: KeyAction, key_pattern: KeyPattern) {
+    pub fn process_key_action(
+        &mut self,
+        seat: &Seat<State>,
+        action: KeyAction,
+        key_pattern: KeyPattern,
+    ) {
         crate::profile_function!();
         if self.fht.is_locked() && !action.allow_while_locked {
             return;
@@ -243,18 +429,55 @@ impl State {
         let active_window = self.fht.space.active_window();
 
         match &action.r#type {
-            KeyActionType::Quit => self.fht.stop = true,
+            KeyActionType::Quit => self.fht.quit(),
             KeyActionType::ReloadConfig => self.reload_config(),
-            KeyActionType::RunCommand(cmd) => crate::utils::spawn(cmd),
+            KeyActionType::RunCommand(cmd) => self.fht.spawn(cmd),
             KeyActionType::SelectNextLayout => self.fht.space.select_next_layout(true),
             KeyActionType::SelectPreviousLayout => self.fht.space.select_previous_layout(true),
             KeyActionType::ChangeMwfact(delta) => self.fht.space.change_mwfact(*delta, true),
             KeyActionType::ChangeNmaster(delta) => self.fht.space.change_nmaster(*delta, true),
+            KeyActionType::ChangeInnerGaps(delta) => self.fht.space.change_gaps(0, *delta, true),
+            KeyActionType::ChangeOuterGaps(delta) => self.fht.space.change_gaps(*delta, 0, true),
+            KeyActionType::ChangeAllInnerGaps(delta) => {
+                self.fht.space.change_all_gaps(0, *delta, true)
+            }
+            KeyActionType::ChangeAllOuterGaps(delta) => {
+                self.fht.space.change_all_gaps(*delta, 0, true)
+            }
+            KeyActionType::SetSplitDirection(direction) => {
+                self.fht.space.set_split_direction(*direction)
+            }
+            KeyActionType::RotateSplit => self.fht.space.rotate_split(true),
+            KeyActionType::EqualizeSplits => self.fht.space.equalize_splits(true),
+            KeyActionType::ConsumeIntoColumn => self.fht.space.consume_into_column(true),
+            KeyActionType::ExpelFromColumn => self.fht.space.expel_from_column(true),
+            KeyActionType::ScrollView(delta) => self.fht.space.scroll_view(*delta, true),
+            KeyActionType::TabWithNextWindow => self.fht.space.tab_with_next_window(true),
+            KeyActionType::UntabFocusedWindow => self.fht.space.untab_focused_window(true),
+            KeyActionType::ToggleStackedContainer => self.fht.space.toggle_stacked_container(true),
+            KeyActionType::MarkInsertionPoint => self.fht.space.toggle_insertion_mark(),
+            KeyActionType::FocusNextTab => {
+                let active = self.fht.space.active_workspace_mut();
+                if let Some(window) = active.activate_next_tab(true) {
+                    self.set_keyboard_focus(seat, Some(window));
+                }
+            }
+            KeyActionType::FocusPreviousTab => {
+                let active = self.fht.space.active_workspace_mut();
+                if let Some(window) = active.activate_next_tab(false) {
+                    self.set_keyboard_focus(seat, Some(window));
+                }
+            }
             KeyActionType::ChangeProportion(delta) => {
                 if let Some(window) = active_window {
                     self.fht.space.change_proportion(&window, *delta, true)
                 }
             }
+            KeyActionType::ChangeOpacity(delta) => {
+                if let Some(tile) = self.fht.space.active_workspace_mut().active_tile_mut() {
+                    tile.change_opacity(*delta);
+                }
+            }
             KeyActionType::MaximizeFocusedWindow => {
                 if let Some(window) = active_window {
                     let prev = window.maximized();
@@ -275,8 +498,14 @@ impl State {
             KeyActionType::FloatFocusedWindow => {
                 let active = self.fht.space.active_workspace_mut();
                 if let Some(tile) = active.active_tile() {
-                    let prev = tile.window().tiled();
-                    tile.window().request_tiled(!prev);
+                    let window = tile.window();
+                    let prev = window.tiled();
+                    if !prev && window.saved_floating_geometry().is_none() {
+                        // Remember the floating geometry for when the window gets tiled, it will
+                        // get it back when floating again.
+                        window.set_saved_floating_geometry(Some(tile.geometry()));
+                    }
+                    window.request_tiled(!prev);
                 }
                 active.arrange_tiles(true);
             }
@@ -290,6 +519,28 @@ impl State {
                     }
                 }
             }
+            KeyActionType::RaiseFloatingWindow
+            | KeyActionType::LowerFloatingWindow
+            | KeyActionType::ToggleRaiseFloatingWindow => {
+                let active = self.fht.space.active_workspace_mut();
+                let is_floating_layout = active.current_layout() == WorkspaceLayout::Floating;
+                if let Some(window) = active
+                    .active_window()
+                    .filter(|window| is_floating_layout || !window.tiled())
+                {
+                    match &action.r#type {
+                        KeyActionType::RaiseFloatingWindow => active.raise_window(&window),
+                        KeyActionType::LowerFloatingWindow => active.lower_window(&window),
+                        _ => active.toggle_raise_window(&window),
+                    }
+                    self.fht.queue_redraw(&output);
+                }
+            }
+            KeyActionType::PinFocusedWindow => {
+                if let Some(window) = active_window {
+                    window.set_pinned(!window.pinned());
+                }
+            }
             KeyActionType::MoveFloatingWindow([dx, dy]) => {
                 let active = self.fht.space.active_workspace_mut();
                 let is_floating_layout = active.current_layout() == WorkspaceLayout::Floating;
@@ -324,9 +575,9 @@ impl State {
                             window.size(),
                         );
 
-                        self.move_pointer(window_geometry.center().to_f64())
+                        self.move_pointer(seat, window_geometry.center().to_f64())
                     }
-                    self.set_keyboard_focus(Some(window));
+                    self.set_keyboard_focus(seat, Some(window));
                 }
             }
             KeyActionType::FocusPreviousWindow => {
@@ -339,9 +590,9 @@ impl State {
                             window.size(),
                         );
 
-                        self.move_pointer(window_geometry.center().to_f64())
+                        self.move_pointer(seat, window_geometry.center().to_f64())
                     }
-                    self.set_keyboard_focus(Some(window));
+                    self.set_keyboard_focus(seat, Some(window));
                 }
             }
             KeyActionType::SwapWithNextWindow => {
@@ -351,9 +602,9 @@ impl State {
                     let window = tile.window().clone();
                     if config.general.cursor_warps {
                         let tile_geo = tile.geometry();
-                        self.move_pointer(tile_geo.center().to_f64())
+                        self.move_pointer(seat, tile_geo.center().to_f64())
                     }
-                    self.set_keyboard_focus(Some(window));
+                    self.set_keyboard_focus(seat, Some(window));
                 }
             }
             KeyActionType::SwapWithPreviousWindow => {
@@ -363,9 +614,9 @@ impl State {
                     let window = tile.window().clone();
                     if config.general.cursor_warps {
                         let tile_geo = tile.geometry();
-                        self.move_pointer(tile_geo.center().to_f64())
+                        self.move_pointer(seat, tile_geo.center().to_f64())
                     }
-                    self.set_keyboard_focus(Some(window));
+                    self.set_keyboard_focus(seat, Some(window));
                 }
             }
             KeyActionType::FocusNextOutput => {
@@ -388,10 +639,10 @@ impl State {
                 let output = outputs.into_iter().nth(next_output_idx).unwrap();
                 if config.general.cursor_warps {
                     let center = output.geometry().center();
-                    self.move_pointer(center.to_f64());
+                    self.move_pointer(seat, center.to_f64());
                 }
                 if let Some(window) = self.fht.space.set_active_output(&output) {
-                    self.set_keyboard_focus(Some(window));
+                    self.set_keyboard_focus(seat, Some(window));
                 }
             }
             KeyActionType::FocusPreviousOutput => {
@@ -414,10 +665,10 @@ impl State {
                 let output = outputs.into_iter().nth(next_output_idx).unwrap();
                 if config.general.cursor_warps {
                     let center = output.geometry().center();
-                    self.move_pointer(center.to_f64());
+                    self.move_pointer(seat, center.to_f64());
                 }
                 if let Some(window) = self.fht.space.set_active_output(&output) {
-                    self.set_keyboard_focus(Some(window));
+                    self.set_keyboard_focus(seat, Some(window));
                 }
             }
             KeyActionType::CloseFocusedWindow => {
@@ -425,25 +676,24 @@ impl State {
                     window.toplevel().send_close();
                 }
             }
-            KeyActionType::FocusWorkspace(idx) => {
-                let mon = self.fht.space.active_monitor_mut();
-                if let Some(window) = mon.set_active_workspace_idx(*idx, true) {
-                    self.set_keyboard_focus(Some(window));
+            KeyActionType::FocusWorkspace(idx) => self.focus_workspace(seat, *idx),
+            KeyActionType::FocusWorkspaceOnOutput(name, idx) => {
+                if let Some(output) = self.fht.output_named(name) {
+                    self.focus_workspace_on_output(seat, &output, *idx);
                 }
             }
+            KeyActionType::ToggleColorFilter(filter) => {
+                self.fht.toggle_color_filter(&output, *filter);
+            }
             KeyActionType::FocusNextWorkspace => {
-                let mon = self.fht.space.active_monitor_mut();
+                let mon = self.fht.space.active_monitor();
                 let idx = (mon.active_workspace_idx() + 1).clamp(0, 8);
-                if let Some(window) = mon.set_active_workspace_idx(idx, true) {
-                    self.set_keyboard_focus(Some(window));
-                }
+                self.focus_workspace(seat, idx);
             }
             KeyActionType::FocusPreviousWorkspace => {
-                let mon = self.fht.space.active_monitor_mut();
+                let mon = self.fht.space.active_monitor();
                 let idx = mon.active_workspace_idx().saturating_sub(1);
-                if let Some(window) = mon.set_active_workspace_idx(idx, true) {
-                    self.set_keyboard_focus(Some(window));
-                }
+                self.focus_workspace(seat, idx);
             }
             KeyActionType::SendFocusedWindowToWorkspace(idx) => {
                 let active = self.fht.space.active_workspace_mut();
@@ -453,7 +703,7 @@ impl State {
                 if active.remove_window(&window, true) {
                     if let Some(window) = active.active_window() {
                         // Focus the new one now
-                        self.set_keyboard_focus(Some(window));
+                        self.set_keyboard_focus(seat, Some(window));
                     }
 
                     let idx = (*idx).clamp(0, 9);
@@ -461,6 +711,21 @@ impl State {
                     mon.workspace_mut_by_index(idx).insert_window(window, true);
                 }
             }
+            KeyActionType::ToggleMouseKeys => self.toggle_mouse_keys(seat),
+            KeyActionType::CycleTabletOutput => self.cycle_tablet_output(),
+            KeyActionType::ToggleDebugOverlay => self.fht.toggle_debug_overlay(),
+            KeyActionType::ToggleFrameTimingsCapture => {
+                if let Err(err) = self.fht.toggle_frame_timings_capture() {
+                    warn!(?err, "Failed to toggle frame timings capture");
+                }
+            }
+            KeyActionType::ToggleProfiling => {
+                if let Err(err) = crate::profiling::toggle_profiling() {
+                    warn!(?err, "Failed to toggle profiling");
+                }
+            }
+            KeyActionType::ChangeZoom(delta) => self.fht.change_zoom(&output, *delta),
+            KeyActionType::ResetZoom => self.fht.reset_zoom(&output),
             KeyActionType::None => (), // disabled the key combo
         }
 
@@ -481,11 +746,12 @@ impl State {
 
             let action = action.clone();
             let keysym = key_pattern.1;
+            let seat = seat.clone();
             let Ok(token) = self
                 .fht
                 .loop_handle
                 .insert_source(repeat_timer, move |_, _, state| {
-                    state.process_key_action(action.clone(), key_pattern);
+                    state.process_key_action(&seat, action.clone(), key_pattern);
                     TimeoutAction::ToDuration(repeat_duration)
                 })
                 .inspect_err(|err| error!(?err, "Failed to create keyaction repeat timer"))
@@ -496,15 +762,69 @@ impl State {
             self.fht.repeated_keyaction_timer = Some((token, keysym));
         }
     }
+
+    /// Switch to this workspace on the active output.
+    ///
+    /// What gets focused afterwards depends on `general.workspace-switch-focus`.
+    fn focus_workspace(&mut self, seat: &Seat<State>, idx: usize) {
+        let mon = self.fht.space.active_monitor_mut();
+        if mon.active_workspace_idx() == idx {
+            return;
+        }
+        mon.set_active_workspace_idx(idx, true);
+
+        let workspace = mon.active_workspace();
+        let window = match self.fht.config.general.workspace_switch_focus {
+            WorkspaceSwitchFocus::LastFocused => workspace.active_window(),
+            WorkspaceSwitchFocus::Master => workspace
+                .windows()
+                .find(|window| window.tiled())
+                .or_else(|| workspace.windows().next())
+                .cloned(),
+            WorkspaceSwitchFocus::UnderCursor => {
+                let pointer_location = seat.get_pointer().unwrap().current_location();
+                self.fht
+                    .space
+                    .window_under(pointer_location)
+                    .map(|(window, _)| window)
+            }
+            WorkspaceSwitchFocus::None => None,
+        };
+
+        if let Some(window) = &window {
+            self.fht.space.activate_window(window, false, false);
+        }
+        self.set_keyboard_focus(seat, window);
+    }
+
+    /// Focus the workspace with this index on an output, without focusing the output itself.
+    pub fn focus_workspace_on_output(&mut self, seat: &Seat<State>, output: &Output, idx: usize) {
+        let idx = idx.clamp(0, 8);
+        if self.fht.space.active_output() == output {
+            self.focus_workspace(seat, idx);
+            return;
+        }
+
+        if let Some(mon) = self.fht.space.monitor_mut_for_output(output) {
+            mon.set_active_workspace_idx(idx, true);
+            self.fht.queue_redraw(output);
+        }
+    }
 }
 
 impl State {
-    pub fn process_mouse_action(&mut self, button: u32, action: MouseAction, serial: Serial) {
+    pub fn process_mouse_action(
+        &mut self,
+        seat: &Seat<State>,
+        button: u32,
+        action: MouseAction,
+        serial: Serial,
+    ) {
         crate::profile_function!();
 
         match action {
             MouseAction::SwapTile => {
-                let pointer_loc = self.fht.pointer.current_location();
+                let pointer_loc = seat.get_pointer().unwrap().current_location();
                 if let Some((PointerFocusTarget::Window(window), _)) =
                     self.fht.focus_target_under(pointer_loc)
                 {
@@ -514,7 +834,7 @@ impl State {
                         .output_for_surface(&*window.wl_surface().unwrap())
                         .unwrap()
                         .clone();
-                    let pointer = self.fht.pointer.clone();
+                    let pointer = seat.get_pointer().unwrap();
                     let start_data = pointer::GrabStartData {
                         focus: None,
                         button,
@@ -523,6 +843,7 @@ impl State {
 
                     if self.fht.space.start_interactive_swap(&window) {
                         let grab = SwapTileGrab {
+                            seat: seat.clone(),
                             window,
                             output,
                             start_data,
@@ -535,7 +856,7 @@ impl State {
                 }
             }
             MouseAction::ResizeTile => {
-                let pointer_loc = self.fht.pointer.current_location();
+                let pointer_loc = seat.get_pointer().unwrap().current_location();
                 if let Some((PointerFocusTarget::Window(window), _)) =
                     self.fht.focus_target_under(pointer_loc)
                 {
@@ -546,7 +867,7 @@ impl State {
                         (true, _) => return,
                     }
 
-                    let pointer_loc = self.fht.pointer.current_location();
+                    let pointer_loc = seat.get_pointer().unwrap().current_location();
                     let loc = self.fht.space.window_location(&window).unwrap().to_f64();
                     let size = window.size();
 
@@ -574,7 +895,7 @@ impl State {
                         edges |= ResizeEdge::BOTTOM;
                     }
 
-                    let pointer = self.fht.pointer.clone();
+                    let pointer = seat.get_pointer().unwrap();
                     let start_data = pointer::GrabStartData {
                         focus: None,
                         button,
@@ -1,6 +1,12 @@
 pub mod actions;
+pub mod mouse_keys;
+pub mod region_select_grab;
+#[cfg(feature = "xdg-remote-desktop-portal")]
+mod remote_desktop;
 pub mod resize_tile_grab;
 pub mod swap_tile_grab;
+pub mod tablet;
+pub mod touch;
 
 pub use actions::*;
 use fht_compositor_config::KeyPattern;
@@ -9,13 +15,14 @@ use smithay::backend::input::{
     GestureEndEvent, GesturePinchUpdateEvent, GestureSwipeUpdateEvent, InputBackend, InputEvent,
     KeyState, KeyboardKeyEvent, PointerAxisEvent, PointerButtonEvent, PointerMotionEvent,
     ProximityState, TabletToolButtonEvent, TabletToolEvent, TabletToolProximityEvent,
-    TabletToolTipEvent, TabletToolTipState,
+    TabletToolTipEvent, TabletToolTipState, TouchEvent,
 };
 use smithay::desktop::{layer_map_for_output, WindowSurfaceType};
 use smithay::input::keyboard::FilterResult;
 use smithay::input::pointer::{self, AxisFrame, ButtonEvent, MotionEvent, RelativeMotionEvent};
+use smithay::input::{touch, Seat};
 use smithay::reexports::wayland_server::protocol::wl_pointer;
-use smithay::utils::{Logical, Point, SERIAL_COUNTER};
+use smithay::utils::{IsAlive, Logical, Point, SERIAL_COUNTER};
 use smithay::wayland::compositor::with_states;
 use smithay::wayland::input_method::InputMethodSeat;
 use smithay::wayland::keyboard_shortcuts_inhibit::KeyboardShortcutsInhibitorSeat;
@@ -29,12 +36,19 @@ use crate::focus_target::{KeyboardFocusTarget, PointerFocusTarget};
 use crate::output::OutputExt;
 use crate::state::State;
 
+/// Upper bound of the time between two pointer motion events used for acceleration curves.
+const MAX_POINTER_MOTION_INTERVAL_MS: f64 = 20.0;
+
 impl State {
-    fn update_keyboard_focus(&mut self) {
+    /// Update the keyboard focus to whatever is under the pointer.
+    ///
+    /// `clicked` should be set when the focus update comes from a button press, in order to raise
+    /// the focused window if enabled in the configuration.
+    fn update_keyboard_focus(&mut self, seat: &Seat<State>, clicked: bool) {
         crate::profile_function!();
-        let keyboard = self.fht.keyboard.clone();
-        let pointer = self.fht.pointer.clone();
-        let input_method = self.fht.seat.input_method();
+        let keyboard = seat.get_keyboard().unwrap();
+        let pointer = seat.get_pointer().unwrap();
+        let input_method = seat.input_method();
 
         // Update the current keyboard focus if both the keyboard and the pointer are not grabbed.
         //
@@ -55,10 +69,10 @@ impl State {
         if self.fht.is_locked() {
             let output_state = self.fht.output_state.get(output).unwrap();
             if let Some(lock_surface) = output_state.lock_surface.clone() {
-                self.set_keyboard_focus(Some(lock_surface));
+                self.set_keyboard_focus(seat, Some(lock_surface));
                 return;
             } else {
-                self.set_keyboard_focus(Option::<LockSurface>::None);
+                self.set_keyboard_focus(seat, Option::<LockSurface>::None);
                 return;
             }
         }
@@ -80,7 +94,7 @@ impl State {
                     )
                     .is_some()
                 {
-                    self.set_keyboard_focus(Some(layer.clone()));
+                    self.set_keyboard_focus(seat, Some(layer.clone()));
                 }
             }
         } else if let Some(fullscreen) = monitor.active_workspace().fullscreened_window() {
@@ -90,7 +104,7 @@ impl State {
                 .is_some()
             {
                 let fullscreen = fullscreen.clone();
-                self.set_keyboard_focus(Some(fullscreen));
+                self.set_keyboard_focus(seat, Some(fullscreen));
                 return;
             }
         } else if let Some(layer) = layer_map.layer_under(Layer::Top, pointer_loc) {
@@ -103,13 +117,14 @@ impl State {
                     )
                     .is_some()
                 {
-                    self.set_keyboard_focus(Some(layer.clone()));
+                    self.set_keyboard_focus(seat, Some(layer.clone()));
                     return;
                 }
             }
         } else if let Some((window, _)) = self.fht.space.window_under(pointer_loc) {
-            assert!(self.fht.space.activate_window(&window, true));
-            self.set_keyboard_focus(Some(window));
+            let raise = clicked && self.fht.config.general.click_to_raise;
+            assert!(self.fht.space.activate_window(&window, raise, true));
+            self.set_keyboard_focus(seat, Some(window));
         } else if let Some(layer) = layer_map
             .layer_under(Layer::Bottom, pointer_loc)
             .or_else(|| layer_map.layer_under(Layer::Background, pointer_loc))
@@ -123,19 +138,52 @@ impl State {
                     )
                     .is_some()
                 {
-                    self.set_keyboard_focus(Some(layer.clone()));
+                    self.set_keyboard_focus(seat, Some(layer.clone()));
                     return;
                 }
             }
         }
     }
 
-    pub fn set_keyboard_focus(&mut self, ft: Option<impl Into<KeyboardFocusTarget>>) {
-        let ft = ft.map(Into::into);
-        self.fht
-            .keyboard
-            .clone()
-            .set_focus(self, ft, SERIAL_COUNTER.next_serial());
+    pub fn set_keyboard_focus(
+        &mut self,
+        seat: &Seat<State>,
+        ft: Option<impl Into<KeyboardFocusTarget>>,
+    ) {
+        let keyboard = seat.get_keyboard().unwrap();
+        let ft = ft.map(Into::into).map(|ft| match ft {
+            // Focusing a window with a modal dialog focuses the dialog instead.
+            KeyboardFocusTarget::Window(window) => match self.fht.modal_dialog_of(&window) {
+                Some(dialog) => {
+                    self.fht.space.activate_window(&dialog, true, true);
+                    KeyboardFocusTarget::Window(dialog)
+                }
+                None => KeyboardFocusTarget::Window(window),
+            },
+            ft => ft,
+        });
+
+        if ft
+            .as_ref()
+            .is_some_and(KeyboardFocusTarget::is_exclusive_layer)
+        {
+            // Remember what we had focused to give it back once the layer surface goes away.
+            let current_focus = keyboard.current_focus().filter(|current| {
+                Some(current) != ft.as_ref()
+                    && current.alive()
+                    && match current {
+                        KeyboardFocusTarget::LayerSurface(layer) => self.fht.is_layer_mapped(layer),
+                        _ => true,
+                    }
+            });
+            if let Some(current_focus) = current_focus {
+                self.fht.focus_restore_stack.push(current_focus);
+            }
+        } else {
+            self.fht.focus_restore_stack.clear();
+        }
+
+        keyboard.set_focus(self, ft, SERIAL_COUNTER.next_serial());
     }
 
     fn has_active_layer(&self) -> bool {
@@ -188,8 +236,64 @@ impl State {
         None
     }
 
-    pub fn move_pointer(&mut self, point: Point<f64, Logical>) {
-        let pointer = self.fht.pointer.clone();
+    /// Move the pointer to this location from an injected event (remote desktop, virtual pointer)
+    ///
+    /// The location gets clamped and the output under it becomes the active one, like physical
+    /// pointer motion.
+    pub fn move_injected_pointer(&mut self, seat: &Seat<State>, location: Point<f64, Logical>) {
+        let location = self.clamp_coords(location);
+        let maybe_new_output = self
+            .fht
+            .space
+            .outputs()
+            .find(|output| output.geometry().to_f64().contains(location))
+            .cloned();
+        if let Some(new_output) = maybe_new_output {
+            self.fht.space.set_active_output(&new_output);
+        }
+
+        self.move_pointer(seat, location);
+    }
+
+    /// Look up the key binding triggered by an injected key (remote desktop, virtual keyboard).
+    ///
+    /// Injected keys only trigger key bindings with `input.virtual-input-bindings`. A key that
+    /// triggers a binding gets intercepted, along with its release, and the key action to run is
+    /// returned.
+    pub fn filter_injected_key(
+        &mut self,
+        key_pattern: KeyPattern,
+        pressed: bool,
+    ) -> FilterResult<Option<KeyAction>> {
+        let keysym = key_pattern.1;
+        if !pressed {
+            if !self.fht.suppressed_keys.remove(&keysym) {
+                return FilterResult::Forward;
+            }
+            if let Some((token, _)) = self
+                .fht
+                .repeated_keyaction_timer
+                .take_if(|(_, k)| *k == keysym)
+            {
+                self.fht.loop_handle.remove(token);
+            }
+            return FilterResult::Intercept(None);
+        }
+
+        if !self.fht.config.input.virtual_input_bindings {
+            return FilterResult::Forward;
+        }
+        match self.fht.config.keybinds.get(&key_pattern).cloned() {
+            Some(action) => {
+                self.fht.suppressed_keys.insert(keysym);
+                FilterResult::Intercept(Some(action.into()))
+            }
+            None => FilterResult::Forward,
+        }
+    }
+
+    pub fn move_pointer(&mut self, seat: &Seat<State>, point: Point<f64, Logical>) {
+        let pointer = seat.get_pointer().unwrap();
         let under = self.fht.focus_target_under(point);
         let filtered_under = self.filter_focus_target_for_active_layers(under.clone());
 
@@ -197,7 +301,7 @@ impl State {
             && !pointer.is_grabbed()
             && !self.has_active_layer()
         {
-            self.update_keyboard_focus();
+            self.update_keyboard_focus(seat, false);
         }
 
         pointer.motion(
@@ -213,11 +317,115 @@ impl State {
             },
         );
         pointer.frame(self);
+        self.refresh_pointer_constraints(seat);
 
         // FIXME: More granular, maybe check for where the point was and is now
         self.fht.queue_redraw_all();
     }
 
+    /// Re-evaluate the pointer constraints against the surface under the pointer.
+    ///
+    /// Windows can move below the pointer without it moving itself (layout changes, animations,
+    /// workspace switches, etc.) so the surface owning the active constraint might not be under
+    /// the pointer anymore, or the pointer can get outside of the constraint region.
+    pub fn refresh_pointer_constraints(&mut self, seat: &Seat<State>) {
+        crate::profile_function!();
+        let pointer = seat.get_pointer().unwrap();
+        if pointer.is_grabbed() {
+            return;
+        }
+
+        let pointer_location = pointer.current_location();
+        let under = self.fht.focus_target_under(pointer_location);
+        let filtered_under = self.filter_focus_target_for_active_layers(under);
+        let under_surface = filtered_under
+            .as_ref()
+            .and_then(|(ft, loc)| Some((ft.wl_surface()?.into_owned(), *loc)));
+
+        let focused_surface = pointer
+            .current_focus()
+            .and_then(|ft| ft.wl_surface().map(|s| s.into_owned()));
+        if let Some(focused_surface) = &focused_surface {
+            let still_under = under_surface
+                .as_ref()
+                .is_some_and(|(surface, _)| surface == focused_surface);
+            with_pointer_constraint(focused_surface, &pointer, |constraint| {
+                let Some(constraint) = constraint.filter(|c| c.is_active()) else {
+                    return;
+                };
+                let in_region = under_surface.as_ref().is_some_and(|(_, loc)| {
+                    constraint.region().is_none_or(|region| {
+                        region.contains((pointer_location - *loc).to_i32_round())
+                    })
+                });
+                if !still_under || !in_region {
+                    constraint.deactivate();
+                }
+            });
+        }
+
+        if focused_surface != under_surface.as_ref().map(|(surface, _)| surface.clone()) {
+            // Send the enter/leave events to the surfaces.
+            pointer.motion(
+                self,
+                filtered_under,
+                &MotionEvent {
+                    location: pointer_location,
+                    serial: SERIAL_COUNTER.next_serial(),
+                    time: {
+                        let duration: std::time::Duration = self.fht.clock.now().into();
+                        duration.as_millis() as u32
+                    },
+                },
+            );
+            pointer.frame(self);
+        }
+
+        if let Some((surface, surface_loc)) = under_surface {
+            with_pointer_constraint(&surface, &pointer, |constraint| match constraint {
+                Some(constraint) if !constraint.is_active() => {
+                    let point = (pointer_location - surface_loc).to_i32_round();
+                    if constraint
+                        .region()
+                        .is_none_or(|region| region.contains(point))
+                    {
+                        constraint.activate();
+                    }
+                }
+                _ => {}
+            });
+        }
+    }
+
+    /// Get the pointer motion delta of this event, applying the device acceleration curve if any.
+    fn pointer_motion_delta<B: InputBackend>(
+        &mut self,
+        event: &B::PointerMotionEvent,
+    ) -> Point<f64, Logical> {
+        let time = event.time();
+        let last_time = self.fht.last_pointer_motion_time.replace(time);
+        let device = event.device();
+        let Some(curve) = self
+            .fht
+            .mouse_config_for_device(&device)
+            .acceleration_curve
+            .as_ref()
+        else {
+            return event.delta();
+        };
+
+        // Clamp the elapsed time to avoid huge speeds from coalesced events and tiny speeds after
+        // the pointer stood still for a while.
+        let elapsed_ms = last_time
+            .map_or(MAX_POINTER_MOTION_INTERVAL_MS, |last_time| {
+                time.saturating_sub(last_time) as f64 / 1000.0
+            })
+            .clamp(1.0, MAX_POINTER_MOTION_INTERVAL_MS);
+        let delta_unaccel = event.delta_unaccel();
+        let speed = delta_unaccel.x.hypot(delta_unaccel.y) / elapsed_ms;
+        delta_unaccel.upscale(curve.multiplier(speed))
+    }
+
     pub fn clamp_coords(&self, pos: Point<f64, Logical>) -> Point<f64, Logical> {
         let (pos_x, pos_y) = pos.into();
         let max_x = self
@@ -243,10 +451,17 @@ impl State {
 
     pub fn process_input_event<B: InputBackend>(&mut self, event: InputEvent<B>) {
         crate::profile_function!();
+        let pointer_motion = matches!(
+            event,
+            InputEvent::PointerMotion { .. } | InputEvent::PointerMotionAbsolute { .. }
+        );
+        let seat = self.fht.seat.clone();
+        let old_pointer_location = seat.get_pointer().unwrap().current_location();
+
         match event {
             InputEvent::DeviceAdded { device } => {
                 if device.has_capability(DeviceCapability::TabletTool) {
-                    self.fht.seat.tablet_seat().add_tablet::<State>(
+                    seat.tablet_seat().add_tablet::<State>(
                         &self.fht.display_handle,
                         &TabletDescriptor::from(&device),
                     );
@@ -254,7 +469,7 @@ impl State {
             }
             InputEvent::DeviceRemoved { device } => {
                 if device.has_capability(DeviceCapability::TabletTool) {
-                    let tablet_seat = self.fht.seat.tablet_seat();
+                    let tablet_seat = seat.tablet_seat();
                     tablet_seat.remove_tablet(&TabletDescriptor::from(&device));
                     // No tablets? then just remove all associated tools.
                     if tablet_seat.count_tablets() == 0 {
@@ -268,7 +483,7 @@ impl State {
                 trace!(?keycode, ?key_state, "Key");
                 let serial = SERIAL_COUNTER.next_serial();
                 let time = event.time_msec();
-                let keyboard = self.fht.keyboard.clone();
+                let keyboard = seat.get_keyboard().unwrap();
 
                 let mut suppressed_keys = self.fht.suppressed_keys.clone();
 
@@ -297,7 +512,7 @@ impl State {
                             cloned
                         });
                         if let Some(surface) = surface {
-                            self.set_keyboard_focus(Some(surface));
+                            self.set_keyboard_focus(&seat, Some(surface));
                             keyboard.input::<(), _>(
                                 self,
                                 keycode,
@@ -311,22 +526,17 @@ impl State {
                     }
                 }
 
-                let pointer_location = self.fht.pointer.current_location();
-                let inhibited = self
-                    .fht
-                    .focus_target_under(pointer_location)
-                    .and_then(|(ft, _)| {
-                        if let PointerFocusTarget::Window(w) = ft {
-                            let wl_surface = w.wl_surface()?;
-                            self.fht
-                                .seat
-                                .keyboard_shortcuts_inhibitor_for_surface(&wl_surface)
-                        } else {
-                            None
-                        }
-                    })
-                    .map(|inhibitor| inhibitor.is_active())
-                    .unwrap_or(false);
+                // Shortcuts inhibitors only apply to the surface that has keyboard focus.
+                let shortcuts_inhibitor = keyboard
+                    .current_focus()
+                    .and_then(|ft| ft.wl_surface().map(|s| s.into_owned()))
+                    .and_then(|wl_surface| {
+                        seat.keyboard_shortcuts_inhibitor_for_surface(&wl_surface)
+                    });
+                let inhibited = shortcuts_inhibitor
+                    .as_ref()
+                    .is_some_and(|inhibitor| inhibitor.is_active());
+                let mut mouse_keys_event = None;
                 let action = keyboard.input(
                     self,
                     keycode,
@@ -369,6 +579,21 @@ impl State {
                             }
                         }
 
+                        // The region selection grabs the keyboard while its active.
+                        if state.fht.region_selection.is_some() && key_state == KeyState::Pressed {
+                            if keysym == smithay::input::keyboard::Keysym::Escape {
+                                state
+                                    .fht
+                                    .loop_handle
+                                    .insert_idle(|state| state.finish_region_selection(false));
+                            }
+                            suppressed_keys.insert(keysym);
+                            return FilterResult::Intercept((
+                                KeyAction::none(),
+                                KeyPattern::default(),
+                            ));
+                        }
+
                         #[allow(unused_mut)]
                         let mut modifiers = *modifiers;
                         // Swap ALT and SUPER under the winit backend since you are probably running
@@ -384,6 +609,45 @@ impl State {
 
                         let key_pattern =
                             fht_compositor_config::KeyPattern(modifiers.into(), keysym);
+
+                        // Mouse keys take precedence over keybinds while enabled.
+                        if let Some(mouse_keys) = state.fht.mouse_keys.as_ref() {
+                            let action = match key_state {
+                                KeyState::Pressed => state
+                                    .fht
+                                    .config
+                                    .input
+                                    .mouse_keys
+                                    .bindings
+                                    .get(&key_pattern)
+                                    .copied(),
+                                KeyState::Released => mouse_keys.held_action(keysym),
+                            };
+                            if let Some(action) = action {
+                                mouse_keys_event =
+                                    Some((keysym, action, key_state == KeyState::Pressed));
+                                return FilterResult::Intercept((KeyAction::none(), key_pattern));
+                            }
+                        }
+
+                        // The escape hatch always stays with the compositor, so that the user can
+                        // get their keybinds back from a misbehaving client.
+                        if let Some(inhibitor) = shortcuts_inhibitor.as_ref().filter(|_| {
+                            key_state == KeyState::Pressed
+                                && key_pattern
+                                    == state.fht.config.input.keyboard.shortcuts_inhibit_escape
+                        }) {
+                            if inhibitor.is_active() {
+                                debug!("Deactivating keyboard shortcuts inhibitor");
+                                inhibitor.inactivate();
+                            } else {
+                                debug!("Activating keyboard shortcuts inhibitor");
+                                inhibitor.activate();
+                            }
+                            suppressed_keys.insert(keysym);
+                            return FilterResult::Intercept((KeyAction::none(), key_pattern));
+                        }
+
                         if key_state == KeyState::Pressed && !inhibited {
                             let action = state
                                 .fht
@@ -420,12 +684,20 @@ impl State {
                 );
 
                 self.fht.suppressed_keys = suppressed_keys;
+                if action.is_none() && mouse_keys_event.is_none() {
+                    // The key went to the focused client, that will commit if it needs to be
+                    // redrawn, no need to wake up the render loop ourselves.
+                    return;
+                }
                 if let Some((action, key_pattern)) = action {
-                    self.process_key_action(action, key_pattern);
+                    self.process_key_action(&seat, action, key_pattern);
+                }
+                if let Some((keysym, action, pressed)) = mouse_keys_event {
+                    self.process_mouse_keys_event(&seat, keysym, action, pressed);
                 }
             }
             InputEvent::PointerMotion { event } => {
-                let pointer = self.fht.pointer.clone();
+                let pointer = seat.get_pointer().unwrap();
                 let mut pointer_location = pointer.current_location();
                 let under = self.fht.focus_target_under(pointer_location);
                 let filtered_under = self.filter_focus_target_for_active_layers(under.clone());
@@ -463,11 +735,12 @@ impl State {
                     });
                 }
 
+                let delta = self.pointer_motion_delta::<B>(&event);
                 pointer.relative_motion(
                     self,
                     filtered_under.clone(),
                     &RelativeMotionEvent {
-                        delta: event.delta(),
+                        delta,
                         delta_unaccel: event.delta_unaccel(),
                         utime: event.time(),
                     },
@@ -479,7 +752,7 @@ impl State {
                     return;
                 }
 
-                pointer_location += event.delta();
+                pointer_location += delta;
                 pointer_location = self.clamp_coords(pointer_location);
                 let new_under = self.fht.focus_target_under(pointer_location);
                 let filtered_new_under =
@@ -519,7 +792,7 @@ impl State {
                     && !pointer.is_grabbed()
                     && !self.has_active_layer()
                 {
-                    self.update_keyboard_focus();
+                    self.update_keyboard_focus(&seat, false);
                 }
 
                 pointer.motion(
@@ -534,8 +807,6 @@ impl State {
                 pointer.frame(self);
 
                 // If pointer is now in a constraint region, activate it
-                // TODO: Anywhere else pointer is moved needs to do this (in the self.move_pointer
-                // function)
                 if let Some((under, surface_location)) = filtered_new_under
                     .and_then(|(target, loc)| Some((target.wl_surface()?.into_owned(), loc)))
                 {
@@ -554,12 +825,15 @@ impl State {
                 }
             }
             InputEvent::PointerMotionAbsolute { event } => {
+                // Unlike touchscreens and tablets, absolute pointers (the winit window, virtual
+                // machine tablets) report positions on the displayed output, the transform is
+                // already applied.
                 let output_geo = self.fht.space.active_output().geometry();
                 let pointer_location =
                     event.position_transformed(output_geo.size) + output_geo.loc.to_f64();
                 let serial = SERIAL_COUNTER.next_serial();
 
-                let pointer = self.fht.pointer.clone();
+                let pointer = seat.get_pointer().unwrap();
                 let under = self.fht.focus_target_under(pointer_location);
                 let filtered_under = self.filter_focus_target_for_active_layers(under.clone());
 
@@ -567,7 +841,7 @@ impl State {
                     && !pointer.is_grabbed()
                     && !self.has_active_layer()
                 {
-                    self.update_keyboard_focus();
+                    self.update_keyboard_focus(&seat, false);
                 }
 
                 pointer.motion(
@@ -585,21 +859,26 @@ impl State {
                 let serial = SERIAL_COUNTER.next_serial();
                 let button = event.button_code();
                 let state = wl_pointer::ButtonState::from(event.state());
-                let pointer = self.fht.pointer.clone();
+                let pointer = seat.get_pointer().unwrap();
 
                 if state == wl_pointer::ButtonState::Pressed && !pointer.is_grabbed() {
                     if !self.has_active_layer() {
-                        self.update_keyboard_focus();
+                        self.update_keyboard_focus(&seat, true);
 
                         if let Some(button) = event.button() {
                             let mouse_pattern = fht_compositor_config::MousePattern(
-                                self.fht.keyboard.modifier_state().into(),
+                                seat.get_keyboard().unwrap().modifier_state().into(),
                                 button.into(),
                             );
                             if let Some(action) =
                                 self.fht.config.mousebinds.get(&mouse_pattern).cloned()
                             {
-                                self.process_mouse_action(event.button_code(), action, serial);
+                                self.process_mouse_action(
+                                    &seat,
+                                    event.button_code(),
+                                    action,
+                                    serial,
+                                );
                             }
                         }
                     }
@@ -624,14 +903,48 @@ impl State {
                 pointer.frame(self);
             }
             InputEvent::PointerAxis { event } => {
-                let horizontal_amount_discrete = event.amount_v120(Axis::Horizontal);
-                let vertical_amount_discrete = event.amount_v120(Axis::Vertical);
-                let horizontal_amount = event
+                let mouse_config = self.fht.mouse_config_for_device(&event.device());
+                let scroll_factor = mouse_config.scroll_factor.unwrap_or(1.0);
+                let discrete_scroll = mouse_config.discrete_scroll.unwrap_or(false)
+                    && event.source() == AxisSource::Wheel;
+
+                let mut horizontal_amount_discrete = event
+                    .amount_v120(Axis::Horizontal)
+                    .map(|amount| amount * scroll_factor);
+                let mut vertical_amount_discrete = event
+                    .amount_v120(Axis::Vertical)
+                    .map(|amount| amount * scroll_factor);
+                let mut horizontal_amount = event
                     .amount(Axis::Horizontal)
-                    .unwrap_or_else(|| horizontal_amount_discrete.unwrap_or(0.0) * 3.0 / 120.0);
-                let vertical_amount = event
+                    .map(|amount| amount * scroll_factor)
+                    .unwrap_or_else(|| {
+                        horizontal_amount_discrete.unwrap_or(0.0) * WHEEL_STEP_AMOUNT / 120.0
+                    });
+                let mut vertical_amount = event
                     .amount(Axis::Vertical)
-                    .unwrap_or_else(|| vertical_amount_discrete.unwrap_or(0.0) * 3.0 / 120.0);
+                    .map(|amount| amount * scroll_factor)
+                    .unwrap_or_else(|| {
+                        vertical_amount_discrete.unwrap_or(0.0) * WHEEL_STEP_AMOUNT / 120.0
+                    });
+
+                if discrete_scroll {
+                    let [horizontal_remainder, vertical_remainder] =
+                        &mut self.fht.discrete_scroll_remainder;
+                    (horizontal_amount, horizontal_amount_discrete) = accumulate_discrete_scroll(
+                        horizontal_remainder,
+                        horizontal_amount_discrete,
+                    );
+                    (vertical_amount, vertical_amount_discrete) =
+                        accumulate_discrete_scroll(vertical_remainder, vertical_amount_discrete);
+                    if horizontal_amount == 0.0 && vertical_amount == 0.0 {
+                        // Not a full wheel step yet.
+                        return;
+                    }
+                }
+
+                if self.zoom_with_scroll(&seat, vertical_amount) {
+                    return;
+                }
 
                 {
                     let mut frame = AxisFrame::new(event.time_msec()).source(event.source());
@@ -667,23 +980,18 @@ impl State {
                         }
                     }
 
-                    let pointer = self.fht.pointer.clone();
+                    let pointer = seat.get_pointer().unwrap();
                     pointer.axis(self, frame);
                     pointer.frame(self);
                 }
             }
             InputEvent::TabletToolAxis { event } => {
-                let tablet_seat = self.fht.seat.tablet_seat();
-                let Some(output_geometry) =
-                    self.fht.space.outputs().next().map(OutputExt::geometry)
-                else {
+                let tablet_seat = seat.tablet_seat();
+                let Some(pointer_location) = self.tablet_tool_location::<B, _>(&event) else {
                     return;
                 };
 
-                let pointer_location =
-                    event.position_transformed(output_geometry.size) + output_geometry.loc.to_f64();
-
-                let pointer = self.fht.pointer.clone();
+                let pointer = seat.get_pointer().unwrap();
                 let under = self.fht.focus_target_under(pointer_location);
                 let filtered_under = self.filter_focus_target_for_active_layers(under.clone());
                 let tablet = tablet_seat.get_tablet(&TabletDescriptor::from(&event.device()));
@@ -743,10 +1051,8 @@ impl State {
                 pointer.frame(self);
             }
             InputEvent::TabletToolProximity { event } => {
-                let tablet_seat = self.fht.seat.tablet_seat();
-
-                let Some(output_geo) = self.fht.space.outputs().next().map(OutputExt::geometry)
-                else {
+                let tablet_seat = seat.tablet_seat();
+                let Some(pointer_location) = self.tablet_tool_location::<B, _>(&event) else {
                     return;
                 };
 
@@ -754,10 +1060,7 @@ impl State {
                 let dh = self.fht.display_handle.clone();
                 tablet_seat.add_tool::<Self>(self, &dh, &tool);
 
-                let pointer_location =
-                    event.position_transformed(output_geo.size) + output_geo.loc.to_f64();
-
-                let pointer = self.fht.pointer.clone();
+                let pointer = seat.get_pointer().unwrap();
                 let under = self.fht.focus_target_under(pointer_location);
                 let filtered_under = self.filter_focus_target_for_active_layers(under.clone());
                 let tablet = tablet_seat.get_tablet(&TabletDescriptor::from(&event.device()));
@@ -795,10 +1098,10 @@ impl State {
                 }
             }
             InputEvent::TabletToolTip { event } => {
-                let tool = self.fht.seat.tablet_seat().get_tool(&event.tool());
+                let tool = seat.tablet_seat().get_tool(&event.tool());
 
                 let allow_tip = if self.has_active_layer() {
-                    let pointer_loc = self.fht.pointer.current_location();
+                    let pointer_loc = seat.get_pointer().unwrap().current_location();
                     let under = self.fht.focus_target_under(pointer_loc);
                     self.filter_focus_target_for_active_layers(under).is_some()
                 } else {
@@ -812,7 +1115,7 @@ impl State {
                                 let serial = SERIAL_COUNTER.next_serial();
                                 tool.tip_down(serial, event.time_msec());
                                 // change the keyboard focus
-                                self.update_keyboard_focus();
+                                self.update_keyboard_focus(&seat, true);
                             }
                             TabletToolTipState::Up => {
                                 tool.tip_up(event.time_msec());
@@ -822,7 +1125,7 @@ impl State {
                 }
             }
             InputEvent::TabletToolButton { event } => {
-                let tool = self.fht.seat.tablet_seat().get_tool(&event.tool());
+                let tool = seat.tablet_seat().get_tool(&event.tool());
 
                 if let Some(tool) = tool {
                     tool.button(
@@ -835,7 +1138,7 @@ impl State {
             }
             InputEvent::GestureSwipeBegin { event } => {
                 let serial = SERIAL_COUNTER.next_serial();
-                let pointer = self.fht.pointer.clone();
+                let pointer = seat.get_pointer().unwrap();
                 pointer.gesture_swipe_begin(
                     self,
                     &pointer::GestureSwipeBeginEvent {
@@ -846,7 +1149,7 @@ impl State {
                 );
             }
             InputEvent::GestureSwipeUpdate { event } => {
-                let pointer = self.fht.pointer.clone();
+                let pointer = seat.get_pointer().unwrap();
                 pointer.gesture_swipe_update(
                     self,
                     &pointer::GestureSwipeUpdateEvent {
@@ -857,7 +1160,7 @@ impl State {
             }
             InputEvent::GestureSwipeEnd { event } => {
                 let serial = SERIAL_COUNTER.next_serial();
-                let pointer = self.fht.pointer.clone();
+                let pointer = seat.get_pointer().unwrap();
                 pointer.gesture_swipe_end(
                     self,
                     &pointer::GestureSwipeEndEvent {
@@ -869,7 +1172,7 @@ impl State {
             }
             InputEvent::GesturePinchBegin { event } => {
                 let serial = SERIAL_COUNTER.next_serial();
-                let pointer = self.fht.pointer.clone();
+                let pointer = seat.get_pointer().unwrap();
                 pointer.gesture_pinch_begin(
                     self,
                     &pointer::GesturePinchBeginEvent {
@@ -880,7 +1183,7 @@ impl State {
                 )
             }
             InputEvent::GesturePinchUpdate { event } => {
-                let pointer = self.fht.pointer.clone();
+                let pointer = seat.get_pointer().unwrap();
                 pointer.gesture_pinch_update(
                     self,
                     &pointer::GesturePinchUpdateEvent {
@@ -893,7 +1196,7 @@ impl State {
             }
             InputEvent::GesturePinchEnd { event } => {
                 let serial = SERIAL_COUNTER.next_serial();
-                let pointer = self.fht.pointer.clone();
+                let pointer = seat.get_pointer().unwrap();
                 pointer.gesture_pinch_end(
                     self,
                     &pointer::GesturePinchEndEvent {
@@ -905,7 +1208,7 @@ impl State {
             }
             InputEvent::GestureHoldBegin { event } => {
                 let serial = SERIAL_COUNTER.next_serial();
-                let pointer = self.fht.pointer.clone();
+                let pointer = seat.get_pointer().unwrap();
                 pointer.gesture_hold_begin(
                     self,
                     &pointer::GestureHoldBeginEvent {
@@ -917,7 +1220,7 @@ impl State {
             }
             InputEvent::GestureHoldEnd { event } => {
                 let serial = SERIAL_COUNTER.next_serial();
-                let pointer = self.fht.pointer.clone();
+                let pointer = seat.get_pointer().unwrap();
                 pointer.gesture_hold_end(
                     self,
                     &pointer::GestureHoldEndEvent {
@@ -927,10 +1230,93 @@ impl State {
                     },
                 )
             }
+            InputEvent::TouchDown { event } => {
+                let Some(location) = self.touch_location::<B, _>(&event) else {
+                    return;
+                };
+                let serial = SERIAL_COUNTER.next_serial();
+                let under = self.fht.focus_target_under(location);
+                let filtered_under = self.filter_focus_target_for_active_layers(under);
+                seat.get_touch().unwrap().down(
+                    self,
+                    filtered_under,
+                    &touch::DownEvent {
+                        slot: event.slot(),
+                        location,
+                        serial,
+                        time: event.time_msec(),
+                    },
+                );
+            }
+            InputEvent::TouchMotion { event } => {
+                let Some(location) = self.touch_location::<B, _>(&event) else {
+                    return;
+                };
+                let under = self.fht.focus_target_under(location);
+                let filtered_under = self.filter_focus_target_for_active_layers(under);
+                seat.get_touch().unwrap().motion(
+                    self,
+                    filtered_under,
+                    &touch::MotionEvent {
+                        slot: event.slot(),
+                        location,
+                        time: event.time_msec(),
+                    },
+                );
+            }
+            InputEvent::TouchUp { event } => {
+                let serial = SERIAL_COUNTER.next_serial();
+                seat.get_touch().unwrap().up(
+                    self,
+                    &touch::UpEvent {
+                        slot: event.slot(),
+                        serial,
+                        time: event.time_msec(),
+                    },
+                );
+            }
+            InputEvent::TouchFrame { .. } => seat.get_touch().unwrap().frame(self),
+            InputEvent::TouchCancel { .. } => seat.get_touch().unwrap().cancel(self),
             _ => {}
         }
 
-        // FIXME: Granular
-        self.fht.queue_redraw_all();
+        if pointer_motion {
+            self.fht.zoom_follow_pointer(&seat);
+            let new_pointer_location = seat.get_pointer().unwrap().current_location();
+            self.fht
+                .queue_redraw_for_pointer_motion(old_pointer_location, new_pointer_location);
+        } else {
+            // FIXME: Granular
+            self.fht.queue_redraw_all();
+        }
+    }
+}
+
+/// Scroll amount of a single wheel step, as reported by libinput.
+///
+/// Also used to convert wheel steps to a scroll amount for events that only have one of them.
+pub const WHEEL_STEP_AMOUNT: f64 = 15.0;
+
+/// Accumulate high-resolution wheel scrolling until it makes full wheel steps.
+///
+/// Returns the scroll amount and v120 value to send to the client, both zero/[`None`] if there's
+/// not enough accumulated scroll for a wheel step.
+fn accumulate_discrete_scroll(remainder: &mut f64, v120: Option<f64>) -> (f64, Option<f64>) {
+    let Some(v120) = v120.filter(|v120| *v120 != 0.0) else {
+        return (0.0, None);
+    };
+
+    if remainder.signum() != v120.signum() {
+        // The user changed the scroll direction, start again.
+        *remainder = 0.0;
+    }
+    *remainder += v120;
+    let steps = (*remainder / 120.0).trunc();
+    *remainder -= steps * 120.0;
+
+    if steps == 0.0 {
+        (0.0, None)
+    } else {
+        (steps * WHEEL_STEP_AMOUNT, Some(steps * 120.0))
     }
 }
@@ -0,0 +1,183 @@
+//! Keyboard-driven pointer, also known as mouse keys.
+//!
+//! When enabled, the keys bound in `input.mouse-keys.bindings` move the pointer and emit clicks
+//! instead of being forwarded to clients. Pointer motion is accelerated while the direction keys
+//! are held down.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use fht_compositor_config::MouseKeysAction;
+use smithay::backend::input::ButtonState;
+use smithay::input::keyboard::Keysym;
+use smithay::input::pointer::ButtonEvent;
+use smithay::input::Seat;
+use smithay::reexports::calloop::timer::{TimeoutAction, Timer};
+use smithay::reexports::calloop::RegistrationToken;
+use smithay::utils::{Point, SERIAL_COUNTER};
+
+use crate::state::State;
+
+/// The interval at which we move the pointer while direction keys are held.
+const TICK_INTERVAL: Duration = Duration::from_millis(10);
+
+// These are from linux/input-event-codes.h
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+const BTN_MIDDLE: u32 = 0x112;
+
+/// The state of mouse keys, when enabled.
+#[derive(Debug, Default)]
+pub struct MouseKeysState {
+    /// The mouse keys actions currently held down, by keysym.
+    held: HashMap<Keysym, MouseKeysAction>,
+    /// The current pointer speed, in logical pixels per tick.
+    speed: f64,
+    /// The timer moving the pointer while direction keys are held.
+    timer: Option<RegistrationToken>,
+}
+
+impl MouseKeysState {
+    /// Get the action bound to this held down keysym.
+    pub fn held_action(&self, keysym: Keysym) -> Option<MouseKeysAction> {
+        self.held.get(&keysym).copied()
+    }
+
+    fn direction(&self) -> Point<f64, smithay::utils::Logical> {
+        self.held
+            .values()
+            .fold(Point::default(), |direction, action| match action {
+                MouseKeysAction::MoveUp => direction + Point::from((0.0, -1.0)),
+                MouseKeysAction::MoveDown => direction + Point::from((0.0, 1.0)),
+                MouseKeysAction::MoveLeft => direction + Point::from((-1.0, 0.0)),
+                MouseKeysAction::MoveRight => direction + Point::from((1.0, 0.0)),
+                _ => direction,
+            })
+    }
+}
+
+fn button_code(action: MouseKeysAction) -> Option<u32> {
+    match action {
+        MouseKeysAction::LeftClick => Some(BTN_LEFT),
+        MouseKeysAction::MiddleClick => Some(BTN_MIDDLE),
+        MouseKeysAction::RightClick => Some(BTN_RIGHT),
+        _ => None,
+    }
+}
+
+impl State {
+    /// Enable or disable mouse keys.
+    pub fn toggle_mouse_keys(&mut self, seat: &Seat<State>) {
+        match self.fht.mouse_keys.take() {
+            Some(mouse_keys) => {
+                debug!("Disabling mouse keys");
+                if let Some(token) = mouse_keys.timer {
+                    self.fht.loop_handle.remove(token);
+                }
+                // Don't leave buttons pressed.
+                for (_, action) in mouse_keys.held {
+                    if let Some(button) = button_code(action) {
+                        self.mouse_keys_button(seat, button, ButtonState::Released);
+                    }
+                }
+            }
+            None => {
+                debug!("Enabling mouse keys");
+                self.fht.mouse_keys = Some(MouseKeysState::default());
+            }
+        }
+    }
+
+    /// Process a key event that got intercepted for mouse keys.
+    pub fn process_mouse_keys_event(
+        &mut self,
+        seat: &Seat<State>,
+        keysym: Keysym,
+        action: MouseKeysAction,
+        pressed: bool,
+    ) {
+        crate::profile_function!();
+        let config = &self.fht.config.input.mouse_keys;
+        let initial_speed = config.initial_speed;
+        let Some(mouse_keys) = self.fht.mouse_keys.as_mut() else {
+            return;
+        };
+
+        if pressed {
+            mouse_keys.held.insert(keysym, action);
+        } else {
+            mouse_keys.held.remove(&keysym);
+        }
+
+        if let Some(button) = button_code(action) {
+            let state = if pressed {
+                ButtonState::Pressed
+            } else {
+                ButtonState::Released
+            };
+            self.mouse_keys_button(seat, button, state);
+            return;
+        }
+
+        if pressed && mouse_keys.timer.is_none() {
+            mouse_keys.speed = initial_speed;
+            let seat = seat.clone();
+            let token = self
+                .fht
+                .loop_handle
+                .insert_source(Timer::immediate(), move |_, _, state| {
+                    state.mouse_keys_tick(&seat)
+                })
+                .expect("Failed to insert mouse keys timer!");
+            mouse_keys.timer = Some(token);
+        }
+    }
+
+    fn mouse_keys_tick(&mut self, seat: &Seat<State>) -> TimeoutAction {
+        let config = &self.fht.config.input.mouse_keys;
+        let (acceleration, max_speed) = (config.acceleration, config.max_speed);
+        let Some(mouse_keys) = self.fht.mouse_keys.as_mut() else {
+            return TimeoutAction::Drop;
+        };
+
+        let direction = mouse_keys.direction();
+        if direction == Point::default() {
+            // Opposite directions cancel out, but we should keep moving if one gets released.
+            if mouse_keys.held.values().all(|a| button_code(*a).is_some()) {
+                mouse_keys.timer = None;
+                return TimeoutAction::Drop;
+            }
+            return TimeoutAction::ToDuration(TICK_INTERVAL);
+        }
+
+        let length = direction.x.hypot(direction.y);
+        let delta = direction.upscale(mouse_keys.speed / length);
+        mouse_keys.speed = (mouse_keys.speed + acceleration).min(max_speed);
+
+        let location = self.clamp_coords(seat.get_pointer().unwrap().current_location() + delta);
+        self.move_pointer(seat, location);
+
+        TimeoutAction::ToDuration(TICK_INTERVAL)
+    }
+
+    fn mouse_keys_button(&mut self, seat: &Seat<State>, button: u32, state: ButtonState) {
+        let pointer = seat.get_pointer().unwrap();
+        if state == ButtonState::Pressed && !pointer.is_grabbed() && !self.has_active_layer() {
+            self.update_keyboard_focus(seat, true);
+        }
+
+        pointer.button(
+            self,
+            &ButtonEvent {
+                button,
+                state,
+                serial: SERIAL_COUNTER.next_serial(),
+                time: {
+                    let duration: Duration = self.fht.clock.now().into();
+                    duration.as_millis() as u32
+                },
+            },
+        );
+        pointer.frame(self);
+    }
+}
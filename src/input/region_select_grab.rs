@@ -0,0 +1,194 @@
+use smithay::backend::input::ButtonState;
+use smithay::input::pointer::{
+    AxisFrame, ButtonEvent, GestureHoldBeginEvent, GestureHoldEndEvent, GesturePinchBeginEvent,
+    GesturePinchEndEvent, GesturePinchUpdateEvent, GestureSwipeBeginEvent, GestureSwipeEndEvent,
+    GestureSwipeUpdateEvent, GrabStartData, MotionEvent, PointerGrab, PointerInnerHandle,
+    RelativeMotionEvent,
+};
+use smithay::utils::{Logical, Point};
+
+use crate::focus_target::PointerFocusTarget;
+use crate::output::OutputExt;
+use crate::state::State;
+
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+
+/// A pointer grab active during an interactive region selection.
+///
+/// Pressing the left button starts the selection, releasing it confirms it. When picking a point,
+/// pressing the left button directly confirms it. Pressing the right button cancels it.
+pub struct RegionSelectGrab {
+    pub start_data: GrabStartData<State>,
+    pub confirm: bool,
+}
+
+impl PointerGrab<State> for RegionSelectGrab {
+    fn motion(
+        &mut self,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        _focus: Option<(PointerFocusTarget, Point<f64, Logical>)>,
+        event: &MotionEvent,
+    ) {
+        let Some(selection) = data.fht.region_selection.as_mut() else {
+            return handle.unset_grab(self, data, event.serial, event.time, true);
+        };
+
+        // Clamp the event's position so that we do not go outside the output.
+        let output = selection.output().clone();
+        let geometry = output.geometry().to_f64();
+        let (pos_x, pos_y) = event.location.into();
+        let clamped_x = pos_x.clamp(geometry.loc.x, geometry.loc.x + geometry.size.w - 1.0);
+        let clamped_y = pos_y.clamp(geometry.loc.y, geometry.loc.y + geometry.size.h - 1.0);
+        let event = MotionEvent {
+            location: (clamped_x, clamped_y).into(),
+            ..*event
+        };
+
+        selection.update(event.location, false);
+        data.fht.queue_redraw(&output);
+
+        // No focus while selecting
+        handle.motion(data, None, &event);
+    }
+
+    fn relative_motion(
+        &mut self,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        focus: Option<(PointerFocusTarget, Point<f64, Logical>)>,
+        event: &RelativeMotionEvent,
+    ) {
+        handle.relative_motion(data, focus, event)
+    }
+
+    fn button(
+        &mut self,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &ButtonEvent,
+    ) {
+        let Some(selection) = data.fht.region_selection.as_mut() else {
+            return handle.unset_grab(self, data, event.serial, event.time, true);
+        };
+
+        match (event.button, event.state) {
+            (BTN_LEFT, ButtonState::Pressed) if selection.pick_point() => {
+                self.confirm = true;
+                handle.unset_grab(self, data, event.serial, event.time, true);
+            }
+            (BTN_LEFT, ButtonState::Pressed) => {
+                let output = selection.output().clone();
+                selection.update(handle.current_location(), true);
+                data.fht.queue_redraw(&output);
+            }
+            (BTN_LEFT, ButtonState::Released) if selection.selection().is_some() => {
+                self.confirm = true;
+                handle.unset_grab(self, data, event.serial, event.time, true);
+            }
+            (BTN_RIGHT, ButtonState::Pressed) => {
+                self.confirm = false;
+                handle.unset_grab(self, data, event.serial, event.time, true);
+            }
+            _ => (),
+        }
+    }
+
+    fn axis(
+        &mut self,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        details: AxisFrame,
+    ) {
+        handle.axis(data, details)
+    }
+
+    fn frame(&mut self, data: &mut State, handle: &mut PointerInnerHandle<'_, State>) {
+        handle.frame(data)
+    }
+
+    fn gesture_swipe_begin(
+        &mut self,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &GestureSwipeBeginEvent,
+    ) {
+        handle.gesture_swipe_begin(data, event)
+    }
+
+    fn gesture_swipe_update(
+        &mut self,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &GestureSwipeUpdateEvent,
+    ) {
+        handle.gesture_swipe_update(data, event)
+    }
+
+    fn gesture_swipe_end(
+        &mut self,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &GestureSwipeEndEvent,
+    ) {
+        handle.gesture_swipe_end(data, event)
+    }
+
+    fn gesture_pinch_begin(
+        &mut self,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &GesturePinchBeginEvent,
+    ) {
+        handle.gesture_pinch_begin(data, event)
+    }
+
+    fn gesture_pinch_update(
+        &mut self,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &GesturePinchUpdateEvent,
+    ) {
+        handle.gesture_pinch_update(data, event)
+    }
+
+    fn gesture_pinch_end(
+        &mut self,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &GesturePinchEndEvent,
+    ) {
+        handle.gesture_pinch_end(data, event)
+    }
+
+    fn gesture_hold_begin(
+        &mut self,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &GestureHoldBeginEvent,
+    ) {
+        handle.gesture_hold_begin(data, event);
+    }
+
+    fn gesture_hold_end(
+        &mut self,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &GestureHoldEndEvent,
+    ) {
+        handle.gesture_hold_end(data, event);
+    }
+
+    fn start_data(&self) -> &GrabStartData<State> {
+        &self.start_data
+    }
+
+    fn unset(&mut self, data: &mut State) {
+        // We can't finish the selection right now since the pointer is still locked.
+        let confirm = self.confirm;
+        data.fht
+            .loop_handle
+            .insert_idle(move |state| state.finish_region_selection(confirm));
+    }
+}
@@ -0,0 +1,158 @@
+//! Input injection from XDG remote desktop sessions.
+//!
+//! The events we receive here are already filtered by the portal, IE. they come from a started
+//! session with the appropriate device types.
+
+use fht_compositor_config::KeyPattern;
+use smithay::backend::input::{Axis, AxisSource, KeyState};
+use smithay::input::keyboard::{FilterResult, Keycode};
+use smithay::input::pointer::{AxisFrame, ButtonEvent};
+use smithay::reexports::wayland_server::protocol::wl_pointer;
+use smithay::utils::{Point, SERIAL_COUNTER};
+
+use super::WHEEL_STEP_AMOUNT;
+use crate::output::OutputExt;
+use crate::portals::remote_desktop::Request;
+use crate::state::State;
+use crate::utils::pipewire::CastSource;
+
+impl State {
+    pub fn handle_remote_desktop_request(&mut self, req: Request) {
+        crate::profile_function!();
+        let time = {
+            let duration: std::time::Duration = self.fht.clock.now().into();
+            duration.as_millis() as u32
+        };
+        // Remote desktop sessions drive the seat of our input devices.
+        let seat = self.fht.seat.clone();
+
+        match req {
+            Request::PointerMotion { dx, dy } => {
+                let location =
+                    seat.get_pointer().unwrap().current_location() + Point::from((dx, dy));
+                self.move_injected_pointer(&seat, location);
+            }
+            Request::PointerMotionAbsolute {
+                session_handle,
+                x,
+                y,
+            } => {
+                let Some(pipewire) = self.fht.pipewire.as_ref() else {
+                    return;
+                };
+                let Some(cast) = pipewire
+                    .casts
+                    .iter()
+                    .find(|cast| cast.session_handle == session_handle)
+                else {
+                    warn!("Absolute pointer motion from a remote desktop session without a stream");
+                    return;
+                };
+
+                let origin = match cast.source() {
+                    CastSource::Output(weak) | CastSource::Workspace { output: weak, .. } => {
+                        weak.upgrade().map(|output| output.current_location())
+                    }
+                    CastSource::Window(weak) => weak
+                        .upgrade()
+                        .and_then(|window| self.fht.space.window_location(&window)),
+                };
+                let Some(origin) = origin else {
+                    return;
+                };
+
+                let location = origin.to_f64() + Point::from((x, y));
+                self.move_injected_pointer(&seat, location);
+            }
+            Request::PointerButton { button, pressed } => {
+                let pointer = seat.get_pointer().unwrap();
+                let state = if pressed {
+                    wl_pointer::ButtonState::Pressed
+                } else {
+                    wl_pointer::ButtonState::Released
+                };
+
+                if pressed && !pointer.is_grabbed() && !self.has_active_layer() {
+                    self.update_keyboard_focus(&seat, true);
+                }
+
+                pointer.button(
+                    self,
+                    &ButtonEvent {
+                        button,
+                        state: state.try_into().unwrap(),
+                        serial: SERIAL_COUNTER.next_serial(),
+                        time,
+                    },
+                );
+                pointer.frame(self);
+            }
+            Request::PointerAxis { dx, dy, finish } => {
+                let mut frame = AxisFrame::new(time).source(AxisSource::Finger);
+                if dx != 0.0 {
+                    frame = frame.value(Axis::Horizontal, dx);
+                }
+                if dy != 0.0 {
+                    frame = frame.value(Axis::Vertical, dy);
+                }
+                if finish {
+                    frame = frame.stop(Axis::Horizontal).stop(Axis::Vertical);
+                }
+
+                let pointer = seat.get_pointer().unwrap();
+                pointer.axis(self, frame);
+                pointer.frame(self);
+            }
+            Request::PointerAxisDiscrete { horizontal, steps } => {
+                let axis = if horizontal {
+                    Axis::Horizontal
+                } else {
+                    Axis::Vertical
+                };
+                // Same conversion we do for physical mouse wheels.
+                let frame = AxisFrame::new(time)
+                    .source(AxisSource::Wheel)
+                    .value(axis, f64::from(steps) * WHEEL_STEP_AMOUNT)
+                    .v120(axis, steps.saturating_mul(120));
+
+                let pointer = seat.get_pointer().unwrap();
+                pointer.axis(self, frame);
+                pointer.frame(self);
+            }
+            Request::KeyboardKeycode { keycode, pressed } => {
+                let key_state = if pressed {
+                    KeyState::Pressed
+                } else {
+                    KeyState::Released
+                };
+                // Portal keycodes are evdev keycodes, xkb ones are offset by 8.
+                let Some(keycode) = keycode.checked_add(8).map(Keycode::new) else {
+                    warn!(?keycode, "Ignoring out of range remote desktop keycode");
+                    return;
+                };
+
+                let keyboard = seat.get_keyboard().unwrap();
+                let action = keyboard.input(
+                    self,
+                    keycode,
+                    key_state,
+                    SERIAL_COUNTER.next_serial(),
+                    time,
+                    |state, modifiers, handle| {
+                        let keysym = *handle.raw_syms().first().unwrap();
+                        let key_pattern = KeyPattern((*modifiers).into(), keysym);
+                        match state.filter_injected_key(key_pattern, pressed) {
+                            FilterResult::Forward => FilterResult::Forward,
+                            FilterResult::Intercept(action) => {
+                                FilterResult::Intercept(action.map(|action| (action, key_pattern)))
+                            }
+                        }
+                    },
+                );
+                if let Some(Some((action, key_pattern))) = action {
+                    self.process_key_action(&seat, action, key_pattern);
+                }
+            }
+        }
+    }
+}
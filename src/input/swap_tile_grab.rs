@@ -4,6 +4,7 @@ use smithay::input::pointer::{
     GestureSwipeUpdateEvent, GrabStartData, MotionEvent, PointerGrab, PointerInnerHandle,
     RelativeMotionEvent,
 };
+use smithay::input::Seat;
 use smithay::output::Output;
 use smithay::utils::{Logical, Point};
 
@@ -11,12 +12,14 @@ use crate::focus_target::PointerFocusTarget;
 use crate::output::OutputExt;
 use crate::state::State;
 use crate::window::Window;
+use crate::workspace_drop_strip::WorkspaceDropStrip;
 
 // NOTE: It is named swap-tile grab, but this name is quite misleading.
 //
 // In case the window is floating, the window will only be moving, the swapping
 // process is only between tiled windows.
 pub struct SwapTileGrab {
+    pub seat: Seat<State>,
     pub window: Window,
     pub output: Output,
     pub start_data: GrabStartData<State>,
@@ -30,21 +33,67 @@ impl PointerGrab<State> for SwapTileGrab {
         _focus: Option<(PointerFocusTarget, Point<f64, Logical>)>,
         event: &MotionEvent,
     ) {
-        // Clamp the event's position so that we do not go outside the output.
+        // Hovering the top edge of another output shows its workspaces as drop targets.
+        let strip_output = data
+            .fht
+            .space
+            .outputs()
+            .find(|output| {
+                **output != self.output && WorkspaceDropStrip::contains(output, event.location)
+            })
+            .cloned();
+        if let Some(output) = strip_output {
+            if data
+                .fht
+                .workspace_drop_strip
+                .as_ref()
+                .map_or(true, |strip| *strip.output() != output)
+            {
+                clear_workspace_drop_strip(data);
+                let workspace_count = data
+                    .fht
+                    .space
+                    .monitor_for_output(&output)
+                    .map_or(0, |mon| mon.workspaces().len());
+                data.fht.workspace_drop_strip =
+                    Some(WorkspaceDropStrip::new(output.clone(), workspace_count));
+            }
+
+            let strip = data.fht.workspace_drop_strip.as_mut().unwrap();
+            strip.update(event.location);
+            data.fht.queue_redraw(&output);
+            // No focus while motion is active
+            handle.motion(data, None, event);
+            return;
+        }
+        clear_workspace_drop_strip(data);
+
+        // Clamp the event's position so that the window does not go outside the output.
         let (pos_x, pos_y) = event.location.into();
         let geometry = self.output.geometry().to_f64();
         // Give is -/+5.0 to avoid the pointer being between two outputs.
         let clamped_x = pos_x.clamp(geometry.loc.x + 5.0, geometry.loc.x + geometry.size.w - 5.0);
         let clamped_y = pos_y.clamp(geometry.loc.y + 5.0, geometry.loc.y + geometry.size.h - 5.0);
-        let event = MotionEvent {
-            location: (clamped_x, clamped_y).into(),
+        let clamped_location = Point::from((clamped_x, clamped_y));
+
+        // The pointer itself can go to other outputs, otherwise it could never reach their drop
+        // strip.
+        let on_other_output = data.fht.space.outputs().any(|output| {
+            *output != self.output && output.geometry().to_f64().contains(event.location)
+        });
+        let pointer_event = MotionEvent {
+            location: if on_other_output {
+                event.location
+            } else {
+                clamped_location
+            },
             ..*event
         };
 
         // No focus while motion is active
-        handle.motion(data, None, &event);
+        handle.motion(data, None, &pointer_event);
 
-        let delta = (event.location - self.start_data.location).to_i32_round();
+        let delta = (clamped_location - self.start_data.location).to_i32_round();
         if data
             .fht
             .space
@@ -74,9 +123,28 @@ impl PointerGrab<State> for SwapTileGrab {
     ) {
         handle.button(data, event);
         if handle.current_pressed().is_empty() {
-            data.fht
-                .space
-                .handle_interactive_swap_end(&self.window, handle.current_location());
+            let drop_target = data
+                .fht
+                .workspace_drop_strip
+                .as_ref()
+                .and_then(|strip| Some((strip.output().clone(), strip.hovered()?)));
+            if let Some((output, idx)) = drop_target {
+                // Put the window back in place before sending it away, we don't want to swap it
+                // with whatever is under the pointer.
+                data.fht
+                    .space
+                    .handle_interactive_swap_end(&self.window, self.start_data.location);
+                data.fht
+                    .space
+                    .move_window_to_workspace(&self.window, &output, idx, true);
+                if let Some(window) = data.fht.space.active_window() {
+                    data.set_keyboard_focus(&self.seat, Some(window));
+                }
+            } else {
+                data.fht
+                    .space
+                    .handle_interactive_swap_end(&self.window, handle.current_location());
+            }
             handle.unset_grab(self, data, event.serial, event.time, true);
         }
     }
@@ -170,5 +238,13 @@ impl PointerGrab<State> for SwapTileGrab {
         &self.start_data
     }
 
-    fn unset(&mut self, _: &mut State) {}
+    fn unset(&mut self, data: &mut State) {
+        clear_workspace_drop_strip(data);
+    }
+}
+
+fn clear_workspace_drop_strip(data: &mut State) {
+    if let Some(strip) = data.fht.workspace_drop_strip.take() {
+        data.fht.queue_redraw(strip.output());
+    }
 }
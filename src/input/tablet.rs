@@ -0,0 +1,95 @@
+//! Graphics tablets mapping.
+//!
+//! Tablets are absolute input devices, we map them to a single output, optionally using only a
+//! sub-rectangle of the tablet, see the `input.tablet` configuration section.
+
+use smithay::backend::input::{Device, InputBackend, TabletToolEvent};
+use smithay::output::Output;
+use smithay::utils::{Logical, Point, Size};
+
+use crate::output::OutputExt;
+use crate::state::State;
+
+impl State {
+    /// Get the output tablets are mapped to.
+    fn tablet_output(&self) -> Option<Output> {
+        let name = self
+            .fht
+            .tablet_output
+            .as_ref()
+            .or(self.fht.config.input.tablet.output.as_ref());
+        name.and_then(|name| self.fht.output_named(name))
+            .or_else(|| self.fht.space.outputs().next().cloned())
+    }
+
+    /// Get the physical size of the tablet device, in millimeters.
+    fn tablet_size(&self, name: &str) -> Option<(f64, f64)> {
+        self.fht
+            .devices
+            .iter()
+            .find(|device| device.name() == name)
+            .and_then(|device| device.size())
+            .filter(|(w, h)| *w > 0.0 && *h > 0.0)
+    }
+
+    /// Get the location of a tablet tool event, in global compositor space.
+    pub fn tablet_tool_location<B: InputBackend, E: TabletToolEvent<B>>(
+        &self,
+        event: &E,
+    ) -> Option<Point<f64, Logical>> {
+        let output = self.tablet_output()?;
+        // The tablet area maps to the output panel, before applying the output transform.
+        let output_size = output.untransformed_size();
+        let tablet_config = &self.fht.config.input.tablet;
+
+        // Start from the normalized position on the tablet.
+        let mut location = event.position_transformed(Size::from((1, 1)));
+        let [mut x, mut y, mut w, mut h] = tablet_config
+            .area
+            .filter(|area| area.iter().all(|value| value.is_finite()))
+            .unwrap_or([0.0, 0.0, 1.0, 1.0]);
+        // Leave room for a non-empty area, otherwise the clamps below would get min > max.
+        x = x.clamp(0.0, 1.0 - f64::EPSILON);
+        y = y.clamp(0.0, 1.0 - f64::EPSILON);
+        w = w.clamp(f64::EPSILON, 1.0 - x);
+        h = h.clamp(f64::EPSILON, 1.0 - y);
+
+        if tablet_config.keep_aspect_ratio && !output_size.is_empty() {
+            if let Some((tablet_w, tablet_h)) = self.tablet_size(&event.device().name()) {
+                let area_ratio = (tablet_w * w) / (tablet_h * h);
+                let output_ratio = output_size.w as f64 / output_size.h as f64;
+                // Letterbox the used area, keeping it centered.
+                if area_ratio > output_ratio {
+                    let new_w = w * output_ratio / area_ratio;
+                    x += (w - new_w) / 2.0;
+                    w = new_w;
+                } else {
+                    let new_h = h * area_ratio / output_ratio;
+                    y += (h - new_h) / 2.0;
+                    h = new_h;
+                }
+            }
+        }
+
+        location.x = ((location.x - x) / w).clamp(0.0, 1.0);
+        location.y = ((location.y - y) / h).clamp(0.0, 1.0);
+
+        Some(output.map_absolute_position(location))
+    }
+
+    /// Map tablets to the next output.
+    pub fn cycle_tablet_output(&mut self) {
+        let outputs: Vec<_> = self.fht.space.outputs().cloned().collect();
+        if outputs.is_empty() {
+            return;
+        }
+
+        let current = self.tablet_output();
+        let next_idx = current
+            .and_then(|current| outputs.iter().position(|output| *output == current))
+            .map_or(0, |idx| (idx + 1) % outputs.len());
+        let next = &outputs[next_idx];
+        debug!(output = next.name(), "Mapping tablets to output");
+        self.fht.tablet_output = Some(next.name());
+    }
+}
@@ -0,0 +1,35 @@
+//! Touchscreens mapping.
+//!
+//! Touchscreens are absolute input devices, we map them to a single output, see the `input.touch`
+//! configuration section.
+
+use smithay::backend::input::{AbsolutePositionEvent, InputBackend};
+use smithay::output::Output;
+use smithay::utils::{Logical, Point, Size};
+
+use crate::output::OutputExt;
+use crate::state::State;
+
+impl State {
+    /// Get the output touchscreens are mapped to.
+    fn touch_output(&self) -> Option<Output> {
+        self.fht
+            .config
+            .input
+            .touch
+            .output
+            .as_ref()
+            .and_then(|name| self.fht.output_named(name))
+            .or_else(|| self.fht.space.outputs().next().cloned())
+    }
+
+    /// Get the location of a touch event, in global compositor space.
+    pub fn touch_location<B: InputBackend, E: AbsolutePositionEvent<B>>(
+        &self,
+        event: &E,
+    ) -> Option<Point<f64, Logical>> {
+        let output = self.touch_output()?;
+        let location = event.position_transformed(Size::from((1, 1)));
+        Some(output.map_absolute_position(location))
+    }
+}
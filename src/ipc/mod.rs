@@ -0,0 +1,410 @@
+//! Inter-process communication with the compositor.
+//!
+//! The compositor listens on a UNIX socket, whose path is exported to children using the
+//! `FHTC_SOCKET_PATH` environment variable. The protocol is kept dead simple: the client writes a
+//! single JSON-encoded [`Request`] on one line, and the compositor answers with a single
+//! JSON-encoded [`Response`] on one line, then closes the connection.
+//!
+//! The only exception is [`Request::EventStream`], after which the connection stays open and the
+//! compositor writes a JSON-encoded [`Event`] on each line when something changes.
+//!
+//! You can use `fht-compositor ipc <request>` to send requests from the command line.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+mod server;
+
+pub use server::Server;
+
+/// The environment variable containing the path to the IPC socket.
+pub const SOCKET_PATH_ENV: &str = "FHTC_SOCKET_PATH";
+
+/// A request that an IPC client can send to the compositor.
+#[derive(Debug, Clone, Serialize, Deserialize, clap::Subcommand)]
+#[serde(rename_all = "kebab-case")]
+pub enum Request {
+    /// Get how each window was launched, if it was launched by the compositor.
+    Launches,
+    /// Get the rendering statistics of each window.
+    ClientStats,
+    /// Render a downscaled preview of a workspace to a PNG file.
+    WorkspacePreview {
+        /// The index of the workspace, starting from zero.
+        workspace: usize,
+        /// The output the workspace belongs to, the active output if not set.
+        #[arg(long)]
+        #[serde(default)]
+        output: Option<String>,
+        /// The maximum width or height of the preview, in pixels.
+        #[arg(long, default_value_t = default_preview_size())]
+        #[serde(default = "default_preview_size")]
+        max_size: u32,
+    },
+    /// Focus a workspace on an output, without focusing the output itself.
+    FocusWorkspaceOnOutput {
+        /// The name of the output.
+        output: String,
+        /// The index of the workspace, starting from zero.
+        workspace: usize,
+    },
+    /// List every action that can be bound in the configuration, with its documentation.
+    ListActions,
+    /// Switch the GPU used for rendering to this render node.
+    SetRenderNode {
+        /// The path of the render node, for example `/dev/dri/renderD129`.
+        path: PathBuf,
+    },
+    /// Toggle the debug overlay, displaying rendering statistics and damage on each output.
+    ToggleDebugOverlay,
+    /// Start or stop capturing the timings of every rendered frame to a CSV file.
+    ToggleFrameTimingsCapture,
+    /// Pause or resume sending profiling data to Tracy, when built with profiling enabled.
+    ToggleProfiling,
+    /// Toggle a color filter on an output, replacing its previous color filter if any.
+    ToggleColorFilter {
+        /// The color filter to toggle.
+        filter: ColorFilter,
+        /// The output to toggle the filter on, the active output if not set.
+        #[arg(long)]
+        #[serde(default)]
+        output: Option<String>,
+    },
+    /// Change the gaps of the active workspace, until the configuration gets reloaded.
+    ChangeGaps {
+        /// The delta to add to the inner gaps, between windows.
+        #[arg(long, default_value_t = 0, allow_hyphen_values = true)]
+        #[serde(default)]
+        inner: i32,
+        /// The delta to add to the outer gaps, around the work area.
+        #[arg(long, default_value_t = 0, allow_hyphen_values = true)]
+        #[serde(default)]
+        outer: i32,
+        /// Change the gaps of every workspace instead.
+        #[arg(long)]
+        #[serde(default)]
+        all: bool,
+    },
+    /// Commit the window geometries of a workspace using the external layout, in answer to an
+    /// [`Event::LayoutDemand`].
+    CommitLayout {
+        /// The output the workspace belongs to.
+        output: String,
+        /// The index of the workspace, starting from zero.
+        workspace: usize,
+        /// The serial of the layout demand.
+        serial: u32,
+        /// The geometry of each window of the layout demand, in the same order, as `x,y,w,h`
+        /// relative to the work area.
+        geometries: Vec<LayoutGeometry>,
+    },
+    /// Keep the connection open and receive events when something changes.
+    EventStream,
+}
+
+fn default_preview_size() -> u32 {
+    256
+}
+
+/// A response from the compositor to a [`Request`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Response {
+    /// The request succeeded, without anything to return.
+    Ok,
+    /// The request failed.
+    Error(String),
+    /// Response to [`Request::Launches`].
+    Launches(Vec<WindowLaunch>),
+    /// Response to [`Request::ClientStats`].
+    ClientStats(Vec<ClientStats>),
+    /// Response to [`Request::WorkspacePreview`].
+    WorkspacePreview(WorkspacePreview),
+    /// Response to [`Request::ListActions`].
+    Actions(ActionList),
+    /// Response to [`Request::ToggleFrameTimingsCapture`].
+    FrameTimingsCapture(FrameTimingsCapture),
+    /// Response to [`Request::ToggleProfiling`], whether profiling is now running.
+    Profiling(bool),
+}
+
+/// How a window was launched by the compositor.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowLaunch {
+    /// The unique ID of the window.
+    pub window_id: usize,
+    /// The app-id of the window.
+    pub app_id: Option<String>,
+    /// The title of the window.
+    pub title: Option<String>,
+    /// The command the window was launched with.
+    pub command: String,
+    /// The XDG activation token given to the command.
+    pub activation_token: String,
+    /// The environment the command was launched with.
+    pub environment: HashMap<String, String>,
+}
+
+/// Rendering statistics of a window.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientStats {
+    /// The unique ID of the window.
+    pub window_id: usize,
+    /// The app-id of the window.
+    pub app_id: Option<String>,
+    /// The title of the window.
+    pub title: Option<String>,
+    /// The PID of the client owning the window.
+    pub pid: Option<i32>,
+    /// The number of commits during the last second.
+    pub commits_per_second: usize,
+    /// The formats of the buffers attached to the window surfaces.
+    pub buffer_formats: Vec<String>,
+    /// An estimate of the GPU memory used by the window surface textures, in bytes.
+    pub texture_memory: usize,
+}
+
+/// The state of the frame timings capture.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrameTimingsCapture {
+    /// Whether the capture is running.
+    pub capturing: bool,
+    /// The path of the CSV file the capture writes to, or wrote to if it got stopped.
+    pub path: PathBuf,
+}
+
+/// A preview of a workspace, rendered by the compositor.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspacePreview {
+    /// The output the workspace belongs to.
+    pub output: String,
+    /// The index of the workspace.
+    pub workspace: usize,
+    /// The width of the preview, in pixels.
+    pub width: i32,
+    /// The height of the preview, in pixels.
+    pub height: i32,
+    /// The path of the PNG file containing the preview.
+    ///
+    /// The file gets overwritten by the next preview of the same workspace.
+    pub path: PathBuf,
+}
+
+/// The geometry of a window, relative to the work area, see [`Request::CommitLayout`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct LayoutGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl FromStr for LayoutGeometry {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split(',')
+            .map(|value| value.trim().parse::<i32>())
+            .collect::<Result<Vec<_>, _>>()
+            .context("Invalid geometry value")?;
+        let [x, y, width, height] = values[..] else {
+            anyhow::bail!("A geometry must be written as x,y,w,h");
+        };
+        Ok(Self {
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+}
+
+/// A color filter applied to a whole output, see [`Request::ToggleColorFilter`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorFilter {
+    /// Display everything in shades of gray.
+    Grayscale,
+    /// Invert all the colors.
+    Invert,
+    /// Correct colors for protanopia (no red cones).
+    Protanopia,
+    /// Correct colors for deuteranopia (no green cones).
+    Deuteranopia,
+    /// Correct colors for tritanopia (no blue cones).
+    Tritanopia,
+}
+
+impl From<ColorFilter> for fht_compositor_config::ColorFilter {
+    fn from(filter: ColorFilter) -> Self {
+        match filter {
+            ColorFilter::Grayscale => Self::Grayscale,
+            ColorFilter::Invert => Self::Invert,
+            ColorFilter::Protanopia => Self::Protanopia,
+            ColorFilter::Deuteranopia => Self::Deuteranopia,
+            ColorFilter::Tritanopia => Self::Tritanopia,
+        }
+    }
+}
+
+/// An event sent to clients that sent [`Request::EventStream`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Event {
+    /// The occupancy of a workspace changed.
+    ///
+    /// This is also sent for every workspace when the event stream starts.
+    WorkspaceOccupancy(WorkspaceOccupancy),
+    /// The active workspace of an output changed.
+    ///
+    /// This is also sent for every output when the event stream starts.
+    ActiveWorkspace {
+        /// The name of the output.
+        output: String,
+        /// The index of the workspace, starting from zero.
+        workspace: usize,
+    },
+    /// A workspace using the external layout needs new window geometries, because its tiled
+    /// windows or its work area changed. Answer with [`Request::CommitLayout`].
+    ///
+    /// Pending demands are also sent when the event stream starts.
+    LayoutDemand(LayoutDemand),
+}
+
+/// A layout demand, see [`Event::LayoutDemand`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayoutDemand {
+    /// The output the workspace belongs to.
+    pub output: String,
+    /// The index of the workspace, starting from zero.
+    pub workspace: usize,
+    /// The serial of the demand, to give back with the geometries.
+    pub serial: u32,
+    /// The width of the work area.
+    pub width: i32,
+    /// The height of the work area.
+    pub height: i32,
+    /// The unique IDs of the tiled windows, in the order of the layout.
+    pub windows: Vec<usize>,
+}
+
+/// What a workspace contains, for bars to display occupancy indicators.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkspaceOccupancy {
+    /// The output the workspace belongs to.
+    pub output: String,
+    /// The index of the workspace, starting from zero.
+    pub workspace: usize,
+    /// The number of windows on the workspace.
+    pub windows: usize,
+    /// Whether a window on the workspace is urgent.
+    pub urgent: bool,
+    /// Whether the workspace has a fullscreened window.
+    pub fullscreen: bool,
+    /// With the monocle layout, the index of the displayed window among the windows of the
+    /// workspace, starting from zero. [`None`] with other layouts.
+    pub monocle_window: Option<usize>,
+}
+
+/// Every action that can be bound in the configuration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionList {
+    /// The actions that can be bound in the `keybinds` section.
+    pub key_actions: Vec<ActionDescription>,
+    /// The actions that can be bound in the `mousebinds` section.
+    pub mouse_actions: Vec<ActionDescription>,
+}
+
+/// A bindable action.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionDescription {
+    /// The name of the action, as written in the configuration.
+    pub name: String,
+    /// The types of the action arguments, given with `arg`. Empty if it takes none.
+    pub args: Vec<String>,
+    /// The documentation of the action.
+    pub doc: String,
+}
+
+impl From<fht_compositor_config::ActionInfo> for ActionDescription {
+    fn from(info: fht_compositor_config::ActionInfo) -> Self {
+        Self {
+            name: info.name,
+            args: info.args.into_iter().map(str::to_string).collect(),
+            doc: info.doc,
+        }
+    }
+}
+
+/// Get the path of the IPC socket for this wayland display.
+pub fn socket_path(wayland_socket_name: &str) -> anyhow::Result<PathBuf> {
+    let base_directories =
+        xdg::BaseDirectories::new().context("Failed to get XDG base directories")?;
+    let runtime_directory = base_directories
+        .get_runtime_directory()
+        .context("Failed to get XDG runtime directory")?;
+    Ok(runtime_directory.join(format!("fht-compositor-{wayland_socket_name}.sock")))
+}
+
+/// Send a [`Request`] to the running compositor and wait for its [`Response`].
+///
+/// If `socket` is [`None`], the path from [`SOCKET_PATH_ENV`] is used.
+pub fn send_request(socket: Option<&Path>, request: &Request) -> anyhow::Result<Response> {
+    let stream = connect_and_write(socket, request)?;
+    let mut line = String::new();
+    BufReader::new(&stream)
+        .read_line(&mut line)
+        .context("Failed to read response")?;
+    serde_json::from_str(&line).context("Failed to deserialize response")
+}
+
+/// Send [`Request::EventStream`] to the running compositor, and call `callback` with every
+/// [`Event`] it sends until the connection gets closed.
+///
+/// If `socket` is [`None`], the path from [`SOCKET_PATH_ENV`] is used.
+pub fn stream_events(socket: Option<&Path>, mut callback: impl FnMut(Event)) -> anyhow::Result<()> {
+    let stream = connect_and_write(socket, &Request::EventStream)?;
+    let mut lines = BufReader::new(&stream).lines();
+
+    let line = lines
+        .next()
+        .context("Connection closed before the response")?
+        .context("Failed to read response")?;
+    match serde_json::from_str(&line).context("Failed to deserialize response")? {
+        Response::Ok => (),
+        Response::Error(err) => anyhow::bail!("Compositor refused the event stream: {err}"),
+        response => anyhow::bail!("Unexpected response: {response:?}"),
+    }
+
+    for line in lines {
+        let line = line.context("Failed to read event")?;
+        callback(serde_json::from_str(&line).context("Failed to deserialize event")?);
+    }
+
+    Ok(())
+}
+
+fn connect_and_write(socket: Option<&Path>, request: &Request) -> anyhow::Result<UnixStream> {
+    let path = match socket {
+        Some(path) => path.to_path_buf(),
+        None => std::env::var_os(SOCKET_PATH_ENV)
+            .map(PathBuf::from)
+            .with_context(|| format!("{SOCKET_PATH_ENV} is not set, is fht-compositor running?"))?,
+    };
+    let mut stream = UnixStream::connect(&path).context("Failed to connect to IPC socket")?;
+
+    let mut buf = serde_json::to_string(request).context("Failed to serialize request")?;
+    buf.push('\n');
+    stream
+        .write_all(buf.as_bytes())
+        .context("Failed to write request")?;
+
+    Ok(stream)
+}
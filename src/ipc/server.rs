@@ -0,0 +1,514 @@
+//! The compositor side of the IPC.
+
+use std::io::{self, Read, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::Context;
+use smithay::reexports::calloop::generic::Generic;
+use smithay::reexports::calloop::{Interest, LoopHandle, Mode, PostAction};
+use smithay::reexports::wayland_server::Resource;
+use smithay::utils::Rectangle;
+use smithay::wayland::seat::WaylandFocus;
+
+use super::{
+    ActionList, ClientStats, Event, FrameTimingsCapture, LayoutDemand, Request, Response,
+    WindowLaunch, WorkspaceOccupancy, WorkspacePreview,
+};
+use crate::state::{Fht, State};
+
+/// The longest request we accept from a client.
+///
+/// Requests are buffered until we get a full line, we don't want a misbehaving client to make us
+/// buffer forever.
+const MAX_REQUEST_LEN: usize = 1024 * 1024;
+
+/// How long we wait for a client to receive its response.
+///
+/// Requests are handled on the compositor thread, we don't want a misbehaving client to freeze
+/// the compositor.
+const WRITE_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// The IPC server.
+///
+/// The socket file gets removed when this is dropped.
+pub struct Server {
+    path: PathBuf,
+}
+
+impl Server {
+    /// Start listening for IPC clients.
+    pub fn start(loop_handle: &LoopHandle<'static, State>, path: PathBuf) -> anyhow::Result<Self> {
+        // Leftover from a previous instance that didn't shut down properly. The path can be
+        // given with --ipc-socket, don't remove anything that isn't a socket.
+        let is_socket = std::fs::symlink_metadata(&path).is_ok_and(|m| m.file_type().is_socket());
+        if is_socket {
+            let _ = std::fs::remove_file(&path);
+        }
+
+        let listener = UnixListener::bind(&path).context("Failed to bind IPC socket")?;
+        listener
+            .set_nonblocking(true)
+            .context("Failed to set IPC socket as non-blocking")?;
+
+        let client_loop_handle = loop_handle.clone();
+        loop_handle
+            .insert_source(
+                Generic::new(listener, Interest::READ, Mode::Level),
+                move |_, listener, _| {
+                    loop {
+                        match listener.accept() {
+                            Ok((stream, _)) => {
+                                if let Err(err) = insert_client(&client_loop_handle, stream) {
+                                    warn!(?err, "Failed to insert IPC client");
+                                }
+                            }
+                            Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => break,
+                            Err(err) => {
+                                warn!(?err, "Failed to accept IPC client");
+                                break;
+                            }
+                        }
+                    }
+
+                    Ok(PostAction::Continue)
+                },
+            )
+            .map_err(|err| anyhow::anyhow!("Failed to insert IPC socket source! {err}"))?;
+
+        info!(?path, "Listening on IPC socket");
+        Ok(Self { path })
+    }
+
+    /// Get the path of the IPC socket.
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Start reading the request of an IPC client.
+///
+/// Each client gets its own event source, so that a slow one doesn't block the others, nor the
+/// compositor.
+fn insert_client(
+    loop_handle: &LoopHandle<'static, State>,
+    stream: UnixStream,
+) -> anyhow::Result<()> {
+    stream
+        .set_nonblocking(true)
+        .context("Failed to set IPC client as non-blocking")?;
+
+    let mut buf = vec![];
+    loop_handle
+        .insert_source(
+            Generic::new(stream, Interest::READ, Mode::Level),
+            move |_, stream, state| {
+                match read_request(stream, &mut buf) {
+                    Ok(Some(line)) => {
+                        if let Err(err) = handle_client(state, stream, &line) {
+                            warn!(?err, "Failed to handle IPC client");
+                        }
+                    }
+                    // Wait for the rest of the request.
+                    Ok(None) => return Ok(PostAction::Continue),
+                    Err(err) => warn!(?err, "Failed to read IPC request"),
+                }
+
+                // Each client only sends one request, event streams get their own copy of the
+                // stream.
+                Ok(PostAction::Remove)
+            },
+        )
+        .map_err(|err| anyhow::anyhow!("Failed to insert IPC client source! {err}"))?;
+
+    Ok(())
+}
+
+/// Read what's available from `stream` into `buf`, returning the request once we have a full
+/// line.
+fn read_request(mut stream: &UnixStream, buf: &mut Vec<u8>) -> anyhow::Result<Option<String>> {
+    let mut chunk = [0; 4096];
+    loop {
+        match stream.read(&mut chunk) {
+            Ok(0) if buf.is_empty() => anyhow::bail!("Client disconnected without a request"),
+            // The client closed its side of the connection without a trailing newline.
+            Ok(0) => break,
+            Ok(len) => buf.extend_from_slice(&chunk[..len]),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err).context("Failed to read request"),
+        }
+
+        if let Some(end) = buf.iter().position(|byte| *byte == b'\n') {
+            buf.truncate(end);
+            break;
+        }
+        if buf.len() > MAX_REQUEST_LEN {
+            anyhow::bail!("Request is too long");
+        }
+    }
+
+    let line = String::from_utf8(std::mem::take(buf)).context("Request is not valid UTF-8")?;
+    Ok(Some(line))
+}
+
+fn handle_client(state: &mut State, stream: &UnixStream, line: &str) -> anyhow::Result<()> {
+    let response = match serde_json::from_str::<Request>(line) {
+        Ok(Request::EventStream) => {
+            let stream = stream.try_clone().context("Failed to clone event stream")?;
+            return state.fht.add_ipc_event_stream(stream);
+        }
+        Ok(request) => state.handle_ipc_request(request),
+        Err(err) => Response::Error(format!("Invalid request: {err}")),
+    };
+
+    stream.set_nonblocking(false)?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    write_line(stream, &response).context("Failed to send response")
+}
+
+impl State {
+    pub fn handle_ipc_request(&mut self, request: Request) -> Response {
+        crate::profile_function!();
+        match request {
+            Request::Launches => {
+                let launches = self
+                    .fht
+                    .space
+                    .windows()
+                    .filter_map(|window| {
+                        let snapshot = window.launch_snapshot()?;
+                        Some(WindowLaunch {
+                            window_id: window.id().get(),
+                            app_id: window.app_id(),
+                            title: window.title(),
+                            command: snapshot.command.clone(),
+                            activation_token: snapshot.activation_token.clone(),
+                            environment: snapshot.environment.clone(),
+                        })
+                    })
+                    .collect();
+                Response::Launches(launches)
+            }
+            Request::ClientStats => {
+                let stats = self
+                    .fht
+                    .space
+                    .windows()
+                    .map(|window| {
+                        let pid = window
+                            .wl_surface()
+                            .and_then(|surface| surface.client())
+                            .and_then(|client| {
+                                client.get_credentials(&self.fht.display_handle).ok()
+                            })
+                            .map(|credentials| credentials.pid);
+                        let stats = window.render_stats();
+                        ClientStats {
+                            window_id: window.id().get(),
+                            app_id: window.app_id(),
+                            title: window.title(),
+                            pid,
+                            commits_per_second: stats.commits_per_second,
+                            buffer_formats: stats.buffer_formats,
+                            texture_memory: stats.texture_memory,
+                        }
+                    })
+                    .collect();
+                Response::ClientStats(stats)
+            }
+            Request::WorkspacePreview {
+                workspace,
+                output,
+                max_size,
+            } => {
+                let output = match output {
+                    Some(name) => match self.fht.output_named(&name) {
+                        Some(output) => output,
+                        None => return Response::Error(format!("No output named {name}")),
+                    },
+                    None => self.fht.space.active_output().clone(),
+                };
+
+                let State { fht, backend } = self;
+                let result = backend
+                    .with_renderer(|renderer| {
+                        fht.render_workspace_preview(renderer, &output, workspace, max_size)
+                    })
+                    .and_then(|(size, pixels)| {
+                        let name = format!("preview-{}-{workspace}", output.name());
+                        let path = crate::screenshot::save_png(&name, size, &pixels)?;
+                        Ok((size, path))
+                    });
+
+                match result {
+                    Ok((size, path)) => Response::WorkspacePreview(WorkspacePreview {
+                        output: output.name(),
+                        workspace,
+                        width: size.w,
+                        height: size.h,
+                        path,
+                    }),
+                    Err(err) => Response::Error(format!("Failed to render preview: {err:?}")),
+                }
+            }
+            Request::FocusWorkspaceOnOutput { output, workspace } => {
+                let Some(output) = self.fht.output_named(&output) else {
+                    return Response::Error(format!("No output named {output}"));
+                };
+                let seat = self.fht.seat.clone();
+                self.focus_workspace_on_output(&seat, &output, workspace);
+                Response::Ok
+            }
+            Request::ListActions => Response::Actions(ActionList {
+                key_actions: fht_compositor_config::ComplexKeyAction::infos()
+                    .into_iter()
+                    .map(Into::into)
+                    .collect(),
+                mouse_actions: fht_compositor_config::MouseAction::infos()
+                    .into_iter()
+                    .map(Into::into)
+                    .collect(),
+            }),
+            Request::SetRenderNode { path } => {
+                match self.backend.set_render_node(&mut self.fht, &path) {
+                    Ok(()) => Response::Ok,
+                    Err(err) => Response::Error(format!("{err:#}")),
+                }
+            }
+            Request::ToggleDebugOverlay => {
+                self.fht.toggle_debug_overlay();
+                Response::Ok
+            }
+            Request::ToggleFrameTimingsCapture => match self.fht.toggle_frame_timings_capture() {
+                Ok((capturing, path)) => {
+                    Response::FrameTimingsCapture(FrameTimingsCapture { capturing, path })
+                }
+                Err(err) => Response::Error(format!("{err:#}")),
+            },
+            Request::ToggleProfiling => match crate::profiling::toggle_profiling() {
+                Ok(profiling) => Response::Profiling(profiling),
+                Err(err) => Response::Error(format!("{err:#}")),
+            },
+            Request::ToggleColorFilter { filter, output } => {
+                let output = match output {
+                    Some(name) => match self.fht.output_named(&name) {
+                        Some(output) => output,
+                        None => return Response::Error(format!("No output named {name}")),
+                    },
+                    None => self.fht.space.active_output().clone(),
+                };
+                self.fht.toggle_color_filter(&output, filter.into());
+                Response::Ok
+            }
+            Request::ChangeGaps { inner, outer, all } => {
+                if all {
+                    self.fht.space.change_all_gaps(outer, inner, true);
+                } else {
+                    self.fht.space.change_gaps(outer, inner, true);
+                }
+                Response::Ok
+            }
+            Request::CommitLayout {
+                output,
+                workspace,
+                serial,
+                geometries,
+            } => {
+                let Some(output) = self.fht.output_named(&output) else {
+                    return Response::Error(format!("No output named {output}"));
+                };
+                let Some(monitor) = self.fht.space.monitor_mut_for_output(&output) else {
+                    return Response::Error("Output has no monitor".to_string());
+                };
+                let Some(workspace) = monitor.workspaces_mut().nth(workspace) else {
+                    return Response::Error("Invalid workspace index".to_string());
+                };
+                let geometries = geometries
+                    .into_iter()
+                    .map(|geometry| {
+                        Rectangle::new(
+                            (geometry.x, geometry.y).into(),
+                            (geometry.width, geometry.height).into(),
+                        )
+                    })
+                    .collect();
+                match workspace.commit_external_layout(serial, geometries, true) {
+                    Ok(()) => {
+                        self.fht.queue_redraw(&output);
+                        Response::Ok
+                    }
+                    Err(err) => Response::Error(format!("{err:#}")),
+                }
+            }
+            Request::EventStream => {
+                Response::Error("An event stream needs its own connection".to_string())
+            }
+        }
+    }
+}
+
+impl Fht {
+    /// Start sending [`Event`]s to an IPC client.
+    fn add_ipc_event_stream(&mut self, stream: UnixStream) -> anyhow::Result<()> {
+        let mut events = vec![];
+        for monitor in self.space.monitors() {
+            events.push(Event::ActiveWorkspace {
+                output: monitor.output().name(),
+                workspace: monitor.active_workspace_idx(),
+            });
+        }
+        events.extend(
+            self.workspace_occupancy()
+                .into_iter()
+                .map(Event::WorkspaceOccupancy),
+        );
+        // Send the current demands, not only the pending ones, since the layout client might have
+        // missed them while not running.
+        for monitor in self.space.monitors() {
+            let output = monitor.output().name();
+            for workspace in monitor.workspaces() {
+                if let Some(demand) = workspace.layout_demand() {
+                    events.push(Event::LayoutDemand(ipc_layout_demand(
+                        &output,
+                        workspace.index(),
+                        demand,
+                    )));
+                }
+            }
+        }
+
+        // We can't afford to wait for slow clients when sending events, see send_ipc_event. This
+        // includes the initial state, clients that can't take it get disconnected.
+        stream.set_nonblocking(true)?;
+        write_line(&stream, &Response::Ok)?;
+        for event in events {
+            write_line(&stream, &event)?;
+        }
+
+        self.ipc_event_streams.push(stream);
+        Ok(())
+    }
+
+    /// Send an [`Event`] to every IPC event stream.
+    ///
+    /// Clients that went away, or that don't keep up with the events, get disconnected.
+    pub fn send_ipc_event(&mut self, event: &Event) {
+        if self.ipc_event_streams.is_empty() {
+            return;
+        }
+
+        self.ipc_event_streams
+            .retain(|stream| match write_line(stream, event) {
+                Ok(()) => true,
+                Err(err) => {
+                    debug!(?err, "Closing IPC event stream");
+                    false
+                }
+            });
+    }
+
+    /// Send [`Event::WorkspaceOccupancy`] for workspaces whose occupancy changed.
+    pub fn refresh_ipc_workspace_occupancy(&mut self) {
+        crate::profile_function!();
+        if self.ipc_event_streams.is_empty() {
+            // New event streams get the whole occupancy when they start anyway.
+            self.ipc_workspace_occupancy.clear();
+            return;
+        }
+
+        let occupancy = self.workspace_occupancy();
+        let changed = occupancy
+            .iter()
+            .filter(|occupancy| !self.ipc_workspace_occupancy.contains(occupancy))
+            .cloned()
+            .collect::<Vec<_>>();
+        self.ipc_workspace_occupancy = occupancy;
+
+        for occupancy in changed {
+            self.send_ipc_event(&Event::WorkspaceOccupancy(occupancy));
+        }
+    }
+
+    /// Send [`Event::LayoutDemand`] for workspaces using the external layout that need new window
+    /// geometries.
+    pub fn refresh_ipc_layout_demands(&mut self) {
+        crate::profile_function!();
+        if self.ipc_event_streams.is_empty() {
+            // Keep the demands pending until a layout client connects.
+            return;
+        }
+
+        let mut events = vec![];
+        for monitor in self.space.monitors_mut() {
+            let output = monitor.output().name();
+            for workspace in monitor.workspaces_mut() {
+                if let Some(demand) = workspace.take_layout_demand() {
+                    events.push(Event::LayoutDemand(ipc_layout_demand(
+                        &output,
+                        workspace.index(),
+                        demand,
+                    )));
+                }
+            }
+        }
+
+        for event in events {
+            self.send_ipc_event(&event);
+        }
+    }
+
+    fn workspace_occupancy(&self) -> Vec<WorkspaceOccupancy> {
+        self.space
+            .monitors()
+            .flat_map(|monitor| {
+                let output = monitor.output().name();
+                monitor
+                    .workspaces()
+                    .map(move |workspace| WorkspaceOccupancy {
+                        output: output.clone(),
+                        workspace: workspace.index(),
+                        windows: workspace.windows().len(),
+                        urgent: workspace.windows().any(|window| window.urgent()),
+                        fullscreen: workspace.fullscreened_window().is_some(),
+                        monocle_window: workspace.monocle_window().and_then(|displayed| {
+                            workspace.windows().position(|window| window == displayed)
+                        }),
+                    })
+            })
+            .collect()
+    }
+}
+
+fn ipc_layout_demand(
+    output: &str,
+    workspace: usize,
+    demand: crate::space::LayoutDemand,
+) -> LayoutDemand {
+    LayoutDemand {
+        output: output.to_string(),
+        workspace,
+        serial: demand.serial,
+        width: demand.size.w,
+        height: demand.size.h,
+        windows: demand
+            .windows
+            .iter()
+            .map(|window| window.id().get())
+            .collect(),
+    }
+}
+
+fn write_line(mut stream: &UnixStream, value: &impl serde::Serialize) -> anyhow::Result<()> {
+    let mut buf = serde_json::to_string(value).context("Failed to serialize")?;
+    buf.push('\n');
+    stream.write_all(buf.as_bytes()).context("Failed to write")
+}
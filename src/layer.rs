@@ -8,12 +8,14 @@ use smithay::backend::renderer::element::surface::{
 use smithay::backend::renderer::element::{AsRenderElements, Kind};
 use smithay::desktop::{layer_map_for_output, LayerSurface, PopupManager};
 use smithay::output::Output;
+use smithay::utils::{Point, Rectangle};
 use smithay::wayland::shell::wlr_layer;
 
+use crate::protocols::kde_blur::surface_blur_region;
 use crate::renderer::blur::element::BlurElement;
 use crate::renderer::pixel_shader_element::FhtPixelShaderElement;
 use crate::renderer::rounded_window::RoundedWindowElement;
-use crate::renderer::{has_transparent_region, FhtRenderer};
+use crate::renderer::{has_transparent_region, surface_alpha_multiplier, FhtRenderer};
 use crate::state::Fht;
 
 // Resolved layer rules that get computed from the configuration.
@@ -24,6 +26,7 @@ pub struct ResolvedLayerRules {
     pub corner_radius: Option<f32>,
     pub shadow: ShadowOverrides,
     pub opacity: Option<f32>,
+    pub on_screen_keyboard: bool,
 }
 
 impl Default for ResolvedLayerRules {
@@ -39,6 +42,7 @@ impl Default for ResolvedLayerRules {
                 ..Default::default()
             },
             opacity: None,
+            on_screen_keyboard: false,
         }
     }
 }
@@ -66,6 +70,10 @@ impl ResolvedLayerRules {
             if let Some(corner_radius) = rule.corner_radius {
                 resolved_rules.corner_radius = Some(corner_radius)
             }
+
+            if let Some(on_screen_keyboard) = rule.on_screen_keyboard {
+                resolved_rules.on_screen_keyboard = on_screen_keyboard;
+            }
         }
 
         let guard = layer
@@ -195,6 +203,7 @@ where
                         corner_radius,
                         layer_geo,
                         output_scale as f64,
+                        0.0,
                     )
                     .into()
                 } else {
@@ -215,20 +224,36 @@ where
             || has_transparent_region(wl_surface, layer_geo.size),
             |o| o < 1.0,
         );
-        if !blur.disabled() && is_transparent {
-            let blur_element = BlurElement::new(
-                renderer,
-                output,
-                layer_geo,
-                location,
-                corner_radius,
-                false, // FIXME: Configurable
-                output_scale,
-                1.0,
-                blur,
-            );
+        // Bars and launchers can ask to blur specific regions of their surface using the KDE blur
+        // protocol, otherwise we blur the whole layer-shell if it has any transparent region.
+        let layer_rect = Rectangle::from_size(layer_geo.size);
+        let blur_region = surface_blur_region(wl_surface, layer_rect);
+        let should_blur = blur_region
+            .as_ref()
+            .map_or(is_transparent, |rects| !rects.is_empty());
+        if !blur.disabled() && should_blur {
+            for rect in blur_region.unwrap_or_else(|| vec![layer_rect]) {
+                // Only round off the blur when it covers the whole layer-shell, other regions are
+                // shaped by the client itself.
+                let corner_radius = if rect == layer_rect {
+                    corner_radius
+                } else {
+                    0.0
+                };
+                let blur_element = BlurElement::new(
+                    renderer,
+                    output,
+                    Rectangle::new(layer_geo.loc + rect.loc, rect.size),
+                    location + rect.loc.to_physical(output_scale),
+                    corner_radius,
+                    false, // FIXME: Configurable
+                    output_scale,
+                    surface_alpha_multiplier(wl_surface),
+                    blur,
+                );
 
-            elements.push(blur_element.into());
+                elements.push(blur_element.into());
+            }
         }
 
         if !shadow.disable && shadow.color[3] > 0.0 {
@@ -238,6 +263,7 @@ where
                 output_scale,
                 layer_geo,
                 shadow.sigma,
+                Point::from((shadow.offset[0], shadow.offset[1])),
                 corner_radius,
                 shadow.color,
             );
@@ -257,6 +283,13 @@ impl Fht {
             }
         }
     }
+
+    /// Whether this [`LayerSurface`] is mapped on any output.
+    pub fn is_layer_mapped(&self, layer: &LayerSurface) -> bool {
+        self.space
+            .outputs()
+            .any(|output| layer_map_for_output(output).layers().any(|l| l == layer))
+    }
 }
 
 crate::fht_render_elements! {
@@ -17,26 +17,35 @@ use smithay::reexports::wayland_server::Display;
 use smithay::wayland::socket::ListeningSocketSource;
 use state::State;
 
+#[cfg(feature = "accessibility")]
+mod a11y;
 mod backend;
 mod cli;
 mod config;
 mod cursor;
 mod egui;
+mod fade;
 mod focus_target;
 mod frame_clock;
 mod handlers;
 mod input;
+mod ipc;
 mod layer;
+mod osd;
 mod output;
-#[cfg(any(feature = "xdg-screencast-portal"))]
+#[cfg(any(feature = "xdg-screencast-portal", feature = "xdg-screenshot-portal"))]
 mod portals;
 mod profiling;
 mod protocols;
 mod renderer;
+mod screenshot;
+mod session;
 mod space;
 mod state;
 mod utils;
 mod window;
+mod workspace_drop_strip;
+mod zoom;
 
 #[cfg(feature = "profile-with-tracy-allocations")]
 #[global_allocator]
@@ -48,8 +57,14 @@ fn main() -> anyhow::Result<(), Box<dyn Error>> {
     //
     // We must have at least one backend, otherwise unmatched branches will occur.
     // This also must be at the very top of the crate so that it pops ups before anything.
-    #[cfg(all(not(feature = "udev-backend"), not(feature = "winit-backend")))]
-    compile_error!("You must enable at least one backend: 'udev-backend' or 'winit-backend");
+    #[cfg(all(
+        not(feature = "udev-backend"),
+        not(feature = "winit-backend"),
+        not(feature = "headless-backend")
+    ))]
+    compile_error!(
+        "You must enable at least one backend: 'udev-backend', 'winit-backend' or 'headless-backend'"
+    );
 
     let filter = tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
         // Allow fatal errors from every crate, compositor can log anything
@@ -61,14 +76,15 @@ fn main() -> anyhow::Result<(), Box<dyn Error>> {
         .init();
 
     let cli = cli::Cli::parse();
-    match cli.command {
+    match &cli.command {
         Some(cli::Command::CheckConfiguration) => check_configuration(cli),
         Some(cli::Command::GenerateCompletions { shell }) => {
             let mut command = cli::Cli::command();
             let name = command.get_name().to_string();
-            clap_complete::generate(shell, &mut command, name, &mut std::io::stdout());
+            clap_complete::generate(*shell, &mut command, name, &mut std::io::stdout());
             std::process::exit(0); // we just want to generate completions, nothing much
         }
+        Some(cli::Command::Ipc { socket, request }) => send_ipc_request(socket.as_deref(), request),
         _ => (),
     }
     // Start tracy client now since everything before is just basic setup or command handling.
@@ -88,7 +104,10 @@ fn main() -> anyhow::Result<(), Box<dyn Error>> {
     let (dh, socket_name) = {
         let display: Display<State> = Display::new()?;
         let dh = display.handle();
-        let listening_socket = ListeningSocketSource::new_auto()?;
+        let listening_socket = match &cli.socket_name {
+            Some(name) => ListeningSocketSource::with_name(name)?,
+            None => ListeningSocketSource::new_auto()?,
+        };
         let socket_name = String::from(listening_socket.socket_name().to_string_lossy());
 
         loop_handle
@@ -125,18 +144,43 @@ fn main() -> anyhow::Result<(), Box<dyn Error>> {
         (dh, socket_name)
     };
 
+    #[cfg(feature = "headless-backend")]
+    let backend = if cli.headless {
+        Some(cli::BackendType::Headless)
+    } else {
+        cli.backend
+    };
+    #[cfg(not(feature = "headless-backend"))]
+    let backend = cli.backend;
+
     let mut state = State::new(
         &dh,
         event_loop.handle(),
         event_loop.get_signal(),
         cli.config_path,
-        cli.backend,
+        backend,
         socket_name.clone(),
     );
 
+    // Keep the server alive for the whole compositor lifetime, dropping it removes the socket.
+    let ipc_socket_path = match cli.ipc_socket {
+        Some(path) => Ok(path),
+        None => ipc::socket_path(&socket_name),
+    };
+    let ipc_server = match ipc_socket_path.and_then(|path| ipc::Server::start(&loop_handle, path)) {
+        Ok(server) => Some(server),
+        Err(err) => {
+            error!(?err, "Failed to start IPC server");
+            None
+        }
+    };
+
     // SAFETY: We do not access these environment variables during these writes/set_var calls,
     // so the race-condition concerns should be non-existent.
     unsafe {
+        if let Some(server) = &ipc_server {
+            std::env::set_var(ipc::SOCKET_PATH_ENV, server.path());
+        }
         std::env::set_var("WAYLAND_DISPLAY", &socket_name);
         std::env::set_var("XDG_CURRENT_DESKTOP", "fht-compositor");
         std::env::set_var("XDG_SESSION_TYPE", "wayland");
@@ -148,15 +192,27 @@ fn main() -> anyhow::Result<(), Box<dyn Error>> {
         }
     }
 
-    #[cfg(any(feature = "xdg-screencast-portal"))]
+    // Let whoever started us find the sockets without going through the environment, for example
+    // test harnesses or nested instances running side by side.
+    match &ipc_server {
+        Some(server) => println!(
+            "WAYLAND_DISPLAY={socket_name} {}={}",
+            ipc::SOCKET_PATH_ENV,
+            server.path().display()
+        ),
+        None => println!("WAYLAND_DISPLAY={socket_name}"),
+    }
+
+    #[cfg(any(feature = "xdg-screencast-portal", feature = "xdg-screenshot-portal"))]
     if let Some(dbus_connection) = &state.fht.dbus_connection {
         if let Err(err) = portals::start(dbus_connection, &loop_handle) {
             error!(?err, "Failed to start XDG portals")
         }
     }
 
-    for cmd in &state.fht.config.autostart {
-        utils::spawn(cmd);
+    let config = Arc::clone(&state.fht.config);
+    for cmd in &config.autostart {
+        state.fht.spawn(cmd);
     }
 
     #[cfg(feature = "uwsm")]
@@ -196,6 +252,7 @@ fn main() -> anyhow::Result<(), Box<dyn Error>> {
 
     std::mem::drop(event_loop);
     std::mem::drop(state);
+    std::mem::drop(ipc_server);
 
     info!("Shutting down! Goodbye~");
 
@@ -222,5 +279,35 @@ fn check_configuration(cli: cli::Cli) -> ! {
     }
 }
 
+fn send_ipc_request(socket: Option<&std::path::Path>, request: &ipc::Request) -> ! {
+    if let ipc::Request::EventStream = request {
+        // Print events one per line, for scripts to process them as they come.
+        let res = ipc::stream_events(socket, |event| {
+            println!("{}", serde_json::to_string(&event).unwrap());
+        });
+        if let Err(err) = res {
+            error!(?err, "Failed to stream IPC events");
+            std::process::exit(1)
+        }
+        std::process::exit(0)
+    }
+
+    match ipc::send_request(socket, request) {
+        Ok(ipc::Response::Error(err)) => {
+            error!(?err, "Compositor failed to handle IPC request");
+            std::process::exit(1)
+        }
+        Ok(response) => {
+            let json = serde_json::to_string_pretty(&response).unwrap();
+            println!("{json}");
+            std::process::exit(0)
+        }
+        Err(err) => {
+            error!(?err, "Failed to send IPC request");
+            std::process::exit(1)
+        }
+    }
+}
+
 #[allow(unused_imports)]
 pub(crate) use profiling::{profile_function, profile_scope};
@@ -0,0 +1,174 @@
+//! On-screen display.
+//!
+//! An [`Osd`] briefly shows some text centered on an output, for example the workspace number when
+//! switching workspaces. Each output has its own [`Osd`], stored inside its
+//! [`OutputState`](crate::output::OutputState).
+
+use std::time::Duration;
+
+use fht_animation::curve::Easing;
+use fht_animation::{Animation, AnimationCurve};
+use smithay::backend::renderer::element::utils::{Relocate, RelocateRenderElement};
+use smithay::output::Output;
+use smithay::utils::{Logical, Point, Size};
+
+use crate::egui::{EguiElement, EguiRenderElement};
+use crate::output::OutputExt;
+use crate::renderer::FhtRenderer;
+
+// The OSD only displays short text, this leaves plenty of room for egui.
+const WIDTH: i32 = 400;
+const HEIGHT: i32 = 200;
+const SHOWN_DURATION: Duration = Duration::from_millis(700);
+const FADE_DURATION: Duration = Duration::from_millis(200);
+const FADE_CURVE: AnimationCurve = AnimationCurve::Simple(Easing::EaseOutCubic);
+
+#[derive(Debug)]
+pub struct Osd {
+    state: State,
+    egui: EguiElement,
+}
+
+crate::fht_render_elements! {
+    OsdRenderElement => {
+        Egui = RelocateRenderElement<EguiRenderElement>,
+    }
+}
+
+#[derive(Debug, Default)]
+enum State {
+    /// The [`Osd`] is shown, waiting for [`SHOWN_DURATION`] before fading out.
+    Shown {
+        text: String,
+        // None until the first animation tick.
+        started_at: Option<Duration>,
+    },
+    /// The [`Osd`] is fading out.
+    FadingOut {
+        text: String,
+        animation: Animation<f64>,
+    },
+    /// The [`Osd`] is hidden.
+    #[default]
+    Hidden,
+}
+
+impl Osd {
+    /// Create a new hidden [`Osd`].
+    pub fn new() -> Self {
+        Self {
+            state: State::Hidden,
+            egui: EguiElement::new(Size::from((WIDTH, HEIGHT))),
+        }
+    }
+
+    /// Show `text` on this [`Osd`], replacing what it was displaying.
+    pub fn show(&mut self, text: String) {
+        // HACK: Make egui forget the size used by the previous text, see ConfigUi::show
+        self.egui
+            .ctx()
+            .memory_mut(|mem| *mem.areas_mut() = Default::default());
+        self.state = State::Shown {
+            text,
+            started_at: None,
+        };
+    }
+
+    /// Advance the animations for this [`Osd`].
+    pub fn advance_animations(
+        &mut self,
+        target_presentation_time: Duration,
+        animate: bool,
+    ) -> bool {
+        let mut animations_ongoing = false;
+        self.state = match std::mem::take(&mut self.state) {
+            State::Shown { text, started_at } => {
+                animations_ongoing = true;
+                let started_at = started_at.unwrap_or(target_presentation_time);
+                if target_presentation_time.saturating_sub(started_at) < SHOWN_DURATION {
+                    State::Shown {
+                        text,
+                        started_at: Some(started_at),
+                    }
+                } else if animate {
+                    State::FadingOut {
+                        text,
+                        animation: Animation::new(1.0, 0.0, FADE_DURATION).with_curve(FADE_CURVE),
+                    }
+                } else {
+                    State::Hidden
+                }
+            }
+            State::FadingOut {
+                text,
+                mut animation,
+            } => {
+                animations_ongoing = true;
+                animation.tick(target_presentation_time);
+                if animation.is_finished() {
+                    State::Hidden
+                } else {
+                    State::FadingOut { text, animation }
+                }
+            }
+            hidden => hidden,
+        };
+
+        animations_ongoing
+    }
+
+    /// Render this [`Osd`], centered on the `output`.
+    pub fn render(
+        &self,
+        renderer: &mut impl FhtRenderer,
+        output: &Output,
+        scale: i32,
+    ) -> Option<OsdRenderElement> {
+        crate::profile_function!();
+        let (text, alpha) = match &self.state {
+            State::Shown { text, .. } => (text, 1.0),
+            State::FadingOut { text, animation } => (text, *animation.value() as f32),
+            State::Hidden => return None,
+        };
+
+        let egui_element = self
+            .egui
+            .render(
+                renderer.glow_renderer_mut(),
+                scale,
+                alpha,
+                Point::default(),
+                output.refresh_interval(),
+                |ctx| ui(ctx, text),
+            )
+            .inspect_err(|err| warn!(?err, "Failed to render egui for OSD"))
+            .ok()?;
+
+        let used_size = self.egui.ctx().used_size();
+        let output_size = output.geometry().size;
+        let loc = Point::<_, Logical>::from((
+            (f64::from(output_size.w) - f64::from(used_size.x)).max(0.0) / 2.0,
+            (f64::from(output_size.h) - f64::from(used_size.y)).max(0.0) / 2.0,
+        ))
+        .to_i32_round::<i32>();
+
+        let element = RelocateRenderElement::from_element(
+            egui_element,
+            loc.to_physical_precise_round(scale),
+            Relocate::Absolute,
+        );
+        Some(element.into())
+    }
+}
+
+fn ui(ctx: &egui::Context, text: &str) {
+    crate::profile_function!();
+    egui::Area::new(egui::Id::NULL).show(ctx, |ui| {
+        egui::Frame::window(ui.style())
+            .inner_margin(egui::Margin::symmetric(24, 12))
+            .shadow(egui::Shadow::NONE)
+            .show(ui, |ui| {
+                ui.label(egui::RichText::new(text).size(48.0).strong());
+            });
+    });
+}
@@ -1,11 +1,19 @@
+use std::time::Duration;
+
+use fht_compositor_config::ColorFilter;
 use smithay::backend::renderer::damage::OutputDamageTracker;
 use smithay::backend::renderer::element::solid::SolidColorBuffer;
-use smithay::output::Output;
+use smithay::output::{Output, Scale};
 use smithay::reexports::calloop::RegistrationToken;
 use smithay::wayland::session_lock::LockSurface;
 
+use crate::fade::Fade;
 use crate::frame_clock::FrameClock;
+use crate::osd::Osd;
 use crate::protocols::screencopy::ScreencopyFrame;
+use crate::renderer::debug::DebugOverlay;
+use crate::renderer::OffscreenBuffer;
+use crate::zoom::Zoom;
 
 #[derive(Debug)]
 pub struct OutputState {
@@ -62,6 +70,21 @@ pub struct OutputState {
     /// displayed while the session is locked. If this is [`None`], a new buffer is
     /// initialized.
     pub lock_backdrop: Option<SolidColorBuffer>,
+
+    /// The on-screen display of this output.
+    pub osd: Osd,
+    /// The debug overlay of this output, only drawn when enabled.
+    pub debug_overlay: DebugOverlay,
+    /// The color filter applied to everything displayed on this output, if any.
+    pub color_filter: Option<ColorFilter>,
+    /// The screen magnifier of this output.
+    pub zoom: Zoom,
+    /// The offscreen render of this output, used by the color filter and the zoom.
+    pub offscreen_buffer: OffscreenBuffer,
+    /// The fade from and to black of this output.
+    pub fade: Fade,
+    /// The last active workspace index of this output, to detect workspace switches.
+    pub last_active_workspace_idx: usize,
 }
 
 /// A state machine to describe where an [`Output`](smithay::output::Output) in the redraw loop.
@@ -87,6 +110,13 @@ pub enum RedrawState {
         /// Whether we need to queue redraw after the VBlank timer fires.
         queued: bool,
     },
+    /// A redraw has been queued, but we wait until shortly before the next presentation time to
+    /// render, so that the frame includes the latest client buffers and input. See the
+    /// `max-render-time` output option.
+    WaitingForRenderDeadline {
+        /// The token of the timer in the compositor event loop.
+        token: RegistrationToken,
+    },
 }
 
 impl RedrawState {
@@ -114,7 +144,7 @@ impl RedrawState {
     }
 }
 
-use smithay::utils::{Logical, Rectangle};
+use smithay::utils::{Logical, Point, Rectangle, Size, Transform};
 
 /// Newtype wrapper around a [`String`] representing an output EDID serial.
 pub struct OutputSerial(pub String);
@@ -129,6 +159,31 @@ pub trait OutputExt {
 
     /// Get this [`Output`]'s EDID serial.
     fn serial(&self) -> Option<String>;
+
+    /// Get the refresh interval of this [`Output`]'s current mode, if it has a refresh rate.
+    fn refresh_interval(&self) -> Option<Duration>;
+
+    /// Change the transform, scale, or location of this [`Output`].
+    ///
+    /// The current mode is sent again alongside, so that the logical size advertised through
+    /// xdg-output gets computed from the new transform, staying consistent with
+    /// [`OutputExt::geometry`].
+    fn change_logical_state(
+        &self,
+        transform: Option<Transform>,
+        scale: Option<Scale>,
+        location: Option<Point<i32, Logical>>,
+    );
+
+    /// Map a normalized position (from 0 to 1 on both axes) from an absolute input device to
+    /// global compositor space.
+    ///
+    /// Absolute devices (touchscreens, tablets) report positions relative to the untransformed
+    /// panel, so the output transform gets applied to the position.
+    fn map_absolute_position(&self, position: Point<f64, Logical>) -> Point<f64, Logical>;
+
+    /// Get the size of this [`Output`] before applying its transform, in logical coordinates.
+    fn untransformed_size(&self) -> Size<i32, Logical>;
 }
 
 impl OutputExt for Output {
@@ -151,4 +206,34 @@ impl OutputExt for Output {
             .get::<OutputSerial>()
             .map(|serial| serial.0.clone())
     }
+
+    fn refresh_interval(&self) -> Option<Duration> {
+        // The mode refresh rate is in mHz.
+        let refresh = u64::try_from(self.current_mode()?.refresh).ok()?;
+        (refresh != 0).then(|| Duration::from_nanos(1_000_000_000_000 / refresh))
+    }
+
+    fn change_logical_state(
+        &self,
+        transform: Option<Transform>,
+        scale: Option<Scale>,
+        location: Option<Point<i32, Logical>>,
+    ) {
+        let mode = transform.is_some().then(|| self.current_mode()).flatten();
+        self.change_current_state(mode, transform, scale, location);
+    }
+
+    fn map_absolute_position(&self, position: Point<f64, Logical>) -> Point<f64, Logical> {
+        let size = self.untransformed_size().to_f64();
+        let position = Point::from((position.x * size.w, position.y * size.h));
+        self.current_transform().transform_point_in(position, &size)
+            + self.current_location().to_f64()
+    }
+
+    fn untransformed_size(&self) -> Size<i32, Logical> {
+        let geometry = self.geometry();
+        self.current_transform()
+            .invert()
+            .transform_size(geometry.size)
+    }
 }
@@ -5,8 +5,12 @@ use crate::state::State;
 
 mod shared;
 
+#[cfg(feature = "xdg-remote-desktop-portal")]
+pub mod remote_desktop;
 #[cfg(feature = "xdg-screencast-portal")]
 pub mod screencast;
+#[cfg(feature = "xdg-screenshot-portal")]
+pub mod screenshot;
 
 pub fn start(
     dbus_connection: &zbus::blocking::Connection,
@@ -16,7 +20,9 @@ pub fn start(
     {
         info!("Starting XDG screencast portal");
         let (to_compositor, from_screencast) = calloop::channel::channel::<screencast::Request>();
-        let portal = screencast::Portal { to_compositor };
+        let portal = screencast::Portal {
+            to_compositor: to_compositor.clone(),
+        };
         loop_handle
             .insert_source(from_screencast, move |event, _, state| {
                 let calloop::channel::Event::Msg(req) = event else {
@@ -31,6 +37,52 @@ pub fn start(
             .object_server()
             .at("/org/freedesktop/portal/desktop", portal)
             .context("Failed to insert XDG screencast portal in dbus!")?);
+
+        #[cfg(feature = "xdg-remote-desktop-portal")]
+        {
+            info!("Starting XDG remote desktop portal");
+            let (input_to_compositor, from_remote_desktop) =
+                calloop::channel::channel::<remote_desktop::Request>();
+            let portal = remote_desktop::Portal {
+                to_compositor: input_to_compositor,
+                screencast_to_compositor: to_compositor,
+            };
+            loop_handle
+                .insert_source(from_remote_desktop, move |event, _, state| {
+                    let calloop::channel::Event::Msg(req) = event else {
+                        return;
+                    };
+                    state.handle_remote_desktop_request(req);
+                })
+                .map_err(|err| {
+                    anyhow::anyhow!("Failed to insert XDG remote desktop portal source! {err}")
+                })?;
+            assert!(dbus_connection
+                .object_server()
+                .at("/org/freedesktop/portal/desktop", portal)
+                .context("Failed to insert XDG remote desktop portal in dbus!")?);
+        }
+    }
+
+    #[cfg(feature = "xdg-screenshot-portal")]
+    {
+        info!("Starting XDG screenshot portal");
+        let (to_compositor, from_screenshot) = calloop::channel::channel::<screenshot::Request>();
+        let portal = screenshot::Portal { to_compositor };
+        loop_handle
+            .insert_source(from_screenshot, move |event, _, state| {
+                let calloop::channel::Event::Msg(req) = event else {
+                    return;
+                };
+                state.handle_screenshot_request(req);
+            })
+            .map_err(|err| {
+                anyhow::anyhow!("Failed to insert XDG screenshot portal source! {err}")
+            })?;
+        assert!(dbus_connection
+            .object_server()
+            .at("/org/freedesktop/portal/desktop", portal)
+            .context("Failed to insert XDG screenshot portal in dbus!")?);
     }
 
     Ok(())
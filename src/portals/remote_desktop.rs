@@ -0,0 +1,378 @@
+//! XDG remote desktop implementation.
+//!
+//! A remote desktop session is a regular screencast session (see `src/portals/screencast.rs`) on
+//! top of which the client can inject pointer and keyboard events, in order to remotely control
+//! the compositor. The frontend will call `ScreenCast.SelectSources` on our remote desktop session
+//! handle, so we share the same session object.
+
+use std::collections::HashMap;
+
+use smithay::reexports::calloop;
+use zbus::object_server::SignalEmitter;
+use zbus::{interface, ObjectServer};
+
+use super::screencast::{
+    self, get_option_value, next_session_id, request_stream, ScreencastSession, SessionData,
+};
+use super::shared::{PortalResponse, Request as RemoteDesktopRequest};
+
+pub const PORTAL_VERSION: u32 = 2;
+
+bitflags::bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct DeviceType: u32 {
+        const KEYBOARD = 1;
+        const POINTER = 2;
+        const TOUCHSCREEN = 4;
+    }
+}
+
+/// A [XDG RemoteDesktop desktop portal](https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.portal.RemoteDesktop.html) instance
+///
+/// This structure can be added inside a zbus [`Connection`] to register the
+/// `org.freedesktop.impl.portal.RemoteDesktop` interface
+pub struct Portal {
+    pub(super) to_compositor: calloop::channel::Sender<Request>,
+    /// Channel used to start the screencast streams of remote desktop sessions.
+    pub(super) screencast_to_compositor: calloop::channel::Sender<screencast::Request>,
+}
+
+/// The remote desktop state of a [`ScreencastSession`].
+pub struct SessionState {
+    /// The devices the user allowed to be controlled.
+    devices: DeviceType,
+    /// Whether the session has been started.
+    ///
+    /// We only accept input events after the session has started.
+    started: bool,
+}
+
+/// An input event that a remote desktop session wants to inject.
+#[derive(Debug)]
+pub enum Request {
+    /// Relative pointer motion, in logical pixels.
+    PointerMotion { dx: f64, dy: f64 },
+    /// Absolute pointer motion, in logical pixels relative to the stream of the session.
+    PointerMotionAbsolute {
+        session_handle: zvariant::OwnedObjectPath,
+        x: f64,
+        y: f64,
+    },
+    /// A pointer button press or release. `button` is an evdev button code.
+    PointerButton { button: u32, pressed: bool },
+    /// Smooth scrolling, in logical pixels.
+    PointerAxis { dx: f64, dy: f64, finish: bool },
+    /// Discrete scrolling, in steps.
+    PointerAxisDiscrete { horizontal: bool, steps: i32 },
+    /// A key press or release. `keycode` is an evdev key code.
+    KeyboardKeycode { keycode: u32, pressed: bool },
+}
+
+#[interface(name = "org.freedesktop.impl.portal.RemoteDesktop")]
+impl Portal {
+    #[zbus(property)]
+    pub fn available_device_types(&self) -> u32 {
+        (DeviceType::KEYBOARD | DeviceType::POINTER).bits()
+    }
+
+    #[zbus(property)]
+    pub fn version(&self) -> u32 {
+        PORTAL_VERSION
+    }
+
+    async fn create_session(
+        &self,
+        request_handle: zvariant::ObjectPath<'_>,
+        session_handle: zvariant::ObjectPath<'_>,
+        _app_id: String,
+        _options: HashMap<&str, zvariant::Value<'_>>,
+        #[zbus(object_server)] object_server: &ObjectServer,
+    ) -> (PortalResponse, HashMap<&str, zvariant::Value<'_>>) {
+        let span = debug_span!("remote_desktop", event = "create_session", ?session_handle);
+        let _span_guard = span.enter();
+
+        let request = RemoteDesktopRequest::new(request_handle.clone());
+        if let Err(err) = object_server.at(&request_handle, request).await {
+            warn!(?err, "Failed to create remote desktop request object");
+            return (PortalResponse::Error, HashMap::new());
+        };
+
+        let id = next_session_id();
+        let session_data = SessionData {
+            id,
+            to_compositor: self.screencast_to_compositor.clone(),
+            cast_id: None,
+            source: None,      // only set if the client also wants a screencast
+            cursor_mode: None, // ^^^^
+            remote_desktop: Some(SessionState {
+                devices: DeviceType::empty(),
+                started: false,
+            }),
+        };
+        let session = ScreencastSession::new(
+            session_handle.clone(),
+            session_data,
+            Some(|data: &SessionData| {
+                if let Some(cast_id) = data.cast_id {
+                    if let Err(err) = data
+                        .to_compositor
+                        .send(screencast::Request::StopCast { cast_id })
+                    {
+                        error!(
+                            ?err,
+                            ?cast_id,
+                            "Failed to send StopCast request to compositor"
+                        );
+                    };
+                }
+            }),
+        );
+
+        if let Err(err) = object_server.at(&session_handle, session).await {
+            let _ = object_server
+                .remove::<RemoteDesktopRequest, _>(&request_handle)
+                .await;
+            warn!(?err, "Failed to create remote desktop session object");
+            return (PortalResponse::Error, HashMap::new());
+        };
+
+        let session_id = format!("fht-compositor-remote-desktop-{id}");
+        let results = HashMap::from_iter([("session_id", session_id.into())]);
+
+        (PortalResponse::Success, results)
+    }
+
+    async fn select_devices(
+        &self,
+        _request_handle: zvariant::ObjectPath<'_>,
+        session_handle: zvariant::ObjectPath<'_>,
+        _app_id: String,
+        options: HashMap<&str, zvariant::Value<'_>>,
+        #[zbus(object_server)] object_server: &ObjectServer,
+    ) -> (PortalResponse, HashMap<&str, zvariant::Value<'_>>) {
+        let Ok(session_ref) = object_server
+            .interface::<_, ScreencastSession>(&session_handle)
+            .await
+        else {
+            warn!(?session_handle, "select_devices called on invalid session");
+            return (PortalResponse::Error, HashMap::new());
+        };
+        let session = session_ref.get_mut().await;
+
+        // If the client does not specify anything, all the available device types are used.
+        let devices = get_option_value::<u32>(&options, "types")
+            .ok()
+            .and_then(DeviceType::from_bits)
+            .unwrap_or(DeviceType::KEYBOARD | DeviceType::POINTER)
+            & (DeviceType::KEYBOARD | DeviceType::POINTER);
+
+        let valid = session.with_data(|data| match data.remote_desktop.as_mut() {
+            Some(state) => {
+                state.devices = devices;
+                true
+            }
+            None => false,
+        });
+        if !valid {
+            warn!(
+                ?session_handle,
+                "select_devices called on a screencast session"
+            );
+            return (PortalResponse::Error, HashMap::new());
+        }
+
+        (PortalResponse::Success, HashMap::new())
+    }
+
+    async fn start(
+        &self,
+        _request_handle: zvariant::ObjectPath<'_>,
+        session_handle: zvariant::ObjectPath<'_>,
+        _app_id: String,
+        _parent_window: String,
+        _options: HashMap<&str, zvariant::Value<'_>>,
+        #[zbus(signal_emitter)] signal_emitter: SignalEmitter<'_>,
+        #[zbus(object_server)] object_server: &ObjectServer,
+    ) -> (PortalResponse, HashMap<&str, zvariant::Value<'_>>) {
+        let span = debug_span!("remote_desktop", event = "start", ?session_handle);
+        let _span_guard = span.enter();
+
+        let Ok(session_ref) = object_server
+            .interface::<_, ScreencastSession>(&session_handle)
+            .await
+        else {
+            warn!("start called on invalid session");
+            return (PortalResponse::Error, HashMap::new());
+        };
+        let session = session_ref.get_mut().await;
+
+        let Some(devices) = session.with_data(|data| {
+            let state = data.remote_desktop.as_mut()?;
+            state.started = true;
+            Some(state.devices)
+        }) else {
+            warn!("start called on a screencast session");
+            return (PortalResponse::Error, HashMap::new());
+        };
+
+        let mut results = HashMap::from_iter([
+            ("devices", zvariant::Value::new(devices.bits())),
+            ("clipboard_enabled", zvariant::Value::new(false)),
+        ]);
+
+        // The client can optionally request a screencast alongside the remote desktop session,
+        // in order to know where to move the pointer.
+        if session.with_data(|data| data.source.is_some()) {
+            let Some(stream) =
+                request_stream(&self.screencast_to_compositor, &session, &session_handle).await
+            else {
+                let _ = session.closed(&signal_emitter, HashMap::new()).await;
+                return (PortalResponse::Error, HashMap::new());
+            };
+            results.insert("streams", stream);
+        }
+
+        (PortalResponse::Success, results)
+    }
+
+    async fn notify_pointer_motion(
+        &self,
+        session_handle: zvariant::ObjectPath<'_>,
+        _options: HashMap<&str, zvariant::Value<'_>>,
+        dx: f64,
+        dy: f64,
+        #[zbus(object_server)] object_server: &ObjectServer,
+    ) {
+        if allows_device(object_server, &session_handle, DeviceType::POINTER).await {
+            self.send(Request::PointerMotion { dx, dy });
+        }
+    }
+
+    async fn notify_pointer_motion_absolute(
+        &self,
+        session_handle: zvariant::ObjectPath<'_>,
+        _options: HashMap<&str, zvariant::Value<'_>>,
+        _stream: u32,
+        x: f64,
+        y: f64,
+        #[zbus(object_server)] object_server: &ObjectServer,
+    ) {
+        // We only ever have one stream per session, so we can find it back using the session
+        // handle on the compositor side.
+        if allows_device(object_server, &session_handle, DeviceType::POINTER).await {
+            self.send(Request::PointerMotionAbsolute {
+                session_handle: session_handle.into(),
+                x,
+                y,
+            });
+        }
+    }
+
+    async fn notify_pointer_button(
+        &self,
+        session_handle: zvariant::ObjectPath<'_>,
+        _options: HashMap<&str, zvariant::Value<'_>>,
+        button: i32,
+        state: u32,
+        #[zbus(object_server)] object_server: &ObjectServer,
+    ) {
+        if allows_device(object_server, &session_handle, DeviceType::POINTER).await {
+            self.send(Request::PointerButton {
+                button: button as u32,
+                pressed: state == 1,
+            });
+        }
+    }
+
+    async fn notify_pointer_axis(
+        &self,
+        session_handle: zvariant::ObjectPath<'_>,
+        options: HashMap<&str, zvariant::Value<'_>>,
+        dx: f64,
+        dy: f64,
+        #[zbus(object_server)] object_server: &ObjectServer,
+    ) {
+        let finish = get_option_value::<bool>(&options, "finish").unwrap_or(false);
+        if allows_device(object_server, &session_handle, DeviceType::POINTER).await {
+            self.send(Request::PointerAxis { dx, dy, finish });
+        }
+    }
+
+    async fn notify_pointer_axis_discrete(
+        &self,
+        session_handle: zvariant::ObjectPath<'_>,
+        _options: HashMap<&str, zvariant::Value<'_>>,
+        axis: u32,
+        steps: i32,
+        #[zbus(object_server)] object_server: &ObjectServer,
+    ) {
+        if allows_device(object_server, &session_handle, DeviceType::POINTER).await {
+            self.send(Request::PointerAxisDiscrete {
+                horizontal: axis == 1,
+                steps,
+            });
+        }
+    }
+
+    async fn notify_keyboard_keycode(
+        &self,
+        session_handle: zvariant::ObjectPath<'_>,
+        _options: HashMap<&str, zvariant::Value<'_>>,
+        keycode: i32,
+        state: u32,
+        #[zbus(object_server)] object_server: &ObjectServer,
+    ) {
+        // Evdev keycodes can't be negative.
+        let Ok(keycode) = u32::try_from(keycode) else {
+            return;
+        };
+        if allows_device(object_server, &session_handle, DeviceType::KEYBOARD).await {
+            self.send(Request::KeyboardKeycode {
+                keycode,
+                pressed: state == 1,
+            });
+        }
+    }
+
+    async fn notify_keyboard_keysym(
+        &self,
+        _session_handle: zvariant::ObjectPath<'_>,
+        _options: HashMap<&str, zvariant::Value<'_>>,
+        keysym: i32,
+        _state: u32,
+    ) {
+        // Translating a keysym back into a keycode depends on the active keymap, and is not
+        // something we can reliably do. Clients should prefer NotifyKeyboardKeycode.
+        warn!(?keysym, "Keysym injection is not supported");
+    }
+}
+
+impl Portal {
+    fn send(&self, req: Request) {
+        if let Err(err) = self.to_compositor.send(req) {
+            warn!(?err, "Failed to send remote desktop request to compositor");
+        }
+    }
+}
+
+/// Check whether the remote desktop session with this handle can inject input events from this
+/// device type.
+async fn allows_device(
+    object_server: &ObjectServer,
+    session_handle: &zvariant::ObjectPath<'_>,
+    device: DeviceType,
+) -> bool {
+    let Ok(session_ref) = object_server
+        .interface::<_, ScreencastSession>(session_handle)
+        .await
+    else {
+        return false;
+    };
+
+    let session = session_ref.get().await;
+    session.with_data(|data| {
+        data.remote_desktop
+            .as_ref()
+            .is_some_and(|state| state.started && state.devices.contains(device))
+    })
+}
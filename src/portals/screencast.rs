@@ -99,6 +99,8 @@ impl Portal {
             cast_id: None,
             source: None,      // lazily created when receiving metadata
             cursor_mode: None, // ^^^^
+            #[cfg(feature = "xdg-remote-desktop-portal")]
+            remote_desktop: None,
         };
         let session = ScreencastSession::new(
             session_handle.clone(),
@@ -232,70 +234,15 @@ impl Portal {
             .await
             .unwrap();
         let session = session_ref.get_mut().await;
-        let Some((source, cursor_mode)) = session.with_data(|data| {
-            data.source
-                .clone()
-                .and_then(|source| Some((source, data.cursor_mode?)))
-        }) else {
-            error!("Tried to start screencast before select_sources");
-            return (PortalResponse::Error, HashMap::new());
-        };
-
-        // What we do now is ask the compositor to start the screencast.
-        // In the dbus thread we block on the receiver until we receive *something*.
-        //
-        // Receive Some(metadata) => continue with streaming
-        // receive None => something bad happened on the compositor/pipewire side, drop
-        let (metadata_sender, metadata_receiver) = async_channel::unbounded();
-
-        if let Err(err) = self.to_compositor.send(Request::StartCast {
-            session_handle: session_handle.clone().into(),
-            metadata_sender,
-            source: source.clone(),
-            cursor_mode,
-        }) {
-            warn!(?err, "Failed to send StartCast request to compositor");
+        let Some(stream) = request_stream(&self.to_compositor, &session, &session_handle).await
+        else {
             let _ = session.closed(&signal_emitter, HashMap::new()).await;
             return (PortalResponse::Error, HashMap::new());
-        }
-
-        let StreamMetadata {
-            cast_id,
-            node_id,
-            size,
-        } = match metadata_receiver.recv().await {
-            Ok(Some(metadata)) => metadata,
-            Ok(None) => {
-                let _ = session.closed(&signal_emitter, HashMap::new()).await;
-                return (PortalResponse::Error, HashMap::new());
-            }
-            Err(err) => {
-                warn!(
-                    ?err,
-                    "Metadata receiver channel closed when it should not, weird..."
-                );
-                let _ = session.closed(&signal_emitter, HashMap::new()).await;
-                return (PortalResponse::Error, HashMap::new());
-            }
         };
 
-        // A client should only be able to call start once per session.
-        // We assert this here.
-        session.with_data(|data| assert!(data.cast_id.replace(cast_id).is_none()));
-
-        let size = zvariant::Value::new((size.w, size.h));
-        let source_type = zvariant::Value::new(match &source {
-            ScreencastSource::Output { .. } => SourceType::MONITOR.bits(),
-            ScreencastSource::Window { .. } => SourceType::WINDOW.bits(),
-            ScreencastSource::Workspace { .. } => SourceType::VIRTUAL.bits(),
-        });
-        let stream_info: HashMap<_, _, std::hash::BuildHasherDefault<std::hash::DefaultHasher>> =
-            HashMap::from_iter([("size", size), ("source_type", source_type)]);
-        let stream = (node_id, stream_info);
-
         // TODO: Support persist mode
         let results = HashMap::from_iter([
-            ("streams", zvariant::Value::new(vec![stream])),
+            ("streams", stream),
             ("persist_mode", zvariant::Value::new("")),
         ]);
 
@@ -305,23 +252,96 @@ impl Portal {
     }
 }
 
+/// Ask the compositor to start streaming the source selected for this session.
+///
+/// On success, this returns the `streams` value to send back in the portal response.
+pub(super) async fn request_stream(
+    to_compositor: &calloop::channel::Sender<Request>,
+    session: &ScreencastSession,
+    session_handle: &zvariant::ObjectPath<'_>,
+) -> Option<zvariant::Value<'static>> {
+    let Some((source, cursor_mode)) = session.with_data(|data| {
+        data.source
+            .clone()
+            .and_then(|source| Some((source, data.cursor_mode?)))
+    }) else {
+        error!("Tried to start screencast before select_sources");
+        return None;
+    };
+
+    // What we do now is ask the compositor to start the screencast.
+    // In the dbus thread we block on the receiver until we receive *something*.
+    //
+    // Receive Some(metadata) => continue with streaming
+    // receive None => something bad happened on the compositor/pipewire side, drop
+    let (metadata_sender, metadata_receiver) = async_channel::unbounded();
+
+    if let Err(err) = to_compositor.send(Request::StartCast {
+        session_handle: session_handle.clone().into(),
+        metadata_sender,
+        source: source.clone(),
+        cursor_mode,
+    }) {
+        warn!(?err, "Failed to send StartCast request to compositor");
+        return None;
+    }
+
+    let StreamMetadata {
+        cast_id,
+        node_id,
+        size,
+    } = match metadata_receiver.recv().await {
+        Ok(Some(metadata)) => metadata,
+        Ok(None) => return None,
+        Err(err) => {
+            warn!(
+                ?err,
+                "Metadata receiver channel closed when it should not, weird..."
+            );
+            return None;
+        }
+    };
+
+    // A client should only be able to call start once per session.
+    // We assert this here.
+    session.with_data(|data| assert!(data.cast_id.replace(cast_id).is_none()));
+
+    let size = zvariant::Value::new((size.w, size.h));
+    let source_type = zvariant::Value::new(match &source {
+        ScreencastSource::Output { .. } => SourceType::MONITOR.bits(),
+        ScreencastSource::Window { .. } => SourceType::WINDOW.bits(),
+        ScreencastSource::Workspace { .. } => SourceType::VIRTUAL.bits(),
+    });
+    let stream_info: HashMap<_, _, std::hash::BuildHasherDefault<std::hash::DefaultHasher>> =
+        HashMap::from_iter([("size", size), ("source_type", source_type)]);
+    let stream = (node_id, stream_info);
+
+    Some(zvariant::Value::new(vec![stream]))
+}
+
 static SESSION_IDS: AtomicUsize = AtomicUsize::new(0);
-fn next_session_id() -> usize {
+pub(super) fn next_session_id() -> usize {
     SESSION_IDS.fetch_add(1, std::sync::atomic::Ordering::SeqCst)
 }
 
 pub struct SessionData {
     /// The unique ID of this [`ScreenCastSession`].
     #[allow(dead_code)]
-    id: usize,
+    pub(super) id: usize,
     /// Channel to send [`Request`]s to the compositor.
-    to_compositor: calloop::channel::Sender<Request>,
+    pub(super) to_compositor: calloop::channel::Sender<Request>,
     /// The pipewire cast that is streaming for this session.
-    cast_id: Option<CastId>,
+    pub(super) cast_id: Option<CastId>,
     /// The source used for this session.
-    source: Option<ScreencastSource>,
+    pub(super) source: Option<ScreencastSource>,
     /// The cursor mode used for this session.
-    cursor_mode: Option<CursorMode>,
+    pub(super) cursor_mode: Option<CursorMode>,
+    /// The remote desktop state of this session.
+    ///
+    /// A remote desktop session is a screencast session that additionally allows the client to
+    /// inject input events inside the compositor.
+    #[cfg(feature = "xdg-remote-desktop-portal")]
+    pub(super) remote_desktop: Option<super::remote_desktop::SessionState>,
 }
 
 /// The metadata associated with a pipewire stream, received from the compositor.
@@ -340,7 +360,7 @@ pub enum ScreencastSource {
     Output { name: String },
 }
 
-fn get_option_value<'value, T: TryFrom<&'value zvariant::Value<'value>>>(
+pub(super) fn get_option_value<'value, T: TryFrom<&'value zvariant::Value<'value>>>(
     options: &'value HashMap<&str, zvariant::Value<'value>>,
     name: &str,
 ) -> anyhow::Result<T> {
@@ -0,0 +1,164 @@
+//! XDG screenshot implementation.
+//!
+//! This file only handles D-Bus communication. For the actual screenshot and region selection
+//! logic, see `src/screenshot.rs`
+
+use std::collections::HashMap;
+
+use smithay::reexports::calloop;
+use smithay::utils::{Physical, Size};
+use zbus::{interface, ObjectServer};
+
+use super::shared::{PortalResponse, Request as ScreenshotRequest};
+
+pub const PORTAL_VERSION: u32 = 2;
+
+/// A [XDG Screenshot desktop portal](https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.portal.Screenshot.html) instance
+///
+/// This structure can be added inside a zbus [`Connection`] to register the
+/// `org.freedesktop.impl.portal.Screenshot` interface
+pub struct Portal {
+    pub(super) to_compositor: calloop::channel::Sender<Request>,
+}
+
+/// A [`Request`] that the [`Portal`] can send to the compositor.
+pub enum Request {
+    /// The [`Portal`] has requested a screenshot.
+    Screenshot {
+        /// Whether the user should pick the region to capture.
+        interactive: bool,
+        /// The channel to send back the screenshot to.
+        ///
+        /// Sending [`None`] means that the screenshot failed or was cancelled.
+        sender: async_channel::Sender<Option<ScreenshotData>>,
+    },
+    /// The [`Portal`] has requested the user to pick a color on screen.
+    PickColor {
+        /// The channel to send back the picked color, in RGB order with values in `0.0..=1.0`.
+        ///
+        /// Sending [`None`] means that the picking failed or was cancelled.
+        sender: async_channel::Sender<Option<[f64; 3]>>,
+    },
+}
+
+/// A screenshot taken by the compositor.
+pub struct ScreenshotData {
+    /// The size of the screenshot.
+    pub size: Size<i32, Physical>,
+    /// The pixels of the screenshot, in RGBA order.
+    pub pixels: Vec<u8>,
+}
+
+#[interface(name = "org.freedesktop.impl.portal.Screenshot")]
+impl Portal {
+    #[zbus(property)]
+    pub fn version(&self) -> u32 {
+        PORTAL_VERSION
+    }
+
+    async fn screenshot(
+        &self,
+        handle: zvariant::ObjectPath<'_>,
+        app_id: String,
+        _parent_window: String,
+        options: HashMap<&str, zvariant::Value<'_>>,
+        #[zbus(object_server)] object_server: &ObjectServer,
+    ) -> (PortalResponse, HashMap<&str, zvariant::Value<'_>>) {
+        let span = debug_span!("screenshot", ?app_id, handle = handle.as_str());
+        let _span_guard = span.enter();
+
+        let request = ScreenshotRequest::new(handle.clone());
+        if let Err(err) = object_server.at(&handle, request).await {
+            warn!(?err, "Failed to create screenshot request object");
+            return (PortalResponse::Error, HashMap::new());
+        };
+
+        let interactive = options
+            .get("interactive")
+            .and_then(|value| bool::try_from(value).ok())
+            .unwrap_or(false);
+
+        let (sender, receiver) = async_channel::bounded(1);
+        if let Err(err) = self.to_compositor.send(Request::Screenshot {
+            interactive,
+            sender,
+        }) {
+            warn!(?err, "Failed to send Screenshot request to compositor");
+            return (PortalResponse::Error, HashMap::new());
+        }
+
+        let ScreenshotData { size, pixels } = match receiver.recv().await {
+            Ok(Some(data)) => data,
+            // The user cancelled the selection.
+            Ok(None) if interactive => return (PortalResponse::Cancelled, HashMap::new()),
+            Ok(None) => return (PortalResponse::Error, HashMap::new()),
+            Err(err) => {
+                warn!(
+                    ?err,
+                    "Screenshot receiver channel closed when it should not"
+                );
+                return (PortalResponse::Error, HashMap::new());
+            }
+        };
+
+        let path = match save_screenshot(size, &pixels) {
+            Ok(path) => path,
+            Err(err) => {
+                warn!(?err, "Failed to save screenshot");
+                return (PortalResponse::Error, HashMap::new());
+            }
+        };
+
+        let uri = format!("file://{}", path.display());
+        let results = HashMap::from_iter([("uri", uri.into())]);
+
+        (PortalResponse::Success, results)
+    }
+
+    async fn pick_color(
+        &self,
+        handle: zvariant::ObjectPath<'_>,
+        app_id: String,
+        _parent_window: String,
+        _options: HashMap<&str, zvariant::Value<'_>>,
+        #[zbus(object_server)] object_server: &ObjectServer,
+    ) -> (PortalResponse, HashMap<&str, zvariant::Value<'_>>) {
+        let span = debug_span!("pick_color", ?app_id, handle = handle.as_str());
+        let _span_guard = span.enter();
+
+        let request = ScreenshotRequest::new(handle.clone());
+        if let Err(err) = object_server.at(&handle, request).await {
+            warn!(?err, "Failed to create pick color request object");
+            return (PortalResponse::Error, HashMap::new());
+        };
+
+        let (sender, receiver) = async_channel::bounded(1);
+        if let Err(err) = self.to_compositor.send(Request::PickColor { sender }) {
+            warn!(?err, "Failed to send PickColor request to compositor");
+            return (PortalResponse::Error, HashMap::new());
+        }
+
+        let [r, g, b] = match receiver.recv().await {
+            Ok(Some(color)) => color,
+            // The user cancelled the picking.
+            Ok(None) => return (PortalResponse::Cancelled, HashMap::new()),
+            Err(err) => {
+                warn!(
+                    ?err,
+                    "Pick color receiver channel closed when it should not"
+                );
+                return (PortalResponse::Error, HashMap::new());
+            }
+        };
+
+        let results = HashMap::from_iter([("color", (r, g, b).into())]);
+        (PortalResponse::Success, results)
+    }
+}
+
+/// Encode the screenshot as a PNG file and save it inside the runtime directory.
+///
+/// Each screenshot replaces the previous one, so they don't pile up.
+fn save_screenshot(size: Size<i32, Physical>, pixels: &[u8]) -> anyhow::Result<std::path::PathBuf> {
+    crate::screenshot::save_png("screenshot", size, pixels)
+}
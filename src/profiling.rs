@@ -1,16 +1,61 @@
 #![allow(unused_macros)]
 #![allow(unused_imports)]
 
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+
+/// Whether the profiling spans get sent to Tracy.
+///
+/// Tracy keeps every span it receives in memory, pausing it allows to capture only what matters.
+/// This starts enabled when building with the `profile-with-tracy` feature.
+static PROFILING: AtomicBool = AtomicBool::new(cfg!(feature = "profile-with-tracy"));
+
+/// Whether the profiling spans get sent to Tracy.
+#[inline]
+pub fn is_profiling() -> bool {
+    PROFILING.load(Ordering::Relaxed)
+}
+
+/// Pause or resume sending the profiling spans and frame marks to Tracy.
+///
+/// Returns whether profiling is now running.
+pub fn toggle_profiling() -> anyhow::Result<bool> {
+    anyhow::ensure!(
+        cfg!(feature = "profile-with-tracy"),
+        "The compositor was built without the profile-with-tracy feature"
+    );
+    let profiling = !PROFILING.fetch_xor(true, Ordering::Relaxed);
+    info!(profiling, "Toggled profiling");
+    Ok(profiling)
+}
+
 macro_rules! profile_function {
     () => {
-        let _tracy_span = tracy_client::span!();
+        let _tracy_span = if $crate::profiling::is_profiling() {
+            Some(tracy_client::span!())
+        } else {
+            None
+        };
     };
     ($data:expr) => {
-        let _location = $crate::tracy_client::span_location!();
-        let _tracy_span = $crate::tracy_client::Client::running()
-            .expect("function_scope! without a running tracy_client::Client")
-            .span(_location, 0);
-        _tracy_span.emit_text($data);
+        let _tracy_span = if $crate::profiling::is_profiling() {
+            let _location = $crate::tracy_client::span_location!();
+            Some(
+                $crate::tracy_client::Client::running()
+                    .expect("function_scope! without a running tracy_client::Client")
+                    .span(_location, 0),
+            )
+        } else {
+            None
+        };
+        if let Some(span) = &_tracy_span {
+            span.emit_text($data);
+        }
     };
 }
 pub(crate) use profile_function;
@@ -19,36 +64,130 @@ pub(crate) use profile_function;
 macro_rules! profile_scope {
     // Note: literal patterns provided as an optimization since they can skip an allocation.
     ($name:literal) => {
-        // Note: callstack_depth is 0 since this has significant overhead
-        let _tracy_span = ::tracy_client::span!($name, 0);
+        let _tracy_span = if $crate::profiling::is_profiling() {
+            // Note: callstack_depth is 0 since this has significant overhead
+            Some(::tracy_client::span!($name, 0))
+        } else {
+            None
+        };
     };
     ($name:literal, $data:expr) => {
-        // Note: callstack_depth is 0 since this has significant overhead
-        let _tracy_span = ::tracy_client::span!($name, 0);
-        _tracy_span.emit_text($data);
+        let _tracy_span = if $crate::profiling::is_profiling() {
+            // Note: callstack_depth is 0 since this has significant overhead
+            Some(::tracy_client::span!($name, 0))
+        } else {
+            None
+        };
+        if let Some(span) = &_tracy_span {
+            span.emit_text($data);
+        }
     };
     ($name:expr) => {
-        let _function_name = {
-            struct S;
-            let type_name = core::any::type_name::<S>();
-            &type_name[..type_name.len() - 3]
+        let _tracy_span = if $crate::profiling::is_profiling() {
+            let _function_name = {
+                struct S;
+                let type_name = core::any::type_name::<S>();
+                &type_name[..type_name.len() - 3]
+            };
+            Some(
+                ::tracy_client::Client::running()
+                    .expect("scope! without a running tracy_client::Client")
+                    // Note: callstack_depth is 0 since this has significant overhead
+                    .span_alloc(Some($name), _function_name, file!(), line!(), 0),
+            )
+        } else {
+            None
         };
-        let _tracy_span = ::tracy_client::Client::running()
-            .expect("scope! without a running tracy_client::Client")
-            // Note: callstack_depth is 0 since this has significant overhead
-            .span_alloc(Some($name), _function_name, file!(), line!(), 0);
     };
     ($name:expr, $data:expr) => {
-        let _function_name = {
-            struct S;
-            let type_name = core::any::type_name::<S>();
-            &type_name[..type_name.len() - 3]
+        let _tracy_span = if $crate::profiling::is_profiling() {
+            let _function_name = {
+                struct S;
+                let type_name = core::any::type_name::<S>();
+                &type_name[..type_name.len() - 3]
+            };
+            Some(
+                ::tracy_client::Client::running()
+                    .expect("scope! without a running tracy_client::Client")
+                    // Note: callstack_depth is 0 since this has significant overhead
+                    .span_alloc(Some($name), _function_name, file!(), line!(), 0),
+            )
+        } else {
+            None
         };
-        let _tracy_span = ::tracy_client::Client::running()
-            .expect("scope! without a running tracy_client::Client")
-            // Note: callstack_depth is 0 since this has significant overhead
-            .span_alloc(Some($name), _function_name, file!(), line!(), 0);
-        _tracy_span.emit_text($data);
+        if let Some(span) = &_tracy_span {
+            span.emit_text($data);
+        }
     };
 }
 pub(crate) use profile_scope;
+
+/// A capture of the timings of every rendered frame, written to a CSV file.
+///
+/// Unlike tracy, this works without building the compositor with profiling enabled, and can be
+/// started and stopped at runtime with the `toggle-frame-timings-capture` key action or IPC request.
+pub struct FrameTimingsCapture {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl FrameTimingsCapture {
+    /// Start a new capture, in a new file inside the runtime directory.
+    pub fn start() -> anyhow::Result<Self> {
+        let base_directories =
+            xdg::BaseDirectories::new().context("Failed to get XDG base directories")?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let path = base_directories
+            .place_runtime_file(format!("fht-compositor/frame-timings-{timestamp}.csv"))
+            .context("Failed to create frame timings file")?;
+        // Never overwrite a previous capture.
+        let file = File::create_new(&path).context("Failed to open frame timings file")?;
+
+        let mut writer = BufWriter::new(file);
+        writeln!(
+            writer,
+            "output,start_us,animations_us,render_us,submitted,target_presentation_us"
+        )?;
+
+        Ok(Self { path, writer })
+    }
+
+    /// Get the path of the file this capture writes to.
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    /// Stop this capture, returning the path of the written file.
+    pub fn stop(mut self) -> anyhow::Result<PathBuf> {
+        self.writer.flush()?;
+        Ok(self.path)
+    }
+
+    /// Record the timings of a frame.
+    ///
+    /// `start` and `target_presentation_time` are monotonic times, `animations` is the time spent
+    /// advancing animations and `render` the time the backend took to render.
+    pub fn record(
+        &mut self,
+        output: &str,
+        start: Duration,
+        animations: Duration,
+        render: Duration,
+        submitted: bool,
+        target_presentation_time: Duration,
+    ) {
+        if let Err(err) = writeln!(
+            self.writer,
+            "{output},{},{},{},{submitted},{}",
+            start.as_micros(),
+            animations.as_micros(),
+            render.as_micros(),
+            target_presentation_time.as_micros(),
+        ) {
+            warn!(?err, "Failed to write frame timings");
+        }
+    }
+}
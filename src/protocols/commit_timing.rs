@@ -0,0 +1,265 @@
+//! Implementation of the `wp-commit-timing-v1` protocol.
+//!
+//! This protocol allows clients to attach a target presentation time to a commit, for example
+//! video players that know when each frame should be shown. The commit is held back until the
+//! refresh cycle presenting at or after that time, see
+//! [`Fht::commit_timing_release_time`](crate::state::Fht::commit_timing_release_time).
+//!
+//! The timestamp is double-buffered state, applying only to the next commit.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use smithay::reexports::wayland_protocols::wp::commit_timing::v1::server::wp_commit_timer_v1::{
+    self, WpCommitTimerV1,
+};
+use smithay::reexports::wayland_protocols::wp::commit_timing::v1::server::wp_commit_timing_manager_v1::{
+    self, WpCommitTimingManagerV1,
+};
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::reexports::wayland_server::{
+    self, Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource, Weak,
+};
+use smithay::wayland::compositor::{with_states, Blocker, BlockerState, Cacheable};
+
+const VERSION: u32 = 1;
+
+pub struct CommitTimingManagerState;
+
+impl CommitTimingManagerState {
+    pub fn new<D>(display: &DisplayHandle) -> Self
+    where
+        D: GlobalDispatch<WpCommitTimingManagerV1, ()>
+            + Dispatch<WpCommitTimingManagerV1, ()>
+            + Dispatch<WpCommitTimerV1, CommitTimerData>
+            + 'static,
+    {
+        display.create_global::<D, WpCommitTimingManagerV1, _>(VERSION, ());
+        Self
+    }
+}
+
+/// A blocker holding back a commit until its target time.
+#[derive(Debug, Clone, Default)]
+pub struct CommitTimingBlocker(Arc<AtomicBool>);
+
+impl CommitTimingBlocker {
+    /// Release the commit held back by this blocker.
+    pub fn release(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+impl Blocker for CommitTimingBlocker {
+    fn state(&self) -> BlockerState {
+        if self.0.load(Ordering::SeqCst) {
+            BlockerState::Released
+        } else {
+            BlockerState::Pending
+        }
+    }
+}
+
+/// Data associated with a surface, to check if it already has a commit timer object.
+#[derive(Default)]
+struct CommitTimingSurfaceData {
+    object: Mutex<Option<Weak<WpCommitTimerV1>>>,
+}
+
+/// Data associated with a [`WpCommitTimerV1`] object.
+pub struct CommitTimerData {
+    surface: Weak<WlSurface>,
+}
+
+/// The double-buffered commit timing state of a surface.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct CommitTimingSurfaceCachedState {
+    // The target presentation time, in the CLOCK_MONOTONIC domain.
+    timestamp: Option<Duration>,
+}
+
+impl Cacheable for CommitTimingSurfaceCachedState {
+    fn commit(&mut self, _dh: &DisplayHandle) -> Self {
+        std::mem::take(self)
+    }
+
+    fn merge_into(self, into: &mut Self, _dh: &DisplayHandle) {
+        *into = self;
+    }
+}
+
+/// Get the target presentation time of the pending commit of this surface, if any.
+pub fn commit_timing_pre_commit(surface: &WlSurface) -> Option<Duration> {
+    with_states(surface, |states| {
+        states
+            .cached_state
+            .get::<CommitTimingSurfaceCachedState>()
+            .pending()
+            .timestamp
+    })
+}
+
+impl<D> GlobalDispatch<WpCommitTimingManagerV1, (), D> for CommitTimingManagerState
+where
+    D: GlobalDispatch<WpCommitTimingManagerV1, ()>
+        + Dispatch<WpCommitTimingManagerV1, ()>
+        + Dispatch<WpCommitTimerV1, CommitTimerData>
+        + 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<WpCommitTimingManagerV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl<D> Dispatch<WpCommitTimingManagerV1, (), D> for CommitTimingManagerState
+where
+    D: GlobalDispatch<WpCommitTimingManagerV1, ()>
+        + Dispatch<WpCommitTimingManagerV1, ()>
+        + Dispatch<WpCommitTimerV1, CommitTimerData>
+        + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        manager: &WpCommitTimingManagerV1,
+        request: <WpCommitTimingManagerV1 as Resource>::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wp_commit_timing_manager_v1::Request::GetTimer { id, surface } => {
+                let already_exists = with_states(&surface, |states| {
+                    states
+                        .data_map
+                        .insert_if_missing_threadsafe(CommitTimingSurfaceData::default);
+                    let data = states.data_map.get::<CommitTimingSurfaceData>().unwrap();
+                    let object = data.object.lock().unwrap();
+                    object
+                        .as_ref()
+                        .is_some_and(|object| object.upgrade().is_ok())
+                });
+
+                if already_exists {
+                    manager.post_error(
+                        wp_commit_timing_manager_v1::Error::CommitTimerExists,
+                        "wl_surface already has a commit timer attached",
+                    );
+                    return;
+                }
+
+                let object = data_init.init(
+                    id,
+                    CommitTimerData {
+                        surface: surface.downgrade(),
+                    },
+                );
+                with_states(&surface, |states| {
+                    let data = states.data_map.get::<CommitTimingSurfaceData>().unwrap();
+                    *data.object.lock().unwrap() = Some(object.downgrade());
+                });
+            }
+            wp_commit_timing_manager_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<WpCommitTimerV1, CommitTimerData, D> for CommitTimingManagerState
+where
+    D: GlobalDispatch<WpCommitTimingManagerV1, ()>
+        + Dispatch<WpCommitTimingManagerV1, ()>
+        + Dispatch<WpCommitTimerV1, CommitTimerData>
+        + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        resource: &WpCommitTimerV1,
+        request: <WpCommitTimerV1 as Resource>::Request,
+        data: &CommitTimerData,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wp_commit_timer_v1::Request::SetTimestamp {
+                tv_sec_hi,
+                tv_sec_lo,
+                tv_nsec,
+            } => {
+                let Ok(surface) = data.surface.upgrade() else {
+                    resource.post_error(
+                        wp_commit_timer_v1::Error::SurfaceDestroyed,
+                        "the wl_surface of this commit timer got destroyed",
+                    );
+                    return;
+                };
+
+                if tv_nsec >= 1_000_000_000 {
+                    resource.post_error(
+                        wp_commit_timer_v1::Error::InvalidTimestamp,
+                        "tv_nsec must be less than a second",
+                    );
+                    return;
+                }
+                let secs = (u64::from(tv_sec_hi) << 32) | u64::from(tv_sec_lo);
+                let timestamp = Duration::new(secs, tv_nsec);
+
+                let already_set = with_states(&surface, |states| {
+                    let mut guard = states.cached_state.get::<CommitTimingSurfaceCachedState>();
+                    guard.pending().timestamp.replace(timestamp).is_some()
+                });
+                if already_set {
+                    resource.post_error(
+                        wp_commit_timer_v1::Error::TimestampExists,
+                        "a timestamp was already set for this commit",
+                    );
+                }
+            }
+            wp_commit_timer_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(
+        _state: &mut D,
+        _client: wayland_server::backend::ClientId,
+        _resource: &WpCommitTimerV1,
+        data: &CommitTimerData,
+    ) {
+        let Ok(surface) = data.surface.upgrade() else {
+            return;
+        };
+        with_states(&surface, |states| {
+            if let Some(data) = states.data_map.get::<CommitTimingSurfaceData>() {
+                *data.object.lock().unwrap() = None;
+            }
+        });
+    }
+}
+
+#[allow(missing_docs)]
+#[macro_export]
+macro_rules! delegate_commit_timing {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::commit_timing::v1::server::wp_commit_timing_manager_v1::WpCommitTimingManagerV1: ()
+        ] => $crate::protocols::commit_timing::CommitTimingManagerState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::commit_timing::v1::server::wp_commit_timing_manager_v1::WpCommitTimingManagerV1: ()
+        ] => $crate::protocols::commit_timing::CommitTimingManagerState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::commit_timing::v1::server::wp_commit_timer_v1::WpCommitTimerV1: $crate::protocols::commit_timing::CommitTimerData
+        ] => $crate::protocols::commit_timing::CommitTimingManagerState);
+    };
+}
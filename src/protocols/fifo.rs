@@ -0,0 +1,290 @@
+//! Implementation of the `wp-fifo-v1` protocol.
+//!
+//! This protocol allows clients to queue their commits so that the compositor applies at most one
+//! per refresh cycle, instead of the client having to wait for frame callbacks. A commit can set a
+//! barrier on the surface, and a later commit can ask to wait for that barrier to be cleared before
+//! being applied.
+//!
+//! The barrier of a surface gets cleared once the surface contents got presented on its primary
+//! output, see [`clear_fifo_barrier`].
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use smithay::reexports::wayland_protocols::wp::fifo::v1::server::wp_fifo_manager_v1::{
+    self, WpFifoManagerV1,
+};
+use smithay::reexports::wayland_protocols::wp::fifo::v1::server::wp_fifo_v1::{self, WpFifoV1};
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::reexports::wayland_server::{
+    self, Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource, Weak,
+};
+use smithay::wayland::compositor::{with_states, Blocker, BlockerState, Cacheable, SurfaceData};
+
+const VERSION: u32 = 1;
+
+pub struct FifoManagerState;
+
+impl FifoManagerState {
+    pub fn new<D>(display: &DisplayHandle) -> Self
+    where
+        D: GlobalDispatch<WpFifoManagerV1, ()>
+            + Dispatch<WpFifoManagerV1, ()>
+            + Dispatch<WpFifoV1, FifoData>
+            + 'static,
+    {
+        display.create_global::<D, WpFifoManagerV1, _>(VERSION, ());
+        Self
+    }
+}
+
+/// A FIFO barrier, set by a commit and cleared once the surface contents got presented.
+///
+/// Commits waiting for the barrier are blocked until it gets cleared.
+#[derive(Debug, Clone, Default)]
+pub struct FifoBarrier(Arc<AtomicBool>);
+
+impl FifoBarrier {
+    /// Clear this barrier, releasing the commits waiting for it.
+    pub fn clear(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns whether this barrier got cleared.
+    pub fn is_cleared(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+impl Blocker for FifoBarrier {
+    fn state(&self) -> BlockerState {
+        if self.is_cleared() {
+            BlockerState::Released
+        } else {
+            BlockerState::Pending
+        }
+    }
+}
+
+/// Data associated with a surface, to check if it already has a FIFO object.
+#[derive(Default)]
+struct FifoSurfaceData {
+    object: Mutex<Option<Weak<WpFifoV1>>>,
+    barrier: Mutex<Option<FifoBarrier>>,
+}
+
+/// Data associated with a [`WpFifoV1`] object.
+pub struct FifoData {
+    surface: Weak<WlSurface>,
+}
+
+/// The double-buffered FIFO state of a surface.
+///
+/// Both requests only apply to the next commit.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct FifoSurfaceCachedState {
+    set_barrier: bool,
+    wait_barrier: bool,
+}
+
+impl Cacheable for FifoSurfaceCachedState {
+    fn commit(&mut self, _dh: &DisplayHandle) -> Self {
+        std::mem::take(self)
+    }
+
+    fn merge_into(self, into: &mut Self, _dh: &DisplayHandle) {
+        *into = self;
+    }
+}
+
+/// Handle the FIFO requests of a commit, before it gets applied.
+///
+/// If the commit waits for a barrier that is not cleared yet, the barrier is returned so that the
+/// commit can get blocked on it.
+pub fn fifo_pre_commit(surface: &WlSurface) -> Option<FifoBarrier> {
+    with_states(surface, |states| {
+        let pending = *states
+            .cached_state
+            .get::<FifoSurfaceCachedState>()
+            .pending();
+        let data = states.data_map.get::<FifoSurfaceData>()?;
+        let mut barrier = data.barrier.lock().unwrap();
+
+        let wait_for = pending
+            .wait_barrier
+            .then(|| barrier.clone())
+            .flatten()
+            .filter(|barrier| !barrier.is_cleared());
+        if pending.set_barrier {
+            *barrier = Some(FifoBarrier::default());
+        }
+
+        wait_for
+    })
+}
+
+/// Clear the FIFO barrier of a surface, if any.
+///
+/// Returns whether a barrier got cleared.
+pub fn clear_fifo_barrier(states: &SurfaceData) -> bool {
+    let Some(data) = states.data_map.get::<FifoSurfaceData>() else {
+        return false;
+    };
+    let Some(barrier) = data.barrier.lock().unwrap().take() else {
+        return false;
+    };
+
+    let cleared = !barrier.is_cleared();
+    barrier.clear();
+    cleared
+}
+
+impl<D> GlobalDispatch<WpFifoManagerV1, (), D> for FifoManagerState
+where
+    D: GlobalDispatch<WpFifoManagerV1, ()>
+        + Dispatch<WpFifoManagerV1, ()>
+        + Dispatch<WpFifoV1, FifoData>
+        + 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<WpFifoManagerV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl<D> Dispatch<WpFifoManagerV1, (), D> for FifoManagerState
+where
+    D: GlobalDispatch<WpFifoManagerV1, ()>
+        + Dispatch<WpFifoManagerV1, ()>
+        + Dispatch<WpFifoV1, FifoData>
+        + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        manager: &WpFifoManagerV1,
+        request: <WpFifoManagerV1 as Resource>::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wp_fifo_manager_v1::Request::GetFifo { id, surface } => {
+                let already_exists = with_states(&surface, |states| {
+                    states
+                        .data_map
+                        .insert_if_missing_threadsafe(FifoSurfaceData::default);
+                    let data = states.data_map.get::<FifoSurfaceData>().unwrap();
+                    let object = data.object.lock().unwrap();
+                    object
+                        .as_ref()
+                        .is_some_and(|object| object.upgrade().is_ok())
+                });
+
+                if already_exists {
+                    manager.post_error(
+                        wp_fifo_manager_v1::Error::AlreadyExists,
+                        "wl_surface already has a fifo object attached",
+                    );
+                    return;
+                }
+
+                let object = data_init.init(
+                    id,
+                    FifoData {
+                        surface: surface.downgrade(),
+                    },
+                );
+                with_states(&surface, |states| {
+                    let data = states.data_map.get::<FifoSurfaceData>().unwrap();
+                    *data.object.lock().unwrap() = Some(object.downgrade());
+                });
+            }
+            wp_fifo_manager_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<WpFifoV1, FifoData, D> for FifoManagerState
+where
+    D: GlobalDispatch<WpFifoManagerV1, ()>
+        + Dispatch<WpFifoManagerV1, ()>
+        + Dispatch<WpFifoV1, FifoData>
+        + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        resource: &WpFifoV1,
+        request: <WpFifoV1 as Resource>::Request,
+        data: &FifoData,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        if matches!(request, wp_fifo_v1::Request::Destroy) {
+            return;
+        }
+
+        let Ok(surface) = data.surface.upgrade() else {
+            resource.post_error(
+                wp_fifo_v1::Error::SurfaceDestroyed,
+                "the wl_surface of this fifo object got destroyed",
+            );
+            return;
+        };
+
+        with_states(&surface, |states| {
+            let mut guard = states.cached_state.get::<FifoSurfaceCachedState>();
+            match request {
+                wp_fifo_v1::Request::SetBarrier => guard.pending().set_barrier = true,
+                wp_fifo_v1::Request::WaitBarrier => guard.pending().wait_barrier = true,
+                _ => unreachable!(),
+            }
+        });
+    }
+
+    fn destroyed(
+        _state: &mut D,
+        _client: wayland_server::backend::ClientId,
+        _resource: &WpFifoV1,
+        data: &FifoData,
+    ) {
+        let Ok(surface) = data.surface.upgrade() else {
+            return;
+        };
+        with_states(&surface, |states| {
+            if let Some(data) = states.data_map.get::<FifoSurfaceData>() {
+                *data.object.lock().unwrap() = None;
+                // Nothing can wait for the barrier anymore.
+                if let Some(barrier) = data.barrier.lock().unwrap().take() {
+                    barrier.clear();
+                }
+            }
+        });
+    }
+}
+
+#[allow(missing_docs)]
+#[macro_export]
+macro_rules! delegate_fifo {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::fifo::v1::server::wp_fifo_manager_v1::WpFifoManagerV1: ()
+        ] => $crate::protocols::fifo::FifoManagerState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::fifo::v1::server::wp_fifo_manager_v1::WpFifoManagerV1: ()
+        ] => $crate::protocols::fifo::FifoManagerState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::fifo::v1::server::wp_fifo_v1::WpFifoV1: $crate::protocols::fifo::FifoData
+        ] => $crate::protocols::fifo::FifoManagerState);
+    };
+}
@@ -0,0 +1,221 @@
+//! Implementation of the `org_kde_kwin_blur` protocol.
+//!
+//! This protocol allows clients to ask the compositor to blur what's behind some regions of their
+//! surfaces, for example translucent terminals or bars. We don't advertise the companion
+//! `org_kde_kwin_contrast` protocol since we don't have a contrast pass.
+//!
+//! The requested regions are double-buffered state, applied on the next surface commit.
+
+use std::sync::Mutex;
+
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::reexports::wayland_server::{
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource, Weak,
+};
+use smithay::utils::{Logical, Rectangle};
+use smithay::wayland::compositor::{
+    get_region_attributes, with_states, Cacheable, RectangleKind, RegionAttributes,
+};
+use wayland_protocols_plasma::blur::server::org_kde_kwin_blur::{self, OrgKdeKwinBlur};
+use wayland_protocols_plasma::blur::server::org_kde_kwin_blur_manager::{
+    self, OrgKdeKwinBlurManager,
+};
+
+const BLUR_VERSION: u32 = 1;
+
+pub struct KdeBlurManagerState;
+
+impl KdeBlurManagerState {
+    pub fn new<D>(display: &DisplayHandle) -> Self
+    where
+        D: GlobalDispatch<OrgKdeKwinBlurManager, ()>
+            + Dispatch<OrgKdeKwinBlurManager, ()>
+            + Dispatch<OrgKdeKwinBlur, KdeBlurData>
+            + 'static,
+    {
+        display.create_global::<D, OrgKdeKwinBlurManager, _>(BLUR_VERSION, ());
+        Self
+    }
+}
+
+/// Data associated with a [`OrgKdeKwinBlur`] object.
+pub struct KdeBlurData {
+    surface: Weak<WlSurface>,
+    // The region set by the client, applied to the surface on the next commit request.
+    pending: Mutex<BlurRegion>,
+}
+
+/// A region of a surface the client asked to blur.
+#[derive(Debug, Clone)]
+pub enum BlurRegion {
+    /// Blur the whole surface.
+    Surface,
+    /// Blur only the given region, in surface-local coordinates.
+    Region(RegionAttributes),
+}
+
+/// The double-buffered blur state of a surface.
+#[derive(Default, Debug, Clone)]
+pub struct KdeBlurSurfaceCachedState {
+    blur: Option<BlurRegion>,
+}
+
+impl Cacheable for KdeBlurSurfaceCachedState {
+    fn commit(&mut self, _dh: &DisplayHandle) -> Self {
+        self.clone()
+    }
+
+    fn merge_into(self, into: &mut Self, _dh: &DisplayHandle) {
+        *into = self;
+    }
+}
+
+/// Get the rectangles of this surface the client asked to blur, in surface-local coordinates.
+///
+/// The rectangles are clipped to `geometry`, also in surface-local coordinates. Returns [`None`]
+/// if the client did not ask for any blur.
+pub fn surface_blur_region(
+    surface: &WlSurface,
+    geometry: Rectangle<i32, Logical>,
+) -> Option<Vec<Rectangle<i32, Logical>>> {
+    let region = with_states(surface, |states| {
+        let mut guard = states.cached_state.get::<KdeBlurSurfaceCachedState>();
+        guard.current().blur.clone()
+    })?;
+
+    let rects = match region {
+        BlurRegion::Surface => vec![geometry],
+        BlurRegion::Region(attributes) => {
+            let mut rects: Vec<Rectangle<i32, Logical>> = vec![];
+            for (kind, rect) in attributes.rects {
+                match kind {
+                    RectangleKind::Add => rects.extend(rect.intersection(geometry)),
+                    RectangleKind::Subtract => {
+                        rects = rects
+                            .into_iter()
+                            .flat_map(|added| added.subtract_rect(rect))
+                            .collect();
+                    }
+                }
+            }
+            rects
+        }
+    };
+
+    Some(rects)
+}
+
+impl<D> GlobalDispatch<OrgKdeKwinBlurManager, (), D> for KdeBlurManagerState
+where
+    D: GlobalDispatch<OrgKdeKwinBlurManager, ()>
+        + Dispatch<OrgKdeKwinBlurManager, ()>
+        + Dispatch<OrgKdeKwinBlur, KdeBlurData>
+        + 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<OrgKdeKwinBlurManager>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl<D> Dispatch<OrgKdeKwinBlurManager, (), D> for KdeBlurManagerState
+where
+    D: GlobalDispatch<OrgKdeKwinBlurManager, ()>
+        + Dispatch<OrgKdeKwinBlurManager, ()>
+        + Dispatch<OrgKdeKwinBlur, KdeBlurData>
+        + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _manager: &OrgKdeKwinBlurManager,
+        request: <OrgKdeKwinBlurManager as Resource>::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            org_kde_kwin_blur_manager::Request::Create { id, surface } => {
+                data_init.init(
+                    id,
+                    KdeBlurData {
+                        surface: surface.downgrade(),
+                        pending: Mutex::new(BlurRegion::Surface),
+                    },
+                );
+            }
+            org_kde_kwin_blur_manager::Request::Unset { surface } => {
+                with_states(&surface, |states| {
+                    let mut guard = states.cached_state.get::<KdeBlurSurfaceCachedState>();
+                    guard.pending().blur = None;
+                });
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<OrgKdeKwinBlur, KdeBlurData, D> for KdeBlurManagerState
+where
+    D: GlobalDispatch<OrgKdeKwinBlurManager, ()>
+        + Dispatch<OrgKdeKwinBlurManager, ()>
+        + Dispatch<OrgKdeKwinBlur, KdeBlurData>
+        + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _resource: &OrgKdeKwinBlur,
+        request: <OrgKdeKwinBlur as Resource>::Request,
+        data: &KdeBlurData,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            org_kde_kwin_blur::Request::SetRegion { region } => {
+                // A null region means the whole surface.
+                *data.pending.lock().unwrap() =
+                    region.as_ref().map_or(BlurRegion::Surface, |region| {
+                        BlurRegion::Region(get_region_attributes(region))
+                    });
+            }
+            org_kde_kwin_blur::Request::Commit => {
+                let Ok(surface) = data.surface.upgrade() else {
+                    return;
+                };
+                let region = data.pending.lock().unwrap().clone();
+                with_states(&surface, |states| {
+                    let mut guard = states.cached_state.get::<KdeBlurSurfaceCachedState>();
+                    guard.pending().blur = Some(region);
+                });
+            }
+            // Releasing the object keeps the blur, clients have to use unset to remove it.
+            org_kde_kwin_blur::Request::Release => (),
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[allow(missing_docs)]
+#[macro_export]
+macro_rules! delegate_kde_blur {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            wayland_protocols_plasma::blur::server::org_kde_kwin_blur_manager::OrgKdeKwinBlurManager: ()
+        ] => $crate::protocols::kde_blur::KdeBlurManagerState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            wayland_protocols_plasma::blur::server::org_kde_kwin_blur_manager::OrgKdeKwinBlurManager: ()
+        ] => $crate::protocols::kde_blur::KdeBlurManagerState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            wayland_protocols_plasma::blur::server::org_kde_kwin_blur::OrgKdeKwinBlur: $crate::protocols::kde_blur::KdeBlurData
+        ] => $crate::protocols::kde_blur::KdeBlurManagerState);
+    };
+}
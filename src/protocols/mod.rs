@@ -1,2 +1,8 @@
+pub mod commit_timing;
+pub mod fifo;
+pub mod kde_blur;
 pub mod output_management;
 pub mod screencopy;
+pub mod tearing_control;
+pub mod toplevel_drag;
+pub mod virtual_pointer;
@@ -0,0 +1,236 @@
+//! Implementation of the `wp-tearing-control-v1` protocol.
+//!
+//! This protocol allows clients to hint to the compositor that they prefer tearing over latency,
+//! IE. asynchronous page flips. This is mostly useful for games.
+//!
+//! The presentation hint is double-buffered state, applied on the next surface commit.
+
+use std::sync::Mutex;
+
+use smithay::reexports::wayland_protocols::wp::tearing_control::v1::server::wp_tearing_control_manager_v1::{
+    self, WpTearingControlManagerV1,
+};
+use smithay::reexports::wayland_protocols::wp::tearing_control::v1::server::wp_tearing_control_v1::{
+    self, WpTearingControlV1,
+};
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::reexports::wayland_server::{
+    self, Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource, WEnum, Weak,
+};
+use smithay::wayland::compositor::{with_states, Cacheable};
+
+pub use wp_tearing_control_v1::PresentationHint;
+
+const VERSION: u32 = 1;
+
+pub struct TearingControlManagerState;
+
+impl TearingControlManagerState {
+    pub fn new<D>(display: &DisplayHandle) -> Self
+    where
+        D: GlobalDispatch<WpTearingControlManagerV1, ()>
+            + Dispatch<WpTearingControlManagerV1, ()>
+            + Dispatch<WpTearingControlV1, TearingControlData>
+            + 'static,
+    {
+        display.create_global::<D, WpTearingControlManagerV1, _>(VERSION, ());
+        Self
+    }
+}
+
+/// Data associated with a surface, to check if it already has a tearing control object.
+#[derive(Default)]
+struct TearingControlSurfaceData {
+    object: Mutex<Option<Weak<WpTearingControlV1>>>,
+}
+
+/// Data associated with a [`WpTearingControlV1`] object.
+pub struct TearingControlData {
+    surface: Weak<WlSurface>,
+}
+
+/// The double-buffered tearing control state of a surface.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct TearingControlSurfaceCachedState {
+    hint: Option<PresentationHint>,
+}
+
+impl TearingControlSurfaceCachedState {
+    /// Get the presentation hint of the surface.
+    pub fn presentation_hint(&self) -> PresentationHint {
+        self.hint.unwrap_or(PresentationHint::Vsync)
+    }
+}
+
+impl Cacheable for TearingControlSurfaceCachedState {
+    fn commit(&mut self, _dh: &DisplayHandle) -> Self {
+        *self
+    }
+
+    fn merge_into(self, into: &mut Self, _dh: &DisplayHandle) {
+        *into = self;
+    }
+}
+
+/// Returns whether this surface currently asks for asynchronous presentation.
+pub fn surface_prefers_tearing(surface: &WlSurface) -> bool {
+    with_states(surface, |states| {
+        let mut guard = states
+            .cached_state
+            .get::<TearingControlSurfaceCachedState>();
+        guard.current().presentation_hint() == PresentationHint::Async
+    })
+}
+
+impl<D> GlobalDispatch<WpTearingControlManagerV1, (), D> for TearingControlManagerState
+where
+    D: GlobalDispatch<WpTearingControlManagerV1, ()>
+        + Dispatch<WpTearingControlManagerV1, ()>
+        + Dispatch<WpTearingControlV1, TearingControlData>
+        + 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<WpTearingControlManagerV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl<D> Dispatch<WpTearingControlManagerV1, (), D> for TearingControlManagerState
+where
+    D: GlobalDispatch<WpTearingControlManagerV1, ()>
+        + Dispatch<WpTearingControlManagerV1, ()>
+        + Dispatch<WpTearingControlV1, TearingControlData>
+        + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        manager: &WpTearingControlManagerV1,
+        request: <WpTearingControlManagerV1 as Resource>::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wp_tearing_control_manager_v1::Request::GetTearingControl { id, surface } => {
+                let already_exists = with_states(&surface, |states| {
+                    states
+                        .data_map
+                        .insert_if_missing_threadsafe(TearingControlSurfaceData::default);
+                    let data = states.data_map.get::<TearingControlSurfaceData>().unwrap();
+                    let object = data.object.lock().unwrap();
+                    object
+                        .as_ref()
+                        .is_some_and(|object| object.upgrade().is_ok())
+                });
+
+                if already_exists {
+                    manager.post_error(
+                        wp_tearing_control_manager_v1::Error::TearingControlExists,
+                        "wl_surface already has a tearing control object attached",
+                    );
+                    return;
+                }
+
+                let object = data_init.init(
+                    id,
+                    TearingControlData {
+                        surface: surface.downgrade(),
+                    },
+                );
+                with_states(&surface, |states| {
+                    let data = states.data_map.get::<TearingControlSurfaceData>().unwrap();
+                    *data.object.lock().unwrap() = Some(object.downgrade());
+                });
+            }
+            wp_tearing_control_manager_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<WpTearingControlV1, TearingControlData, D> for TearingControlManagerState
+where
+    D: GlobalDispatch<WpTearingControlManagerV1, ()>
+        + Dispatch<WpTearingControlManagerV1, ()>
+        + Dispatch<WpTearingControlV1, TearingControlData>
+        + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _resource: &WpTearingControlV1,
+        request: <WpTearingControlV1 as Resource>::Request,
+        data: &TearingControlData,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        let Ok(surface) = data.surface.upgrade() else {
+            return;
+        };
+
+        match request {
+            wp_tearing_control_v1::Request::SetPresentationHint { hint } => {
+                let WEnum::Value(hint) = hint else {
+                    return;
+                };
+                with_states(&surface, |states| {
+                    let mut guard = states
+                        .cached_state
+                        .get::<TearingControlSurfaceCachedState>();
+                    guard.pending().hint = Some(hint);
+                });
+            }
+            wp_tearing_control_v1::Request::Destroy => {
+                // Destroying the object resets the hint to vsync on the next commit.
+                with_states(&surface, |states| {
+                    let mut guard = states
+                        .cached_state
+                        .get::<TearingControlSurfaceCachedState>();
+                    guard.pending().hint = None;
+                });
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(
+        _state: &mut D,
+        _client: wayland_server::backend::ClientId,
+        _resource: &WpTearingControlV1,
+        data: &TearingControlData,
+    ) {
+        let Ok(surface) = data.surface.upgrade() else {
+            return;
+        };
+        with_states(&surface, |states| {
+            if let Some(data) = states.data_map.get::<TearingControlSurfaceData>() {
+                *data.object.lock().unwrap() = None;
+            }
+        });
+    }
+}
+
+#[allow(missing_docs)]
+#[macro_export]
+macro_rules! delegate_tearing_control {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::tearing_control::v1::server::wp_tearing_control_manager_v1::WpTearingControlManagerV1: ()
+        ] => $crate::protocols::tearing_control::TearingControlManagerState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::tearing_control::v1::server::wp_tearing_control_manager_v1::WpTearingControlManagerV1: ()
+        ] => $crate::protocols::tearing_control::TearingControlManagerState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::tearing_control::v1::server::wp_tearing_control_v1::WpTearingControlV1: $crate::protocols::tearing_control::TearingControlData
+        ] => $crate::protocols::tearing_control::TearingControlManagerState);
+    };
+}
@@ -0,0 +1,213 @@
+//! Implementation of the `xdg-toplevel-drag-v1` protocol.
+//!
+//! This protocol allows clients to attach a toplevel to a drag-and-drop operation, so that the
+//! toplevel follows the pointer while the operation is ongoing. Browsers use this when dragging a
+//! tab out of a window.
+//!
+//! The compositor side (moving the toplevel around) is handled in `src/handlers/toplevel_drag.rs`
+
+use std::sync::Mutex;
+
+use smithay::reexports::wayland_protocols::xdg::shell::server::xdg_toplevel::XdgToplevel;
+use smithay::reexports::wayland_protocols::xdg::toplevel_drag::v1::server::xdg_toplevel_drag_manager_v1::{
+    self, XdgToplevelDragManagerV1,
+};
+use smithay::reexports::wayland_protocols::xdg::toplevel_drag::v1::server::xdg_toplevel_drag_v1::{
+    self, XdgToplevelDragV1,
+};
+use smithay::reexports::wayland_server::protocol::wl_data_source::WlDataSource;
+use smithay::reexports::wayland_server::{
+    self, Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+};
+use smithay::utils::{Logical, Point};
+
+const VERSION: u32 = 1;
+
+pub struct ToplevelDragManagerState {
+    drags: Vec<XdgToplevelDragV1>,
+}
+
+pub trait ToplevelDragHandler {
+    fn toplevel_drag_state(&mut self) -> &mut ToplevelDragManagerState;
+}
+
+impl ToplevelDragManagerState {
+    pub fn new<D>(display: &DisplayHandle) -> Self
+    where
+        D: GlobalDispatch<XdgToplevelDragManagerV1, ()>
+            + Dispatch<XdgToplevelDragManagerV1, ()>
+            + Dispatch<XdgToplevelDragV1, ToplevelDragData>
+            + ToplevelDragHandler
+            + 'static,
+    {
+        display.create_global::<D, XdgToplevelDragManagerV1, _>(VERSION, ());
+        Self { drags: vec![] }
+    }
+
+    /// Get the toplevel attached to the drag using this data source, if any.
+    ///
+    /// The offset is the position of the pointer relative to the toplevel window geometry.
+    pub fn attached_toplevel(
+        &self,
+        source: &WlDataSource,
+    ) -> Option<(XdgToplevel, Point<i32, Logical>)> {
+        self.drags.iter().find_map(|drag| {
+            let data = drag.data::<ToplevelDragData>()?;
+            if data.source != *source {
+                return None;
+            }
+
+            data.toplevel
+                .lock()
+                .unwrap()
+                .clone()
+                .filter(|(toplevel, _)| toplevel.is_alive())
+        })
+    }
+}
+
+/// Data associated with a [`XdgToplevelDragV1`] object.
+pub struct ToplevelDragData {
+    source: WlDataSource,
+    toplevel: Mutex<Option<(XdgToplevel, Point<i32, Logical>)>>,
+}
+
+impl<D> GlobalDispatch<XdgToplevelDragManagerV1, (), D> for ToplevelDragManagerState
+where
+    D: GlobalDispatch<XdgToplevelDragManagerV1, ()>
+        + Dispatch<XdgToplevelDragManagerV1, ()>
+        + Dispatch<XdgToplevelDragV1, ToplevelDragData>
+        + ToplevelDragHandler
+        + 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<XdgToplevelDragManagerV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl<D> Dispatch<XdgToplevelDragManagerV1, (), D> for ToplevelDragManagerState
+where
+    D: GlobalDispatch<XdgToplevelDragManagerV1, ()>
+        + Dispatch<XdgToplevelDragManagerV1, ()>
+        + Dispatch<XdgToplevelDragV1, ToplevelDragData>
+        + ToplevelDragHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        manager: &XdgToplevelDragManagerV1,
+        request: <XdgToplevelDragManagerV1 as Resource>::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            xdg_toplevel_drag_manager_v1::Request::GetXdgToplevelDrag { id, data_source } => {
+                let drag_state = state.toplevel_drag_state();
+                let already_used = drag_state.drags.iter().any(|drag| {
+                    drag.data::<ToplevelDragData>()
+                        .is_some_and(|data| data.source == data_source)
+                });
+                if already_used {
+                    manager.post_error(
+                        xdg_toplevel_drag_manager_v1::Error::InvalidSource,
+                        "wl_data_source is already used for a toplevel drag",
+                    );
+                    return;
+                }
+
+                let drag = data_init.init(
+                    id,
+                    ToplevelDragData {
+                        source: data_source,
+                        toplevel: Mutex::new(None),
+                    },
+                );
+                drag_state.drags.push(drag);
+            }
+            xdg_toplevel_drag_manager_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<XdgToplevelDragV1, ToplevelDragData, D> for ToplevelDragManagerState
+where
+    D: GlobalDispatch<XdgToplevelDragManagerV1, ()>
+        + Dispatch<XdgToplevelDragManagerV1, ()>
+        + Dispatch<XdgToplevelDragV1, ToplevelDragData>
+        + ToplevelDragHandler
+        + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        resource: &XdgToplevelDragV1,
+        request: <XdgToplevelDragV1 as Resource>::Request,
+        data: &ToplevelDragData,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            xdg_toplevel_drag_v1::Request::Attach {
+                toplevel,
+                x_offset,
+                y_offset,
+            } => {
+                let mut guard = data.toplevel.lock().unwrap();
+                if guard
+                    .as_ref()
+                    .is_some_and(|(toplevel, _)| toplevel.is_alive())
+                {
+                    resource.post_error(
+                        xdg_toplevel_drag_v1::Error::ToplevelAttached,
+                        "a toplevel is already attached to this drag",
+                    );
+                    return;
+                }
+
+                *guard = Some((toplevel, Point::from((x_offset, y_offset))));
+            }
+            xdg_toplevel_drag_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut D,
+        _client: wayland_server::backend::ClientId,
+        resource: &XdgToplevelDragV1,
+        _data: &ToplevelDragData,
+    ) {
+        state
+            .toplevel_drag_state()
+            .drags
+            .retain(|drag| drag != resource);
+    }
+}
+
+#[allow(missing_docs)]
+#[macro_export]
+macro_rules! delegate_toplevel_drag {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::xdg::toplevel_drag::v1::server::xdg_toplevel_drag_manager_v1::XdgToplevelDragManagerV1: ()
+        ] => $crate::protocols::toplevel_drag::ToplevelDragManagerState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::xdg::toplevel_drag::v1::server::xdg_toplevel_drag_manager_v1::XdgToplevelDragManagerV1: ()
+        ] => $crate::protocols::toplevel_drag::ToplevelDragManagerState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::xdg::toplevel_drag::v1::server::xdg_toplevel_drag_v1::XdgToplevelDragV1: $crate::protocols::toplevel_drag::ToplevelDragData
+        ] => $crate::protocols::toplevel_drag::ToplevelDragManagerState);
+    };
+}
@@ -0,0 +1,305 @@
+//! Implementation of the `wlr-virtual-pointer-unstable-v1` protocol.
+//!
+//! This protocol allows clients to emulate a physical pointer, for example remote desktop servers
+//! (wayvnc) or automation tools (ydotool, wlrctl).
+//!
+//! Axis events are accumulated until the client sends a frame, like libinput does.
+
+use std::sync::Mutex;
+
+use smithay::backend::input::{Axis, AxisSource, ButtonState};
+use smithay::input::pointer::AxisFrame;
+use smithay::output::{Output, WeakOutput};
+use smithay::reexports::wayland_protocols_wlr::virtual_pointer::v1::server::zwlr_virtual_pointer_manager_v1::{
+    self, ZwlrVirtualPointerManagerV1,
+};
+use smithay::reexports::wayland_protocols_wlr::virtual_pointer::v1::server::zwlr_virtual_pointer_v1::{
+    self, ZwlrVirtualPointerV1,
+};
+use smithay::reexports::wayland_server::protocol::wl_pointer;
+use smithay::reexports::wayland_server::{
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource, WEnum,
+};
+use smithay::utils::{Logical, Point};
+
+const VERSION: u32 = 2;
+
+pub struct VirtualPointerManagerState;
+
+pub struct VirtualPointerManagerGlobalData {
+    filter: Box<dyn Fn(&Client) -> bool + Send + Sync>,
+}
+
+/// An event emitted by a virtual pointer.
+pub enum VirtualPointerEvent {
+    /// Relative pointer motion.
+    Motion {
+        time: u32,
+        delta: Point<f64, Logical>,
+    },
+    /// Absolute pointer motion.
+    ///
+    /// The position is normalized between 0 and 1, relative to the output the virtual pointer is
+    /// bound to, if any.
+    MotionAbsolute {
+        time: u32,
+        position: Point<f64, Logical>,
+        output: Option<Output>,
+    },
+    Button {
+        time: u32,
+        button: u32,
+        state: ButtonState,
+    },
+    Axis(AxisFrame),
+    Frame,
+}
+
+pub trait VirtualPointerHandler {
+    fn virtual_pointer_event(&mut self, event: VirtualPointerEvent);
+}
+
+impl VirtualPointerManagerState {
+    pub fn new<D, F>(display: &DisplayHandle, filter: F) -> Self
+    where
+        D: GlobalDispatch<ZwlrVirtualPointerManagerV1, VirtualPointerManagerGlobalData>
+            + Dispatch<ZwlrVirtualPointerManagerV1, ()>
+            + Dispatch<ZwlrVirtualPointerV1, VirtualPointerData>
+            + VirtualPointerHandler
+            + 'static,
+        F: Fn(&Client) -> bool + Send + Sync + 'static,
+    {
+        let global_data = VirtualPointerManagerGlobalData {
+            filter: Box::new(filter),
+        };
+        display.create_global::<D, ZwlrVirtualPointerManagerV1, _>(VERSION, global_data);
+        Self
+    }
+}
+
+/// Data associated with a [`ZwlrVirtualPointerV1`] object.
+pub struct VirtualPointerData {
+    output: Option<WeakOutput>,
+    // The axis events of the current frame, if any.
+    axis_frame: Mutex<Option<AxisFrame>>,
+}
+
+impl<D> GlobalDispatch<ZwlrVirtualPointerManagerV1, VirtualPointerManagerGlobalData, D>
+    for VirtualPointerManagerState
+where
+    D: GlobalDispatch<ZwlrVirtualPointerManagerV1, VirtualPointerManagerGlobalData>
+        + Dispatch<ZwlrVirtualPointerManagerV1, ()>
+        + Dispatch<ZwlrVirtualPointerV1, VirtualPointerData>
+        + VirtualPointerHandler
+        + 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwlrVirtualPointerManagerV1>,
+        _global_data: &VirtualPointerManagerGlobalData,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+
+    fn can_view(client: Client, global_data: &VirtualPointerManagerGlobalData) -> bool {
+        (global_data.filter)(&client)
+    }
+}
+
+impl<D> Dispatch<ZwlrVirtualPointerManagerV1, (), D> for VirtualPointerManagerState
+where
+    D: GlobalDispatch<ZwlrVirtualPointerManagerV1, VirtualPointerManagerGlobalData>
+        + Dispatch<ZwlrVirtualPointerManagerV1, ()>
+        + Dispatch<ZwlrVirtualPointerV1, VirtualPointerData>
+        + VirtualPointerHandler
+        + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _manager: &ZwlrVirtualPointerManagerV1,
+        request: <ZwlrVirtualPointerManagerV1 as Resource>::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        // NOTE: We only have a single seat, so we ignore the requested one.
+        let (id, output) = match request {
+            zwlr_virtual_pointer_manager_v1::Request::CreateVirtualPointer { id, .. } => (id, None),
+            zwlr_virtual_pointer_manager_v1::Request::CreateVirtualPointerWithOutput {
+                id,
+                output,
+                ..
+            } => {
+                let output = output.as_ref().and_then(Output::from_resource);
+                (id, output.map(|output| output.downgrade()))
+            }
+            zwlr_virtual_pointer_manager_v1::Request::Destroy => return,
+            _ => unreachable!(),
+        };
+
+        data_init.init(
+            id,
+            VirtualPointerData {
+                output,
+                axis_frame: Mutex::new(None),
+            },
+        );
+    }
+}
+
+impl<D> Dispatch<ZwlrVirtualPointerV1, VirtualPointerData, D> for VirtualPointerManagerState
+where
+    D: GlobalDispatch<ZwlrVirtualPointerManagerV1, VirtualPointerManagerGlobalData>
+        + Dispatch<ZwlrVirtualPointerManagerV1, ()>
+        + Dispatch<ZwlrVirtualPointerV1, VirtualPointerData>
+        + VirtualPointerHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        resource: &ZwlrVirtualPointerV1,
+        request: <ZwlrVirtualPointerV1 as Resource>::Request,
+        data: &VirtualPointerData,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        let axis = |axis: WEnum<wl_pointer::Axis>| match axis {
+            WEnum::Value(wl_pointer::Axis::HorizontalScroll) => Some(Axis::Horizontal),
+            WEnum::Value(wl_pointer::Axis::VerticalScroll) => Some(Axis::Vertical),
+            _ => {
+                resource.post_error(zwlr_virtual_pointer_v1::Error::InvalidAxis, "invalid axis");
+                None
+            }
+        };
+        let with_axis_frame = |time: u32, f: &dyn Fn(AxisFrame) -> AxisFrame| {
+            let mut guard = data.axis_frame.lock().unwrap();
+            let frame = guard.take().unwrap_or_else(|| AxisFrame::new(time));
+            *guard = Some(f(frame));
+        };
+
+        match request {
+            zwlr_virtual_pointer_v1::Request::Motion { time, dx, dy } => {
+                state.virtual_pointer_event(VirtualPointerEvent::Motion {
+                    time,
+                    delta: Point::from((dx, dy)),
+                });
+            }
+            zwlr_virtual_pointer_v1::Request::MotionAbsolute {
+                time,
+                x,
+                y,
+                x_extent,
+                y_extent,
+            } => {
+                if x_extent == 0 || y_extent == 0 {
+                    return;
+                }
+
+                let position = Point::from((
+                    f64::from(x) / f64::from(x_extent),
+                    f64::from(y) / f64::from(y_extent),
+                ));
+                let output = data.output.as_ref().and_then(WeakOutput::upgrade);
+                state.virtual_pointer_event(VirtualPointerEvent::MotionAbsolute {
+                    time,
+                    position,
+                    output,
+                });
+            }
+            zwlr_virtual_pointer_v1::Request::Button {
+                time,
+                button,
+                state: button_state,
+            } => {
+                let button_state = match button_state {
+                    WEnum::Value(wl_pointer::ButtonState::Pressed) => ButtonState::Pressed,
+                    _ => ButtonState::Released,
+                };
+                state.virtual_pointer_event(VirtualPointerEvent::Button {
+                    time,
+                    button,
+                    state: button_state,
+                });
+            }
+            zwlr_virtual_pointer_v1::Request::Axis {
+                time,
+                axis: wl_axis,
+                value,
+            } => {
+                if let Some(axis) = axis(wl_axis) {
+                    with_axis_frame(time, &|frame| frame.value(axis, value));
+                }
+            }
+            zwlr_virtual_pointer_v1::Request::AxisSource { axis_source } => {
+                let source = match axis_source {
+                    WEnum::Value(wl_pointer::AxisSource::Wheel) => AxisSource::Wheel,
+                    WEnum::Value(wl_pointer::AxisSource::Finger) => AxisSource::Finger,
+                    WEnum::Value(wl_pointer::AxisSource::Continuous) => AxisSource::Continuous,
+                    WEnum::Value(wl_pointer::AxisSource::WheelTilt) => AxisSource::WheelTilt,
+                    _ => {
+                        resource.post_error(
+                            zwlr_virtual_pointer_v1::Error::InvalidAxisSource,
+                            "invalid axis source",
+                        );
+                        return;
+                    }
+                };
+                with_axis_frame(0, &|frame| frame.source(source));
+            }
+            zwlr_virtual_pointer_v1::Request::AxisStop {
+                time,
+                axis: wl_axis,
+            } => {
+                if let Some(axis) = axis(wl_axis) {
+                    with_axis_frame(time, &|frame| frame.stop(axis));
+                }
+            }
+            zwlr_virtual_pointer_v1::Request::AxisDiscrete {
+                time,
+                axis: wl_axis,
+                value,
+                discrete,
+            } => {
+                if let Some(axis) = axis(wl_axis) {
+                    with_axis_frame(time, &|frame| {
+                        frame
+                            .value(axis, value)
+                            .v120(axis, discrete.saturating_mul(120))
+                    });
+                }
+            }
+            zwlr_virtual_pointer_v1::Request::Frame => {
+                let axis_frame = data.axis_frame.lock().unwrap().take();
+                if let Some(axis_frame) = axis_frame {
+                    state.virtual_pointer_event(VirtualPointerEvent::Axis(axis_frame));
+                }
+                state.virtual_pointer_event(VirtualPointerEvent::Frame);
+            }
+            zwlr_virtual_pointer_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[allow(missing_docs)]
+#[macro_export]
+macro_rules! delegate_virtual_pointer {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols_wlr::virtual_pointer::v1::server::zwlr_virtual_pointer_manager_v1::ZwlrVirtualPointerManagerV1: $crate::protocols::virtual_pointer::VirtualPointerManagerGlobalData
+        ] => $crate::protocols::virtual_pointer::VirtualPointerManagerState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols_wlr::virtual_pointer::v1::server::zwlr_virtual_pointer_manager_v1::ZwlrVirtualPointerManagerV1: ()
+        ] => $crate::protocols::virtual_pointer::VirtualPointerManagerState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols_wlr::virtual_pointer::v1::server::zwlr_virtual_pointer_v1::ZwlrVirtualPointerV1: $crate::protocols::virtual_pointer::VirtualPointerData
+        ] => $crate::protocols::virtual_pointer::VirtualPointerManagerState);
+    };
+}
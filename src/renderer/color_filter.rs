@@ -0,0 +1,74 @@
+//! Screen-wide color filters.
+//!
+//! When an output has a color filter, everything below the cursor gets rendered offscreen, then
+//! drawn with the color filter shader that applies a color matrix on each pixel. See the
+//! `color-filter` output option and the `toggle-color-filter` key action.
+//!
+//! The color blindness correction uses the daltonization algorithm: we simulate how the colors
+//! are seen with the deficiency, then shift the lost information to the channels that can still be
+//! told apart.
+
+use fht_compositor_config::ColorFilter;
+use glam::{Mat3, Vec3};
+use smithay::output::Output;
+
+use crate::state::Fht;
+
+impl Fht {
+    /// Toggle `filter` on `output`, replacing the previous color filter if there was any.
+    pub fn toggle_color_filter(&mut self, output: &Output, filter: ColorFilter) {
+        let Some(state) = self.output_state.get_mut(output) else {
+            return;
+        };
+        state.color_filter = if state.color_filter == Some(filter) {
+            None
+        } else {
+            Some(filter)
+        };
+        self.queue_redraw(output);
+    }
+}
+
+/// Get the matrix and the offset to apply to the colors for `filter`.
+pub(super) fn color_matrix(filter: ColorFilter) -> (Mat3, Vec3) {
+    // Conversion to the LMS color space, matching the response of the eye cones.
+    const RGB_TO_LMS: Mat3 = Mat3::from_cols(
+        Vec3::new(17.8824, 3.45565, 0.0299566),
+        Vec3::new(43.5161, 27.1554, 0.184309),
+        Vec3::new(4.11935, 3.86714, 1.46709),
+    );
+    // How the information lost by the missing cones gets shifted to the remaining channels.
+    const ERROR_SHIFT: Mat3 = Mat3::from_cols(
+        Vec3::new(0.0, 0.7, 0.7),
+        Vec3::new(0.0, 1.0, 0.0),
+        Vec3::new(0.0, 0.0, 1.0),
+    );
+
+    let simulation = match filter {
+        ColorFilter::Grayscale => {
+            // Rec. 709 luma coefficients.
+            let luma = Vec3::new(0.2126, 0.7152, 0.0722);
+            return (Mat3::from_cols(luma, luma, luma).transpose(), Vec3::ZERO);
+        }
+        ColorFilter::Invert => return (-Mat3::IDENTITY, Vec3::ONE),
+        ColorFilter::Protanopia => Mat3::from_cols(
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(2.02344, 1.0, 0.0),
+            Vec3::new(-2.52581, 0.0, 1.0),
+        ),
+        ColorFilter::Deuteranopia => Mat3::from_cols(
+            Vec3::new(1.0, 0.494207, 0.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.24827, 1.0),
+        ),
+        ColorFilter::Tritanopia => Mat3::from_cols(
+            Vec3::new(1.0, 0.0, -0.395913),
+            Vec3::new(0.0, 1.0, 0.801109),
+            Vec3::new(0.0, 0.0, 0.0),
+        ),
+    };
+
+    let simulation = RGB_TO_LMS.inverse() * simulation * RGB_TO_LMS;
+    let correction = Mat3::IDENTITY + ERROR_SHIFT * (Mat3::IDENTITY - simulation);
+    (correction, Vec3::ZERO)
+}
@@ -0,0 +1,263 @@
+//! Debug render elements.
+//!
+//! These are drawn on top of the output elements to visualize what the damage tracker does, see
+//! the `debug.draw-damage` and `debug.draw-opaque-regions` options.
+//!
+//! There's also a [`DebugOverlay`] displaying the rendering statistics of each output, toggled with
+//! the `toggle-debug-overlay` key action or IPC request.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use smithay::backend::renderer::damage::OutputDamageTracker;
+use smithay::backend::renderer::element::solid::SolidColorRenderElement;
+use smithay::backend::renderer::element::{
+    Element, Id, Kind, RenderElementPresentationState, RenderElementStates,
+};
+use smithay::backend::renderer::utils::CommitCounter;
+use smithay::backend::renderer::Color32F;
+use smithay::output::Output;
+use smithay::utils::{Point, Scale, Size};
+
+use super::{DebugRenderElement, FhtRenderElement, FhtRenderer};
+use crate::egui::EguiElement;
+use crate::output::OutputExt;
+use crate::state::Fht;
+use crate::utils::get_monotonic_time;
+
+// Plenty of room for the statistics text.
+const OVERLAY_SIZE: (i32, i32) = (350, 200);
+// The statistics are computed over the frames of the last second.
+const STATS_WINDOW: Duration = Duration::from_secs(1);
+
+impl Fht {
+    /// Add the debug render elements enabled in the configuration to `elements`.
+    pub fn draw_debug_elements<R: FhtRenderer>(
+        &mut self,
+        renderer: &mut R,
+        output: &Output,
+        elements: &mut Vec<FhtRenderElement<R>>,
+    ) {
+        // To render damage we just use solid color elements,
+        if self.config.debug.draw_damage || self.debug_overlay {
+            let state = self.output_state.get_mut(output).unwrap();
+            draw_damage(output, &mut state.debug_damage_tracker, elements);
+        }
+
+        if self.config.debug.draw_opaque_regions {
+            let scale = output.current_scale().integer_scale() as f64;
+            draw_opaque_regions(elements, scale.into());
+        }
+
+        if self.debug_overlay {
+            let state = self.output_state.get(output).unwrap();
+            let scale = output.current_scale().integer_scale();
+            if let Some(element) = state.debug_overlay.render(renderer, output, scale) {
+                elements.insert(0, element.into());
+            }
+        }
+    }
+
+    /// Toggle the [`DebugOverlay`] of all outputs.
+    pub fn toggle_debug_overlay(&mut self) {
+        self.debug_overlay = !self.debug_overlay;
+        self.queue_redraw_all();
+    }
+
+    /// Record how the render elements of the last frame of `output` got presented.
+    pub fn record_debug_element_states(&mut self, output: &Output, states: &RenderElementStates) {
+        if self.debug_overlay {
+            let state = self.output_state.get_mut(output).unwrap();
+            state.debug_overlay.record_element_states(states);
+        }
+    }
+}
+
+/// Rendering statistics of an output, displayed in its top left corner.
+#[derive(Debug)]
+pub struct DebugOverlay {
+    egui: EguiElement,
+    /// The frames rendered during the last [`STATS_WINDOW`], as (time, render duration, submitted).
+    frames: VecDeque<(Duration, Duration, bool)>,
+    /// The number of elements that got composited by the renderer during the last frame.
+    composited_elements: usize,
+    /// The number of elements that got scanned out directly, on the primary plane or on overlay
+    /// planes, during the last frame.
+    scanned_out_elements: usize,
+}
+
+impl DebugOverlay {
+    pub fn new() -> Self {
+        Self {
+            egui: EguiElement::new(Size::from(OVERLAY_SIZE)),
+            frames: VecDeque::new(),
+            composited_elements: 0,
+            scanned_out_elements: 0,
+        }
+    }
+
+    /// Record a frame that took `render_time` to render, and was `submitted` if it had damage.
+    pub fn record_frame(&mut self, render_time: Duration, submitted: bool) {
+        let now = get_monotonic_time();
+        self.frames.push_back((now, render_time, submitted));
+        while self
+            .frames
+            .front()
+            .is_some_and(|(time, _, _)| now.saturating_sub(*time) > STATS_WINDOW)
+        {
+            self.frames.pop_front();
+        }
+    }
+
+    fn record_element_states(&mut self, states: &RenderElementStates) {
+        self.composited_elements = 0;
+        self.scanned_out_elements = 0;
+        for state in states.states.values() {
+            match state.presentation_state {
+                RenderElementPresentationState::ZeroCopy => self.scanned_out_elements += 1,
+                RenderElementPresentationState::Rendering { .. } => self.composited_elements += 1,
+                RenderElementPresentationState::Skipped => (),
+            }
+        }
+    }
+
+    fn render<R: FhtRenderer>(
+        &self,
+        renderer: &mut R,
+        output: &Output,
+        scale: i32,
+    ) -> Option<DebugRenderElement> {
+        crate::profile_function!();
+        let submitted = self
+            .frames
+            .iter()
+            .filter(|(_, _, submitted)| *submitted)
+            .count();
+        let render_times = self.frames.iter().map(|(_, render_time, _)| *render_time);
+        let max_render_time = render_times.clone().max().unwrap_or_default();
+        let average_render_time = render_times
+            .sum::<Duration>()
+            .checked_div(self.frames.len() as u32)
+            .unwrap_or_default();
+
+        self.egui
+            .render(
+                renderer.glow_renderer_mut(),
+                scale,
+                1.0,
+                Point::default(),
+                output.refresh_interval(),
+                |ctx| {
+                    egui::Area::new(egui::Id::NULL).show(ctx, |ui| {
+                        egui::Frame::window(ui.style())
+                            .inner_margin(4.0)
+                            .shadow(egui::Shadow::NONE)
+                            .show(ui, |ui| {
+                                ui.label(format!("FPS: {submitted}"));
+                                ui.label(format!("Frames rendered: {}", self.frames.len()));
+                                ui.label(format!(
+                                    "Render time: {:.2} ms average, {:.2} ms max",
+                                    average_render_time.as_secs_f64() * 1000.0,
+                                    max_render_time.as_secs_f64() * 1000.0,
+                                ));
+                                ui.label(format!(
+                                    "Composited elements: {}",
+                                    self.composited_elements
+                                ));
+                                ui.label(format!(
+                                    "Scanned out elements: {}",
+                                    self.scanned_out_elements
+                                ));
+                            });
+                    });
+                },
+            )
+            .inspect_err(|err| warn!(?err, "Failed to render debug overlay"))
+            .ok()
+            .map(DebugRenderElement::Overlay)
+    }
+}
+
+const DAMAGE_COLOR: Color32F = Color32F::new(0.3, 0.0, 0.0, 0.3);
+const OPAQUE_REGION_COLOR: Color32F = Color32F::new(0.0, 0.0, 0.3, 0.3);
+const SEMITRANSPARENT_COLOR: Color32F = Color32F::new(0.0, 0.3, 0.0, 0.3);
+
+pub fn draw_damage<R: FhtRenderer>(
+    output: &Output,
+    dt: &mut Option<OutputDamageTracker>,
+    elements: &mut Vec<FhtRenderElement<R>>,
+) {
+    let dt = dt.get_or_insert_with(|| OutputDamageTracker::from_output(output));
+    let Ok((Some(damage), _)) = dt.damage_output(1, elements) else {
+        return;
+    };
+
+    for damage_rect in damage {
+        let damage_element: DebugRenderElement = SolidColorRenderElement::new(
+            Id::new(),
+            *damage_rect,
+            CommitCounter::default(),
+            DAMAGE_COLOR,
+            Kind::Unspecified,
+        )
+        .into();
+        elements.insert(0, damage_element.into())
+    }
+}
+
+pub fn draw_opaque_regions<R: FhtRenderer>(
+    elements: &mut Vec<FhtRenderElement<R>>,
+    scale: Scale<f64>,
+) {
+    crate::profile_function!();
+
+    let mut i = 0;
+    while i < elements.len() {
+        let elem = &elements[i];
+        i += 1;
+
+        // HACK
+        if format!("{elem:?}").contains("ExtraDamage") {
+            continue;
+        }
+
+        let geo = elem.geometry(scale);
+        let mut opaque = elem.opaque_regions(scale).to_vec();
+
+        for rect in &mut opaque {
+            rect.loc += geo.loc;
+        }
+
+        let semitransparent = geo.subtract_rects(opaque.iter().copied());
+
+        for rect in opaque {
+            let color = SolidColorRenderElement::new(
+                Id::new(),
+                rect,
+                CommitCounter::default(),
+                OPAQUE_REGION_COLOR,
+                Kind::Unspecified,
+            );
+            elements.insert(
+                i - 1,
+                FhtRenderElement::Debug(DebugRenderElement::Solid(color)),
+            );
+            i += 1;
+        }
+
+        for rect in semitransparent {
+            let color = SolidColorRenderElement::new(
+                Id::new(),
+                rect,
+                CommitCounter::default(),
+                SEMITRANSPARENT_COLOR,
+                Kind::Unspecified,
+            );
+            elements.insert(
+                i - 1,
+                FhtRenderElement::Debug(DebugRenderElement::Solid(color)),
+            );
+            i += 1;
+        }
+    }
+}
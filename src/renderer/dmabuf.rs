@@ -0,0 +1,84 @@
+//! DMA-BUF format and modifier restrictions.
+//!
+//! Some hardware/driver combinations have issues with some formats or modifiers (for example
+//! Intel CCS modifiers), so the user can restrict what we advertise to clients and what we accept
+//! when importing buffers. See the `debug` section of the configuration.
+
+use fht_compositor_config::Debug as DebugConfig;
+use smithay::backend::allocator::format::FormatSet;
+use smithay::backend::allocator::{Format, Modifier};
+
+/// DRM vendor code of Intel modifiers, see `drm_fourcc.h`
+const DRM_FORMAT_MOD_VENDOR_INTEL: u64 = 0x01;
+
+/// Intel modifiers using a compression control surface, see `drm_fourcc.h`
+const INTEL_CCS_MODIFIERS: &[u64] = &[
+    4,  // I915_FORMAT_MOD_Y_TILED_CCS
+    5,  // I915_FORMAT_MOD_Yf_TILED_CCS
+    6,  // I915_FORMAT_MOD_Y_TILED_GEN12_RC_CCS
+    7,  // I915_FORMAT_MOD_Y_TILED_GEN12_MC_CCS
+    8,  // I915_FORMAT_MOD_Y_TILED_GEN12_RC_CCS_CC
+    10, // I915_FORMAT_MOD_4_TILED_DG2_RC_CCS
+    11, // I915_FORMAT_MOD_4_TILED_DG2_MC_CCS
+    12, // I915_FORMAT_MOD_4_TILED_DG2_RC_CCS_CC
+    13, // I915_FORMAT_MOD_4_TILED_MTL_RC_CCS
+    14, // I915_FORMAT_MOD_4_TILED_MTL_MC_CCS
+    15, // I915_FORMAT_MOD_4_TILED_MTL_RC_CCS_CC
+    16, // I915_FORMAT_MOD_4_TILED_LNL_CCS
+    17, // I915_FORMAT_MOD_4_TILED_BMG_CCS
+];
+
+fn is_ccs_modifier(modifier: Modifier) -> bool {
+    let modifier = u64::from(modifier);
+    let vendor = modifier >> 56;
+    let value = modifier & ((1 << 56) - 1);
+    vendor == DRM_FORMAT_MOD_VENDOR_INTEL && INTEL_CCS_MODIFIERS.contains(&value)
+}
+
+/// Whether this DMA-BUF format is allowed by the user configuration.
+pub fn is_format_allowed(format: Format, config: &DebugConfig) -> bool {
+    if config.disallowed_dmabuf_formats.contains(&format.code) {
+        return false;
+    }
+
+    if config
+        .disallowed_dmabuf_modifiers
+        .contains(&u64::from(format.modifier))
+    {
+        return false;
+    }
+
+    !(config.disable_ccs_modifiers && is_ccs_modifier(format.modifier))
+}
+
+/// Filter the formats to advertise to clients according to the user configuration.
+pub fn filter_formats(formats: &FormatSet, config: &DebugConfig) -> FormatSet {
+    formats
+        .iter()
+        .copied()
+        .filter(|format| is_format_allowed(*format, config))
+        .collect()
+}
+
+/// Get the formats to offer to screencast consumers.
+///
+/// If the user restrictions filter out every format, or if there's no linear format available
+/// when `screencast-force-linear` is enabled, we fallback to the unfiltered formats so that the
+/// screencast can still start.
+pub fn screencast_formats(formats: &FormatSet, config: &DebugConfig) -> FormatSet {
+    let mut filtered = filter_formats(formats, config);
+    if config.screencast_force_linear {
+        filtered = filtered
+            .iter()
+            .copied()
+            .filter(|format| format.modifier == Modifier::Linear)
+            .collect();
+    }
+
+    if filtered.iter().next().is_none() {
+        warn!("No screencast formats left after applying restrictions, ignoring them");
+        return formats.clone();
+    }
+
+    filtered
+}
@@ -7,7 +7,10 @@
 //! This module also has some helpers to create render elements.
 
 pub mod blur;
+mod color_filter;
 mod data;
+pub mod debug;
+pub mod dmabuf;
 pub mod extra_damage;
 pub mod pixel_shader_element;
 pub mod render_elements;
@@ -20,12 +23,19 @@ use std::borrow::BorrowMut;
 
 use anyhow::Context;
 use blur::EffectsFramebuffers;
-use glam::{Mat3, Vec2};
+use color_filter::color_matrix;
+use fht_compositor_config::ColorFilter;
+use glam::{Mat3, Vec2, Vec3};
+use shaders::Shaders;
 use smithay::backend::allocator::dmabuf::Dmabuf;
 use smithay::backend::allocator::{Buffer as _, Fourcc};
 use smithay::backend::renderer::damage::OutputDamageTracker;
 use smithay::backend::renderer::element::solid::SolidColorRenderElement;
-use smithay::backend::renderer::element::{AsRenderElements, RenderElement};
+use smithay::backend::renderer::element::texture::{TextureRenderBuffer, TextureRenderElement};
+use smithay::backend::renderer::element::{
+    AsRenderElements, Kind, RenderElement, RenderElementStates,
+};
+use smithay::backend::renderer::gles::element::TextureShaderElement;
 use smithay::backend::renderer::gles::{
     GlesError, GlesMapping, GlesTexture, Uniform, UniformValue,
 };
@@ -46,26 +56,37 @@ use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
 use smithay::utils::{
     Buffer, IsAlive, Logical, Physical, Point, Rectangle, Scale, Size, Transform,
 };
+use smithay::wayland::alpha_modifier::AlphaModifierSurfaceCachedState;
 use smithay::wayland::compositor::with_states;
 use smithay::wayland::shell::wlr_layer::Layer;
 use smithay::wayland::shm::with_buffer_contents_mut;
+use texture_shader_element::FhtTextureShaderElement;
 
 use crate::config::ui::ConfigUiRenderElement;
 use crate::cursor::CursorRenderElement;
+use crate::egui::EguiRenderElement;
 use crate::handlers::session_lock::SessionLockRenderElement;
 use crate::layer::{layer_elements, LayerShellRenderElement};
+use crate::osd::OsdRenderElement;
 use crate::protocols::screencopy::{ScreencopyBuffer, ScreencopyFrame};
+use crate::screenshot::RegionSelectionRenderElement;
 use crate::space::{MonitorRenderElement, MonitorRenderResult};
 use crate::state::Fht;
 use crate::utils::get_monotonic_time;
+use crate::workspace_drop_strip::WorkspaceDropStripRenderElement;
 
 crate::fht_render_elements! {
     FhtRenderElement<R> => {
         Cursor = CursorRenderElement<R>,
         ConfigUi = ConfigUiRenderElement,
+        Osd = OsdRenderElement,
         Monitor = MonitorRenderElement<R>,
         LayerShell = LayerShellRenderElement<R>,
         SessionLock = SessionLockRenderElement<R>,
+        RegionSelection = RegionSelectionRenderElement,
+        WorkspaceDropStrip = WorkspaceDropStripRenderElement,
+        Fade = SolidColorRenderElement,
+        Offscreen = FhtTextureShaderElement,
         Debug = DebugRenderElement,
     }
 }
@@ -73,6 +94,7 @@ crate::fht_render_elements! {
 crate::fht_render_elements! {
     DebugRenderElement => {
         Solid = SolidColorRenderElement,
+        Overlay = EguiRenderElement,
     }
 }
 
@@ -89,6 +111,13 @@ pub struct OutputElementsResult<R: FhtRenderer> {
     /// `self.elements`, to exclude the cursor elements from them, you can slice the vector at
     /// `[cursor_elements_len..]`
     pub cursor_elements_len: usize,
+    /// The fade overlay elements len.
+    ///
+    /// The fade overlay comes right after the cursor elements, see [`Self::capture_elements`].
+    pub fade_elements_len: usize,
+    /// The states of the elements rendered offscreen, when the output has a color filter or is
+    /// zoomed, see [`Self::merge_offscreen_states`].
+    pub offscreen_states: Option<RenderElementStates>,
 }
 
 impl<R: FhtRenderer> Default for OutputElementsResult<R> {
@@ -96,6 +125,31 @@ impl<R: FhtRenderer> Default for OutputElementsResult<R> {
         Self {
             elements: vec![],
             cursor_elements_len: 0,
+            fade_elements_len: 0,
+            offscreen_states: None,
+        }
+    }
+}
+
+impl<R: FhtRenderer> OutputElementsResult<R> {
+    /// Get the render elements to capture the output with, for screencopy, screencasts and
+    /// screenshots.
+    ///
+    /// The fade overlay is only there for the user looking at the screen, so it's never captured.
+    pub fn capture_elements(&self, with_cursor: bool) -> Vec<&FhtRenderElement<R>> {
+        let cursor_elements = &self.elements[..self.cursor_elements_len];
+        let scene_elements = &self.elements[self.cursor_elements_len + self.fade_elements_len..];
+        let cursor_elements = if with_cursor { cursor_elements } else { &[] };
+        cursor_elements.iter().chain(scene_elements).collect()
+    }
+
+    /// Add the states of the elements rendered offscreen to the `states` of the output render.
+    ///
+    /// When rendering offscreen, the output render only contains the offscreen texture element,
+    /// so without them windows would never get the output as their primary scanout output.
+    pub fn merge_offscreen_states(&mut self, states: &mut RenderElementStates) {
+        if let Some(offscreen_states) = self.offscreen_states.take() {
+            states.states.extend(offscreen_states.states);
         }
     }
 }
@@ -107,7 +161,69 @@ impl Fht {
         output: &Output,
     ) -> OutputElementsResult<R> {
         crate::profile_function!();
-        let active_output = self.space.active_output();
+        let mut rv = OutputElementsResult::default();
+
+        let color_filter = self
+            .output_state
+            .get(output)
+            .and_then(|state| state.color_filter);
+        let zoom_area = self.zoom_visible_area(output);
+        if color_filter.is_none() && zoom_area.is_none() {
+            rv.cursor_elements_len = self.cursor_elements(renderer, output, &mut rv.elements);
+            self.scene_elements(renderer, output, &mut rv.elements);
+        } else {
+            // Color filters apply to everything below the cursor, that we render offscreen first.
+            // When zoomed in, the cursor gets magnified with the rest of the output.
+            if zoom_area.is_none() {
+                rv.cursor_elements_len = self.cursor_elements(renderer, output, &mut rv.elements);
+            }
+            match self.offscreen_element(
+                renderer.glow_renderer_mut(),
+                output,
+                color_filter,
+                zoom_area,
+            ) {
+                Ok((element, states)) => {
+                    rv.elements.push(element.into());
+                    rv.offscreen_states = Some(states);
+                }
+                Err(err) => {
+                    warn!(?err, "Failed to render output offscreen");
+                    if zoom_area.is_some() {
+                        rv.cursor_elements_len =
+                            self.cursor_elements(renderer, output, &mut rv.elements);
+                    }
+                    self.scene_elements(renderer, output, &mut rv.elements);
+                }
+            }
+        }
+
+        // The fade overlay is drawn above everything else, except the cursor.
+        let scale = output.current_scale().integer_scale();
+        let fade = &mut self.output_state.get_mut(output).unwrap().fade;
+        if let Some(element) = fade.render(output, scale) {
+            rv.elements.insert(rv.cursor_elements_len, element.into());
+            rv.fade_elements_len = 1;
+        }
+
+        rv
+    }
+
+    /// Add the render elements of the cursor and the drag and drop icon to `elements`, returning
+    /// how many got added.
+    ///
+    /// The cursor is only rendered on the active output.
+    fn cursor_elements<R: FhtRenderer>(
+        &mut self,
+        renderer: &mut R,
+        output: &Output,
+        elements: &mut Vec<FhtRenderElement<R>>,
+    ) -> usize {
+        crate::profile_function!();
+        if self.space.active_output() != output {
+            return 0;
+        }
+
         // Yes, we do not support fractional scale.
         //
         // For now, the ecosystem in wayland has all the required protocols and stuff to support it
@@ -117,50 +233,70 @@ impl Fht {
         // When the Wayland space will see evolutions regarding fractional scaling, I'll reconsider
         // this choice and support it. But as far as I can see, this isn't happening.
         let scale = output.current_scale().integer_scale();
+        let len = elements.len();
+        let reset = matches!(
+            self.cursor_theme_manager.image_status(),
+            CursorImageStatus::Surface(ref surface) if !surface.alive()
+        );
+        if reset {
+            self.cursor_theme_manager
+                .set_image_status(CursorImageStatus::default_named());
+        }
 
-        let mut rv = OutputElementsResult::default();
-
-        // Start with the cursor
-        //
-        // Why include cursor_elements_len? Since if we are rendering to screencast, we can take a
-        // slice of elements to skip cursor_elements (slice [cursor_elements_len..])
-        if active_output == output {
-            // Render the cursor only on the active output
-            let reset = matches!(
-                self.cursor_theme_manager.image_status(),
-                CursorImageStatus::Surface(ref surface) if !surface.alive()
-            );
-            if reset {
-                self.cursor_theme_manager
-                    .set_image_status(CursorImageStatus::default_named());
-            }
+        let cursor_element_pos = (self.seat.get_pointer().unwrap().current_location()
+            - output.current_location().to_f64())
+        .to_physical_precise_round(scale);
+        if let Ok(cursor_elements) = self.cursor_theme_manager.render(
+            renderer,
+            cursor_element_pos,
+            scale,
+            1.0,
+            self.clock.now().into(),
+        ) {
+            elements.extend(cursor_elements.into_iter().map(Into::into));
+        }
 
-            let cursor_element_pos = (self.pointer.current_location()
-                - output.current_location().to_f64())
-            .to_physical_precise_round(scale);
-            if let Ok(elements) = self.cursor_theme_manager.render(
+        // Draw drag and drop icon.
+        if let Some(surface) = self.dnd_icon.as_ref().filter(IsAlive::alive) {
+            let dnd_elements = AsRenderElements::<R>::render_elements::<CursorRenderElement<R>>(
+                &SurfaceTree::from_surface(surface),
                 renderer,
                 cursor_element_pos,
-                scale,
+                Scale::from(scale as f64),
                 1.0,
-                self.clock.now().into(),
-            ) {
-                rv.cursor_elements_len += elements.len();
-                rv.elements.extend(elements.into_iter().map(Into::into));
-            }
+            );
+            elements.extend(dnd_elements.into_iter().map(Into::into));
+        }
 
-            // Draw drag and drop icon.
-            if let Some(surface) = self.dnd_icon.as_ref().filter(IsAlive::alive) {
-                let elements = AsRenderElements::<R>::render_elements::<CursorRenderElement<R>>(
-                    &SurfaceTree::from_surface(surface),
-                    renderer,
-                    cursor_element_pos,
-                    Scale::from(scale as f64),
-                    1.0,
-                );
-                rv.cursor_elements_len += elements.len();
-                rv.elements.extend(elements.into_iter().map(Into::into));
-            }
+        elements.len() - len
+    }
+
+    /// Add the render elements of everything displayed on `output` to `elements`, except the
+    /// cursor.
+    fn scene_elements<R: FhtRenderer>(
+        &mut self,
+        renderer: &mut R,
+        output: &Output,
+        elements: &mut Vec<FhtRenderElement<R>>,
+    ) {
+        crate::profile_function!();
+        let scale = output.current_scale().integer_scale();
+
+        // The region selection is drawn above everything else, but still below the cursor.
+        if let Some(selection) = self
+            .region_selection
+            .as_ref()
+            .filter(|selection| selection.output() == output)
+        {
+            elements.extend(selection.render(scale).into_iter().map(Into::into));
+        }
+
+        if let Some(strip) = self
+            .workspace_drop_strip
+            .as_ref()
+            .filter(|strip| strip.output() == output)
+        {
+            elements.extend(strip.render(scale).into_iter().map(Into::into));
         }
 
         if !self.config_ui.hidden() {
@@ -168,22 +304,27 @@ impl Fht {
             let config_ui_output = self.config_ui_output.get_or_insert_with(|| output.clone());
             if config_ui_output == output {
                 if let Some(element) = self.config_ui.render(renderer, output, scale) {
-                    rv.elements.push(element.into())
+                    elements.push(element.into())
                 }
             }
         } else {
             let _ = self.config_ui_output.take();
         }
 
+        let osd = &self.output_state.get(output).unwrap().osd;
+        if let Some(element) = osd.render(renderer, output, scale) {
+            elements.push(element.into());
+        }
+
         // Render session lock surface between output and elements
         if self.is_locked() {
-            let elements = self.session_lock_elements(renderer, output);
-            rv.elements.extend(elements.into_iter().map(Into::into));
+            let lock_elements = self.session_lock_elements(renderer, output);
+            elements.extend(lock_elements.into_iter().map(Into::into));
         }
 
         // Overlay layer shells are drawn above everything else, including fullscreen windows
         let overlay_elements = layer_elements(renderer, output, Layer::Overlay, &self.config);
-        rv.elements.extend(overlay_elements);
+        elements.extend(overlay_elements);
 
         // Top layer shells sit between the normal windows and fullscreen windows.
         //
@@ -200,12 +341,10 @@ impl Fht {
         } = monitor.render(renderer, scale);
 
         if !has_fullscreen {
-            rv.elements
-                .extend(layer_elements(renderer, output, Layer::Top, &self.config));
+            elements.extend(layer_elements(renderer, output, Layer::Top, &self.config));
         }
 
-        rv.elements
-            .extend(monitor_elements.into_iter().map(Into::into));
+        elements.extend(monitor_elements.into_iter().map(Into::into));
 
         // Finally we have background and bottom elements.
         let background = layer_elements(renderer, output, Layer::Bottom, &self.config)
@@ -216,7 +355,7 @@ impl Fht {
                 Layer::Background,
                 &self.config,
             ));
-        rv.elements.extend(background);
+        elements.extend(background);
 
         // In case the optimized blur layer is dirty, re-render
         // It only has the bottom and background layer shells drawn onto with blur applied.
@@ -240,8 +379,56 @@ impl Fht {
                 fx_buffers.optimized_blur_dirty = false;
             }
         }
+    }
 
-        rv
+    /// Render the scene of `output` offscreen, then display it with `color_filter` applied and
+    /// only showing `zoom_area`, stretched to fill the output.
+    ///
+    /// When `zoom_area` is set, the cursor gets rendered offscreen too.
+    ///
+    /// Also returns the states of the elements rendered offscreen.
+    fn offscreen_element(
+        &mut self,
+        renderer: &mut GlowRenderer,
+        output: &Output,
+        color_filter: Option<ColorFilter>,
+        zoom_area: Option<Rectangle<f64, Logical>>,
+    ) -> anyhow::Result<(FhtTextureShaderElement, RenderElementStates)> {
+        crate::profile_function!();
+        let scale = output.current_scale().integer_scale();
+        let size = output
+            .current_transform()
+            .transform_size(output.current_mode().context("Output has no mode")?.size);
+
+        let mut elements = vec![];
+        if zoom_area.is_some() {
+            self.cursor_elements(renderer, output, &mut elements);
+        }
+        self.scene_elements(renderer, output, &mut elements);
+
+        let offscreen_buffer = &mut self.output_state.get_mut(output).unwrap().offscreen_buffer;
+        let (buffer, states) =
+            offscreen_buffer.render(renderer, size, scale, &elements, (color_filter, zoom_area))?;
+        let texture = TextureRenderElement::from_texture_render_buffer(
+            (0., 0.),
+            buffer,
+            None,
+            zoom_area,
+            Some(output.geometry().size),
+            Kind::Unspecified,
+        );
+
+        let (matrix, offset) = color_filter.map_or((Mat3::IDENTITY, Vec3::ZERO), color_matrix);
+        let program = Shaders::get(renderer).color_filter.clone();
+        let element = TextureShaderElement::new(
+            texture,
+            program,
+            vec![
+                mat3_uniform("color_matrix", matrix),
+                Uniform::new("color_offset", offset.to_array()),
+            ],
+        );
+        Ok((element.into(), states))
     }
 
     #[cfg(feature = "xdg-screencast-portal")]
@@ -648,10 +835,111 @@ impl AsGlowRenderer for GlowRenderer {
 
 /// Inititalize needed structs and shaders for custom rendering.
 pub fn init(renderer: &mut GlowRenderer) {
+    if renderer
+        .egl_context()
+        .user_data()
+        .get::<shaders::Shaders>()
+        .is_some()
+    {
+        // Already initialized, for example when switching back to a previous primary GPU.
+        return;
+    }
+
     shaders::Shaders::init(renderer);
     data::RendererData::init(renderer.borrow_mut());
 }
 
+/// The offscreen render of the scene of an output, used when it has a color filter or is zoomed.
+///
+/// The texture is kept across frames with its own damage tracking, so that the element displaying
+/// it only gets damaged where the scene did.
+#[derive(Debug, Default)]
+pub struct OffscreenBuffer {
+    /// The texture and the damage tracker rendering into it, along with the ID of the renderer
+    /// that created them, and the size and scale they got created for.
+    inner: Option<(
+        usize,
+        Size<i32, Physical>,
+        i32,
+        TextureRenderBuffer<GlesTexture>,
+        OutputDamageTracker,
+    )>,
+    /// The color filter and zoom area of the last frame.
+    ///
+    /// They don't change the texture contents, but change how it's displayed.
+    last_params: Option<(Option<ColorFilter>, Option<Rectangle<f64, Logical>>)>,
+}
+
+impl OffscreenBuffer {
+    /// Render `elements` inside the texture, and get it along with the states of `elements`.
+    ///
+    /// `params` are the color filter and zoom area the texture will be displayed with, changing
+    /// them damages the whole texture.
+    fn render(
+        &mut self,
+        renderer: &mut GlowRenderer,
+        size: Size<i32, Physical>,
+        scale: i32,
+        elements: &[FhtRenderElement<GlowRenderer>],
+        params: (Option<ColorFilter>, Option<Rectangle<f64, Logical>>),
+    ) -> anyhow::Result<(&TextureRenderBuffer<GlesTexture>, RenderElementStates)> {
+        crate::profile_function!();
+        let renderer_id = renderer.id();
+        let _ = self
+            .inner
+            .take_if(|(id, s, sc, _, _)| *id != renderer_id || *s != size || *sc != scale);
+        let mut full_damage = self.last_params.replace(params) != Some(params);
+
+        if self.inner.is_none() {
+            let texture: GlesTexture = renderer
+                .create_buffer(
+                    Fourcc::Abgr8888,
+                    size.to_logical(1).to_buffer(1, Transform::Normal),
+                )
+                .context("Failed to create offscreen texture")?;
+            let buffer = TextureRenderBuffer::from_texture(
+                renderer,
+                texture,
+                scale,
+                Transform::Normal,
+                None,
+            );
+            let damage_tracker = OutputDamageTracker::new(size, scale as f64, Transform::Normal);
+            self.inner = Some((renderer_id, size, scale, buffer, damage_tracker));
+            full_damage = true;
+        }
+        let (_, _, _, buffer, damage_tracker) = self.inner.as_mut().unwrap();
+
+        let mut states = RenderElementStates::default();
+        buffer.render().draw(|texture| {
+            let mut texture = texture.clone();
+            let mut fb = renderer.bind(&mut texture)?;
+            // We always render in the same texture, so it is always one frame old.
+            let res = damage_tracker
+                .render_output(renderer, &mut fb, 1, elements, Color32F::TRANSPARENT)
+                .map_err(|err| anyhow::anyhow!("Failed to render offscreen: {err:?}"))?;
+
+            let damage = if full_damage {
+                vec![Rectangle::from_size(size)]
+            } else {
+                res.damage.cloned().unwrap_or_default()
+            };
+            states = res.states;
+            anyhow::Ok(
+                damage
+                    .into_iter()
+                    .map(|rect| {
+                        rect.to_logical(1)
+                            .to_buffer(1, Transform::Normal, &size.to_logical(1))
+                    })
+                    .collect(),
+            )
+        })?;
+
+        Ok((buffer, states))
+    }
+}
+
 /// Render the given `elements` inside a [`GlesTexture`].
 ///
 /// It is up to **YOU** to unbind the renderer after rendering.
@@ -746,12 +1034,9 @@ where
     let damage_tracker = damage_tracker
         .get_or_insert_with(|| OutputDamageTracker::new(output_region.size, scale, transform));
 
-    let elements = match screencopy.overlay_cursor() {
-        true => &output_elements_result.elements,
-        false => &output_elements_result.elements[output_elements_result.cursor_elements_len..],
-    };
+    let elements = output_elements_result.capture_elements(screencopy.overlay_cursor());
 
-    let (damage, _) = damage_tracker.damage_output(1, elements)?;
+    let (damage, _) = damage_tracker.damage_output(1, &elements)?;
     if screencopy.with_damage() && damage.is_none() {
         return Ok((None, None));
     }
@@ -912,9 +1197,27 @@ fn build_texture_mat(
     tex_mat
 }
 
+/// Get the alpha multiplier of a surface, set by the client using the alpha-modifier protocol.
+///
+/// Surface render elements already apply it, this is for the effects we render ourselves.
+pub fn surface_alpha_multiplier(surface: &WlSurface) -> f32 {
+    with_states(surface, |data| {
+        data.cached_state
+            .get::<AlphaModifierSurfaceCachedState>()
+            .current()
+            .multiplier_f64()
+            .map_or(1.0, |multiplier| multiplier as f32)
+    })
+}
+
 /// Get whether a surface has any transparent region. This is calculated from opaque regions
 /// provided by the surface aswell as the render format.
 pub fn has_transparent_region(surface: &WlSurface, surface_size: Size<i32, Logical>) -> bool {
+    if surface_alpha_multiplier(surface) < 1.0 {
+        // The client made the whole surface translucent, opaque regions don't hold anymore.
+        return true;
+    }
+
     // Opaque regions are described in surface-local coordinates.
     let surface_geo = Rectangle::from_size(surface_size);
     with_states(&surface, |data| {
@@ -19,6 +19,8 @@ use crate::backend::udev::{UdevFrame, UdevRenderError, UdevRenderer};
 pub struct RoundedWindowElement<R: FhtRenderer> {
     element: WaylandSurfaceRenderElement<R>,
     corner_radius: f32,
+    // How much to darken the surface, see `decorations.dim-inactive`
+    dim: f32,
     input_to_geo: Mat3,
     // where is the rounded rectangle that is going to contain everything.
     geo: Rectangle<i32, Logical>,
@@ -30,6 +32,7 @@ impl<R: FhtRenderer> RoundedWindowElement<R> {
         corner_radius: f32,
         geometry: Rectangle<i32, Logical>,
         scale: impl Into<Scale<f64>>,
+        dim: f32,
     ) -> Self {
         let scale = scale.into();
         // Cool trick for subsurfaces geometry by niri. (I am sometimes too stupid todo stuff)
@@ -62,6 +65,7 @@ impl<R: FhtRenderer> RoundedWindowElement<R> {
         Self {
             element,
             corner_radius,
+            dim,
             geo: geometry,
             input_to_geo,
         }
@@ -217,7 +221,7 @@ impl RenderElement<GlowRenderer> for RoundedWindowElement<GlowRenderer> {
         damage: &[Rectangle<i32, Physical>],
         opaque_regions: &[Rectangle<i32, Physical>],
     ) -> Result<(), GlesError> {
-        if self.corner_radius == 0.0 {
+        if self.corner_radius == 0.0 && self.dim == 0.0 {
             self.element.draw(frame, src, dst, damage, opaque_regions)
         } else {
             // Override texture shader with our uniforms
@@ -231,6 +235,7 @@ impl RenderElement<GlowRenderer> for RoundedWindowElement<GlowRenderer> {
                 vec![
                     Uniform::new("geo_size", (self.geo.size.w as f32, self.geo.size.h as f32)),
                     Uniform::new("corner_radius", self.corner_radius),
+                    Uniform::new("dim", self.dim),
                     super::mat3_uniform("input_to_geo", self.input_to_geo),
                 ],
             );
@@ -259,7 +264,7 @@ impl<'a> RenderElement<UdevRenderer<'a>> for RoundedWindowElement<UdevRenderer<'
         damage: &[Rectangle<i32, Physical>],
         opaque_regions: &[Rectangle<i32, Physical>],
     ) -> Result<(), UdevRenderError> {
-        if self.corner_radius == 0.0 {
+        if self.corner_radius == 0.0 && self.dim == 0.0 {
             self.element.draw(frame, src, dst, damage, opaque_regions)
         } else {
             let glow_frame = frame.as_mut();
@@ -273,6 +278,7 @@ impl<'a> RenderElement<UdevRenderer<'a>> for RoundedWindowElement<UdevRenderer<'
                 vec![
                     Uniform::new("geo_size", (self.geo.size.w as f32, self.geo.size.h as f32)),
                     Uniform::new("corner_radius", self.corner_radius),
+                    Uniform::new("dim", self.dim),
                     super::mat3_uniform("input_to_geo", self.input_to_geo),
                 ],
             );
@@ -12,6 +12,7 @@ const BOX_SHADOW_SRC: &str = include_str!("./box-shadow.frag");
 const ROUNDED_WINDOW_SRC: &str = include_str!("./rounded-window.frag");
 const BLUR_FINISH_SRC: &str = include_str!("./blur-finish.frag");
 const RESIZING_TEXTURE_SRC: &str = include_str!("./resizing-texture.frag");
+const COLOR_FILTER_SRC: &str = include_str!("./color-filter.frag");
 pub(super) const BLUR_DOWN_SRC: &str = include_str!("./blur-down.frag");
 pub(super) const BLUR_UP_SRC: &str = include_str!("./blur-up.frag");
 pub(super) const VERTEX_SRC: &str = include_str!("./texture.vert");
@@ -25,6 +26,8 @@ pub struct Shaders {
     // blur_finish => apply rounded corners and additional effects
     pub blur_finish: GlesTexProgram,
     pub resizing_texture: GlesTexProgram,
+    // color_filter => apply a color matrix to the whole output, see src/renderer/color_filter.rs
+    pub color_filter: GlesTexProgram,
     pub blur: BlurShaders,
 }
 
@@ -39,6 +42,7 @@ impl Shaders {
                     UniformName::new("corner_radius", UniformType::_1f),
                     UniformName::new("geo_size", UniformType::_2f),
                     UniformName::new("input_to_geo", UniformType::Matrix3x3),
+                    UniformName::new("dim", UniformType::_1f),
                 ],
             )
             .expect("Shader source should always compile!");
@@ -61,6 +65,16 @@ impl Shaders {
                     // the size of the window texture we sampled from
                     UniformName::new("win_size", UniformType::_2f),
                     UniformName::new("curr_size", UniformType::_2f),
+                    UniformName::new("dim", UniformType::_1f),
+                ],
+            )
+            .expect("Shader source should always compile!");
+        let color_filter = renderer
+            .compile_custom_texture_shader(
+                COLOR_FILTER_SRC,
+                &[
+                    UniformName::new("color_matrix", UniformType::Matrix3x3),
+                    UniformName::new("color_offset", UniformType::_3f),
                 ],
             )
             .expect("Shader source should always compile!");
@@ -94,6 +108,7 @@ impl Shaders {
             rounded_window,
             blur_finish,
             resizing_texture,
+            color_filter,
             blur,
         };
 
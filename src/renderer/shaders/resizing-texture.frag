@@ -23,6 +23,8 @@ uniform vec2 curr_size;
 varying vec2 v_coords;
 // The corner radius of the tile.
 uniform float corner_radius;
+// How much to darken the window, from 0.0 to 1.0
+uniform float dim;
 
 #if defined(DEBUG_FLAGS)
 uniform float tint;
@@ -69,6 +71,8 @@ void main()
     if (corner_radius > 0.0)
         color *= rounding_alpha(v_coords * curr_size, curr_size, corner_radius);
 
+    color.rgb = color.rgb * (1.0 - dim);
+
 #if defined(NO_ALPHA)
     color = vec4(color.rgb, 1.0);
 #endif
@@ -25,6 +25,8 @@ uniform float tint;
 uniform vec2 geo_size;
 uniform float corner_radius;
 uniform mat3 input_to_geo;
+// How much to darken the window, from 0.0 to 1.0
+uniform float dim;
 
 float rounding_alpha(vec2 coords, vec2 size, float radius) {
     vec2 center;
@@ -54,14 +56,19 @@ void main() {
     color = vec4(color.rgb, 1.0);
 #endif
 
-    if (coords_geo.x < 0.0 || 1.0 < coords_geo.x || coords_geo.y < 0.0 || 1.0 < coords_geo.y) {
-        // Clip outside geometry.
-        color = vec4(0.0);
-    } else {
-        // Apply corner rounding inside geometry.
-        color = color * rounding_alpha(coords_geo.xy * geo_size, geo_size, corner_radius);
+    if (corner_radius > 0.0) {
+        if (coords_geo.x < 0.0 || 1.0 < coords_geo.x || coords_geo.y < 0.0 || 1.0 < coords_geo.y) {
+            // Clip outside geometry.
+            color = vec4(0.0);
+        } else {
+            // Apply corner rounding inside geometry.
+            color = color * rounding_alpha(coords_geo.xy * geo_size, geo_size, corner_radius);
+        }
     }
 
+    // Darken the window, the color is premultiplied so alpha stays untouched.
+    color.rgb = color.rgb * (1.0 - dim);
+
     // Apply final alpha and tint.
     color = color * alpha;
 
@@ -0,0 +1,468 @@
+//! Compositor-side screenshot logic.
+//!
+//! This handles taking screenshots of outputs, and the interactive region selection that the user
+//! can use to pick which part of the output to capture. The region selection is drawn by the
+//! compositor itself on top of everything else, and grabs the pointer and keyboard while active.
+
+use std::path::PathBuf;
+
+use anyhow::Context;
+use smithay::backend::allocator::Fourcc;
+use smithay::backend::renderer::element::solid::SolidColorRenderElement;
+use smithay::backend::renderer::element::utils::RescaleRenderElement;
+use smithay::backend::renderer::element::{Id, Kind, RenderElement};
+use smithay::backend::renderer::utils::CommitCounter;
+use smithay::backend::renderer::{Bind, Color32F, ExportMem};
+use smithay::input::pointer::{CursorIcon, CursorImageStatus, Focus, GrabStartData};
+use smithay::output::Output;
+use smithay::utils::{Logical, Physical, Point, Rectangle, Scale, Size, Transform, SERIAL_COUNTER};
+
+use crate::input::region_select_grab::RegionSelectGrab;
+use crate::output::OutputExt;
+use crate::renderer::{render_to_texture, FhtRenderElement, FhtRenderer};
+use crate::space::WorkspaceRenderElement;
+use crate::state::{Fht, State};
+
+const DIM_COLOR: Color32F = Color32F::new(0.0, 0.0, 0.0, 0.4);
+const BORDER_COLOR: Color32F = Color32F::new(1.0, 1.0, 1.0, 1.0);
+const BORDER_THICKNESS: i32 = 2;
+
+crate::fht_render_elements! {
+    RegionSelectionRenderElement => {
+        Solid = SolidColorRenderElement,
+    }
+}
+
+/// A callback to run when a [`RegionSelection`] finishes.
+///
+/// It receives [`None`] if the user cancelled the selection, otherwise the output and selected
+/// region, relative to the output.
+pub type RegionSelectionCallback =
+    Box<dyn FnOnce(&mut State, Option<(Output, Rectangle<i32, Logical>)>)>;
+
+/// An interactive region selection.
+pub struct RegionSelection {
+    /// The output the selection is happening on.
+    output: Output,
+    /// Whether the user picks a single point instead of a region.
+    ///
+    /// The selected region is then the pixel under the pointer, and a click confirms it.
+    pick_point: bool,
+    /// Where the user started dragging, relative to the output.
+    start: Option<Point<i32, Logical>>,
+    /// The current pointer location, relative to the output.
+    current: Point<i32, Logical>,
+    /// Callback to run when the selection is done.
+    on_done: Option<RegionSelectionCallback>,
+    /// The [`Id`]s of the render elements, to keep damage tracking working properly.
+    ///
+    /// Four for the dimmed areas around the selection, four for the selection border.
+    ids: [Id; 8],
+    /// The commit counter of our render elements.
+    commit: CommitCounter,
+}
+
+impl RegionSelection {
+    /// Get the output the selection is happening on.
+    pub fn output(&self) -> &Output {
+        &self.output
+    }
+
+    /// Whether the user picks a single point instead of a region.
+    pub fn pick_point(&self) -> bool {
+        self.pick_point
+    }
+
+    /// Get the currently selected region, relative to the output.
+    pub fn selection(&self) -> Option<Rectangle<i32, Logical>> {
+        if self.pick_point {
+            let output_size = self.output.geometry().size;
+            let location = Point::from((
+                self.current.x.min(output_size.w - 1),
+                self.current.y.min(output_size.h - 1),
+            ));
+            return Some(Rectangle::new(location, (1, 1).into()));
+        }
+
+        let start = self.start?;
+        let (x1, x2) = (start.x.min(self.current.x), start.x.max(self.current.x));
+        let (y1, y2) = (start.y.min(self.current.y), start.y.max(self.current.y));
+        Some(Rectangle::new((x1, y1).into(), (x2 - x1, y2 - y1).into()))
+    }
+
+    /// Start or update the selected region.
+    ///
+    /// `location` is in global compositor coordinates.
+    pub fn update(&mut self, location: Point<f64, Logical>, start: bool) {
+        let output_geometry = self.output.geometry();
+        let location = location.to_i32_round() - output_geometry.loc;
+        let location = Point::from((
+            location.x.clamp(0, output_geometry.size.w),
+            location.y.clamp(0, output_geometry.size.h),
+        ));
+
+        if start {
+            self.start = Some(location);
+        }
+        self.current = location;
+        self.commit.increment();
+    }
+
+    /// Render the region selection overlay.
+    pub fn render(&self, scale: i32) -> Vec<RegionSelectionRenderElement> {
+        if self.pick_point {
+            // The crosshair cursor is enough, and we don't want to tint the picked color.
+            return vec![];
+        }
+
+        let output_size = self.output.geometry().size;
+        let selection = self
+            .selection()
+            .unwrap_or_else(|| Rectangle::new(self.current, Size::default()));
+
+        let (x, y, w, h) = (
+            selection.loc.x,
+            selection.loc.y,
+            selection.size.w,
+            selection.size.h,
+        );
+        let t = BORDER_THICKNESS;
+        let dim_rects = [
+            Rectangle::new((0, 0).into(), (output_size.w, y).into()),
+            Rectangle::new(
+                (0, y + h).into(),
+                (output_size.w, output_size.h - y - h).into(),
+            ),
+            Rectangle::new((0, y).into(), (x, h).into()),
+            Rectangle::new((x + w, y).into(), (output_size.w - x - w, h).into()),
+        ];
+        let border_rects = [
+            Rectangle::new((x - t, y - t).into(), (w + 2 * t, t).into()),
+            Rectangle::new((x - t, y + h).into(), (w + 2 * t, t).into()),
+            Rectangle::new((x - t, y).into(), (t, h).into()),
+            Rectangle::new((x + w, y).into(), (t, h).into()),
+        ];
+
+        let has_selection = self.start.is_some();
+        dim_rects
+            .into_iter()
+            .map(|rect| (rect, DIM_COLOR))
+            .chain(
+                border_rects
+                    .into_iter()
+                    .filter(|_| has_selection)
+                    .map(|rect| (rect, BORDER_COLOR)),
+            )
+            .zip(&self.ids)
+            .filter(|((rect, _), _)| !rect.is_empty())
+            .map(|((rect, color), id)| {
+                SolidColorRenderElement::new(
+                    id.clone(),
+                    rect.to_physical(scale),
+                    self.commit,
+                    color,
+                    Kind::Unspecified,
+                )
+                .into()
+            })
+            .collect()
+    }
+}
+
+impl State {
+    /// Start an interactive region selection on the active output.
+    ///
+    /// The callback will get called once the user confirms or cancels the selection.
+    pub fn start_region_selection(&mut self, on_done: RegionSelectionCallback) {
+        self.start_selection(false, on_done);
+    }
+
+    /// Start an interactive point selection on the active output.
+    ///
+    /// The callback will get called once the user clicks or cancels the selection, with the
+    /// selected pixel as the region.
+    pub fn start_point_selection(&mut self, on_done: RegionSelectionCallback) {
+        self.start_selection(true, on_done);
+    }
+
+    fn start_selection(&mut self, pick_point: bool, on_done: RegionSelectionCallback) {
+        if self.fht.region_selection.is_some() {
+            // Only one selection can be active at a time.
+            on_done(self, None);
+            return;
+        }
+
+        let output = self.fht.space.active_output().clone();
+        let pointer = self.fht.seat.get_pointer().unwrap();
+        let location = pointer.current_location();
+        let mut selection = RegionSelection {
+            output: output.clone(),
+            pick_point,
+            start: None,
+            current: Point::default(),
+            on_done: Some(on_done),
+            ids: std::array::from_fn(|_| Id::new()),
+            commit: CommitCounter::default(),
+        };
+        selection.update(location, false);
+        self.fht.region_selection = Some(selection);
+
+        let start_data = GrabStartData {
+            focus: None,
+            button: 0,
+            location,
+        };
+        let grab = RegionSelectGrab {
+            start_data,
+            confirm: false,
+        };
+        pointer.set_grab(self, grab, SERIAL_COUNTER.next_serial(), Focus::Clear);
+        self.fht
+            .cursor_theme_manager
+            .set_image_status(CursorImageStatus::Named(CursorIcon::Crosshair));
+        self.fht.queue_redraw(&output);
+    }
+
+    /// Finish the current region selection, if any.
+    ///
+    /// If `confirm` is false, the selection is cancelled.
+    pub fn finish_region_selection(&mut self, confirm: bool) {
+        let Some(mut selection) = self.fht.region_selection.take() else {
+            return;
+        };
+
+        let pointer = self.fht.seat.get_pointer().unwrap();
+        if pointer.is_grabbed() {
+            let time = {
+                let duration: std::time::Duration = self.fht.clock.now().into();
+                duration.as_millis() as u32
+            };
+            pointer.unset_grab(self, SERIAL_COUNTER.next_serial(), time);
+        }
+        self.fht
+            .cursor_theme_manager
+            .set_image_status(CursorImageStatus::default_named());
+        self.fht.queue_redraw(&selection.output);
+
+        let result = selection
+            .selection()
+            .filter(|region| confirm && !region.is_empty())
+            .map(|region| (selection.output.clone(), region));
+        if let Some(on_done) = selection.on_done.take() {
+            on_done(self, result);
+        }
+    }
+}
+
+/// Encode RGBA pixels as a PNG file and save it inside the runtime directory.
+///
+/// `name` is the name of the file, without the extension. Saving a file with the same name again
+/// replaces it, atomically so that whoever is still reading the previous file is not affected.
+pub fn save_png(name: &str, size: Size<i32, Physical>, pixels: &[u8]) -> anyhow::Result<PathBuf> {
+    let base_directories =
+        xdg::BaseDirectories::new().context("Failed to get XDG base directories")?;
+    let path = base_directories
+        .place_runtime_file(format!("fht-compositor/{name}.png"))
+        .context("Failed to create PNG file")?;
+    let tmp_path = path.with_extension("png.tmp");
+    let file = std::fs::File::create(&tmp_path).context("Failed to open PNG file")?;
+
+    let mut encoder =
+        png::Encoder::new(std::io::BufWriter::new(file), size.w as u32, size.h as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder
+        .write_header()
+        .context("Failed to write PNG header")?;
+    writer
+        .write_image_data(pixels)
+        .context("Failed to write PNG data")?;
+    writer.finish().context("Failed to write PNG data")?;
+    std::fs::rename(&tmp_path, &path).context("Failed to save PNG file")?;
+
+    Ok(path)
+}
+
+#[cfg(feature = "xdg-screenshot-portal")]
+impl State {
+    pub fn handle_screenshot_request(&mut self, req: crate::portals::screenshot::Request) {
+        use crate::portals::screenshot::{Request, ScreenshotData};
+
+        let (interactive, sender) = match req {
+            Request::Screenshot {
+                interactive,
+                sender,
+            } => (interactive, sender),
+            Request::PickColor { sender } => return self.pick_color(sender),
+        };
+        let take_screenshot =
+            move |state: &mut State, target: Option<(Output, Option<Rectangle<i32, Logical>>)>| {
+                let data = target.and_then(|(output, region)| {
+                    let State { fht, backend } = state;
+                    match backend
+                        .with_renderer(|renderer| fht.screenshot_output(renderer, &output, region))
+                    {
+                        Ok((size, pixels)) => Some(ScreenshotData { size, pixels }),
+                        Err(err) => {
+                            warn!(?err, "Failed to take screenshot");
+                            None
+                        }
+                    }
+                });
+
+                if let Err(err) = sender.send_blocking(data) {
+                    warn!(?err, "Failed to send screenshot to portal");
+                }
+            };
+
+        if interactive {
+            self.start_region_selection(Box::new(move |state, selection| {
+                let target = selection.map(|(output, region)| (output, Some(region)));
+                take_screenshot(state, target)
+            }));
+        } else {
+            let output = self.fht.space.active_output().clone();
+            take_screenshot(self, Some((output, None)));
+        }
+    }
+
+    /// Let the user pick the color of a pixel, and send it back to the portal.
+    fn pick_color(&mut self, sender: async_channel::Sender<Option<[f64; 3]>>) {
+        self.start_point_selection(Box::new(move |state, selection| {
+            let color = selection.and_then(|(output, region)| {
+                let State { fht, backend } = state;
+                match backend.with_renderer(|renderer| {
+                    fht.screenshot_output(renderer, &output, Some(region))
+                }) {
+                    // The region covers the pixel at every scale, the first one is enough.
+                    Ok((_, pixels)) => {
+                        let [r, g, b, _] = pixels.first_chunk::<4>()?;
+                        Some([r, g, b].map(|channel| f64::from(*channel) / 255.0))
+                    }
+                    Err(err) => {
+                        warn!(?err, "Failed to pick color");
+                        None
+                    }
+                }
+            });
+
+            if let Err(err) = sender.send_blocking(color) {
+                warn!(?err, "Failed to send picked color to portal");
+            }
+        }));
+    }
+}
+
+impl Fht {
+    /// Take a screenshot of this output.
+    ///
+    /// `region` is relative to the output, if [`None`], the whole output is captured. The cursor
+    /// is never included in the screenshot.
+    ///
+    /// Returns the size of the screenshot and its pixels, in RGBA order.
+    pub fn screenshot_output<R: FhtRenderer>(
+        &mut self,
+        renderer: &mut R,
+        output: &Output,
+        region: Option<Rectangle<i32, Logical>>,
+    ) -> anyhow::Result<(Size<i32, Physical>, Vec<u8>)>
+    where
+        FhtRenderElement<R>: RenderElement<R>,
+    {
+        crate::profile_function!();
+        // See note in Fht::output_elements about fractional scale
+        let scale = output.current_scale().integer_scale();
+        // The region is in the output space, with the output transform applied. Render the output
+        // upright in that same space, so that the region can be copied as is.
+        let output_size = output
+            .current_transform()
+            .transform_size(output.current_mode().unwrap().size);
+        let region = region
+            .map(|region| region.to_physical(scale))
+            .unwrap_or_else(|| Rectangle::from_size(output_size));
+
+        let elements = self.output_elements(renderer, output);
+        let elements = elements.capture_elements(false);
+        let elements = elements.iter().rev();
+
+        let (mut texture, _) = render_to_texture(
+            renderer,
+            output_size,
+            Scale::from(scale as f64),
+            Transform::Normal,
+            Fourcc::Abgr8888,
+            elements,
+        )?;
+
+        let mut fb = renderer.bind(&mut texture)?;
+        let mapping = renderer.copy_framebuffer(
+            &mut fb,
+            region
+                .to_logical(1)
+                .to_buffer(1, Transform::Normal, &region.size.to_logical(1)),
+            Fourcc::Abgr8888,
+        )?;
+        let pixels = renderer.map_texture(&mapping)?.to_vec();
+
+        Ok((region.size, pixels))
+    }
+
+    /// Render a downscaled preview of a workspace, offscreen.
+    ///
+    /// The preview keeps the aspect ratio of the output, and its largest side will be at most
+    /// `max_size` pixels. The cursor is never included in the preview.
+    ///
+    /// Returns the size of the preview and its pixels, in RGBA order.
+    pub fn render_workspace_preview<R: FhtRenderer>(
+        &self,
+        renderer: &mut R,
+        output: &Output,
+        workspace_idx: usize,
+        max_size: u32,
+    ) -> anyhow::Result<(Size<i32, Physical>, Vec<u8>)>
+    where
+        WorkspaceRenderElement<R>: RenderElement<R>,
+    {
+        crate::profile_function!();
+        let monitor = self
+            .space
+            .monitor_for_output(output)
+            .context("Output has no monitor")?;
+        let workspace = monitor
+            .workspaces()
+            .nth(workspace_idx)
+            .context("Invalid workspace index")?;
+
+        // See note in Fht::output_elements about fractional scale
+        let scale = output.current_scale().integer_scale();
+        let output_size = output.geometry().size.to_physical(scale);
+        let preview_scale = (max_size as f64 / output_size.w.max(output_size.h) as f64).min(1.0);
+        let preview_size = output_size.to_f64().upscale(preview_scale).to_i32_round();
+        anyhow::ensure!(!preview_size.is_empty(), "Preview size is empty");
+
+        // The workspace already renders to the origin (0, 0), we only have to scale it down.
+        let elements = workspace.render(renderer, scale, Some(Point::default()));
+        let elements = elements.into_iter().rev().map(|element| {
+            RescaleRenderElement::from_element(element, Point::default(), preview_scale)
+        });
+
+        let (mut texture, _) = render_to_texture(
+            renderer,
+            preview_size,
+            Scale::from(scale as f64),
+            Transform::Normal,
+            Fourcc::Abgr8888,
+            elements,
+        )?;
+
+        let mut fb = renderer.bind(&mut texture)?;
+        let mapping = renderer.copy_framebuffer(
+            &mut fb,
+            Rectangle::from_size(preview_size.to_logical(1).to_buffer(1, Transform::Normal)),
+            Fourcc::Abgr8888,
+        )?;
+        let pixels = renderer.map_texture(&mapping)?.to_vec();
+
+        Ok((preview_size, pixels))
+    }
+}
@@ -0,0 +1,147 @@
+//! Window placement persistence across compositor instances.
+//!
+//! Wayland clients can't survive their compositor going away, but a lot of them reconnect (or get
+//! restarted by the user session) when a new compositor comes up. We periodically save where each
+//! window is, so that after a crash or a restart, windows reconnecting to the new instance get
+//! mapped back on their previous output and workspace.
+//!
+//! The placement file lives in the runtime directory, so it does not survive the user session. It
+//! is named after the Wayland socket, so that compositor instances running side by side (for
+//! example nested ones) don't restore each other's windows.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use smithay::reexports::calloop::timer::{TimeoutAction, Timer};
+use smithay::reexports::calloop::LoopHandle;
+
+use crate::state::{Fht, State};
+use crate::window::Window;
+
+/// How often we save window placements.
+const SAVE_INTERVAL: Duration = Duration::from_secs(5);
+/// For how long after startup the saved window placements get restored.
+const RESTORE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The saved placement of a window.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedWindow {
+    pub app_id: String,
+    pub title: Option<String>,
+    pub output: String,
+    pub workspace: usize,
+}
+
+pub struct Session {
+    // Placements saved by the previous instance, waiting for their windows to come back.
+    restorable: Vec<SavedWindow>,
+    // When we started, after RESTORE_TIMEOUT we stop restoring placements.
+    start_time: Duration,
+    // What we last wrote to the placement file.
+    last_saved: Vec<SavedWindow>,
+    // The name of the Wayland socket we listen on, the placement file is named after it.
+    socket_name: String,
+}
+
+fn session_path(socket_name: &str) -> anyhow::Result<PathBuf> {
+    let base_directories =
+        xdg::BaseDirectories::new().context("Failed to get XDG base directories")?;
+    base_directories
+        .place_runtime_file(format!("fht-compositor/session-{socket_name}.json"))
+        .context("Failed to create session file")
+}
+
+impl Session {
+    /// Load the window placements saved by a previous compositor instance, and start saving ours.
+    pub fn new(loop_handle: &LoopHandle<'static, State>, socket_name: &str) -> Self {
+        let restorable = match session_path(socket_name).and_then(|path| {
+            let contents = std::fs::read_to_string(path).context("Failed to read session file")?;
+            serde_json::from_str(&contents).context("Failed to parse session file")
+        }) {
+            Ok(restorable) => restorable,
+            Err(err) => {
+                debug!(?err, "No window placements to restore");
+                vec![]
+            }
+        };
+
+        let timer = Timer::from_duration(SAVE_INTERVAL);
+        if let Err(err) = loop_handle.insert_source(timer, |_, _, state| {
+            state.fht.save_session();
+            TimeoutAction::ToDuration(SAVE_INTERVAL)
+        }) {
+            warn!(?err, "Failed to insert session save timer");
+        }
+
+        Self {
+            restorable,
+            start_time: crate::utils::get_monotonic_time(),
+            last_saved: vec![],
+            socket_name: socket_name.to_string(),
+        }
+    }
+
+    /// Take the saved placement of this [`Window`], if any.
+    ///
+    /// Windows with the same app-id and title are preferred, falling back to the same app-id.
+    pub fn take_placement(&mut self, window: &Window) -> Option<SavedWindow> {
+        if crate::utils::get_monotonic_time() - self.start_time > RESTORE_TIMEOUT {
+            self.restorable.clear();
+            return None;
+        }
+
+        let app_id = window.app_id()?;
+        let title = window.title();
+        let idx = self
+            .restorable
+            .iter()
+            .position(|saved| saved.app_id == app_id && saved.title == title)
+            .or_else(|| {
+                self.restorable
+                    .iter()
+                    .position(|saved| saved.app_id == app_id)
+            })?;
+        Some(self.restorable.remove(idx))
+    }
+}
+
+impl Fht {
+    /// Save the current window placements, if they changed.
+    pub fn save_session(&mut self) {
+        crate::profile_function!();
+        let saved: Vec<SavedWindow> = self
+            .space
+            .monitors()
+            .flat_map(|monitor| {
+                let output = monitor.output().name();
+                monitor.workspaces().flat_map(move |workspace| {
+                    let output = output.clone();
+                    workspace.windows().filter_map(move |window| {
+                        Some(SavedWindow {
+                            app_id: window.app_id()?,
+                            title: window.title(),
+                            output: output.clone(),
+                            workspace: workspace.index(),
+                        })
+                    })
+                })
+            })
+            .collect();
+
+        if saved == self.session.last_saved {
+            return;
+        }
+
+        let res = session_path(&self.session.socket_name).and_then(|path| {
+            let contents = serde_json::to_string(&saved).context("Failed to serialize session")?;
+            std::fs::write(path, contents).context("Failed to write session file")
+        });
+        if let Err(err) = res {
+            warn!(?err, "Failed to save window placements");
+        }
+
+        self.session.last_saved = saved;
+    }
+}
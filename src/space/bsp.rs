@@ -0,0 +1,315 @@
+//! Manual binary space partitioning, used by the `bsp` layout.
+//!
+//! Each [`Workspace`](super::Workspace) keeps a [`BspTree`] of the containers of its tiled
+//! windows. Every node of the tree is either a single container, or an area split in two along a
+//! [`SplitDirection`].
+//!
+//! New windows split the node of the focused container. The direction of the split can be chosen
+//! beforehand with the `set-split-direction` key action, otherwise the longest side of the
+//! focused container gets split.
+//!
+//! The `change-mwfact` key action resizes the focused container inside the split containing it,
+//! and `equalize-splits` gives back the same size to both sides of the splits.
+
+use fht_compositor_config::SplitDirection;
+use smithay::utils::{Logical, Rectangle};
+
+use super::container::ContainerId;
+
+#[derive(Debug, Clone)]
+enum Node {
+    Leaf(ContainerId),
+    Split {
+        direction: SplitDirection,
+        /// How much of the area the first child takes.
+        ratio: f64,
+        first: Box<Node>,
+        second: Box<Node>,
+    },
+}
+
+impl Node {
+    fn contains(&self, container: ContainerId) -> bool {
+        match self {
+            Self::Leaf(leaf) => *leaf == container,
+            Self::Split { first, second, .. } => {
+                first.contains(container) || second.contains(container)
+            }
+        }
+    }
+
+    fn last_leaf(&self) -> ContainerId {
+        match self {
+            Self::Leaf(leaf) => *leaf,
+            Self::Split { second, .. } => second.last_leaf(),
+        }
+    }
+
+    /// Replace the leaf of `target` with a split between it and `container`.
+    fn split_leaf(
+        &mut self,
+        target: ContainerId,
+        container: ContainerId,
+        direction: SplitDirection,
+    ) -> bool {
+        match self {
+            Self::Leaf(leaf) if *leaf == target => {
+                *self = Self::Split {
+                    direction,
+                    ratio: 0.5,
+                    first: Box::new(Self::Leaf(*leaf)),
+                    second: Box::new(Self::Leaf(container)),
+                };
+                true
+            }
+            Self::Leaf(_) => false,
+            Self::Split { first, second, .. } => {
+                first.split_leaf(target, container, direction)
+                    || second.split_leaf(target, container, direction)
+            }
+        }
+    }
+
+    /// Remove the leaves whose container does not match `f`, collapsing the splits left with a
+    /// single child.
+    fn retain(self, f: &impl Fn(ContainerId) -> bool) -> Option<Self> {
+        match self {
+            Self::Leaf(leaf) => f(leaf).then_some(Self::Leaf(leaf)),
+            Self::Split {
+                direction,
+                ratio,
+                first,
+                second,
+            } => match ((*first).retain(f), (*second).retain(f)) {
+                (Some(first), Some(second)) => Some(Self::Split {
+                    direction,
+                    ratio,
+                    first: Box::new(first),
+                    second: Box::new(second),
+                }),
+                (Some(node), None) | (None, Some(node)) => Some(node),
+                (None, None) => None,
+            },
+        }
+    }
+
+    fn geometries(
+        &self,
+        area: Rectangle<i32, Logical>,
+        inner_gaps: i32,
+        geometries: &mut Vec<(ContainerId, Rectangle<i32, Logical>)>,
+    ) {
+        match self {
+            Self::Leaf(leaf) => geometries.push((*leaf, area)),
+            Self::Split {
+                direction,
+                ratio,
+                first,
+                second,
+            } => {
+                let (first_area, second_area) = split_area(area, *direction, *ratio, inner_gaps);
+                first.geometries(first_area, inner_gaps, geometries);
+                second.geometries(second_area, inner_gaps, geometries);
+            }
+        }
+    }
+
+    /// Get the path to the leaf of `container`, `false` meaning the first child of a split.
+    fn path_to(&self, container: ContainerId) -> Option<Vec<bool>> {
+        match self {
+            Self::Leaf(leaf) => (*leaf == container).then(Vec::new),
+            Self::Split { first, second, .. } => {
+                let (is_second, mut path) = first
+                    .path_to(container)
+                    .map(|path| (false, path))
+                    .or_else(|| second.path_to(container).map(|path| (true, path)))?;
+                path.insert(0, is_second);
+                Some(path)
+            }
+        }
+    }
+
+    fn node_at_mut(&mut self, path: &[bool]) -> &mut Self {
+        match (self, path.split_first()) {
+            (Self::Split { first, second, .. }, Some((&is_second, path))) => {
+                if is_second {
+                    second.node_at_mut(path)
+                } else {
+                    first.node_at_mut(path)
+                }
+            }
+            (node, _) => node,
+        }
+    }
+
+    fn for_each_split(&mut self, f: &mut impl FnMut(&mut SplitDirection, &mut f64)) {
+        if let Self::Split {
+            direction,
+            ratio,
+            first,
+            second,
+        } = self
+        {
+            f(direction, ratio);
+            first.for_each_split(f);
+            second.for_each_split(f);
+        }
+    }
+}
+
+/// A binary space partition of the containers of the tiled windows of a
+/// [`Workspace`](super::Workspace).
+#[derive(Debug, Clone, Default)]
+pub struct BspTree {
+    root: Option<Node>,
+    /// The direction of the next split, if chosen by the user.
+    next_split: Option<SplitDirection>,
+}
+
+impl BspTree {
+    /// Set the direction of the split made by the next inserted container.
+    pub fn set_next_split(&mut self, direction: SplitDirection) {
+        self.next_split = Some(direction);
+    }
+
+    /// Insert `container`, splitting the node of `target`, or the last node if `target` is not in
+    /// the tree.
+    pub fn insert(
+        &mut self,
+        container: ContainerId,
+        target: Option<ContainerId>,
+        area: Rectangle<i32, Logical>,
+        inner_gaps: i32,
+    ) {
+        let Some(root) = &self.root else {
+            self.root = Some(Node::Leaf(container));
+            return;
+        };
+        if root.contains(container) {
+            return;
+        }
+
+        let target = target
+            .filter(|target| root.contains(*target))
+            .unwrap_or_else(|| root.last_leaf());
+        let direction = self.next_split.take().unwrap_or_else(|| {
+            // Split the longest side of the target.
+            let geometry = self
+                .geometries(area, inner_gaps)
+                .into_iter()
+                .find_map(|(leaf, geometry)| (leaf == target).then_some(geometry))
+                .unwrap_or(area);
+            if geometry.size.w >= geometry.size.h {
+                SplitDirection::Horizontal
+            } else {
+                SplitDirection::Vertical
+            }
+        });
+
+        self.root
+            .as_mut()
+            .unwrap()
+            .split_leaf(target, container, direction);
+    }
+
+    /// Make the tree contain exactly `containers`, inserting the missing ones by splitting the node
+    /// of `target`.
+    pub fn sync(
+        &mut self,
+        containers: &[ContainerId],
+        target: Option<ContainerId>,
+        area: Rectangle<i32, Logical>,
+        inner_gaps: i32,
+    ) {
+        self.root = self
+            .root
+            .take()
+            .and_then(|root| root.retain(&|container| containers.contains(&container)));
+        for &container in containers {
+            self.insert(container, target, area, inner_gaps);
+        }
+    }
+
+    /// Get the geometry of each container inside `area`.
+    pub fn geometries(
+        &self,
+        area: Rectangle<i32, Logical>,
+        inner_gaps: i32,
+    ) -> Vec<(ContainerId, Rectangle<i32, Logical>)> {
+        let mut geometries = vec![];
+        if let Some(root) = &self.root {
+            root.geometries(area, inner_gaps, &mut geometries);
+        }
+        geometries
+    }
+
+    /// Rotate the subtree containing `container` by 90 degrees, flipping the direction of all its
+    /// splits.
+    pub fn rotate(&mut self, container: ContainerId) {
+        if let Some(subtree) = self.parent_mut(container) {
+            subtree.for_each_split(&mut |direction, _| {
+                *direction = match direction {
+                    SplitDirection::Horizontal => SplitDirection::Vertical,
+                    SplitDirection::Vertical => SplitDirection::Horizontal,
+                }
+            });
+        }
+    }
+
+    /// Grow the node of `container` by `delta` inside the split containing it.
+    pub fn resize(&mut self, container: ContainerId, delta: f64) {
+        let Some(root) = self.root.as_mut() else {
+            return;
+        };
+        let Some(mut path) = root.path_to(container) else {
+            return;
+        };
+        let Some(is_second) = path.pop() else {
+            // The container is alone in the tree.
+            return;
+        };
+
+        if let Node::Split { ratio, .. } = root.node_at_mut(&path) {
+            // The ratio is how much the first child takes.
+            let delta = if is_second { -delta } else { delta };
+            *ratio = (*ratio + delta).clamp(0.01, 0.99);
+        }
+    }
+
+    /// Give the same size to both sides of every split in the subtree containing `container`.
+    pub fn equalize(&mut self, container: ContainerId) {
+        if let Some(subtree) = self.parent_mut(container) {
+            subtree.for_each_split(&mut |_, ratio| *ratio = 0.5);
+        }
+    }
+
+    /// Get the split containing the node of `container`.
+    fn parent_mut(&mut self, container: ContainerId) -> Option<&mut Node> {
+        let root = self.root.as_mut()?;
+        let mut path = root.path_to(container)?;
+        path.pop()?;
+        Some(root.node_at_mut(&path))
+    }
+}
+
+fn split_area(
+    area: Rectangle<i32, Logical>,
+    direction: SplitDirection,
+    ratio: f64,
+    inner_gaps: i32,
+) -> (Rectangle<i32, Logical>, Rectangle<i32, Logical>) {
+    let (mut first, mut second) = (area, area);
+    match direction {
+        SplitDirection::Horizontal => {
+            first.size.w = (f64::from(area.size.w - inner_gaps) * ratio).round() as i32;
+            second.loc.x += first.size.w + inner_gaps;
+            second.size.w -= first.size.w + inner_gaps;
+        }
+        SplitDirection::Vertical => {
+            first.size.h = (f64::from(area.size.h - inner_gaps) * ratio).round() as i32;
+            second.loc.y += first.size.h + inner_gaps;
+            second.size.h -= first.size.h + inner_gaps;
+        }
+    }
+    (first, second)
+}
@@ -0,0 +1,236 @@
+//! Containers of tiled windows.
+//!
+//! Every tiled window of a [`Workspace`](super::Workspace) lives in a [`Container`], that takes a
+//! single slot of the layout. The layouts arrange containers, and the BSP tree and the columns
+//! refer to them by their [`ContainerId`], so a container keeps its slot regardless of the windows
+//! it holds.
+//!
+//! A container usually holds a single window. Windows grouped in the same container are displayed
+//! as tabs: only the active window of the container is displayed, below the titles of all its
+//! windows. The focused window is always the displayed tab of its container.
+//!
+//! A tabbed container shows the titles side by side in a tab bar, while a stacked container shows
+//! them as a vertical list of title bars, like i3 does.
+
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use smithay::output::Output;
+use smithay::utils::{Logical, Rectangle, Size};
+
+use crate::egui::{EguiElement, EguiRenderElement};
+use crate::output::OutputExt;
+use crate::renderer::FhtRenderer;
+use crate::window::Window;
+
+/// The height of a single row of titles displayed above the windows of a [`Container`].
+const TITLE_BAR_HEIGHT: i32 = 32;
+
+static CONTAINER_IDS: AtomicUsize = AtomicUsize::new(0);
+
+/// Identifier of a [`Container`].
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct ContainerId(usize);
+impl ContainerId {
+    /// Create a unique [`ContainerId`].
+    pub fn unique() -> Self {
+        Self(CONTAINER_IDS.fetch_add(1, Ordering::SeqCst))
+    }
+}
+
+#[derive(Debug)]
+pub struct Container {
+    id: ContainerId,
+    /// The windows of this [`Container`], in the order of their tabs.
+    windows: Vec<Window>,
+    /// The index of the displayed window.
+    active_idx: usize,
+    /// Whether the titles are displayed as a vertical list instead of tabs.
+    stacked: bool,
+    /// The title bars, along with the size they were created for.
+    ///
+    /// Lazily created when rendering, and recreated when the size changes.
+    title_bars: RefCell<Option<(Size<i32, Logical>, EguiElement)>>,
+}
+
+impl Container {
+    /// Create a new [`Container`] holding a single window.
+    pub fn new(window: Window) -> Self {
+        Self {
+            id: ContainerId::unique(),
+            windows: vec![window],
+            active_idx: 0,
+            stacked: false,
+            title_bars: RefCell::new(None),
+        }
+    }
+
+    /// Get the [`ContainerId`] of this [`Container`].
+    pub fn id(&self) -> ContainerId {
+        self.id
+    }
+
+    /// Get the windows of this [`Container`], in the order of their tabs.
+    pub fn windows(&self) -> &[Window] {
+        &self.windows
+    }
+
+    /// Whether this [`Container`] contains `window`.
+    pub fn contains(&self, window: &Window) -> bool {
+        self.windows.contains(window)
+    }
+
+    /// Whether this [`Container`] holds more than one window, displayed as tabs.
+    pub fn is_tabbed(&self) -> bool {
+        self.windows.len() > 1
+    }
+
+    /// Get the displayed window of this [`Container`].
+    pub fn active_window(&self) -> Option<&Window> {
+        self.windows.get(self.active_idx)
+    }
+
+    /// Display `window`, if it's in this [`Container`].
+    ///
+    /// Returns whether the displayed window changed.
+    pub fn activate(&mut self, window: &Window) -> bool {
+        match self.windows.iter().position(|w| w == window) {
+            Some(idx) if idx != self.active_idx => {
+                self.active_idx = idx;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Add `window` as the last tab of this [`Container`].
+    pub fn push(&mut self, window: Window) {
+        if !self.contains(&window) {
+            self.windows.push(window);
+        }
+    }
+
+    /// Only keep the windows matching `f`.
+    ///
+    /// If the displayed window gets removed, the tab that took its place gets displayed.
+    pub fn retain(&mut self, f: impl FnMut(&Window) -> bool) {
+        let active_window = self.active_window().cloned();
+        self.windows.retain(f);
+        self.active_idx = active_window
+            .and_then(|active| self.windows.iter().position(|w| *w == active))
+            .unwrap_or_else(|| self.active_idx.min(self.windows.len().saturating_sub(1)));
+    }
+
+    /// Swap the tabs of these two windows, or put one in place of the other if only one of them
+    /// is in this [`Container`].
+    pub fn swap(&mut self, a: &Window, b: &Window) {
+        for window in &mut self.windows {
+            if window == a {
+                *window = b.clone();
+            } else if window == b {
+                *window = a.clone();
+            }
+        }
+    }
+
+    /// Switch this [`Container`] between tabbed and stacked.
+    pub fn toggle_stacked(&mut self) {
+        self.stacked = !self.stacked;
+    }
+
+    /// Get the height of the title bars displayed above the windows of this [`Container`].
+    ///
+    /// A [`Container`] holding a single window has no title bars.
+    pub fn title_bar_height(&self) -> i32 {
+        if !self.is_tabbed() {
+            0
+        } else if self.stacked {
+            TITLE_BAR_HEIGHT * self.windows.len() as i32
+        } else {
+            TITLE_BAR_HEIGHT
+        }
+    }
+
+    /// Get the tab that comes after (or before, if `forward` is false) `window`, cycling around.
+    pub fn cycle_from(&self, window: &Window, forward: bool) -> Option<&Window> {
+        let idx = self.windows.iter().position(|w| w == window)?;
+        let len = self.windows.len();
+        let idx = if forward {
+            (idx + 1) % len
+        } else {
+            (idx + len - 1) % len
+        };
+        self.windows.get(idx)
+    }
+
+    /// Render the title bars of this [`Container`] inside `geometry`, relative to the output.
+    pub fn render_title_bars<R: FhtRenderer>(
+        &self,
+        renderer: &mut R,
+        output: &Output,
+        scale: i32,
+        alpha: f32,
+        geometry: Rectangle<i32, Logical>,
+    ) -> Option<EguiRenderElement> {
+        crate::profile_function!();
+        let mut title_bars = self.title_bars.borrow_mut();
+        let _ = title_bars.take_if(|(size, _)| *size != geometry.size);
+        let (_, title_bars) =
+            title_bars.get_or_insert_with(|| (geometry.size, EguiElement::new(geometry.size)));
+
+        let titles: Vec<_> = self
+            .windows
+            .iter()
+            .enumerate()
+            .map(|(idx, window)| (window.title().unwrap_or_default(), idx == self.active_idx))
+            .collect();
+        let (stacked, width) = (self.stacked, geometry.size.w);
+        title_bars
+            .render(
+                renderer.glow_renderer_mut(),
+                scale,
+                alpha,
+                geometry.loc.to_physical(scale),
+                output.refresh_interval(),
+                |ctx| title_bars_ui(ctx, &titles, stacked, width),
+            )
+            .inspect_err(|err| warn!(?err, "Failed to render title bars"))
+            .ok()
+    }
+}
+
+fn title_bars_ui(ctx: &egui::Context, titles: &[(String, bool)], stacked: bool, width: i32) {
+    crate::profile_function!();
+    let title = |ui: &mut egui::Ui, title: &str, active: bool| {
+        let text = egui::RichText::new(title);
+        let text = if active { text.strong() } else { text.weak() };
+        ui.add(egui::Label::new(text).truncate());
+    };
+
+    egui::Area::new(egui::Id::NULL).show(ctx, |ui| {
+        // Account for the frame margin and stroke.
+        let width = width as f32 - 18.0;
+        let frame = egui::Frame::window(ui.style())
+            .inner_margin(egui::Margin::symmetric(8, 4))
+            .shadow(egui::Shadow::NONE);
+        if stacked {
+            ui.spacing_mut().item_spacing.y = 0.0;
+            for (text, active) in titles {
+                frame.show(ui, |ui| {
+                    ui.set_width(width);
+                    ui.set_height(TITLE_BAR_HEIGHT as f32 - 10.0);
+                    title(ui, text, *active);
+                });
+            }
+        } else {
+            frame.show(ui, |ui| {
+                ui.set_width(width);
+                ui.columns(titles.len(), |columns| {
+                    for (column, (text, active)) in columns.iter_mut().zip(titles) {
+                        column.vertical_centered(|ui| title(ui, text, *active));
+                    }
+                });
+            });
+        }
+    });
+}
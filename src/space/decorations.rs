@@ -2,14 +2,77 @@
 //!
 //! This is achieved using a GlesPixelShader, nothing special otherwise.
 
+use std::time::Duration;
+
+use fht_animation::Animation;
+use fht_compositor_config::Color;
 use smithay::backend::renderer::element::Kind;
 use smithay::backend::renderer::gles::Uniform;
 use smithay::utils::{Logical, Point, Rectangle, Size};
 
+use super::AnimationConfig;
 use crate::renderer::pixel_shader_element::FhtPixelShaderElement;
 use crate::renderer::shaders::Shaders;
 use crate::renderer::AsGlowRenderer;
 
+/// The animation of a border color, from the color the border had when it started to the color the
+/// border should have now.
+#[derive(Debug)]
+pub struct AnimatedBorderColor {
+    from: Color,
+    animation: Animation<f64>,
+}
+
+impl AnimatedBorderColor {
+    pub fn new(from: Color, config: &AnimationConfig) -> Self {
+        Self {
+            from,
+            animation: Animation::new(0.0, 1.0, config.duration).with_curve(config.curve),
+        }
+    }
+
+    pub fn tick(&mut self, target_presentation_time: Duration) {
+        self.animation.tick(target_presentation_time);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.animation.is_finished()
+    }
+
+    /// Get the current border color, going to `to`.
+    pub fn color(&self, to: Color) -> Color {
+        let progress = self.animation.value().clamp(0.0, 1.0) as f32;
+        let lerp =
+            |a: [f32; 4], b: [f32; 4]| std::array::from_fn(|i| a[i] + (b[i] - a[i]) * progress);
+        match (self.from, to) {
+            (Color::Solid(from), Color::Solid(to)) => Color::Solid(lerp(from, to)),
+            (from, to) => {
+                let (from_start, from_end, from_angle) = gradient_components(from);
+                let (to_start, to_end, to_angle) = gradient_components(to);
+                // Solid colors have no angle, keep the one of the gradient.
+                let (from_angle, to_angle) = match (from, to) {
+                    (Color::Solid(_), _) => (to_angle, to_angle),
+                    (_, Color::Solid(_)) => (from_angle, from_angle),
+                    _ => (from_angle, to_angle),
+                };
+                Color::Gradient {
+                    start: lerp(from_start, to_start),
+                    end: lerp(from_end, to_end),
+                    angle: from_angle + (to_angle - from_angle) * progress,
+                }
+            }
+        }
+    }
+}
+
+/// Get the start color, end color and angle of the gradient drawn for `color`.
+fn gradient_components(color: Color) -> ([f32; 4], [f32; 4], f32) {
+    match color {
+        Color::Solid(color) => (color, color, 0.0),
+        Color::Gradient { start, end, angle } => (start, end, angle),
+    }
+}
+
 pub fn draw_border(
     renderer: &mut impl AsGlowRenderer,
     scale: i32,
@@ -17,13 +80,10 @@ pub fn draw_border(
     geometry: Rectangle<i32, Logical>,
     thickness: f64,
     radius: f64,
-    color: fht_compositor_config::Color,
+    color: Color,
 ) -> FhtPixelShaderElement {
     let scaled_thickness = thickness * scale as f64;
-    let (start_color, end_color, angle) = match color {
-        fht_compositor_config::Color::Solid(color) => (color, color, 0.0),
-        fht_compositor_config::Color::Gradient { start, end, angle } => (start, end, angle),
-    };
+    let (start_color, end_color, angle) = gradient_components(color);
 
     // Only draw whats needed for the border.
     //
@@ -71,11 +131,13 @@ pub fn draw_shadow(
     scale: i32,
     mut geometry: Rectangle<i32, Logical>,
     blur_sigma: f32,
+    offset: Point<i32, Logical>,
     corner_radius: f32,
     color: [f32; 4],
 ) -> FhtPixelShaderElement {
     let scaled_blur_sigma = (blur_sigma / scale as f32).round() as i32;
     geometry.loc -= Point::from((scaled_blur_sigma, scaled_blur_sigma));
+    geometry.loc += offset;
     geometry.size += Size::from((2 * scaled_blur_sigma, 2 * scaled_blur_sigma));
 
     // We only draw the shadow around the window, its pointless to damage everything, only causing
@@ -90,8 +152,9 @@ pub fn draw_shadow(
     //
     // We generate the damage for the regions marked by X, relative to (0,0)
     // FIXME: const?
+    // The window itself is moved by the opposite of the shadow offset.
     let shadow_damage = Rectangle::from_size(geometry.size).subtract_rect(Rectangle::new(
-        Point::from((scaled_blur_sigma, scaled_blur_sigma)),
+        Point::from((scaled_blur_sigma, scaled_blur_sigma)) - offset,
         geometry.size - Size::from((scaled_blur_sigma, scaled_blur_sigma)).upscale(2),
     ));
 
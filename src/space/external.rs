@@ -0,0 +1,115 @@
+//! External layouts, used by the `external` layout.
+//!
+//! The geometries of the tiled windows of a [`Workspace`](super::Workspace) get computed by
+//! another process, talking to the compositor over IPC. Each time the tiled windows or the work
+//! area change, the workspace makes a new layout demand with an increasing serial, that gets sent
+//! to the IPC event streams. The layout client answers with the geometries of the windows, that are
+//! relative to the work area.
+//!
+//! Until the first answer comes in, or when the committed geometries don't match the number of
+//! windows anymore, the workspace falls back to the `tile` layout.
+
+use smithay::utils::{Logical, Rectangle, Size};
+
+use crate::window::Window;
+
+#[derive(Debug, Clone, Default)]
+pub struct ExternalLayout {
+    /// The serial of the last layout demand.
+    serial: u32,
+    /// The windows and work area size of the last layout demand.
+    demand: Option<(Vec<Window>, Size<i32, Logical>)>,
+    /// Whether the last layout demand still has to be sent to the layout clients.
+    pending: bool,
+    /// The geometries committed by the layout client, relative to the work area.
+    geometries: Vec<Rectangle<i32, Logical>>,
+}
+
+/// A layout demand, for the layout client to compute the geometries of `windows`.
+#[derive(Debug, Clone)]
+pub struct LayoutDemand {
+    pub serial: u32,
+    pub windows: Vec<Window>,
+    pub size: Size<i32, Logical>,
+}
+
+impl ExternalLayout {
+    /// Get the geometry of each window inside `work_area`, if the layout client committed them.
+    ///
+    /// This makes a new layout demand if `windows` or the work area size changed.
+    pub fn geometries(
+        &mut self,
+        windows: &[Window],
+        work_area: Rectangle<i32, Logical>,
+    ) -> Option<Vec<(Window, Rectangle<i32, Logical>)>> {
+        let unchanged = self
+            .demand
+            .as_ref()
+            .is_some_and(|(demand, size)| demand == windows && *size == work_area.size);
+        if !unchanged {
+            self.serial = self.serial.wrapping_add(1);
+            self.demand = Some((windows.to_vec(), work_area.size));
+            self.pending = true;
+        }
+
+        // Keep using the previous geometries while the layout client computes new ones, as long as
+        // they still fit the windows.
+        if self.geometries.len() != windows.len() {
+            return None;
+        }
+
+        let geometries = windows
+            .iter()
+            .zip(&self.geometries)
+            .map(|(window, geometry)| {
+                let mut geometry = *geometry;
+                geometry.loc += work_area.loc;
+                (window.clone(), geometry)
+            })
+            .collect();
+        Some(geometries)
+    }
+
+    /// Get the last layout demand.
+    pub fn demand(&self) -> Option<LayoutDemand> {
+        let (windows, size) = self.demand.clone()?;
+        Some(LayoutDemand {
+            serial: self.serial,
+            windows,
+            size,
+        })
+    }
+
+    /// Take the last layout demand, if it was not sent to the layout clients yet.
+    pub fn take_pending_demand(&mut self) -> Option<LayoutDemand> {
+        std::mem::take(&mut self.pending)
+            .then(|| self.demand())
+            .flatten()
+    }
+
+    /// Commit the geometries computed by the layout client for the layout demand with `serial`.
+    pub fn commit(
+        &mut self,
+        serial: u32,
+        geometries: Vec<Rectangle<i32, Logical>>,
+    ) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            serial == self.serial,
+            "Outdated serial {serial}, the last layout demand is {}",
+            self.serial
+        );
+        let windows = self.demand.as_ref().map_or(0, |(windows, _)| windows.len());
+        anyhow::ensure!(
+            geometries.len() == windows,
+            "Got {} geometries for {windows} windows",
+            geometries.len()
+        );
+        anyhow::ensure!(
+            geometries.iter().all(|geometry| !geometry.is_empty()),
+            "Geometries can't be empty"
+        );
+
+        self.geometries = geometries;
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+//! Layout plugins, used by the `plugin` layout.
+//!
+//! A layout plugin is a shared library, loaded from the `general.layout-plugin` path when the
+//! configuration gets loaded, and loaded again on each configuration reload. It must export these
+//! C functions:
+//!
+//! ```c
+//! struct fht_layout_params {
+//!     int32_t width, height;  // The size of the work area.
+//!     uint32_t window_count;  // The number of tiled windows.
+//!     uint32_t nmaster;
+//!     double mwfact;
+//!     int32_t inner_gaps;
+//! };
+//!
+//! struct fht_layout_rect {
+//!     int32_t x, y, width, height;
+//! };
+//!
+//! // Must return FHT_LAYOUT_ABI_VERSION, currently 1.
+//! uint32_t fht_layout_abi_version(void);
+//!
+//! // Write the geometry of each window, relative to the work area, to `geometries`, which has
+//! // room for `params->window_count` rectangles. Return false to fall back to the tile layout.
+//! bool fht_layout_arrange(const struct fht_layout_params *params,
+//!                         struct fht_layout_rect *geometries);
+//! ```
+//!
+//! The previous plugin gets unloaded before loading it again, so rebuilding it and reloading the
+//! configuration picks up its new version. Replace the plugin file instead of writing over it
+//! though, writing over a library that is loaded crashes the compositor.
+
+use std::ffi::{c_void, CString};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use smithay::utils::{Logical, Rectangle};
+
+/// The version of the plugin interface described in this module.
+const ABI_VERSION: u32 = 1;
+
+#[repr(C)]
+struct LayoutParams {
+    width: i32,
+    height: i32,
+    window_count: u32,
+    nmaster: u32,
+    mwfact: f64,
+    inner_gaps: i32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct LayoutRect {
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+}
+
+type AbiVersionFn = unsafe extern "C" fn() -> u32;
+type ArrangeFn = unsafe extern "C" fn(*const LayoutParams, *mut LayoutRect) -> bool;
+
+pub struct LayoutPlugin {
+    path: PathBuf,
+    handle: *mut c_void,
+    arrange: ArrangeFn,
+}
+
+impl std::fmt::Debug for LayoutPlugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LayoutPlugin")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl LayoutPlugin {
+    /// Load the layout plugin at `path`.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let c_path = CString::new(path.as_os_str().as_bytes()).context("Invalid plugin path")?;
+        // SAFETY: Loading a library runs its initializers, we have to trust the plugin the user
+        // configured.
+        let handle = unsafe { libc::dlopen(c_path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+        if handle.is_null() {
+            anyhow::bail!("Failed to load plugin: {}", dlerror());
+        }
+
+        match arrange_fn(handle) {
+            Ok(arrange) => Ok(Self {
+                path: path.to_path_buf(),
+                handle,
+                arrange,
+            }),
+            Err(err) => {
+                // SAFETY: Nothing from the library got used.
+                unsafe { libc::dlclose(handle) };
+                Err(err)
+            }
+        }
+    }
+
+    /// Get the geometry of `window_count` windows inside `work_area`.
+    ///
+    /// Returns [`None`] if the plugin refused to arrange the windows, or gave empty geometries.
+    pub fn arrange(
+        &self,
+        work_area: Rectangle<i32, Logical>,
+        window_count: usize,
+        nmaster: usize,
+        mwfact: f64,
+        inner_gaps: i32,
+    ) -> Option<Vec<Rectangle<i32, Logical>>> {
+        crate::profile_function!();
+        let params = LayoutParams {
+            width: work_area.size.w,
+            height: work_area.size.h,
+            window_count: u32::try_from(window_count).ok()?,
+            nmaster: u32::try_from(nmaster).ok()?,
+            mwfact,
+            inner_gaps,
+        };
+        let mut rects = vec![LayoutRect::default(); window_count];
+        // SAFETY: `rects` has room for `window_count` rectangles, as the plugin expects.
+        if !unsafe { (self.arrange)(&params, rects.as_mut_ptr()) } {
+            return None;
+        }
+
+        rects
+            .into_iter()
+            .map(|rect| {
+                // The plugin could give anything, don't overflow.
+                let loc = (
+                    work_area.loc.x.saturating_add(rect.x),
+                    work_area.loc.y.saturating_add(rect.y),
+                );
+                let geometry = Rectangle::new(loc.into(), (rect.width, rect.height).into());
+                (!geometry.is_empty()).then_some(geometry)
+            })
+            .collect()
+    }
+}
+
+impl Drop for LayoutPlugin {
+    fn drop(&mut self) {
+        // SAFETY: Nothing from the library is used after this.
+        unsafe {
+            libc::dlclose(self.handle);
+        }
+    }
+}
+
+/// Check the ABI version of the library and get its arrange function.
+fn arrange_fn(handle: *mut c_void) -> anyhow::Result<ArrangeFn> {
+    // SAFETY: The symbol has the signature described in the module documentation.
+    let abi_version = unsafe {
+        let symbol = symbol(handle, "fht_layout_abi_version")?;
+        std::mem::transmute::<*mut c_void, AbiVersionFn>(symbol)()
+    };
+    anyhow::ensure!(
+        abi_version == ABI_VERSION,
+        "Plugin has ABI version {abi_version}, expected {ABI_VERSION}"
+    );
+
+    let arrange = symbol(handle, "fht_layout_arrange")?;
+    // SAFETY: Same as above.
+    Ok(unsafe { std::mem::transmute::<*mut c_void, ArrangeFn>(arrange) })
+}
+
+fn symbol(handle: *mut c_void, name: &str) -> anyhow::Result<*mut c_void> {
+    let c_name = CString::new(name).unwrap();
+    // SAFETY: The handle comes from a successful dlopen.
+    let symbol = unsafe { libc::dlsym(handle, c_name.as_ptr()) };
+    if symbol.is_null() {
+        anyhow::bail!("Plugin does not export {name}: {}", dlerror());
+    }
+    Ok(symbol)
+}
+
+fn dlerror() -> String {
+    // SAFETY: dlerror returns either null or a valid C string.
+    unsafe {
+        let err = libc::dlerror();
+        if err.is_null() {
+            "unknown error".to_string()
+        } else {
+            std::ffi::CStr::from_ptr(err).to_string_lossy().into_owned()
+        }
+    }
+}
@@ -10,10 +10,15 @@
 //! can create a new node thats opposite to the current node direction (either vertical or
 //! horizontal)
 
+use std::cell::RefCell;
 use std::rc::Rc;
 use std::time::Duration;
 
+pub use external::LayoutDemand;
+use fht_animation::curve::Easing;
 use fht_animation::AnimationCurve;
+use fht_compositor_config::SplitDirection;
+use layout_plugin::LayoutPlugin;
 pub use monitor::{Monitor, MonitorRenderElement, MonitorRenderResult};
 use smithay::desktop::WindowSurfaceType;
 use smithay::output::Output;
@@ -27,9 +32,14 @@ use crate::input::resize_tile_grab::ResizeEdge;
 use crate::output::OutputExt;
 use crate::window::Window;
 
+mod bsp;
 mod closing_tile;
+mod container;
 pub mod decorations;
+mod external;
+mod layout_plugin;
 mod monitor;
+mod scrolling;
 mod tile;
 mod workspace;
 
@@ -76,6 +86,9 @@ impl Space {
     /// Reload the [`Config`] of the [`Space`].
     pub fn reload_config(&mut self, config: &fht_compositor_config::Config) {
         crate::profile_function!();
+        // The dynamic loader gives back libraries that are already loaded, unload the layout
+        // plugin first so that we get its new version.
+        drop(self.config.layout_plugin.take());
         let config = Config::new(config).expect("Space configuration invariants");
         self.config = Rc::new(config);
 
@@ -87,11 +100,27 @@ impl Space {
         }
     }
 
+    /// Discard the closing tiles and closing snapshots of all the workspaces.
+    ///
+    /// They live on the GPU that rendered them, and can't be used after switching GPUs.
+    pub fn discard_closing_tiles(&mut self) {
+        for monitor in &mut self.monitors {
+            for workspace in monitor.workspaces_mut() {
+                workspace.discard_closing_tiles();
+            }
+        }
+    }
+
     /// Get an iterator over the [`Space`]'s tracked [`Monitor`](s)
     pub fn monitors(&self) -> impl ExactSizeIterator<Item = &Monitor> {
         self.monitors.iter()
     }
 
+    /// Get a mutable iterator over the [`Space`]'s tracked [`Monitor`](s)
+    pub fn monitors_mut(&mut self) -> impl ExactSizeIterator<Item = &mut Monitor> {
+        self.monitors.iter_mut()
+    }
+
     /// Get the [`Monitor`] associated with this [`Output`].
     pub fn monitor_for_output(&self, output: &Output) -> Option<&Monitor> {
         self.monitors.iter().find(|mon| mon.output() == output)
@@ -112,7 +141,6 @@ impl Space {
     }
 
     /// Get the [`Window`]s on the associated [`Output`].
-    #[allow(unused)]
     pub fn windows_on_output(&self, output: &Output) -> impl Iterator<Item = &Window> {
         self.monitors
             .iter()
@@ -375,7 +403,9 @@ impl Space {
     }
 
     /// Activate a [`Window`].
-    pub fn activate_window(&mut self, window: &Window, animate: bool) -> bool {
+    ///
+    /// If `raise` is set, the window will also get raised above others in its workspace.
+    pub fn activate_window(&mut self, window: &Window, raise: bool, animate: bool) -> bool {
         let mut ret = false;
         let mut new_monitor_idx = None;
 
@@ -394,6 +424,9 @@ impl Space {
 
                 if let Some(new_tile_idx) = new_tile_idx {
                     workspace.set_active_tile_idx(new_tile_idx);
+                    if raise {
+                        workspace.raise_window(window);
+                    }
                     ret = true;
                     new_workspace_idx = Some(workspace_idx)
                 }
@@ -464,6 +497,112 @@ impl Space {
         active_workspace.change_nmaster(delta, animate);
     }
 
+    /// Change the gaps of the active [`Monitor`]'s active [`Workspace`].
+    ///
+    /// See [`Workspace::change_gaps`]
+    pub fn change_gaps(&mut self, outer_delta: i32, inner_delta: i32, animate: bool) {
+        let active_workspace = self.active_workspace_mut();
+        active_workspace.change_gaps(outer_delta, inner_delta, animate);
+    }
+
+    /// Change the gaps of every [`Workspace`] of every [`Monitor`].
+    ///
+    /// See [`Workspace::change_gaps`]
+    pub fn change_all_gaps(&mut self, outer_delta: i32, inner_delta: i32, animate: bool) {
+        for monitor in &mut self.monitors {
+            for workspace in monitor.workspaces_mut() {
+                workspace.change_gaps(outer_delta, inner_delta, animate);
+            }
+        }
+    }
+
+    /// Set the direction of the next split on the active [`Monitor`]'s active [`Workspace`].
+    ///
+    /// See [`Workspace::set_split_direction`]
+    pub fn set_split_direction(&mut self, direction: SplitDirection) {
+        let active_workspace = self.active_workspace_mut();
+        active_workspace.set_split_direction(direction);
+    }
+
+    /// Rotate the split of the focused window on the active [`Monitor`]'s active [`Workspace`].
+    ///
+    /// See [`Workspace::rotate_split`]
+    pub fn rotate_split(&mut self, animate: bool) {
+        let active_workspace = self.active_workspace_mut();
+        active_workspace.rotate_split(animate);
+    }
+
+    /// Equalize the split of the focused window on the active [`Monitor`]'s active
+    /// [`Workspace`].
+    ///
+    /// See [`Workspace::equalize_splits`]
+    pub fn equalize_splits(&mut self, animate: bool) {
+        let active_workspace = self.active_workspace_mut();
+        active_workspace.equalize_splits(animate);
+    }
+
+    /// Consume the focused window into the column on its left, on the active [`Monitor`]'s active
+    /// [`Workspace`].
+    ///
+    /// See [`Workspace::consume_into_column`]
+    pub fn consume_into_column(&mut self, animate: bool) {
+        let active_workspace = self.active_workspace_mut();
+        active_workspace.consume_into_column(animate);
+    }
+
+    /// Expel the focused window from its column, on the active [`Monitor`]'s active
+    /// [`Workspace`].
+    ///
+    /// See [`Workspace::expel_from_column`]
+    pub fn expel_from_column(&mut self, animate: bool) {
+        let active_workspace = self.active_workspace_mut();
+        active_workspace.expel_from_column(animate);
+    }
+
+    /// Add the next window as a tab of the focused window on the active [`Monitor`]'s active
+    /// [`Workspace`].
+    ///
+    /// See [`Workspace::tab_with_next_window`]
+    pub fn tab_with_next_window(&mut self, animate: bool) {
+        let active_workspace = self.active_workspace_mut();
+        active_workspace.tab_with_next_window(animate);
+    }
+
+    /// Switch the container of the focused window between tabbed and stacked, on the active
+    /// [`Monitor`]'s active [`Workspace`].
+    ///
+    /// See [`Workspace::toggle_stacked_container`]
+    pub fn toggle_stacked_container(&mut self, animate: bool) {
+        let active_workspace = self.active_workspace_mut();
+        active_workspace.toggle_stacked_container(animate);
+    }
+
+    /// Mark the focused window as the insertion point of the active [`Monitor`]'s active
+    /// [`Workspace`].
+    ///
+    /// See [`Workspace::toggle_insertion_mark`]
+    pub fn toggle_insertion_mark(&mut self) {
+        let active_workspace = self.active_workspace_mut();
+        active_workspace.toggle_insertion_mark();
+    }
+
+    /// Move the focused window out of its tabbed container on the active [`Monitor`]'s active
+    /// [`Workspace`].
+    ///
+    /// See [`Workspace::untab_active_window`]
+    pub fn untab_focused_window(&mut self, animate: bool) {
+        let active_workspace = self.active_workspace_mut();
+        active_workspace.untab_active_window(animate);
+    }
+
+    /// Scroll the view of the columns on the active [`Monitor`]'s active [`Workspace`].
+    ///
+    /// See [`Workspace::scroll_view`]
+    pub fn scroll_view(&mut self, delta: i32, animate: bool) {
+        let active_workspace = self.active_workspace_mut();
+        active_workspace.scroll_view(delta, animate);
+    }
+
     /// Maximize the [`Tile`] associated with this [`Window`].
     pub fn maximize_window(&mut self, window: &Window, maximize: bool, animate: bool) -> bool {
         for monitor in &mut self.monitors {
@@ -565,6 +704,38 @@ impl Space {
         active.insert_window(window.clone(), animate);
     }
 
+    /// Move a [`Window`] to the workspace with `index` of this [`Output`].
+    ///
+    /// The active workspace of the [`Output`] does not change.
+    pub fn move_window_to_workspace(
+        &mut self,
+        window: &Window,
+        output: &Output,
+        index: usize,
+        animate: bool,
+    ) {
+        let Some(target_monitor) = self.monitors.iter().position(|mon| mon.output() == output)
+        else {
+            return;
+        };
+        if index >= self.monitors[target_monitor].workspaces().len() {
+            return;
+        }
+
+        let removed = self
+            .monitors
+            .iter_mut()
+            .flat_map(|mon| mon.workspaces_mut())
+            .any(|workspace| workspace.remove_window(window, true));
+        if !removed {
+            return;
+        }
+
+        self.monitors[target_monitor]
+            .workspace_mut_by_index(index)
+            .insert_window(window.clone(), animate);
+    }
+
     /// Get the fullscreen [`Window`] under the `point`, and its position in global space.
     ///
     /// `point` is expected to be in global coordinate space.
@@ -598,27 +769,14 @@ impl Space {
             }
         }
 
-        // Active tile is always above everything else
-        if let Some(tile) = active.active_tile() {
+        // Then respect the stacking order of the workspace.
+        for tile in active.stacked_tiles() {
             let window = tile.window();
-            let loc = tile.location() + tile.window_loc();
-            let bbox = {
-                let mut bbox = window.bbox();
-                bbox.loc += loc;
-                bbox
-            };
-            let render_location = loc - window.render_offset();
-            if bbox.to_f64().contains(point)
-                && window
-                    .surface_under(point - render_location.to_f64(), WindowSurfaceType::ALL)
-                    .is_some()
-            {
-                return Some((window.clone(), render_location));
+            if window.dragged() {
+                // The window follows the pointer, we want what's under it instead.
+                continue;
             }
-        }
 
-        for tile in active.tiles() {
-            let window = tile.window();
             let loc = tile.location() + tile.window_loc();
             let bbox = {
                 let mut bbox = window.bbox();
@@ -626,9 +784,15 @@ impl Space {
                 bbox
             };
             let render_location = loc - window.render_offset();
+            // Only popups can receive input in the cut corners of the window.
+            let surface_type = if tile.point_in_cut_corner(point) {
+                WindowSurfaceType::POPUP
+            } else {
+                WindowSurfaceType::ALL
+            };
             if bbox.to_f64().contains(point)
                 && window
-                    .surface_under(point - render_location.to_f64(), WindowSurfaceType::ALL)
+                    .surface_under(point - render_location.to_f64(), surface_type)
                     .is_some()
             {
                 return Some((window.clone(), render_location));
@@ -772,17 +936,33 @@ pub struct Config {
         AnimationConfig,
         fht_compositor_config::WorkspaceSwitchAnimationDirection,
     )>,
+    /// The delay between each tile when using the staggered workspace switch animation.
+    ///
+    /// If [`None`], the whole workspace slides instead.
+    pub workspace_switch_stagger_delay: Option<Duration>,
     pub window_geometry_animation: Option<AnimationConfig>,
     pub window_open_close_animation: Option<AnimationConfig>,
+    pub border_color_animation: Option<AnimationConfig>,
     pub shadow: Option<fht_compositor_config::Shadow>,
     pub insert_window_strategy: fht_compositor_config::InsertWindowStrategy,
     pub border: fht_compositor_config::Border,
     pub layouts: Vec<fht_compositor_config::WorkspaceLayout>,
+    /// The plugin used by the `plugin` layout, if it got loaded.
+    ///
+    /// It gets taken out when reloading the configuration, see [`Space::reload_config`].
+    pub layout_plugin: RefCell<Option<LayoutPlugin>>,
     pub nmaster: usize,
     pub gaps: (i32, i32),
     pub mwfact: f64,
+    /// Per-workspace overrides of `layouts`, `nmaster` and `mwfact`.
+    pub workspaces: Vec<fht_compositor_config::WorkspaceDefaults>,
     pub focus_new_windows: bool,
     pub blur: fht_compositor_config::Blur,
+    /// How much to darken unfocused tiles. Zero means dimming is disabled.
+    pub dim_inactive_strength: f32,
+    pub dim_inactive_animation: Option<AnimationConfig>,
+    pub opacity_animation: Option<AnimationConfig>,
+    pub tile_debug_overlay: bool,
 }
 
 impl Config {
@@ -796,9 +976,48 @@ impl Config {
         if config.general.layouts.is_empty() {
             anyhow::bail!("general.layouts must never be empty!");
         }
+        for defaults in &config.workspaces {
+            let index = defaults.index;
+            if defaults.nmaster == Some(0) {
+                anyhow::bail!("workspaces.nmaster cannot be zero! (workspace {index})");
+            }
+            if defaults
+                .mwfact
+                .is_some_and(|mwfact| !(0.01..=0.99).contains(&mwfact))
+            {
+                anyhow::bail!("workspaces.mwfact must be between 0.01 and 0.99 (workspace {index})")
+            }
+            if defaults.layouts.as_ref().is_some_and(Vec::is_empty) {
+                anyhow::bail!("workspaces.layouts must never be empty! (workspace {index})");
+            }
+        }
         Ok(())
     }
 
+    /// Get the layouts, nmaster and mwfact a [`Workspace`] starts with.
+    pub fn workspace_layout_defaults(
+        &self,
+        output: &Output,
+        index: usize,
+    ) -> (Vec<fht_compositor_config::WorkspaceLayout>, usize, f64) {
+        let (mut layouts, mut nmaster, mut mwfact) = (&self.layouts, self.nmaster, self.mwfact);
+        // Defaults for a specific output come last, to take precedence.
+        let output_name = output.name();
+        let matching = self
+            .workspaces
+            .iter()
+            .filter(|defaults| defaults.index == index && defaults.output.is_none())
+            .chain(self.workspaces.iter().filter(|defaults| {
+                defaults.index == index && defaults.output.as_ref() == Some(&output_name)
+            }));
+        for defaults in matching {
+            layouts = defaults.layouts.as_ref().unwrap_or(layouts);
+            nmaster = defaults.nmaster.unwrap_or(nmaster);
+            mwfact = defaults.mwfact.unwrap_or(mwfact);
+        }
+        (layouts.clone(), nmaster, mwfact)
+    }
+
     fn new(config: &fht_compositor_config::Config) -> anyhow::Result<Self> {
         Self::check_invariants(config)?;
         Ok(Self {
@@ -808,6 +1027,9 @@ impl Config {
                 !config.animations.disable && !config.animations.workspace_switch.disable,
             )
             .map(|a| (a, config.animations.workspace_switch.direction)),
+            workspace_switch_stagger_delay: (config.animations.workspace_switch.style
+                == fht_compositor_config::WorkspaceSwitchAnimationStyle::Stagger)
+                .then_some(config.animations.workspace_switch.stagger_delay),
             window_geometry_animation: AnimationConfig::new(
                 config.animations.window_geometry.duration,
                 config.animations.window_geometry.curve,
@@ -818,15 +1040,38 @@ impl Config {
                 config.animations.window_open_close.curve,
                 !config.animations.disable && !config.animations.window_open_close.disable,
             ),
+            border_color_animation: AnimationConfig::new(
+                config.animations.border_color.duration,
+                config.animations.border_color.curve,
+                !config.animations.disable && !config.animations.border_color.disable,
+            ),
             shadow: (!config.decorations.shadow.disable).then_some(config.decorations.shadow),
             insert_window_strategy: config.general.insert_window_strategy,
             focus_new_windows: config.general.focus_new_windows,
             layouts: config.general.layouts.clone(),
+            layout_plugin: RefCell::new(config.general.layout_plugin.as_deref().and_then(|path| {
+                LayoutPlugin::load(path)
+                    .inspect_err(|err| warn!(?path, ?err, "Failed to load layout plugin"))
+                    .ok()
+            })),
             nmaster: config.general.nmaster,
             gaps: (config.general.outer_gaps, config.general.inner_gaps),
             mwfact: config.general.mwfact,
+            workspaces: config.workspaces.clone(),
             border: config.decorations.border,
             blur: config.decorations.blur,
+            dim_inactive_strength: config.decorations.dim_inactive.strength.clamp(0.0, 1.0),
+            dim_inactive_animation: AnimationConfig::new(
+                config.decorations.dim_inactive.duration,
+                AnimationCurve::Simple(Easing::EaseOutCubic),
+                !config.animations.disable && !config.decorations.dim_inactive.duration.is_zero(),
+            ),
+            opacity_animation: AnimationConfig::new(
+                config.animations.opacity.duration,
+                config.animations.opacity.curve,
+                !config.animations.disable && !config.animations.opacity.disable,
+            ),
+            tile_debug_overlay: config.debug.tile_debug_overlay,
         })
     }
 }
@@ -118,8 +118,8 @@ impl Monitor {
     }
 
     /// Get the all the visible [`Window`] on this output.
-    pub fn visible_windows(&self) -> impl ExactSizeIterator<Item = &Window> {
-        self.workspaces[self.active_idx].windows()
+    pub fn visible_windows(&self) -> impl Iterator<Item = &Window> {
+        self.workspaces[self.active_idx].visible_windows()
     }
 
     /// Set the active [`Workspace`] index.
@@ -128,10 +128,36 @@ impl Monitor {
             return None;
         }
 
+        // Pinned windows follow the active workspace.
+        let pinned_windows: Vec<_> = self.workspaces[self.active_idx]
+            .windows()
+            .filter(|window| window.pinned())
+            .cloned()
+            .collect();
+        for window in pinned_windows {
+            self.workspaces[self.active_idx].remove_window(&window, animate);
+            self.workspaces[idx].insert_window(window, animate);
+        }
+
         // The workspace switch animation is done on a per-workspace level.
         // Each workspace has a render offset.
         if animate {
-            if let Some((config, direction)) = &self.config.workspace_switch_animation {
+            if let (Some((config, direction)), Some(delay)) = (
+                &self.config.workspace_switch_animation,
+                self.config.workspace_switch_stagger_delay,
+            ) {
+                // Tiles only slide by a fraction of the output size, they mostly fade.
+                let (width, height) = self.output.geometry().size.into();
+                let sign = if self.active_idx > idx { -1 } else { 1 };
+                let offset = match direction {
+                    WorkspaceSwitchAnimationDirection::Horizontal => (sign * width / 8, 0).into(),
+                    WorkspaceSwitchAnimationDirection::Vertical => (0, sign * height / 8).into(),
+                };
+
+                self.workspaces[self.active_idx]
+                    .start_staggered_switch_animation(false, offset, delay, config);
+                self.workspaces[idx].start_staggered_switch_animation(true, offset, delay, config);
+            } else if let Some((config, direction)) = &self.config.workspace_switch_animation {
                 let (width, height) = self.output.geometry().size.into();
                 match direction {
                     WorkspaceSwitchAnimationDirection::Horizontal => {
@@ -208,10 +234,16 @@ impl Monitor {
     }
 
     /// Advance animations for this [`Monitor`].
-    pub fn advance_animations(&mut self, target_presentation_time: Duration) -> bool {
+    ///
+    /// `focused_window` is the window with the keyboard focus, if any.
+    pub fn advance_animations(
+        &mut self,
+        target_presentation_time: Duration,
+        focused_window: Option<&Window>,
+    ) -> bool {
         crate::profile_function!();
         self.workspaces.iter_mut().fold(false, |acc, ws| {
-            ws.advance_animations(target_presentation_time) || acc
+            ws.advance_animations(target_presentation_time, focused_window) || acc
         })
     }
 
@@ -219,7 +251,7 @@ impl Monitor {
     pub fn has_blur(&self) -> bool {
         for workspace in &self.workspaces {
             // only check for visible workspaces
-            if (workspace.index() == self.active_idx || workspace.has_render_offset_animation())
+            if (workspace.index() == self.active_idx || workspace.has_switch_animation())
                 && workspace.tiles().any(|tile| tile.has_transparent_region())
             {
                 return true;
@@ -238,7 +270,7 @@ impl Monitor {
         let mut has_fullscreen = false;
 
         for (idx, workspace) in self.workspaces.iter().enumerate() {
-            if idx == self.active_idx || workspace.has_render_offset_animation() {
+            if idx == self.active_idx || workspace.has_switch_animation() {
                 has_fullscreen |= workspace.fullscreened_tile().is_some();
                 elements.extend(
                     workspace
@@ -0,0 +1,153 @@
+//! Scrollable tiling, used by the `scrolling` layout.
+//!
+//! Each [`Workspace`](super::Workspace) keeps the containers of its tiled windows in [`Columns`]
+//! placed on an infinite horizontal strip. Every column takes `mwfact` of the work area width, and
+//! the containers inside a column share its height according to their proportions.
+//!
+//! Only part of the strip fits on the output: the view follows the focused container, and can be
+//! scrolled with the `scroll-view` key action.
+
+use smithay::utils::{Logical, Rectangle};
+
+use super::container::ContainerId;
+
+#[derive(Debug, Clone, Default)]
+pub struct Columns {
+    columns: Vec<Vec<ContainerId>>,
+    /// The position of the strip displayed at the left edge of the work area.
+    view_offset: i32,
+    /// The container the view last scrolled to.
+    followed_container: Option<ContainerId>,
+}
+
+impl Columns {
+    /// Make the strip contain exactly `containers`.
+    ///
+    /// Missing containers get their own column, after the column of the container preceding them
+    /// in `containers`.
+    pub fn sync(&mut self, containers: &[ContainerId]) {
+        for column in &mut self.columns {
+            column.retain(|container| containers.contains(container));
+        }
+        self.columns.retain(|column| !column.is_empty());
+
+        for (idx, &container) in containers.iter().enumerate() {
+            if self.column_idx(container).is_some() {
+                continue;
+            }
+            let column_idx = containers[..idx]
+                .iter()
+                .rev()
+                .find_map(|&previous| self.column_idx(previous))
+                .map_or(0, |idx| idx + 1);
+            self.columns.insert(column_idx, vec![container]);
+        }
+    }
+
+    fn column_idx(&self, container: ContainerId) -> Option<usize> {
+        self.columns
+            .iter()
+            .position(|column| column.contains(&container))
+    }
+
+    /// Move `container` to the bottom of the column on its left.
+    pub fn consume_into_column(&mut self, container: ContainerId) {
+        let Some(idx) = self.column_idx(container) else {
+            return;
+        };
+        if idx == 0 {
+            return;
+        }
+        self.columns[idx].retain(|c| *c != container);
+        self.columns[idx - 1].push(container);
+        if self.columns[idx].is_empty() {
+            self.columns.remove(idx);
+        }
+    }
+
+    /// Move `container` out of its column, into a new column on its right.
+    pub fn expel_from_column(&mut self, container: ContainerId) {
+        let Some(idx) = self.column_idx(container) else {
+            return;
+        };
+        if self.columns[idx].len() < 2 {
+            return;
+        }
+        self.columns[idx].retain(|c| *c != container);
+        self.columns.insert(idx + 1, vec![container]);
+    }
+
+    /// Scroll the view by `delta` logical pixels, without going past the strip edges.
+    pub fn scroll(
+        &mut self,
+        delta: i32,
+        work_area: Rectangle<i32, Logical>,
+        mwfact: f64,
+        inner_gaps: i32,
+    ) {
+        let column_width = column_width(work_area, mwfact, inner_gaps);
+        let max_offset = self.max_offset(column_width, work_area, inner_gaps);
+        self.view_offset = (self.view_offset + delta).clamp(0, max_offset);
+    }
+
+    /// Get the view offset at which the last column touches the right edge of `work_area`.
+    fn max_offset(
+        &self,
+        column_width: i32,
+        work_area: Rectangle<i32, Logical>,
+        inner_gaps: i32,
+    ) -> i32 {
+        let strip_width = (column_width + inner_gaps) * self.columns.len() as i32 - inner_gaps;
+        (strip_width - work_area.size.w).max(0)
+    }
+
+    /// Get the geometry of each container inside `work_area`.
+    ///
+    /// If `active_container` changed since the last call, the view scrolls to make it fully
+    /// visible. `heights` computes the heights of the containers inside a column, given the column
+    /// height.
+    pub fn geometries(
+        &mut self,
+        work_area: Rectangle<i32, Logical>,
+        mwfact: f64,
+        inner_gaps: i32,
+        active_container: Option<ContainerId>,
+        heights: impl Fn(&[ContainerId], i32) -> Vec<i32>,
+    ) -> Vec<(ContainerId, Rectangle<i32, Logical>)> {
+        let column_width = column_width(work_area, mwfact, inner_gaps);
+
+        if let Some(active_container) = active_container {
+            if self.followed_container != Some(active_container) {
+                if let Some(idx) = self.column_idx(active_container) {
+                    let column_x = (column_width + inner_gaps) * idx as i32;
+                    if column_x < self.view_offset {
+                        self.view_offset = column_x;
+                    } else if column_x + column_width > self.view_offset + work_area.size.w {
+                        self.view_offset = column_x + column_width - work_area.size.w;
+                    }
+                    self.followed_container = Some(active_container);
+                }
+            }
+        }
+        // Columns might have been removed, or the work area grown, since the last scroll.
+        let max_offset = self.max_offset(column_width, work_area, inner_gaps);
+        self.view_offset = self.view_offset.clamp(0, max_offset);
+
+        let mut geometries = vec![];
+        for (idx, column) in self.columns.iter().enumerate() {
+            let x = work_area.loc.x + (column_width + inner_gaps) * idx as i32 - self.view_offset;
+            let mut y = work_area.loc.y;
+            for (&container, height) in column.iter().zip(heights(column, work_area.size.h)) {
+                let geometry = Rectangle::new((x, y).into(), (column_width, height).into());
+                geometries.push((container, geometry));
+                y += height + inner_gaps;
+            }
+        }
+        geometries
+    }
+}
+
+/// Get the width of a column, so that `1 / mwfact` columns fill the work area.
+pub fn column_width(work_area: Rectangle<i32, Logical>, mwfact: f64, inner_gaps: i32) -> i32 {
+    (f64::from(work_area.size.w + inner_gaps) * mwfact).round() as i32 - inner_gaps
+}
@@ -1,7 +1,9 @@
+use std::cell::RefCell;
 use std::rc::Rc;
 use std::time::Duration;
 
 use fht_animation::Animation;
+use fht_compositor_config::Color;
 use smithay::backend::allocator::Fourcc;
 use smithay::backend::renderer::element::surface::WaylandSurfaceRenderElement;
 use smithay::backend::renderer::element::texture::TextureRenderElement;
@@ -13,14 +15,18 @@ use smithay::backend::renderer::glow::GlowRenderer;
 use smithay::backend::renderer::Renderer as _;
 use smithay::desktop::{PopupManager, WindowSurfaceType};
 use smithay::output::Output;
+use smithay::reexports::wayland_protocols::wp::content_type::v1::server::wp_content_type_v1::Type as ContentType;
 use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
 use smithay::utils::{Logical, Point, Rectangle, Scale, Size, Transform};
 use smithay::wayland::compositor::{with_surface_tree_downward, TraversalAction};
 use smithay::wayland::seat::WaylandFocus;
 
 use super::closing_tile::ClosingTile;
+use super::decorations::AnimatedBorderColor;
 use super::Config;
-use crate::egui::EguiRenderElement;
+use crate::egui::{EguiElement, EguiRenderElement};
+use crate::output::OutputExt;
+use crate::protocols::kde_blur::surface_blur_region;
 use crate::renderer::blur::element::BlurElement;
 use crate::renderer::extra_damage::ExtraDamage;
 use crate::renderer::pixel_shader_element::FhtPixelShaderElement;
@@ -28,9 +34,13 @@ use crate::renderer::rounded_window::RoundedWindowElement;
 use crate::renderer::shaders::Shaders;
 use crate::renderer::texture_element::FhtTextureElement;
 use crate::renderer::texture_shader_element::FhtTextureShaderElement;
-use crate::renderer::{has_transparent_region, render_to_texture, FhtRenderer};
+use crate::renderer::{
+    has_transparent_region, render_to_texture, surface_alpha_multiplier, FhtRenderer,
+};
 use crate::utils::RectCenterExt;
-use crate::window::Window;
+use crate::window::{RenderStats, Window};
+
+const DEBUG_OVERLAY_SIZE: (i32, i32) = (300, 150);
 
 /// A single workspace tile.
 ///
@@ -63,8 +73,8 @@ pub struct Tile {
     /// The animation value's (if any) is the visual size we should display the [`Tile`] with.
     size_animation: Option<Animation<[i32; 2]>>,
 
-    /// Extra damage bag to apply when the tile corners are being rounded.
-    /// This is due to an implementation detail of [`RoundedWindowElement`]
+    /// Extra damage bag to apply when the tile corners are being rounded or when the tile is being
+    /// dimmed. This is due to an implementation detail of [`RoundedWindowElement`]
     extra_damage: ExtraDamage,
 
     /// The current opening animation.
@@ -72,12 +82,47 @@ pub struct Tile {
     /// This affects the [`Tile`]'s final scale and opacity, in order to give a pop-in effect.
     opening_animation: Option<Animation<f64>>,
 
+    /// Whether the window of this [`Tile`] has the keyboard focus.
+    ///
+    /// Inactive tiles get dimmed and use the `inactive-opacity` window rule.
+    active: bool,
+
+    /// The current dimming animation, from `0.0` (not dimmed) to `1.0` (fully dimmed).
+    dim_animation: Option<Animation<f64>>,
+
+    /// The animation of the border color when this [`Tile`] gets focused or unfocused.
+    border_color_animation: Option<AnimatedBorderColor>,
+
+    /// The height of the title bars of the container this [`Tile`] is the displayed tab of.
+    ///
+    /// The [`Tile`] leaves room for them above it.
+    title_bar_height: i32,
+
+    /// Opacity set at runtime with the `change-window-opacity` key action.
+    ///
+    /// This takes precedence over the `opacity` window rule.
+    opacity_override: Option<f32>,
+
+    /// The opacity this [`Tile`] is displayed with, or is animating to.
+    ///
+    /// This is [`None`] until the first [`Tile::advance_animations`], to avoid animating the
+    /// initial opacity.
+    opacity: Option<f32>,
+
+    /// The current opacity animation.
+    opacity_animation: Option<Animation<f64>>,
+
     /// Prepared render elements for a [`ClosingTile`].
     ///
     /// These are rendered the frame before `self.window` removes/unmaps its buffers, in order to
     /// create a "snapshot" of the window to animate the [`ClosingTile`]
     close_animation_snapshot: Option<Vec<TileRenderElement<GlowRenderer>>>,
 
+    /// The debug overlay displaying the window rendering statistics.
+    ///
+    /// Lazily created when the `debug.tile-debug-overlay` config option is enabled.
+    debug_overlay: RefCell<Option<EguiElement>>,
+
     /// Configuration specific to the workspace system.
     pub config: Rc<Config>,
 }
@@ -108,8 +153,16 @@ impl Tile {
             location_animation: None,
             size_animation: None,
             opening_animation: None,
+            active: true,
+            dim_animation: None,
+            border_color_animation: None,
+            title_bar_height: 0,
+            opacity_override: None,
+            opacity: None,
+            opacity_animation: None,
             extra_damage: ExtraDamage::new(size),
             close_animation_snapshot: None,
+            debug_overlay: RefCell::new(None),
             config,
         }
     }
@@ -132,6 +185,9 @@ impl Tile {
         scale: Scale<f64>,
     ) -> Option<ClosingTile> {
         let render_elements = self.close_animation_snapshot.take()?;
+        if self.animations_disabled() {
+            return None;
+        }
         let animation = self.config.window_open_close_animation.as_ref()?;
         let geometry = self.visual_geometry();
         Some(ClosingTile::new(
@@ -143,6 +199,14 @@ impl Tile {
         ))
     }
 
+    /// Whether animations are disabled for this [`Tile`].
+    ///
+    /// Games advertising their content type do not get animated, since the animations only add
+    /// latency and can hurt performance.
+    fn animations_disabled(&self) -> bool {
+        self.window.content_type() == ContentType::Game
+    }
+
     /// Get the proportion of this [`Tile`].
     pub const fn proportion(&self) -> f64 {
         self.proportion
@@ -165,7 +229,6 @@ impl Tile {
         }
 
         if surface_type.contains(WindowSurfaceType::SUBSURFACE) {
-            use std::cell::RefCell;
             let found = RefCell::new(false);
             with_surface_tree_downward(
                 window_surface,
@@ -195,11 +258,63 @@ impl Tile {
     /// The `new_geometry` argument will the geometry of the whole [`Tile`], including its border.
     ///
     /// This does not call [`Window::send_configure`]!
-    pub fn set_geometry(&mut self, new_geometry: Rectangle<i32, Logical>, animate: bool) {
+    pub fn set_geometry(&mut self, mut new_geometry: Rectangle<i32, Logical>, animate: bool) {
+        new_geometry.loc.y += self.title_bar_height;
+        new_geometry.size.h -= self.title_bar_height;
+        if self.window.tiled() && !self.window.fullscreen() && !self.window.maximized() {
+            new_geometry = self.constrain_geometry(new_geometry);
+        }
         self.set_location(new_geometry.loc, animate);
         self.set_size(new_geometry.size, animate);
     }
 
+    /// Fit a geometry given by the layout to the min and max size of the window, and to its size
+    /// increments.
+    ///
+    /// When the window ends up smaller, it gets centered inside the geometry so that the slack
+    /// goes into the gaps. When its min size is larger, it overflows the geometry from the top
+    /// left corner.
+    fn constrain_geometry(&self, geometry: Rectangle<i32, Logical>) -> Rectangle<i32, Logical> {
+        let border_thickness = {
+            let rules = self.window.rules();
+            self.config.border.with_overrides(&rules.border).thickness
+        };
+        let (min_size, max_size) = self.window.size_constraints();
+        let mut width = geometry.size.w - 2 * border_thickness;
+        let mut height = geometry.size.h - 2 * border_thickness;
+
+        if let Some(increments) = self.window.size_increments() {
+            if width > increments.w {
+                width -= width % increments.w;
+            }
+            if height > increments.h {
+                height -= height % increments.h;
+            }
+        }
+        if max_size.w > 0 {
+            width = width.min(max_size.w);
+        }
+        if max_size.h > 0 {
+            height = height.min(max_size.h);
+        }
+        width = width.max(min_size.w);
+        height = height.max(min_size.h);
+
+        let size = Size::from((width + 2 * border_thickness, height + 2 * border_thickness));
+        let offset = Point::from((
+            (geometry.size.w - size.w).max(0) / 2,
+            (geometry.size.h - size.h).max(0) / 2,
+        ));
+        Rectangle::new(geometry.loc + offset, size)
+    }
+
+    /// Set the height of the title bars this [`Tile`] leaves room for above it.
+    ///
+    /// This only affects the geometries given by the next calls to [`Tile::set_geometry`].
+    pub fn set_title_bar_height(&mut self, height: i32) {
+        self.title_bar_height = height;
+    }
+
     /// Get this [`Tile`]'s geometry, in other words its effective [`Rectangle`] in [`Workspace`]
     /// space.
     ///
@@ -224,6 +339,7 @@ impl Tile {
     ///
     /// The `new_location` argument will the location of the whole [`Tile`], including its border.
     pub fn set_location(&mut self, new_location: Point<i32, Logical>, animate: bool) {
+        let animate = animate && !self.animations_disabled();
         if let Some(window_geometry_animation) = &self.config.window_geometry_animation {
             let mut old_location = self.location;
             if let Some(previous_animation) = self.location_animation.take() {
@@ -307,12 +423,53 @@ impl Tile {
         }
     }
 
+    /// Whether `point` is inside one of the corners of this [`Tile`] cut by its border radius.
+    ///
+    /// `point` is expected to be relative to the [`Workspace`]. Nothing of the window is drawn
+    /// there, so input should go through to what's below.
+    pub fn point_in_cut_corner(&self, point: Point<f64, Logical>) -> bool {
+        if self.window.fullscreen() {
+            return false; // No border is drawn when the window is fullscreened.
+        }
+
+        let radius = {
+            let rules = self.window.rules();
+            self.config.border.with_overrides(&rules.border).radius as f64
+        };
+        let geometry = Rectangle::new(self.location, self.size()).to_f64();
+        // https://drafts.csswg.org/css-backgrounds/#corner-overlap
+        let radius = radius.min(geometry.size.w / 2.).min(geometry.size.h / 2.);
+        if radius <= 0.0 || !geometry.contains(point) {
+            return false;
+        }
+
+        // Find the center of the rounded corner closest to the point, if it's in a corner.
+        let point = point - geometry.loc;
+        let center_x = if point.x < radius {
+            radius
+        } else if point.x > geometry.size.w - radius {
+            geometry.size.w - radius
+        } else {
+            return false;
+        };
+        let center_y = if point.y < radius {
+            radius
+        } else if point.y > geometry.size.h - radius {
+            geometry.size.h - radius
+        } else {
+            return false;
+        };
+
+        (point.x - center_x).powi(2) + (point.y - center_y).powi(2) > radius.powi(2)
+    }
+
     /// Set this [`Tile`]'s size.
     ///
     /// The `new_size` argument will the size of the whole [`Tile`], including its border.
     ///
     /// This does not call [`Window::send_configure`]!
     pub fn set_size(&mut self, new_size: Size<i32, Logical>, animate: bool) {
+        let animate = animate && !self.animations_disabled();
         let previous_size = self.visual_size(); // we need visual for animation
         if previous_size == new_size
             || self.size_animation.as_ref().is_some_and(|anim| {
@@ -392,12 +549,59 @@ impl Tile {
             animation.tick(target_presentation_time);
         }
 
+        let opacity = self.target_opacity();
+        match self.opacity {
+            Some(prev) if prev != opacity => {
+                match &self.config.opacity_animation {
+                    Some(animation) if !self.animations_disabled() => {
+                        let start = self
+                            .opacity_animation
+                            .as_ref()
+                            .map_or(prev as f64, |a| *a.value());
+                        self.opacity_animation = Some(
+                            Animation::new(start, opacity as f64, animation.duration)
+                                .with_curve(animation.curve),
+                        );
+                    }
+                    _ => {
+                        let _ = self.opacity_animation.take();
+                    }
+                }
+                self.opacity = Some(opacity);
+            }
+            Some(_) => (),
+            None => self.opacity = Some(opacity),
+        }
+
+        let _ = self.opacity_animation.take_if(|a| a.is_finished());
+        if let Some(animation) = &mut self.opacity_animation {
+            animations_ongoing = true;
+            animation.tick(target_presentation_time);
+        }
+
+        let _ = self.border_color_animation.take_if(|a| a.is_finished());
+        if let Some(animation) = &mut self.border_color_animation {
+            animations_ongoing = true;
+            animation.tick(target_presentation_time);
+        }
+
+        let _ = self.dim_animation.take_if(|a| a.is_finished());
+        if let Some(animation) = &mut self.dim_animation {
+            animations_ongoing = true;
+            animation.tick(target_presentation_time);
+            // The dim is a shader uniform, the window surfaces themselves are not damaged.
+            self.extra_damage.damage_all();
+        }
+
         animations_ongoing
     }
 
     /// Start the opening animation for this [`Tile`], if the window open/close animation is
     /// enabled.
     pub fn start_opening_animation(&mut self) {
+        if self.animations_disabled() {
+            return;
+        }
         let Some(animation) = &self.config.window_open_close_animation else {
             return;
         };
@@ -406,6 +610,100 @@ impl Tile {
             Some(Animation::new(0.0, 1.0, animation.duration).with_curve(animation.curve));
     }
 
+    /// Set whether the window of this [`Tile`] has the keyboard focus, animating the dimming if
+    /// needed.
+    pub fn set_active(&mut self, active: bool) {
+        if self.active == active {
+            return;
+        }
+
+        self.border_color_animation = match &self.config.border_color_animation {
+            Some(animation) if !self.animations_disabled() => {
+                let rules = self.window.rules();
+                let border = self.config.border.with_overrides(&rules.border);
+                drop(rules);
+                let from = self.border_color(&border, self.active);
+                Some(AnimatedBorderColor::new(from, animation))
+            }
+            _ => None,
+        };
+        self.active = active;
+
+        let target = if active { 0.0 } else { 1.0 };
+        match &self.config.dim_inactive_animation {
+            Some(animation)
+                if !self.animations_disabled() && self.config.dim_inactive_strength != 0.0 =>
+            {
+                let start = self
+                    .dim_animation
+                    .as_ref()
+                    .map_or(1.0 - target, |a| *a.value());
+                self.dim_animation = Some(
+                    Animation::new(start, target, animation.duration).with_curve(animation.curve),
+                );
+            }
+            _ => {
+                let _ = self.dim_animation.take();
+                self.extra_damage.damage_all();
+            }
+        }
+    }
+
+    /// Get the color of the border of this [`Tile`], accounting for the border color animation.
+    fn border_color(&self, border: &fht_compositor_config::Border, active: bool) -> Color {
+        let color = if active {
+            border.focused_color
+        } else if self.window.urgent() {
+            border.urgent_color
+        } else {
+            border.normal_color
+        };
+
+        match &self.border_color_animation {
+            Some(animation) => animation.color(color),
+            None => color,
+        }
+    }
+
+    /// Get how much this [`Tile`] should be darkened.
+    fn dim(&self) -> f32 {
+        let progress = self
+            .dim_animation
+            .as_ref()
+            .map_or(if self.active { 0.0 } else { 1.0 }, |a| *a.value());
+        self.config.dim_inactive_strength * progress.clamp(0.0, 1.0) as f32
+    }
+
+    /// Change the opacity of this [`Tile`] by a delta.
+    pub fn change_opacity(&mut self, delta: f32) {
+        let opacity = self
+            .opacity_override
+            .or(self.window.rules().opacity)
+            .unwrap_or(1.0);
+        self.opacity_override = Some((opacity + delta).clamp(0.0, 1.0));
+    }
+
+    /// Get the opacity this [`Tile`] should have, from the window rules and the active state.
+    fn target_opacity(&self) -> f32 {
+        let rules = self.window.rules();
+        let opacity = self.opacity_override.or(rules.opacity).unwrap_or(1.0);
+        let opacity = if self.active {
+            opacity
+        } else {
+            rules.inactive_opacity.unwrap_or(opacity)
+        };
+
+        opacity.clamp(0.0, 1.0)
+    }
+
+    /// Get the opacity this [`Tile`] is currently displayed with.
+    fn opacity(&self) -> f32 {
+        match &self.opacity_animation {
+            Some(animation) => (*animation.value()).clamp(0.0, 1.0) as f32,
+            None => self.opacity.unwrap_or_else(|| self.target_opacity()),
+        }
+    }
+
     /// Take a snapshot for running a [`ClosingTile`].
     pub fn prepare_close_animation_if_needed(
         &mut self,
@@ -456,6 +754,8 @@ impl Tile {
     ) -> Vec<TileRenderElement<R>> {
         crate::profile_function!();
         let mut elements = vec![];
+        // Must be computed before locking the window rules.
+        let opacity = self.opacity();
         let rules = self.window.rules();
         let is_floating = !self.window.tiled();
         let is_fullscreen = self.window.fullscreen();
@@ -463,7 +763,7 @@ impl Tile {
         let alpha = if is_fullscreen {
             alpha
         } else {
-            alpha * rules.opacity.unwrap_or(1.0)
+            alpha * opacity
         };
 
         let border = self.config.border.with_overrides(&rules.border);
@@ -503,7 +803,8 @@ impl Tile {
         let reduction = f32::min(1.0, reduction);
         let border_radius = border_radius * reduction;
 
-        if border_radius != 0.0 {
+        let dim = self.dim();
+        if border_radius != 0.0 || dim != 0.0 {
             let damage = self.extra_damage.clone().with_location(window_geometry.loc);
             elements.push(TileRenderElement::RoundedSurfaceDamage(damage));
         }
@@ -572,6 +873,7 @@ impl Tile {
                         Uniform::new("corner_radius", border_radius / scale as f32),
                         Uniform::new("win_size", [win_size.w as f32, win_size.h as f32]),
                         Uniform::new("curr_size", [curr_size.w as f32, curr_size.h as f32]),
+                        Uniform::new("dim", dim),
                     ],
                 );
 
@@ -601,17 +903,22 @@ impl Tile {
                     // this with the preview window)
                     //
                     // To counter this, we check here if the surface is going to clip.
-                    if RoundedWindowElement::will_clip(
+                    //
+                    // Dimming is also done by the rounded window shader, so every surface goes
+                    // through it when the tile is dimmed.
+                    let will_clip = RoundedWindowElement::will_clip(
                         &e,
                         scale as f64,
                         window_geometry,
                         border_radius,
-                    ) {
+                    );
+                    if will_clip || dim != 0.0 {
                         let rounded = RoundedWindowElement::new(
                             e,
                             border_radius,
                             window_geometry,
                             scale as f64,
+                            dim,
                         );
                         TileRenderElement::RoundedSurface(rounded)
                     } else {
@@ -621,7 +928,26 @@ impl Tile {
             elements.extend(window_elements);
         };
 
-        if !blur.disabled() && self.has_transparent_region() {
+        // Clients can ask to blur specific regions of their surface using the KDE blur protocol,
+        // otherwise we blur the whole window if it has any transparent region.
+        let wl_surface = self.window.wl_surface().unwrap();
+        let surface_offset = self.window.render_offset();
+        let window_rect = Rectangle::from_size(self.window.size());
+        let blur_region = surface_blur_region(
+            &wl_surface,
+            Rectangle::new(surface_offset, window_rect.size),
+        )
+        .map(|rects| {
+            rects
+                .into_iter()
+                .map(|rect| Rectangle::new(rect.loc - surface_offset, rect.size))
+                .collect::<Vec<_>>()
+        });
+        let should_blur = blur_region
+            .as_ref()
+            .map_or_else(|| self.has_transparent_region(), |rects| !rects.is_empty());
+
+        if !blur.disabled() && should_blur {
             // Optimized blur uses a pre-blurred texture containing background and bottom
             // layer shells. True blur (non-optimized) blurs in real time whatever is behind the
             // window.
@@ -644,27 +970,42 @@ impl Tile {
                 optimized
             });
 
-            // Since tile_geometry and window_geometry are dependent on what we are rendering for
-            // (opening animation, size animation) we use data gathered from self instead
-            //
-            // render_offset is from the workspace, to account for switching animations
-            let sample_area = Rectangle::new(
-                self.visual_location() + self.window_loc() + render_offset,
-                window_geometry.size,
-            );
+            // The blur should fade along with the window surface when the client uses the
+            // alpha-modifier protocol.
+            let blur_alpha = alpha * surface_alpha_multiplier(&wl_surface);
+
+            let blur_rects = blur_region.unwrap_or_else(|| vec![window_rect]);
+            for rect in blur_rects {
+                // Only round off the blur when it covers the whole window, other regions are
+                // shaped by the client itself.
+                let (rect, corner_radius) = if rect == window_rect {
+                    (Rectangle::from_size(window_geometry.size), border_radius)
+                } else {
+                    (rect, 0.0)
+                };
+
+                // Since tile_geometry and window_geometry are dependent on what we are rendering
+                // for (opening animation, size animation) we use data gathered from self instead
+                //
+                // render_offset is from the workspace, to account for switching animations
+                let sample_area = Rectangle::new(
+                    self.visual_location() + self.window_loc() + render_offset + rect.loc,
+                    rect.size,
+                );
 
-            let blur_element = BlurElement::new(
-                renderer,
-                output,
-                sample_area,
-                window_geometry.loc.to_physical(scale),
-                border_radius,
-                optimized,
-                scale,
-                alpha,
-                blur,
-            );
-            elements.push(blur_element.into());
+                let blur_element = BlurElement::new(
+                    renderer,
+                    output,
+                    sample_area,
+                    (window_geometry.loc + rect.loc).to_physical(scale),
+                    corner_radius,
+                    optimized,
+                    scale,
+                    blur_alpha,
+                    blur,
+                );
+                elements.push(blur_element.into());
+            }
         }
 
         if border_thickness != 0 {
@@ -676,11 +1017,7 @@ impl Tile {
                     tile_geometry,
                     border_thickness as f64,
                     border_radius as f64,
-                    if active {
-                        border.focused_color
-                    } else {
-                        border.normal_color
-                    },
+                    self.border_color(&border, active),
                 )
                 .into(),
             );
@@ -698,7 +1035,15 @@ impl Tile {
                 }
             };
 
-            if !is_fullscreen && shadow_config.color[3] > 0.0 && should_draw {
+            let color = if active {
+                shadow_config.color
+            } else {
+                shadow_config.inactive_color.unwrap_or(shadow_config.color)
+            };
+            // Maximized windows fill the whole output, like fullscreen ones, the shadow would only
+            // spill on the other outputs.
+            let is_maximized = self.window.maximized();
+            if !is_fullscreen && !is_maximized && color[3] > 0.0 && should_draw {
                 elements.push(
                     super::decorations::draw_shadow(
                         renderer,
@@ -706,8 +1051,9 @@ impl Tile {
                         scale,
                         window_geometry,
                         shadow_config.sigma,
+                        Point::from((shadow_config.offset[0], shadow_config.offset[1])),
                         border_radius,
-                        shadow_config.color,
+                        color,
                     )
                     .into(),
                 );
@@ -749,7 +1095,12 @@ impl Tile {
                 .map(|shadow| shadow.with_overrides(&rules.shadow))
             {
                 let scaled_sigma = (shadow.sigma / scale as f32).round() as i32;
-                shadow_offset = Point::from((scaled_sigma, scaled_sigma));
+                // A shadow moved to the top or left also needs more room.
+                let [offset_x, offset_y] = shadow.offset;
+                shadow_offset = Point::from((
+                    scaled_sigma - offset_x.min(0),
+                    scaled_sigma - offset_y.min(0),
+                ));
             }
             drop(rules);
 
@@ -827,8 +1178,64 @@ impl Tile {
             )
         }
 
-        opening_element.into_iter().chain(normal_elements)
+        let debug_overlay = self
+            .config
+            .tile_debug_overlay
+            .then(|| self.render_debug_overlay(renderer, output, scale))
+            .flatten();
+
+        debug_overlay
+            .into_iter()
+            .chain(opening_element)
+            .chain(normal_elements)
     }
+
+    /// Render the debug overlay of this [`Tile`], displaying the window rendering statistics.
+    fn render_debug_overlay<R: FhtRenderer>(
+        &self,
+        renderer: &mut R,
+        output: &Output,
+        scale: i32,
+    ) -> Option<TileRenderElement<R>> {
+        crate::profile_function!();
+        let mut debug_overlay = self.debug_overlay.borrow_mut();
+        let debug_overlay =
+            debug_overlay.get_or_insert_with(|| EguiElement::new(Size::from(DEBUG_OVERLAY_SIZE)));
+
+        let stats = self.window.render_stats();
+        let location = (self.visual_location() + self.window_loc()).to_physical(scale);
+        debug_overlay
+            .render(
+                renderer.glow_renderer_mut(),
+                scale,
+                1.0,
+                location,
+                output.refresh_interval(),
+                |ctx| debug_overlay_ui(ctx, &stats),
+            )
+            .inspect_err(|err| warn!(?err, "Failed to render tile debug overlay"))
+            .ok()
+            .map(TileRenderElement::DebugOverlay)
+    }
+}
+
+fn debug_overlay_ui(ctx: &egui::Context, stats: &RenderStats) {
+    crate::profile_function!();
+    egui::Area::new(egui::Id::NULL).show(ctx, |ui| {
+        egui::Frame::window(ui.style())
+            .inner_margin(4.0)
+            .shadow(egui::Shadow::NONE)
+            .show(ui, |ui| {
+                ui.label(format!("Commits/s: {}", stats.commits_per_second));
+                ui.label(format!(
+                    "Texture memory: {:.2} MiB",
+                    stats.texture_memory as f64 / (1024.0 * 1024.0)
+                ));
+                for format in &stats.buffer_formats {
+                    ui.monospace(format);
+                }
+            });
+    });
 }
 
 fn opening_animation_progress_to_scale(progress: f64) -> f64 {
@@ -3,8 +3,8 @@ use std::rc::Rc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 
-use fht_animation::Animation;
-use fht_compositor_config::{InsertWindowStrategy, WorkspaceLayout};
+use fht_animation::{Animation, AnimationCurve};
+use fht_compositor_config::{InsertWindowStrategy, SplitDirection, WorkspaceLayout};
 use smithay::backend::renderer::element::utils::{Relocate, RelocateRenderElement};
 use smithay::backend::renderer::glow::GlowRenderer;
 use smithay::desktop::layer_map_for_output;
@@ -12,11 +12,17 @@ use smithay::output::Output;
 use smithay::utils::{IsAlive, Logical, Point, Rectangle, Size};
 use smithay::wayland::seat::WaylandFocus;
 
+use super::bsp::BspTree;
 use super::closing_tile::{ClosingTile, ClosingTileRenderElement};
+use super::container::{Container, ContainerId};
+use super::external::{ExternalLayout, LayoutDemand};
+use super::scrolling::{column_width, Columns};
 use super::tile::{Tile, TileRenderElement};
 use super::Config;
+use crate::egui::EguiRenderElement;
 use crate::fht_render_elements;
 use crate::input::resize_tile_grab::ResizeEdge;
+use crate::layer::ResolvedLayerRules;
 use crate::output::OutputExt;
 use crate::renderer::FhtRenderer;
 use crate::utils::RectCenterExt;
@@ -54,6 +60,73 @@ struct InteractiveResize {
     edges: ResizeEdge,
 }
 
+/// A staggered workspace switch animation.
+///
+/// Instead of moving the whole workspace plane, each [`Tile`] fades and slides on its own, starting
+/// a bit after the previous one.
+#[derive(Debug)]
+struct StaggeredSwitch {
+    /// Whether we are switching to this workspace, or away from it.
+    entering: bool,
+    /// How much each tile slides during the animation.
+    offset: Point<i32, Logical>,
+    /// When the animation started, set on the first tick.
+    started_at: Option<Duration>,
+    /// The delay between the start of each tile animation.
+    delay: Duration,
+    duration: Duration,
+    curve: AnimationCurve,
+    /// The progress of each tile, in stacking order.
+    ///
+    /// The animation is [`None`] until the tile delay has elapsed.
+    tiles: Vec<(Window, Option<Animation<f64>>)>,
+}
+
+impl StaggeredSwitch {
+    fn tick(&mut self, target_presentation_time: Duration) {
+        let started_at = *self.started_at.get_or_insert(target_presentation_time);
+        let elapsed = target_presentation_time.saturating_sub(started_at);
+
+        for (idx, (_, animation)) in self.tiles.iter_mut().enumerate() {
+            if animation.is_none() && elapsed >= self.delay * idx as u32 {
+                *animation = Some(Animation::new(0.0, 1.0, self.duration).with_curve(self.curve));
+            }
+
+            if let Some(animation) = animation {
+                animation.tick(target_presentation_time);
+            }
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.tiles
+            .iter()
+            .all(|(_, animation)| animation.as_ref().is_some_and(|a| a.is_finished()))
+    }
+
+    /// Get the alpha and the offset to apply to the [`Tile`] of this [`Window`].
+    fn tile_state(&self, window: &Window) -> (f32, Point<i32, Logical>) {
+        let progress = self
+            .tiles
+            .iter()
+            .find(|(w, _)| w == window)
+            // Tiles that got added during the animation are not animated.
+            .map_or(1.0, |(_, animation)| {
+                animation.as_ref().map_or(0.0, |a| *a.value())
+            });
+        // Spring curves can overshoot, only the offset should follow that.
+        let alpha = progress.clamp(0.0, 1.0) as f32;
+
+        if self.entering {
+            let offset = self.offset.to_f64().upscale(1.0 - progress);
+            (alpha, offset.to_i32_round())
+        } else {
+            let offset = self.offset.to_f64().upscale(-progress);
+            (1.0 - alpha, offset.to_i32_round())
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Workspace {
     /// The unique ID of this workspace.
@@ -75,6 +148,12 @@ pub struct Workspace {
     /// while it fades out and then gets cleaned from this vector.
     closing_tiles: Vec<ClosingTile>,
 
+    /// The stacking order of the [`Tile`]s, from top to bottom.
+    ///
+    /// This is separate from `tiles` since the order of `tiles` is the order they get arranged in
+    /// by the layout, and it should not affect which floating window is above which.
+    stacking_order: Vec<Window>,
+
     /// The active [`Tile`] index. Must be < tiles.len()
     ///
     /// If `tiles.len() == 0`, this is [`None`]
@@ -116,6 +195,27 @@ pub struct Workspace {
     /// - `gaps.1`: inner gaps, between [`Tile`]s
     gaps: (i32, i32),
 
+    /// The containers of the tiled windows, each taking a single slot of the layout.
+    ///
+    /// They are kept in the order of their windows in `self.tiles`.
+    containers: Vec<Container>,
+
+    /// The binary space partition of the containers, used by the `bsp` layout.
+    ///
+    /// It is kept up to date with the other layouts too, so that switching back to the `bsp`
+    /// layout does not lose the splits made by the user.
+    bsp: BspTree,
+
+    /// The columns of the containers, used by the `scrolling` layout.
+    columns: Columns,
+
+    /// The window new windows get inserted after, with [`InsertWindowStrategy::AfterMarked`].
+    insertion_mark: Option<Window>,
+
+    /// The geometries of the tiled windows computed by a layout client, used by the `external`
+    /// layout.
+    external_layout: ExternalLayout,
+
     /// Whether this [`Workspace`] has transient layout changes.
     ///
     /// When the user applies changes to the [`Workspace`] layout settings, for example using
@@ -130,6 +230,11 @@ pub struct Workspace {
     /// render elements from [`Workspace::render`].
     render_offset: Option<Animation<[i32; 2]>>,
 
+    /// Staggered workspace switch animation.
+    ///
+    /// This is an alternative to the render offset, where each tile gets animated separately.
+    staggered_switch: Option<StaggeredSwitch>,
+
     /// Fade out animations for non-fullscreen windows.
     ///
     /// When fullscreening a window, we run a fade-out animations on all other windows in the
@@ -158,21 +263,29 @@ pub struct Workspace {
 impl Workspace {
     /// Create a new [`Workspace`] on this [`Output`].
     pub fn new(output: Output, index: usize, config: &Rc<Config>) -> Self {
+        let (layouts, nmaster, mwfact) = config.workspace_layout_defaults(&output, index);
         Self {
             id: WorkspaceId::unique(),
             index,
             output,
             tiles: vec![],
             closing_tiles: vec![],
+            stacking_order: vec![],
             active_tile_idx: None,
             fullscreened_tile_idx: None,
-            layouts: config.layouts.clone(),
+            layouts,
             active_layout_idx: 0,
-            mwfact: config.mwfact,
-            nmaster: config.nmaster,
+            mwfact,
+            nmaster,
             gaps: config.gaps,
+            containers: vec![],
+            bsp: BspTree::default(),
+            columns: Columns::default(),
+            insertion_mark: None,
+            external_layout: ExternalLayout::default(),
             has_transient_layout_changes: false,
             render_offset: None,
+            staggered_switch: None,
             fullscreen_fade_animation: None,
             interactive_swap: None,
             interactive_resize: None,
@@ -229,15 +342,16 @@ impl Workspace {
         // Workspace-specific layout changes.
 
         // These are only the layout parameters, layout list still gets updated as usual.
-        self.layouts = config.layouts.clone();
+        let (layouts, nmaster, mwfact) = config.workspace_layout_defaults(&self.output, self.index);
+        self.layouts = layouts;
         self.active_layout_idx = self.active_layout_idx.clamp(0, self.layouts.len() - 1);
 
         // Gaps are purely visual, they should do not affect the layout much...
         self.gaps = config.gaps;
 
         if !self.has_transient_layout_changes {
-            self.mwfact = config.mwfact;
-            self.nmaster = config.nmaster;
+            self.mwfact = mwfact;
+            self.nmaster = nmaster;
         }
 
         self.arrange_tiles(true);
@@ -331,6 +445,9 @@ impl Workspace {
             true
         });
         self.closing_tiles.retain(|tile| !tile.is_finished());
+        let tiles = &self.tiles;
+        self.stacking_order
+            .retain(|window| tiles.iter().any(|tile| tile.window() == window));
 
         if !self.tiles.is_empty() {
             if let Some(active_idx) = &mut self.active_tile_idx {
@@ -345,6 +462,10 @@ impl Workspace {
         if arrange {
             self.arrange_tiles(true);
         }
+        if self.activate_active_tab() {
+            // The focus moved to a hidden tab, that takes the place of the displayed one.
+            self.arrange_tiles(false);
+        }
 
         for (idx, tile) in self.tiles.iter_mut().enumerate() {
             Self::refresh_window(
@@ -407,7 +528,9 @@ impl Workspace {
             idx => idx,
         });
         self.arrange_tiles(animate);
-        self.active_window()
+        let window = self.active_window()?;
+        self.raise_window(&window);
+        Some(window)
     }
 
     /// Activate the [`Tile`] that comes previous in the [`Workspace`].
@@ -426,7 +549,163 @@ impl Workspace {
             Some(idx) => idx,
         });
         self.arrange_tiles(animate);
-        self.active_window()
+        let window = self.active_window()?;
+        self.raise_window(&window);
+        Some(window)
+    }
+
+    /// Raise this [`Window`] above all the others in the stacking order.
+    pub fn raise_window(&mut self, window: &Window) {
+        if !self.tiles.iter().any(|tile| tile.window() == window) {
+            return;
+        }
+        self.stacking_order.retain(|w| w != window);
+        self.stacking_order.insert(0, window.clone());
+
+        // Modal dialogs are always kept above their parent.
+        let dialogs: Vec<_> = self
+            .tiles
+            .iter()
+            .map(Tile::window)
+            .filter(|w| w.is_modal_dialog_of(window))
+            .cloned()
+            .collect();
+        for dialog in dialogs {
+            self.stacking_order.retain(|w| *w != dialog);
+            self.stacking_order.insert(0, dialog);
+        }
+    }
+
+    /// Lower this [`Window`] below all the others in the stacking order.
+    pub fn lower_window(&mut self, window: &Window) {
+        if !self.tiles.iter().any(|tile| tile.window() == window) {
+            return;
+        }
+        self.stacking_order.retain(|w| w != window);
+        self.stacking_order.push(window.clone());
+    }
+
+    /// Raise this [`Window`] if any other [`Window`] covers it, otherwise lower it.
+    pub fn toggle_raise_window(&mut self, window: &Window) {
+        let Some(geometry) = self
+            .tiles
+            .iter()
+            .find(|tile| tile.window() == window)
+            .map(Tile::geometry)
+        else {
+            return;
+        };
+
+        let is_covered = self
+            .stacked_tiles()
+            .take_while(|tile| tile.window() != window)
+            .any(|tile| tile.geometry().overlaps(geometry));
+        if is_covered {
+            self.raise_window(window);
+        } else {
+            self.lower_window(window);
+        }
+    }
+
+    /// Get the [`Tile`]s of this [`Workspace`] in stacking order, from top to bottom.
+    pub fn stacked_tiles(&self) -> impl Iterator<Item = &Tile> {
+        let stacked = self
+            .stacking_order
+            .iter()
+            .filter_map(|window| self.tiles.iter().find(|tile| tile.window() == window));
+        // Should not happen, but don't lose tiles if they are not stacked.
+        let unstacked = self
+            .tiles
+            .iter()
+            .filter(|tile| !self.stacking_order.contains(tile.window()));
+        let (floating, tiled): (Vec<_>, Vec<_>) = stacked
+            .chain(unstacked)
+            .filter(|tile| !self.is_hidden(tile.window()))
+            .partition(|tile| !tile.window().tiled());
+        // Floating windows are always displayed above the tiled ones.
+        floating.into_iter().chain(tiled)
+    }
+
+    /// Get the only tiled [`Window`] displayed with the monocle layout, the active one.
+    ///
+    /// If the active [`Window`] is floating, this is the topmost tiled one.
+    ///
+    /// Returns [`None`] if the [`Workspace`] does not use the monocle layout.
+    pub fn monocle_window(&self) -> Option<&Window> {
+        if self.current_layout() != WorkspaceLayout::Monocle {
+            return None;
+        }
+        let active_window = self.active_tile().map(Tile::window);
+        active_window
+            .into_iter()
+            .chain(&self.stacking_order)
+            .chain(self.tiles.iter().map(Tile::window))
+            .find(|window| window.tiled() && self.tiles.iter().any(|tile| tile.window() == *window))
+    }
+
+    /// Whether this [`Window`] is hidden behind another one with the monocle layout.
+    fn hidden_by_monocle(&self, window: &Window) -> bool {
+        self.monocle_window()
+            .is_some_and(|displayed| window.tiled() && window != displayed)
+    }
+
+    /// Get the [`Container`] of this tiled [`Window`].
+    fn container(&self, window: &Window) -> Option<&Container> {
+        self.containers
+            .iter()
+            .find(|container| container.contains(window))
+    }
+
+    /// Get the [`ContainerId`] of the [`Container`] of this tiled [`Window`].
+    fn container_id(&self, window: &Window) -> Option<ContainerId> {
+        self.container(window).map(Container::id)
+    }
+
+    /// Whether this [`Window`] is the tab of a [`Container`] that is not displayed.
+    fn hidden_in_container(&self, window: &Window) -> bool {
+        self.container(window)
+            .and_then(Container::active_window)
+            .is_some_and(|displayed| window != displayed)
+    }
+
+    /// Whether this [`Window`] is hidden, either by the monocle layout or by a [`Container`].
+    fn is_hidden(&self, window: &Window) -> bool {
+        self.hidden_by_monocle(window) || self.hidden_in_container(window)
+    }
+
+    /// Get the tabbed [`Container`] whose title bars are displayed above this [`Window`], if any.
+    fn title_bar_container(&self, window: &Window) -> Option<&Container> {
+        if self.current_layout() == WorkspaceLayout::Floating
+            || !window.tiled()
+            || window.maximized()
+            || window.fullscreen()
+        {
+            return None;
+        }
+        self.container(window)
+            .filter(|container| container.is_tabbed())
+            .filter(|container| container.active_window() == Some(window))
+    }
+
+    /// Display the active [`Window`] in its [`Container`], since the focused window is always the
+    /// displayed tab.
+    ///
+    /// Returns whether the displayed tab changed.
+    fn activate_active_tab(&mut self) -> bool {
+        let Some(window) = self.active_window() else {
+            return false;
+        };
+        self.containers
+            .iter_mut()
+            .find(|container| container.contains(&window))
+            .is_some_and(|container| container.activate(&window))
+    }
+
+    /// Swap these two tiled [`Window`]s between their [`Container`]s.
+    fn swap_container_windows(&mut self, a: &Window, b: &Window) {
+        for container in &mut self.containers {
+            container.swap(a, b);
+        }
     }
 
     /// Swap the two [`Tile`]s associated with these [`Window`]s
@@ -439,6 +718,7 @@ impl Workspace {
         };
 
         self.tiles.swap(a_idx, b_idx);
+        self.swap_container_windows(a, b);
         self.arrange_tiles(animate);
     }
 
@@ -459,6 +739,9 @@ impl Workspace {
             self.active_tile_idx = Some(next_idx);
         }
         self.tiles.swap(active_idx, next_idx);
+        let a = self.tiles[active_idx].window().clone();
+        let b = self.tiles[next_idx].window().clone();
+        self.swap_container_windows(&a, &b);
         self.arrange_tiles(animate);
         true
     }
@@ -480,6 +763,9 @@ impl Workspace {
             self.active_tile_idx = Some(prev_idx);
         }
         self.tiles.swap(active_idx, prev_idx);
+        let a = self.tiles[active_idx].window().clone();
+        let b = self.tiles[prev_idx].window().clone();
+        self.swap_container_windows(&a, &b);
         self.arrange_tiles(animate);
         true
     }
@@ -509,6 +795,24 @@ impl Workspace {
         self.tiles.iter().map(Tile::window)
     }
 
+    /// Get an iterator over the [`Workspace`]'s [`Window`]s that are not completely covered.
+    ///
+    /// A fullscreened [`Window`] covers all the others, unless they are fading out.
+    pub fn visible_windows(&self) -> impl Iterator<Item = &Window> {
+        let covering_idx = self.fullscreened_tile_idx.filter(|_| {
+            self.fullscreen_fade_animation
+                .as_ref()
+                .and_then(|(idx, _)| *idx)
+                .is_none()
+        });
+        self.tiles
+            .iter()
+            .enumerate()
+            .filter(move |(idx, _)| covering_idx.is_none_or(|covering_idx| covering_idx == *idx))
+            .map(|(_, tile)| tile.window())
+            .filter(move |window| !self.is_hidden(window))
+    }
+
     /// Get an iterator over the [`Workspace`]'s [`Tile`]s.
     ///
     /// This includes the fullscreened [`Tile`], if any.
@@ -531,6 +835,8 @@ impl Workspace {
             return;
         }
         self.remove_current_fullscreen();
+        // New windows split the node of the container of the window that was focused before them.
+        let focused = self.active_window();
 
         window.request_bounds(Some(self.output.geometry().size));
         window.configure_for_output(&self.output);
@@ -631,11 +937,29 @@ impl Workspace {
                         active_idx
                     }
                 }
+                InsertWindowStrategy::AfterMarked => {
+                    let marked_idx = self.insertion_mark.as_ref().and_then(|marked| {
+                        self.tiles.iter().position(|tile| tile.window() == marked)
+                    });
+                    let new_idx = marked_idx.map_or(self.tiles.len(), |idx| idx + 1);
+                    self.tiles.insert(new_idx, tile);
+                    new_idx
+                }
             }
         };
         if self.config.focus_new_windows {
             self.active_tile_idx = Some(new_idx)
         }
+        if window.tiled() {
+            let container = Container::new(window.clone());
+            let target = focused.and_then(|focused| self.container_id(&focused));
+            let work_area = calculate_work_area(&self.output, self.gaps.0);
+            self.bsp
+                .insert(container.id(), target, work_area, self.gaps.1);
+            self.containers.push(container);
+        }
+        // New windows always open on top.
+        self.stacking_order.insert(0, window);
 
         self.arrange_tiles(animate);
         self.tiles[new_idx].stop_location_animation();
@@ -663,7 +987,9 @@ impl Workspace {
             self.remove_current_fullscreen();
         }
 
+        let was_active = Some(idx) == self.active_tile_idx;
         let window = self.tiles.remove(idx).into_window();
+        let _ = self.insertion_mark.take_if(|marked| *marked == window);
         window.request_bounds(None);
         window.leave_output(&self.output);
         if self.tiles.is_empty() {
@@ -672,6 +998,7 @@ impl Workspace {
             let idx = self.active_tile_idx.unwrap();
             self.active_tile_idx = Some(idx.clamp(0, self.tiles.len() - 1));
         }
+        self.remove_from_container(&window, was_active);
 
         self.refresh();
         self.arrange_tiles(animate);
@@ -679,6 +1006,31 @@ impl Workspace {
         true
     }
 
+    /// Remove this [`Window`] from its [`Container`].
+    ///
+    /// If the [`Window`] was active, the tab displayed in its place gets activated, instead of
+    /// whatever [`Tile`] ends up at the active index.
+    fn remove_from_container(&mut self, window: &Window, was_active: bool) {
+        let Some(container) = self
+            .containers
+            .iter_mut()
+            .find(|container| container.contains(window))
+        else {
+            return;
+        };
+        container.retain(|w| w != window);
+        let Some(displayed) = container.active_window().filter(|_| was_active) else {
+            return;
+        };
+        if let Some(idx) = self
+            .tiles
+            .iter()
+            .position(|tile| tile.window() == displayed)
+        {
+            self.active_tile_idx = Some(idx);
+        }
+    }
+
     /// Close the [`Tile`] associated with this [`Window`], running a close animation.
     pub fn close_window(
         &mut self,
@@ -693,7 +1045,12 @@ impl Workspace {
             .fullscreened_tile_idx
             .take_if(|&mut f_idx| f_idx == idx);
 
+        let was_active = Some(idx) == self.active_tile_idx;
         let tile = self.tiles.remove(idx);
+        let _ = self
+            .insertion_mark
+            .take_if(|marked| marked == tile.window());
+        self.remove_from_container(tile.window(), was_active);
         let scale = self.output.current_scale().fractional_scale().into();
         if animate {
             if let Some(closing_tile) = tile.into_closing_tile(renderer, scale) {
@@ -742,6 +1099,14 @@ impl Workspace {
         tile.clear_close_animation_snapshot();
     }
 
+    /// Discard the [`ClosingTile`]s and the taken closing snapshots of this workspace.
+    pub fn discard_closing_tiles(&mut self) {
+        self.closing_tiles.clear();
+        for tile in &mut self.tiles {
+            tile.clear_close_animation_snapshot();
+        }
+    }
+
     /// Fullscreen the [`Tile`] associated with this window.
     pub fn fullscreen_window(&mut self, window: &Window, animate: bool) -> bool {
         let Some(idx) = self
@@ -837,9 +1202,19 @@ impl Workspace {
     }
 
     /// Change the master width factor of this [`Workspace`].
+    ///
+    /// The bsp layout has no master area, the active [`Window`] gets resized inside the split
+    /// containing it instead.
     pub fn change_mwfact(&mut self, delta: f64, animate: bool) {
-        self.has_transient_layout_changes = true;
-        self.mwfact = (self.mwfact + delta).clamp(0.01, 0.99);
+        if self.current_layout() == WorkspaceLayout::Bsp {
+            let Some(container) = self.active_window().and_then(|w| self.container_id(&w)) else {
+                return;
+            };
+            self.bsp.resize(container, delta);
+        } else {
+            self.has_transient_layout_changes = true;
+            self.mwfact = (self.mwfact + delta).clamp(0.01, 0.99);
+        }
         self.arrange_tiles(animate);
     }
 
@@ -850,6 +1225,216 @@ impl Workspace {
         self.arrange_tiles(animate);
     }
 
+    /// Change the gaps of this [`Workspace`].
+    ///
+    /// Unlike the other layout parameters, the gaps get back to the configured ones when reloading
+    /// the configuration.
+    pub fn change_gaps(&mut self, outer_delta: i32, inner_delta: i32, animate: bool) {
+        // The deltas can come from IPC clients, don't overflow.
+        self.gaps.0 = self.gaps.0.saturating_add(outer_delta).max(0);
+        self.gaps.1 = self.gaps.1.saturating_add(inner_delta).max(0);
+        self.arrange_tiles(animate);
+    }
+
+    /// Set the direction of the split made by the next window inserted in this [`Workspace`].
+    pub fn set_split_direction(&mut self, direction: SplitDirection) {
+        self.bsp.set_next_split(direction);
+    }
+
+    /// Rotate the split containing the active [`Window`] by 90 degrees.
+    pub fn rotate_split(&mut self, animate: bool) {
+        let Some(container) = self.active_window().and_then(|w| self.container_id(&w)) else {
+            return;
+        };
+        self.bsp.rotate(container);
+        self.arrange_tiles(animate);
+    }
+
+    /// Give the same size to every [`Window`] of the split containing the active one.
+    pub fn equalize_splits(&mut self, animate: bool) {
+        let Some(container) = self.active_window().and_then(|w| self.container_id(&w)) else {
+            return;
+        };
+        self.bsp.equalize(container);
+        self.arrange_tiles(animate);
+    }
+
+    /// Move the active [`Window`] to the bottom of the column on its left.
+    pub fn consume_into_column(&mut self, animate: bool) {
+        let Some(container) = self.active_window().and_then(|w| self.container_id(&w)) else {
+            return;
+        };
+        self.columns.consume_into_column(container);
+        self.arrange_tiles(animate);
+    }
+
+    /// Move the active [`Window`] out of its column, into a new column on its right.
+    pub fn expel_from_column(&mut self, animate: bool) {
+        let Some(container) = self.active_window().and_then(|w| self.container_id(&w)) else {
+            return;
+        };
+        self.columns.expel_from_column(container);
+        self.arrange_tiles(animate);
+    }
+
+    /// Move the displayed [`Window`] of the next [`Container`] into the active [`Window`]'s
+    /// [`Container`], as its last tab.
+    pub fn tab_with_next_window(&mut self, animate: bool) {
+        let Some(window) = self.active_window().filter(Window::tiled) else {
+            return;
+        };
+        let Some(container_idx) = self
+            .containers
+            .iter()
+            .position(|container| container.contains(&window))
+        else {
+            return;
+        };
+        let len = self.containers.len();
+        let Some(next) = (1..len)
+            .map(|offset| &self.containers[(container_idx + offset) % len])
+            .find_map(Container::active_window)
+            .cloned()
+        else {
+            return;
+        };
+        self.remove_current_fullscreen();
+
+        // The next window leaves its previous container, that gets removed if it's now empty.
+        for (idx, container) in self.containers.iter_mut().enumerate() {
+            if idx != container_idx {
+                container.retain(|w| *w != next);
+            }
+        }
+        self.containers[container_idx].push(next.clone());
+        self.containers
+            .retain(|container| !container.windows().is_empty());
+
+        // Keep the windows of a container next to each other in the tile list, so that cycling
+        // the focus goes through its tabs in a row.
+        let next_idx = self
+            .tiles
+            .iter()
+            .position(|tile| *tile.window() == next)
+            .unwrap();
+        let tile = self.tiles.remove(next_idx);
+        let window_idx = self
+            .tiles
+            .iter()
+            .position(|tile| *tile.window() == window)
+            .unwrap();
+        self.tiles.insert(window_idx + 1, tile);
+        self.active_tile_idx = Some(window_idx);
+        self.arrange_tiles(animate);
+    }
+
+    /// Mark the active [`Window`] as the insertion point of this [`Workspace`], or remove the mark
+    /// if it's already on it.
+    pub fn toggle_insertion_mark(&mut self) {
+        let Some(window) = self.active_window() else {
+            return;
+        };
+        if self.insertion_mark.as_ref() == Some(&window) {
+            self.insertion_mark = None;
+        } else {
+            self.insertion_mark = Some(window);
+        }
+    }
+
+    /// Switch the [`Container`] of the active [`Window`] between tabbed and stacked.
+    pub fn toggle_stacked_container(&mut self, animate: bool) {
+        let Some(window) = self.active_window() else {
+            return;
+        };
+        let Some(container) = self
+            .containers
+            .iter_mut()
+            .find(|container| container.contains(&window))
+        else {
+            return;
+        };
+        container.toggle_stacked();
+        self.arrange_tiles(animate);
+    }
+
+    /// Move the active [`Window`] out of its tabbed [`Container`], into a new [`Container`] next
+    /// to it.
+    pub fn untab_active_window(&mut self, animate: bool) {
+        let Some(window) = self.active_window() else {
+            return;
+        };
+        let Some(container) = self
+            .containers
+            .iter_mut()
+            .find(|container| container.contains(&window) && container.is_tabbed())
+        else {
+            return;
+        };
+        container.retain(|w| *w != window);
+        let target = container.id();
+
+        let new_container = Container::new(window);
+        let work_area = calculate_work_area(&self.output, self.gaps.0);
+        self.bsp
+            .insert(new_container.id(), Some(target), work_area, self.gaps.1);
+        self.containers.push(new_container);
+        self.arrange_tiles(animate);
+    }
+
+    /// Activate the next (or previous, if `forward` is false) tab of the active [`Window`]'s
+    /// [`Container`].
+    pub fn activate_next_tab(&mut self, forward: bool) -> Option<Window> {
+        let window = self.active_window()?;
+        let next = self
+            .container(&window)?
+            .cycle_from(&window, forward)?
+            .clone();
+        let idx = self.tiles.iter().position(|tile| *tile.window() == next)?;
+        self.active_tile_idx = Some(idx);
+        self.raise_window(&next);
+        // The newly displayed tab takes the place of the previous one.
+        self.arrange_tiles(false);
+        Some(next)
+    }
+
+    /// Get the last layout demand of the `external` layout, if this [`Workspace`] uses it.
+    pub fn layout_demand(&self) -> Option<LayoutDemand> {
+        (self.current_layout() == WorkspaceLayout::External)
+            .then(|| self.external_layout.demand())
+            .flatten()
+    }
+
+    /// Take the layout demand of the `external` layout, if it was not sent to the layout clients
+    /// yet.
+    pub fn take_layout_demand(&mut self) -> Option<LayoutDemand> {
+        if self.current_layout() != WorkspaceLayout::External {
+            return None;
+        }
+        self.external_layout.take_pending_demand()
+    }
+
+    /// Commit the geometries computed by the layout client for the layout demand with `serial`.
+    ///
+    /// The geometries are relative to the work area, in the order of the windows of the demand.
+    pub fn commit_external_layout(
+        &mut self,
+        serial: u32,
+        geometries: Vec<Rectangle<i32, Logical>>,
+        animate: bool,
+    ) -> anyhow::Result<()> {
+        self.external_layout.commit(serial, geometries)?;
+        self.arrange_tiles(animate);
+        Ok(())
+    }
+
+    /// Scroll the view of the columns by `delta` logical pixels.
+    pub fn scroll_view(&mut self, delta: i32, animate: bool) {
+        let work_area = calculate_work_area(&self.output, self.gaps.0);
+        self.columns
+            .scroll(delta, work_area, self.mwfact, self.gaps.1);
+        self.arrange_tiles(animate);
+    }
+
     /// Prepare an unconfigured [`Window`] for insertion in the workspace.
     ///
     /// This function runs the same algorithms that are used inside [`Self::arrange_tiles`] but
@@ -900,15 +1485,23 @@ impl Workspace {
             .active_tile()
             .expect("there should be an active tile here");
         let mut active_idx = None;
+        let mut marked_idx = None;
         let mut tiled_proportions: Vec<_> = self
             .tiles
             .iter()
-            .filter(|tile| tile.window().tiled() && !tile.window().maximized())
+            .filter(|tile| {
+                let window = tile.window();
+                // Hidden tabs share the slot of their container.
+                window.tiled() && !window.maximized() && !self.hidden_in_container(window)
+            })
             .enumerate()
             .map(|(idx, tile)| {
                 if tile.window() == active_tile.window() {
                     active_idx = Some(idx);
                 }
+                if self.insertion_mark.as_ref() == Some(tile.window()) {
+                    marked_idx = Some(idx);
+                }
                 tile.proportion()
             })
             .collect();
@@ -933,6 +1526,11 @@ impl Workspace {
                     active_idx
                 }
             }
+            InsertWindowStrategy::AfterMarked => {
+                let marked_idx = marked_idx.map_or(tiled_proportions.len(), |idx| idx + 1);
+                tiled_proportions.insert(marked_idx, prepared_proportion);
+                marked_idx
+            }
         };
 
         let tiles_len =
@@ -1118,6 +1716,66 @@ impl Workspace {
                     }
                 }
             }
+            WorkspaceLayout::Dwindle => {
+                let geometries =
+                    dwindle_geometries(work_area, tiles_len as usize, mwfact, inner_gaps);
+                let size = geometries[unconfigured_idx].size;
+                unconfigured_window.request_size(Size::from((
+                    size.w - 2 * border_width,
+                    size.h - 2 * border_width,
+                )));
+            }
+            WorkspaceLayout::Monocle => {
+                unconfigured_window.request_size(Size::from((
+                    work_area.size.w - 2 * border_width,
+                    work_area.size.h - 2 * border_width,
+                )));
+            }
+            WorkspaceLayout::Scrolling => {
+                // New windows always get a column of their own.
+                let width = column_width(work_area, mwfact, inner_gaps);
+                unconfigured_window.request_size(Size::from((
+                    width - 2 * border_width,
+                    work_area.size.h - 2 * border_width,
+                )));
+            }
+            WorkspaceLayout::Bsp => {
+                let mut bsp = self.bsp.clone();
+                let container = ContainerId::unique();
+                let target = self.container_id(active_tile.window());
+                bsp.insert(container, target, work_area, inner_gaps);
+                if let Some((_, geo)) = bsp
+                    .geometries(work_area, inner_gaps)
+                    .into_iter()
+                    .find(|(id, _)| *id == container)
+                {
+                    unconfigured_window.request_size(Size::from((
+                        geo.size.w - 2 * border_width,
+                        geo.size.h - 2 * border_width,
+                    )));
+                }
+            }
+            // The layout client only computes the geometry once the window gets inserted.
+            WorkspaceLayout::External => {}
+            WorkspaceLayout::Plugin => {
+                let plugin = self.config.layout_plugin.borrow();
+                let Some(geometries) = plugin.as_ref().and_then(|plugin| {
+                    plugin.arrange(
+                        work_area,
+                        tiles_len as usize,
+                        self.nmaster,
+                        mwfact,
+                        inner_gaps,
+                    )
+                }) else {
+                    return;
+                };
+                let size = geometries[unconfigured_idx].size;
+                unconfigured_window.request_size(Size::from((
+                    size.w - 2 * border_width,
+                    size.h - 2 * border_width,
+                )));
+            }
             WorkspaceLayout::Floating => {}
         }
     }
@@ -1127,12 +1785,58 @@ impl Workspace {
         self.layouts[self.active_layout_idx]
     }
 
+    /// Make the [`Container`]s hold exactly the tiled [`Window`]s.
+    ///
+    /// Windows that are not in a [`Container`] yet get their own.
+    fn sync_containers(&mut self) {
+        let tiled_windows: Vec<_> = self
+            .tiles
+            .iter()
+            .map(Tile::window)
+            .filter(|window| window.tiled())
+            .cloned()
+            .collect();
+        for container in &mut self.containers {
+            container.retain(|window| tiled_windows.contains(window));
+        }
+        self.containers
+            .retain(|container| !container.windows().is_empty());
+        for window in &tiled_windows {
+            if self.container(window).is_none() {
+                self.containers.push(Container::new(window.clone()));
+            }
+        }
+
+        // The tile list order changes when swapping or inserting tiles.
+        self.containers.sort_by_key(|container| {
+            tiled_windows
+                .iter()
+                .position(|window| container.contains(window))
+        });
+        self.activate_active_tab();
+    }
+
     /// Arrange all the [`Tile`]s in this [`Workspace`]
     pub fn arrange_tiles(&mut self, animate: bool) {
         crate::profile_function!();
         let mut output_geometry = self.output.geometry();
         output_geometry.loc = Point::default(); // tile locations are all relative to output
 
+        // Floating tiles remember their geometry when getting fullscreened, maximized or tiled,
+        // and get it back when leaving these states, regardless of what made them leave.
+        for tile in self.tiles.iter_mut().filter(|tile| !tile.window().tiled()) {
+            let window = tile.window().clone();
+            let constrained = window.fullscreen() || window.maximized();
+            match (constrained, window.saved_floating_geometry()) {
+                (true, None) => window.set_saved_floating_geometry(Some(tile.geometry())),
+                (false, Some(geometry)) => {
+                    window.set_saved_floating_geometry(None);
+                    tile.set_geometry(geometry, animate);
+                }
+                _ => (),
+            }
+        }
+
         if let Some(fullscreen_idx) = self.fullscreened_tile_idx {
             // The fullscreen tile should be positionned at (0,0), the origin of the output.
             self.tiles[fullscreen_idx].set_geometry(output_geometry, animate);
@@ -1143,37 +1847,100 @@ impl Workspace {
         }
 
         let (outer_gaps, inner_gaps) = self.gaps;
+        let work_area = calculate_work_area(&self.output, outer_gaps);
 
-        // We distinguish between tiled, maximized, and floating since a floating tile can be
-        // maximized.
-        let mut maximized_tiles = vec![];
-        let mut tiled = vec![];
-
-        for tile in self.tiles.iter_mut() {
-            let window = tile.window();
-            match (window.tiled(), window.maximized()) {
-                (true, false) => tiled.push(tile),
-                // Maximized gets maximized regardless of floating status.
-                (_, true) => maximized_tiles.push(tile),
-                // Otherwise we don't touch floating tiles
-                _ => (),
-            }
+        // Keep the containers, the BSP tree and the columns in sync with the tiled windows, for
+        // example when a window gets removed or stops floating.
+        self.sync_containers();
+        let active_container = self
+            .active_window()
+            .and_then(|window| self.container_id(&window));
+        let container_ids: Vec<_> = self.containers.iter().map(Container::id).collect();
+        self.bsp
+            .sync(&container_ids, active_container, work_area, inner_gaps);
+        self.columns.sync(&container_ids);
+
+        // Each container is laid out as its displayed tab, that leaves room for the title bars.
+        let displayed_windows: Vec<_> = self
+            .containers
+            .iter()
+            .filter_map(|container| Some((container.active_window()?.clone(), container.id())))
+            .collect();
+        let title_bar_heights: Vec<_> = self
+            .tiles
+            .iter()
+            .map(|tile| {
+                self.title_bar_container(tile.window())
+                    .map_or(0, Container::title_bar_height)
+            })
+            .collect();
+        for (tile, height) in self.tiles.iter_mut().zip(title_bar_heights) {
+            tile.set_title_bar_height(height);
         }
 
+        // We distinguish between tiled, maximized, and floating since a floating tile can be
+        // maximized. Maximized gets maximized regardless of floating status, otherwise we don't
+        // touch floating tiles.
         let layout = self.current_layout();
-        let (maximized, tiles) = self
+        let (maximized, mut tiles) = self
             .tiles
             .iter_mut()
             // We do not want to affect the fullscreened tile
-            .filter(|tile| tile.window().tiled() && !tile.window().fullscreen())
+            .filter(|tile| {
+                let window = tile.window();
+                (window.maximized()
+                    || displayed_windows
+                        .iter()
+                        .any(|(displayed, _)| displayed == window))
+                    && !window.fullscreen()
+            })
             .partition::<Vec<_>, _>(|tile| tile.window().maximized());
-        let work_area = calculate_work_area(&self.output, outer_gaps);
+        // The layouts arrange the containers in order.
+        let container_of = |tile: &Tile| {
+            displayed_windows
+                .iter()
+                .find_map(|(displayed, id)| (displayed == tile.window()).then_some(*id))
+        };
+        tiles.sort_by_key(|tile| {
+            displayed_windows
+                .iter()
+                .position(|(displayed, _)| displayed == tile.window())
+        });
 
         for tile in maximized {
             // Maximized tiles get all the work area, while the tiled abide to layout algo.
             tile.set_geometry(work_area, animate);
         }
 
+        // The external layout falls back to the tile layout until the layout client commits
+        // geometries for these windows.
+        let external_geometries = if layout == WorkspaceLayout::External {
+            let windows: Vec<_> = tiles.iter().map(|tile| tile.window().clone()).collect();
+            self.external_layout.geometries(&windows, work_area)
+        } else {
+            None
+        };
+        let layout = match (layout, &external_geometries) {
+            (WorkspaceLayout::External, None) => WorkspaceLayout::Tile,
+            (layout, _) => layout,
+        };
+        // Same for the plugin layout, when the plugin is not loaded or refuses to arrange.
+        let plugin_geometries = if layout == WorkspaceLayout::Plugin {
+            self.config
+                .layout_plugin
+                .borrow()
+                .as_ref()
+                .and_then(|plugin| {
+                    plugin.arrange(work_area, tiles.len(), self.nmaster, mwfact, inner_gaps)
+                })
+        } else {
+            None
+        };
+        let layout = match (layout, &plugin_geometries) {
+            (WorkspaceLayout::Plugin, None) => WorkspaceLayout::Tile,
+            (layout, _) => layout,
+        };
+
         let tiles_len = i32::try_from(tiles.len()).expect("tiles.len() overflow");
         let mwfact = self.mwfact;
 
@@ -1379,6 +2146,80 @@ impl Workspace {
                     right_geo.loc.y += height + inner_gaps;
                 }
             }
+            WorkspaceLayout::Dwindle => {
+                let geometries = dwindle_geometries(work_area, tiles.len(), mwfact, inner_gaps);
+                for (idx, (tile, geo)) in tiles.into_iter().zip(geometries).enumerate() {
+                    if Some(idx) == self.fullscreened_tile_idx {
+                        // Don't affect the fullscreened tile.
+                        continue;
+                    }
+                    tile.set_geometry(geo, animate);
+                }
+            }
+            WorkspaceLayout::Monocle => {
+                for (idx, tile) in tiles.into_iter().enumerate() {
+                    if Some(idx) == self.fullscreened_tile_idx {
+                        // Don't affect the fullscreened tile.
+                        continue;
+                    }
+                    tile.set_geometry(work_area, animate);
+                }
+            }
+            WorkspaceLayout::Scrolling => {
+                let proportions: Vec<_> = tiles
+                    .iter()
+                    .filter_map(|tile| Some((container_of(tile)?, tile.proportion())))
+                    .collect();
+                let heights = |column: &[ContainerId], height: i32| {
+                    let column_proportions: Vec<_> = column
+                        .iter()
+                        .map(|container| {
+                            proportions
+                                .iter()
+                                .find_map(|(c, proportion)| (c == container).then_some(*proportion))
+                                .unwrap_or(1.0)
+                        })
+                        .collect();
+                    let gaps = (column.len() as i32 - 1).max(0) * inner_gaps;
+                    proportion_length(&column_proportions, height - gaps)
+                };
+                let geometries = self.columns.geometries(
+                    work_area,
+                    mwfact,
+                    inner_gaps,
+                    active_container,
+                    heights,
+                );
+                for tile in tiles {
+                    let container = container_of(tile);
+                    if let Some((_, geo)) = geometries.iter().find(|(id, _)| Some(*id) == container)
+                    {
+                        tile.set_geometry(*geo, animate);
+                    }
+                }
+            }
+            WorkspaceLayout::Bsp => {
+                let geometries = self.bsp.geometries(work_area, inner_gaps);
+                for tile in tiles {
+                    let container = container_of(tile);
+                    if let Some((_, geo)) = geometries.iter().find(|(id, _)| Some(*id) == container)
+                    {
+                        tile.set_geometry(*geo, animate);
+                    }
+                }
+            }
+            WorkspaceLayout::External => {
+                let geometries = external_geometries.unwrap_or_default();
+                for (tile, (_, geo)) in tiles.into_iter().zip(geometries) {
+                    tile.set_geometry(geo, animate);
+                }
+            }
+            WorkspaceLayout::Plugin => {
+                let geometries = plugin_geometries.unwrap_or_default();
+                for (tile, geo) in tiles.into_iter().zip(geometries) {
+                    tile.set_geometry(geo, animate);
+                }
+            }
             WorkspaceLayout::Floating => {}
         }
     }
@@ -1475,6 +2316,7 @@ impl Workspace {
                 self.swap_tiles(window, &other_window, true);
                 if let Some(idx) = self.tiles.iter().position(|tile| tile.window() == window) {
                     self.set_active_tile_idx(idx);
+                    self.raise_window(window);
                 }
             } else {
                 // We still run the arrange tiles function in order to get the swapped/grabbed
@@ -1518,6 +2360,41 @@ impl Workspace {
         true
     }
 
+    /// Pan a floating [`Window`] up so that its bottom edge fits inside the work area.
+    ///
+    /// Returns by how much the window got moved up, if it was.
+    pub fn pan_floating_window_into_work_area(&mut self, window: &Window) -> Option<i32> {
+        let work_area = calculate_work_area(&self.output, 0);
+        let tile = self
+            .tiles
+            .iter_mut()
+            .find(|tile| tile.window() == window && !tile.window().tiled())?;
+        let tile_geo = tile.geometry();
+
+        let overflow = (tile_geo.loc.y + tile_geo.size.h) - (work_area.loc.y + work_area.size.h);
+        // Never move the window above the work area.
+        let offset = overflow.min(tile_geo.loc.y - work_area.loc.y);
+        if offset <= 0 {
+            return None;
+        }
+
+        tile.set_location(tile_geo.loc - Point::from((0, offset)), true);
+        Some(offset)
+    }
+
+    /// Move back down a floating [`Window`] that was panned with
+    /// [`Workspace::pan_floating_window_into_work_area`].
+    pub fn unpan_floating_window(&mut self, window: &Window, offset: i32) {
+        if let Some(tile) = self
+            .tiles
+            .iter_mut()
+            .find(|tile| tile.window() == window && !tile.window().tiled())
+        {
+            let tile_loc = tile.geometry().loc;
+            tile.set_location(tile_loc + Point::from((0, offset)), true);
+        }
+    }
+
     /// Start an interactive resize grab.
     ///
     /// Returns [`true`] if the grab was successfully registered.
@@ -1605,6 +2482,16 @@ impl Workspace {
             new_size.h += dy;
         }
 
+        if let Some(increments) = window.size_increments() {
+            // Snap relative to the initial size, to keep whatever padding the client has.
+            let initial_size = interactive_resize.initial_window_geometry.size;
+            let snap = |new: i32, initial: i32, increment: i32| {
+                initial + ((new - initial) as f64 / increment as f64).round() as i32 * increment
+            };
+            new_size.w = snap(new_size.w, initial_size.w, increments.w);
+            new_size.h = snap(new_size.h, initial_size.h, increments.h);
+        }
+
         window.request_size(new_size);
 
         true
@@ -1636,6 +2523,38 @@ impl Workspace {
         self.render_offset.is_some()
     }
 
+    /// Whether this [`Workspace`] has a running workspace switch animation.
+    pub fn has_switch_animation(&self) -> bool {
+        self.render_offset.is_some() || self.staggered_switch.is_some()
+    }
+
+    /// Start a staggered workspace switch animation.
+    ///
+    /// When `entering`, the tiles will fade in and slide from `offset`, otherwise they will fade
+    /// out and slide towards the opposite of `offset`.
+    pub fn start_staggered_switch_animation(
+        &mut self,
+        entering: bool,
+        offset: Point<i32, Logical>,
+        delay: Duration,
+        animation_config: &super::AnimationConfig,
+    ) {
+        let _ = self.render_offset.take();
+        let tiles = self
+            .stacked_tiles()
+            .map(|tile| (tile.window().clone(), None))
+            .collect();
+        self.staggered_switch = Some(StaggeredSwitch {
+            entering,
+            offset,
+            started_at: None,
+            delay,
+            duration: animation_config.duration,
+            curve: animation_config.curve,
+            tiles,
+        });
+    }
+
     /// Start a render offset animation
     pub fn start_render_offset_animation(
         &mut self,
@@ -1659,7 +2578,13 @@ impl Workspace {
     }
 
     /// Advance animations for this [`Workspace`]
-    pub fn advance_animations(&mut self, target_presentation_time: Duration) -> bool {
+    ///
+    /// `focused_window` is the window with the keyboard focus, if any.
+    pub fn advance_animations(
+        &mut self,
+        target_presentation_time: Duration,
+        focused_window: Option<&Window>,
+    ) -> bool {
         crate::profile_function!();
         let mut running = false;
 
@@ -1669,6 +2594,12 @@ impl Workspace {
             running = true;
         }
 
+        let _ = self.staggered_switch.take_if(|s| s.is_finished());
+        if let Some(staggered_switch) = &mut self.staggered_switch {
+            staggered_switch.tick(target_presentation_time);
+            running = true;
+        }
+
         let _ = self
             .fullscreen_fade_animation
             .take_if(|(_, a)| a.is_finished());
@@ -1678,6 +2609,7 @@ impl Workspace {
         }
 
         for tile in &mut self.tiles {
+            tile.set_active(focused_window == Some(tile.window()));
             running |= tile.advance_animations(target_presentation_time);
         }
 
@@ -1783,42 +2715,45 @@ impl Workspace {
             elements.push(element);
         }
 
-        if let Some(tile) = self.active_tile() {
-            let alpha = if self.active_tile_idx == skip_alpha_animation_idx {
-                1.0
-            } else {
-                alpha
-            };
-
-            // Active gets rendered above others.
-            elements.extend(
-                tile.render(renderer, scale, alpha, &self.output, render_offset, true)
-                    .map(|element| {
-                        RelocateRenderElement::from_element(
-                            element,
-                            render_offset_physical,
-                            Relocate::Relative,
-                        )
-                        .into()
-                    }),
-            );
-        }
-
-        // Now render others, just fine.
-        for (idx, tile) in self.tiles.iter().enumerate() {
-            // NOTE: active_tile_idx is always fullscreen_tile_idx, ensured by Workspace::refresh
-            if Some(idx) == self.active_tile_idx {
-                continue; // active tile has already been rendered.
-            }
-
+        // Render tiles in stacking order, from top to bottom.
+        for tile in self.stacked_tiles() {
+            let idx = self
+                .tiles
+                .iter()
+                .position(|t| t.window() == tile.window())
+                .unwrap();
             let alpha = if Some(idx) == skip_alpha_animation_idx {
                 1.0
             } else {
                 alpha
             };
+            let active = Some(idx) == self.active_tile_idx;
+            let (alpha, render_offset) = match &self.staggered_switch {
+                Some(staggered_switch) => {
+                    let (tile_alpha, tile_offset) = staggered_switch.tile_state(tile.window());
+                    (alpha * tile_alpha, render_offset + tile_offset)
+                }
+                None => (alpha, render_offset),
+            };
+            if alpha <= 0.0 {
+                continue;
+            }
+            let render_offset_physical = render_offset.to_physical_precise_round(scale);
+
+            if let Some(container) = self.title_bar_container(tile.window()) {
+                let mut geometry = tile.visual_geometry();
+                geometry.size.h = container.title_bar_height();
+                geometry.loc.y -= geometry.size.h;
+                geometry.loc += render_offset;
+                elements.extend(
+                    container
+                        .render_title_bars(renderer, &self.output, scale, alpha, geometry)
+                        .map(WorkspaceRenderElement::TitleBars),
+                );
+            }
 
             elements.extend(
-                tile.render(renderer, scale, alpha, &self.output, render_offset, false)
+                tile.render(renderer, scale, alpha, &self.output, render_offset, active)
                     .map(|element| {
                         RelocateRenderElement::from_element(
                             element,
@@ -1838,16 +2773,68 @@ fht_render_elements! {
     WorkspaceRenderElement<R> => {
         Tile = RelocateRenderElement<TileRenderElement<R>>,
         ClosingTile = RelocateRenderElement<ClosingTileRenderElement>,
+        TitleBars = EguiRenderElement,
     }
 }
 
 fn calculate_work_area(output: &Output, outer_gaps: i32) -> Rectangle<i32, Logical> {
-    let mut work_area = layer_map_for_output(output).non_exclusive_zone();
+    let layer_map = layer_map_for_output(output);
+    let mut work_area = layer_map.non_exclusive_zone();
+    // On-screen keyboards don't always set an exclusive zone, but they should still not cover
+    // windows. Keyboards with an exclusive zone are already outside of the work area.
+    for layer in layer_map
+        .layers()
+        .filter(|layer| ResolvedLayerRules::get(layer).on_screen_keyboard)
+    {
+        let Some(layer_geo) = layer_map.layer_geometry(layer) else {
+            continue;
+        };
+        let work_area_bottom = work_area.loc.y + work_area.size.h;
+        if layer_geo.loc.y > work_area.loc.y && layer_geo.loc.y < work_area_bottom {
+            work_area.size.h = layer_geo.loc.y - work_area.loc.y;
+        }
+    }
+
     work_area.loc += Point::from((outer_gaps, outer_gaps));
     work_area.size -= Size::from((outer_gaps, outer_gaps)).upscale(2);
     work_area
 }
 
+/// Compute the geometries of `count` tiles using the dwindle layout.
+///
+/// Each tile takes a part of the area left by the previous ones, splitting it alternatively
+/// vertically and horizontally, and the last tile takes what remains. The first split uses
+/// `mwfact`, the next ones split the area in half.
+fn dwindle_geometries(
+    mut area: Rectangle<i32, Logical>,
+    count: usize,
+    mwfact: f64,
+    inner_gaps: i32,
+) -> Vec<Rectangle<i32, Logical>> {
+    let mut geometries = Vec::with_capacity(count);
+    for idx in 0..count {
+        if idx == count - 1 {
+            geometries.push(area);
+            break;
+        }
+
+        let fact = if idx == 0 { mwfact } else { 0.5 };
+        let mut geo = area;
+        if idx % 2 == 0 {
+            geo.size.w = (f64::from(area.size.w - inner_gaps) * fact).round() as i32;
+            area.loc.x += geo.size.w + inner_gaps;
+            area.size.w -= geo.size.w + inner_gaps;
+        } else {
+            geo.size.h = (f64::from(area.size.h - inner_gaps) * fact).round() as i32;
+            area.loc.y += geo.size.h + inner_gaps;
+            area.size.h -= geo.size.h + inner_gaps;
+        }
+        geometries.push(geo);
+    }
+
+    geometries
+}
+
 /// Proportion a given length with given proportions.
 ///
 /// This function ensures that the the returned lengths' sum is equal to `length`
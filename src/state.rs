@@ -1,10 +1,13 @@
 use std::cell::RefCell;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::os::unix::net::UnixStream;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::Context;
-use fht_compositor_config::{BlurOverrides, BorderOverrides, DecorationMode, ShadowOverrides};
+use fht_compositor_config::{
+    BlurOverrides, BorderOverrides, DecorationMode, ShadowOverrides, VrrMode,
+};
 use smithay::backend::renderer::element::utils::select_dmabuf_feedback;
 use smithay::backend::renderer::element::{
     default_primary_scanout_output_compare, PrimaryScanoutOutput, RenderElementStates,
@@ -16,23 +19,27 @@ use smithay::desktop::utils::{
     update_surface_primary_scanout_output, OutputPresentationFeedback,
 };
 use smithay::desktop::{
-    layer_map_for_output, LayerSurface, PopupKind, PopupManager, WindowSurfaceType,
+    find_popup_root_surface, layer_map_for_output, LayerSurface, PopupKind, PopupManager,
+    WindowSurfaceType,
 };
-use smithay::input::keyboard::{KeyboardHandle, Keysym, XkbConfig};
-use smithay::input::pointer::{CursorImageStatus, PointerHandle};
+use smithay::input::keyboard::{Keysym, Layout, XkbConfig};
+use smithay::input::pointer::CursorImageStatus;
 use smithay::input::{Seat, SeatState};
 use smithay::output::Output;
+use smithay::reexports::calloop::timer::{TimeoutAction, Timer};
 use smithay::reexports::calloop::{LoopHandle, LoopSignal, RegistrationToken};
 use smithay::reexports::input::{self, DeviceCapability, SendEventsMode};
-use smithay::reexports::wayland_server::backend::ClientData;
+use smithay::reexports::wayland_protocols::wp::content_type::v1::server::wp_content_type_v1::Type as ContentType;
+use smithay::reexports::wayland_server::backend::{ClientData, ObjectId};
+use smithay::reexports::wayland_server::protocol::wl_data_source::WlDataSource;
 use smithay::reexports::wayland_server::protocol::wl_shm;
 use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
-use smithay::reexports::wayland_server::DisplayHandle;
+use smithay::reexports::wayland_server::{Client, DisplayHandle, Resource};
 use smithay::utils::{Clock, IsAlive, Logical, Monotonic, Point, Rectangle};
 use smithay::wayland::alpha_modifier::AlphaModifierState;
 use smithay::wayland::compositor::{
-    with_states, with_surface_tree_downward, CompositorClientState, CompositorState, SurfaceData,
-    TraversalAction,
+    with_states, with_surface_tree_downward, CompositorClientState, CompositorHandler,
+    CompositorState, SurfaceData, TraversalAction,
 };
 use smithay::wayland::content_type::ContentTypeState;
 use smithay::wayland::cursor_shape::CursorShapeManagerState;
@@ -45,13 +52,14 @@ use smithay::wayland::input_method::InputMethodManagerState;
 use smithay::wayland::keyboard_shortcuts_inhibit::KeyboardShortcutsInhibitState;
 use smithay::wayland::output::OutputManagerState;
 use smithay::wayland::pointer_constraints::PointerConstraintsState;
+use smithay::wayland::pointer_gestures::PointerGesturesState;
 use smithay::wayland::presentation::PresentationState;
 use smithay::wayland::relative_pointer::RelativePointerManagerState;
 use smithay::wayland::seat::WaylandFocus;
 use smithay::wayland::security_context::{SecurityContext, SecurityContextState};
 use smithay::wayland::selection::data_device::DataDeviceState;
 use smithay::wayland::selection::primary_selection::PrimarySelectionState;
-use smithay::wayland::selection::wlr_data_control::DataControlState;
+use smithay::wayland::selection::{ext_data_control, wlr_data_control};
 use smithay::wayland::session_lock::{LockSurface, SessionLockManagerState};
 use smithay::wayland::shell::wlr_layer::{Layer, WlrLayerShellState};
 use smithay::wayland::shell::xdg::decoration::XdgDecorationState;
@@ -60,32 +68,46 @@ use smithay::wayland::shell::xdg::{PopupSurface, XdgShellState};
 use smithay::wayland::shm::ShmState;
 use smithay::wayland::single_pixel_buffer::SinglePixelBufferState;
 use smithay::wayland::tablet_manager::TabletManagerState;
-use smithay::wayland::text_input::TextInputManagerState;
+use smithay::wayland::text_input::{TextInputManagerState, TextInputSeat};
 use smithay::wayland::viewporter::ViewporterState;
 use smithay::wayland::virtual_keyboard::VirtualKeyboardManagerState;
-use smithay::wayland::xdg_activation::XdgActivationState;
+use smithay::wayland::xdg_activation::{XdgActivationState, XdgActivationToken};
 use smithay::wayland::xdg_foreign::XdgForeignState;
 
 use crate::backend::Backend;
 use crate::cli;
 use crate::config::ui as config_ui;
 use crate::cursor::CursorThemeManager;
-use crate::focus_target::{KeyboardFocusTarget, PointerFocusTarget};
+use crate::fade::Fade;
+use crate::focus_target::{FocusRestoreStack, KeyboardFocusTarget, PointerFocusTarget};
 use crate::frame_clock::FrameClock;
 use crate::handlers::session_lock::LockState;
+use crate::handlers::virtual_keyboard::VirtualKeyboardKeymap;
+use crate::handlers::xdg_activation::{ActivationTokenContext, ACTIVATION_TIMEOUT};
+use crate::osd::Osd;
 use crate::output::{self, OutputExt, RedrawState};
 #[cfg(feature = "xdg-screencast-portal")]
 use crate::portals::screencast::{
     self, CursorMode, ScreencastSession, ScreencastSource, StreamMetadata,
 };
+use crate::protocols::commit_timing::CommitTimingManagerState;
+use crate::protocols::fifo::{clear_fifo_barrier, FifoManagerState};
+use crate::protocols::kde_blur::KdeBlurManagerState;
 use crate::protocols::output_management::OutputManagementManagerState;
 use crate::protocols::screencopy::ScreencopyManagerState;
+use crate::protocols::tearing_control::{surface_prefers_tearing, TearingControlManagerState};
+use crate::protocols::toplevel_drag::ToplevelDragManagerState;
+use crate::protocols::virtual_pointer::VirtualPointerManagerState;
 use crate::renderer::blur::EffectsFramebuffers;
+use crate::renderer::debug::DebugOverlay;
+use crate::renderer::OffscreenBuffer;
+use crate::session::Session;
 use crate::space::{Space, WorkspaceId};
 #[cfg(feature = "xdg-screencast-portal")]
 use crate::utils::pipewire::{CastId, CastSource, PipeWire, PwToCompositor};
-use crate::utils::RectCenterExt;
+use crate::utils::{get_monotonic_time, LaunchSnapshot, RectCenterExt};
 use crate::window::Window;
+use crate::zoom::Zoom;
 
 pub struct State {
     pub fht: Fht,
@@ -99,10 +121,10 @@ impl State {
         loop_signal: LoopSignal,
         config_path: Option<std::path::PathBuf>,
         backend: Option<crate::cli::BackendType>,
-        _socket_name: String,
+        socket_name: String,
     ) -> Self {
         #[allow(unused)]
-        let mut fht = Fht::new(dh, loop_handle, loop_signal, config_path);
+        let mut fht = Fht::new(dh, loop_handle, loop_signal, config_path, &socket_name);
         #[allow(unused)]
         let backend: crate::backend::Backend = if let Some(backend_type) = backend {
             match backend_type {
@@ -114,6 +136,10 @@ impl State {
                 cli::BackendType::Udev => crate::backend::udev::UdevData::new(&mut fht)
                     .unwrap()
                     .into(),
+                #[cfg(feature = "headless-backend")]
+                cli::BackendType::Headless => crate::backend::headless::HeadlessData::new(&mut fht)
+                    .unwrap()
+                    .into(),
             }
         } else if std::env::var("DISPLAY").is_ok() || std::env::var("WAYLAND_DISPLAY").is_ok() {
             info!("Detected (WAYLAND_)DISPLAY. Running in nested Winit window");
@@ -146,6 +172,13 @@ impl State {
         self.fht.space.refresh();
         self.fht.popups.cleanup();
         self.fht.refresh_idle_inhibit();
+        self.fht.refresh_on_screen_keyboard();
+        self.fht.refresh_workspace_switches();
+        self.fht.refresh_ipc_workspace_occupancy();
+        self.fht.refresh_ipc_layout_demands();
+        #[cfg(feature = "accessibility")]
+        self.fht.refresh_a11y_focus();
+        self.fht.refresh_toplevel_drag();
         self.fht.resolve_rules_for_all_windows_if_needed();
 
         {
@@ -169,9 +202,19 @@ impl State {
                 }
             }
 
+            outputs_to_redraw.retain(|output| !self.fht.delay_redraw_until_render_deadline(output));
+
+            // Windows can move below the pointer while redrawing (layout changes and animations),
+            // pointer constraints must follow them.
+            let refresh_pointer_constraints = !outputs_to_redraw.is_empty();
             for output in outputs_to_redraw {
                 self.redraw(output);
             }
+
+            if refresh_pointer_constraints && !locked {
+                let seat = self.fht.seat.clone();
+                self.refresh_pointer_constraints(&seat);
+            }
         };
         self.fht.lock_state = match std::mem::take(&mut self.fht.lock_state) {
             // Switch from pending to locked when we finished drawing a backdrop at least once.
@@ -186,6 +229,7 @@ impl State {
                 }) =>
             {
                 locker.lock();
+                self.fht.fade_in_outputs();
                 LockState::Locked
             }
             state => state,
@@ -195,6 +239,8 @@ impl State {
             crate::profile_scope!("refresh_focus");
             // Make sure the surface is not dead (otherwise wayland wont be happy)
             // NOTE: focus_target from state is always guaranteed to be the same as keyboard focus.
+            let seat = self.fht.seat.clone();
+            let keyboard = seat.get_keyboard().unwrap();
             if self.fht.is_locked() {
                 // If we are locked, locked surface of active output gets precedence before
                 // everything. This also includes pointer focus too.
@@ -205,25 +251,31 @@ impl State {
                 if let Some(lock_surface) = output_state.lock_surface.clone() {
                     // Focus new surface if its different to avoid spamming wl_keyboard::enter event
                     let new_focus = KeyboardFocusTarget::LockSurface(lock_surface);
-                    if self.fht.keyboard.current_focus().as_ref() != Some(&new_focus) {
-                        self.set_keyboard_focus(Some(new_focus));
+                    if keyboard.current_focus().as_ref() != Some(&new_focus) {
+                        self.set_keyboard_focus(&seat, Some(new_focus));
                     }
                 } else {
                     // We do not have a lock surface on active output, default to not focusing
                     // anything.
-                    self.set_keyboard_focus(Option::<LockSurface>::None);
+                    self.set_keyboard_focus(&seat, Option::<LockSurface>::None);
                 }
             } else {
-                // We are focusing nothing, default to the active workspace focused window.
-                let old_focus_dead = self
-                    .fht
-                    .keyboard
-                    .current_focus()
-                    .is_some_and(|ft| !ft.alive());
-                {
-                    if old_focus_dead {
-                        self.set_keyboard_focus(self.fht.space.active_window());
+                // We are focusing nothing, restore the focus from before an exclusive layer surface
+                // took it, or default to the active workspace focused window.
+                let current_focus = keyboard.current_focus();
+                let old_focus_gone = current_focus.is_some_and(|ft| match ft {
+                    KeyboardFocusTarget::LayerSurface(layer) => {
+                        !layer.alive() || !self.fht.is_layer_mapped(&layer)
                     }
+                    ft => !ft.alive(),
+                });
+                if old_focus_gone {
+                    let new_focus = self
+                        .fht
+                        .focus_restore_stack
+                        .pop()
+                        .or_else(|| self.fht.space.active_window().map(Into::into));
+                    self.set_keyboard_focus(&seat, new_focus);
                 }
             }
         }
@@ -252,21 +304,45 @@ impl State {
         let output_state = self.fht.output_state.get_mut(&output).unwrap();
         assert!(output_state.redraw_state.is_queued());
 
+        let frame_start = Instant::now();
+        let frame_start_time = get_monotonic_time();
+
         // Advance animations.
         let target_presentation_time = output_state.frame_clock.next_presentation_time();
         let animations_running = {
             crate::profile_scope!("advance_animations");
-            let mut ongoing = self.fht.config_ui.advance_animations(
-                target_presentation_time,
-                !self.fht.config.animations.disable,
-            );
+            // The config UI is only drawn on one output, only tick it with the presentation times
+            // of that output, otherwise outputs with different refresh rates would make it advance
+            // at a mismatched cadence.
+            let mut ongoing = false;
+            let config_ui_output = self.fht.config_ui_output.as_ref();
+            if config_ui_output.is_none_or(|config_ui_output| *config_ui_output == output) {
+                ongoing |= self.fht.config_ui.advance_animations(
+                    target_presentation_time,
+                    !self.fht.config.animations.disable,
+                );
+            }
 
+            let focused_window = self.fht.keyboard_focused_window(&self.fht.seat);
             let monitor = self
                 .fht
                 .space
                 .monitor_mut_for_output(&output)
                 .expect("all outputs should be tracked by Space");
-            ongoing |= monitor.advance_animations(target_presentation_time);
+            ongoing |=
+                monitor.advance_animations(target_presentation_time, focused_window.as_ref());
+
+            let output_state = self.fht.output_state.get_mut(&output).unwrap();
+            ongoing |= output_state.osd.advance_animations(
+                target_presentation_time,
+                !self.fht.config.animations.disable,
+            );
+            ongoing |= output_state
+                .zoom
+                .advance_animations(target_presentation_time);
+            ongoing |= output_state
+                .fade
+                .advance_animations(target_presentation_time);
 
             ongoing
         };
@@ -278,12 +354,31 @@ impl State {
         // if res.is_err() == something wrong happened and we didnt render anything.
         // if res == Ok(true) we rendered and submitted a new buffer
         // if res == Ok(false) we rendered but had no damage to submit
+        let render_start = Instant::now();
         let res = self
             .backend
             .render(&mut self.fht, &output, target_presentation_time);
+        let render_time = render_start.elapsed();
+        let submitted = matches!(res, Ok(true));
+
+        if let Some(capture) = self.fht.frame_timings_capture.as_mut() {
+            capture.record(
+                &output.name(),
+                frame_start_time,
+                render_start - frame_start,
+                render_time,
+                submitted,
+                target_presentation_time,
+            );
+        }
 
         {
             let output_state = self.fht.output_state.get_mut(&output).unwrap();
+            if self.fht.debug_overlay {
+                output_state
+                    .debug_overlay
+                    .record_frame(render_time, submitted);
+            }
             if res.is_err() {
                 // Update the redraw state on failed render.
                 output_state.redraw_state =
@@ -345,7 +440,7 @@ impl State {
         // Some invariants must be upheld when reloading the configuration
         // If any reloading function errors out, the configuration is not valid
 
-        let keyboard = self.fht.keyboard.clone();
+        let keyboard = self.fht.seat.get_keyboard().unwrap();
         if let Err(err) = keyboard.set_xkb_config(self, config.input.keyboard.xkb_config()) {
             error!(?err, "Failed to apply configuration");
             return;
@@ -371,6 +466,25 @@ impl State {
             self.fht.reload_output_config();
         }
 
+        if old_config.backend.render_node != self.fht.config.backend.render_node {
+            if let Some(path) = self.fht.config.backend.render_node.clone() {
+                if let Err(err) = self.backend.set_render_node(&mut self.fht, &path) {
+                    error!(?err, "Failed to switch render node");
+                }
+            }
+        }
+
+        let (old_debug, new_debug) = (&old_config.debug, &self.fht.config.debug);
+        if old_debug.disallowed_dmabuf_formats != new_debug.disallowed_dmabuf_formats
+            || old_debug.disallowed_dmabuf_modifiers != new_debug.disallowed_dmabuf_modifiers
+            || old_debug.disable_ccs_modifiers != new_debug.disable_ccs_modifiers
+        {
+            self.backend.reload_dmabuf_formats(&mut self.fht);
+        }
+        if old_debug.debug_overlay != new_debug.debug_overlay {
+            self.fht.debug_overlay = new_debug.debug_overlay;
+        }
+
         // These devices are just handles, so cleaning the devices vector and adding them all
         // back should not be an issue. (input device configuration code in inside
         // add_libinput_device function)
@@ -379,6 +493,12 @@ impl State {
             self.fht.add_libinput_device(device);
         }
 
+        // Window rules might have changed, resolve them again for existing windows.
+        for window in self.fht.space.windows() {
+            window.set_need_to_resolve_rules();
+        }
+        self.fht.apply_retroactive_window_rules();
+
         // For layer shell rules, we only recompute them on layer-shell commit. Some layer shells
         // just don't commit (for example your wallpaper). so we must refresh them at least once
         // here.
@@ -540,6 +660,10 @@ impl State {
             let render_formats = self
                 .backend
                 .with_renderer(|renderer| renderer.egl_context().dmabuf_render_formats().clone());
+            let render_formats = crate::renderer::dmabuf::screencast_formats(
+                &render_formats,
+                &self.fht.config.debug,
+            );
 
             let (to_compositor, from_pw) = calloop::channel::channel();
             let token = self
@@ -603,6 +727,9 @@ impl State {
     }
 }
 
+/// How many launch snapshots we keep around, see [`Fht::spawn`].
+const MAX_LAUNCH_SNAPSHOTS: usize = 64;
+
 pub struct Fht {
     pub display_handle: DisplayHandle,
     pub loop_handle: LoopHandle<'static, State>,
@@ -610,25 +737,67 @@ pub struct Fht {
     pub stop: bool,
 
     pub seat_state: SeatState<State>,
+    /// The seat driven by our input devices.
+    ///
+    /// Input handling gets the seat it handles passed down instead of using this one, so that
+    /// it works the same with any seat.
     pub seat: Seat<State>,
-    pub keyboard: KeyboardHandle<State>,
-    pub pointer: PointerHandle<State>,
+    // Additional seats without any input device, see `debug.extra-test-seat`.
+    pub extra_seats: Vec<Seat<State>>,
     pub clock: Clock<Monotonic>,
     pub suppressed_keys: HashSet<Keysym>,
     // We store both the timer and the keysym used to trigger the key action.
     // When we remove the keysym from suppressed keys we stop it.
     pub repeated_keyaction_timer: Option<(RegistrationToken, Keysym)>,
+    // The keymaps of virtual keyboards, to look up key bindings of injected keys.
+    pub virtual_keyboards: HashMap<ObjectId, VirtualKeyboardKeymap>,
 
     pub devices: Vec<input::Device>,
+    // Time of the last pointer motion event, in microseconds, used for acceleration curves.
+    pub last_pointer_motion_time: Option<u64>,
+    // Leftover high-resolution scroll (horizontal, vertical) that didn't make a full wheel step.
+    pub discrete_scroll_remainder: [f64; 2],
+    // Mouse keys state, when enabled.
+    pub mouse_keys: Option<crate::input::mouse_keys::MouseKeysState>,
+    // The output tablets got mapped to with the cycle-tablet-output key action, overriding the
+    // configured one.
+    pub tablet_output: Option<String>,
+    // The keyboard layout that was active before a window rule changed it.
+    pub keyboard_layout_to_restore: Option<Layout>,
+    // Whether the debug overlay is drawn on the outputs, see `debug.debug-overlay`.
+    pub debug_overlay: bool,
+    // The ongoing frame timings capture, if any.
+    pub frame_timings_capture: Option<crate::profiling::FrameTimingsCapture>,
 
     pub dnd_icon: Option<WlSurface>,
+    // The data source of the ongoing drag-and-drop operation, if any.
+    pub dnd_source: Option<WlDataSource>,
+    // The window attached to the ongoing drag-and-drop operation with xdg-toplevel-drag.
+    pub dragged_window: Option<Window>,
     pub cursor_theme_manager: CursorThemeManager,
     pub space: Space,
     pub unmapped_windows: Vec<UnmappedWindow>,
+    // Snapshots of the last commands we spawned, so we can know which window came from where.
+    pub launch_snapshots: VecDeque<Arc<LaunchSnapshot>>,
+    // Window placements of the previous compositor instance, see src/session.rs
+    pub session: Session,
     pub popups: PopupManager,
     pub root_surfaces: HashMap<WlSurface, WlSurface>,
     pub idle_inhibiting_surfaces: Vec<WlSurface>,
+    // Whether the keyboard focus supports text input, to show/hide on-screen keyboards.
+    pub text_input_focused: bool,
+    // The floating window we moved up to not get covered by an on-screen keyboard, and by how much.
+    pub osk_panned_window: Option<(Window, i32)>,
+    // The focus targets to give back the keyboard focus to once exclusive layer surfaces go away.
+    pub focus_restore_stack: FocusRestoreStack,
+    // Clients that sent ipc::Request::EventStream.
+    pub ipc_event_streams: Vec<UnixStream>,
+    // The workspace occupancy last sent to the IPC event streams.
+    pub ipc_workspace_occupancy: Vec<crate::ipc::WorkspaceOccupancy>,
     pub lock_state: LockState,
+    pub region_selection: Option<crate::screenshot::RegionSelection>,
+    // Shown while moving a window over the top edge of another output.
+    pub workspace_drop_strip: Option<crate::workspace_drop_strip::WorkspaceDropStrip>,
 
     pub output_state: HashMap<Output, output::OutputState>,
     // Keep track whether we did some transient output changes.
@@ -657,6 +826,8 @@ pub struct Fht {
 
     #[cfg(feature = "dbus")]
     pub dbus_connection: Option<zbus::blocking::Connection>,
+    #[cfg(feature = "accessibility")]
+    pub a11y: Option<crate::a11y::A11yBridge>,
 
     #[cfg(feature = "xdg-screencast-portal")]
     pub pipewire_initialised: std::sync::Once,
@@ -664,7 +835,8 @@ pub struct Fht {
     pub pipewire: Option<PipeWire>,
 
     pub compositor_state: CompositorState,
-    pub data_control_state: DataControlState,
+    pub data_control_state: wlr_data_control::DataControlState,
+    pub ext_data_control_state: ext_data_control::DataControlState,
     pub data_device_state: DataDeviceState,
     pub dmabuf_state: DmabufState,
     pub foreign_toplevel_list_state: ForeignToplevelListState,
@@ -678,6 +850,7 @@ pub struct Fht {
     pub xdg_activation_state: XdgActivationState,
     pub xdg_shell_state: XdgShellState,
     pub xdg_foreign_state: XdgForeignState,
+    pub toplevel_drag_state: ToplevelDragManagerState,
 }
 
 impl Fht {
@@ -686,6 +859,7 @@ impl Fht {
         loop_handle: LoopHandle<'static, State>,
         loop_signal: LoopSignal,
         config_path: Option<std::path::PathBuf>,
+        socket_name: &str,
     ) -> Self {
         let mut config_ui = config_ui::ConfigUi::new();
         let (config, paths) = match fht_compositor_config::load(config_path.clone()) {
@@ -716,53 +890,47 @@ impl Fht {
 
         let compositor_state = CompositorState::new_v6::<State>(dh);
         let primary_selection_state = PrimarySelectionState::new::<State>(dh);
-        let data_control_state =
-            DataControlState::new::<State, _>(dh, Some(&primary_selection_state), |_| true);
+        // Sandboxed clients (connecting through a security context) don't get access to
+        // privileged protocols: clipboard snooping, screen capture, output configuration, input
+        // injection, etc.
+        let data_control_state = wlr_data_control::DataControlState::new::<State, _>(
+            dh,
+            Some(&primary_selection_state),
+            client_is_unrestricted,
+        );
+        let ext_data_control_state = ext_data_control::DataControlState::new::<State, _>(
+            dh,
+            Some(&primary_selection_state),
+            client_is_unrestricted,
+        );
         let data_device_state = DataDeviceState::new::<State>(dh);
         let idle_notifier_state = IdleNotifierState::new(dh, loop_handle.clone());
         let foreign_toplevel_list_state = ForeignToplevelListState::new::<State>(dh);
         let dmabuf_state = DmabufState::new();
         let layer_shell_state = WlrLayerShellState::new::<State>(dh);
         let output_management_manager_state =
-            OutputManagementManagerState::new::<State, _>(dh, |client| {
-                // Only privileded clients
-                client
-                    .get_data::<ClientState>()
-                    .is_none_or(|data| data.security_context.is_none())
-            });
+            OutputManagementManagerState::new::<State, _>(dh, client_is_unrestricted);
         let shm_state =
             ShmState::new::<State>(dh, vec![wl_shm::Format::Xbgr8888, wl_shm::Format::Abgr8888]);
-        let session_lock_manager_state = SessionLockManagerState::new::<State, _>(dh, |client| {
-            // From: https://wayland.app/protocols/security-context-v1
-            // "Compositors should forbid nesting multiple security contexts"
-            client
-                .get_data::<ClientState>()
-                .is_none_or(|data| data.security_context.is_none())
-        });
+        let session_lock_manager_state =
+            SessionLockManagerState::new::<State, _>(dh, client_is_unrestricted);
         let xdg_activation_state = XdgActivationState::new::<State>(dh);
         let xdg_shell_state = XdgShellState::new::<State>(dh);
         let xdg_foreign_state = XdgForeignState::new::<State>(dh);
+        let toplevel_drag_state = ToplevelDragManagerState::new::<State>(dh);
         ContentTypeState::new::<State>(dh);
         CursorShapeManagerState::new::<State>(dh);
         TextInputManagerState::new::<State>(dh);
-        InputMethodManagerState::new::<State, _>(dh, |_| true);
+        InputMethodManagerState::new::<State, _>(dh, client_is_unrestricted);
         IdleInhibitManagerState::new::<State>(dh);
-        VirtualKeyboardManagerState::new::<State, _>(dh, |_| true);
+        VirtualKeyboardManagerState::new::<State, _>(dh, client_is_unrestricted);
+        VirtualPointerManagerState::new::<State, _>(dh, client_is_unrestricted);
         PointerConstraintsState::new::<State>(dh);
         TabletManagerState::new::<State>(dh);
-        SecurityContextState::new::<State, _>(dh, |client| {
-            // From: https://wayland.app/protocols/security-context-v1
-            // "Compositors should forbid nesting multiple security contexts"
-            client
-                .get_data::<ClientState>()
-                .is_none_or(|data| data.security_context.is_none())
-        });
-        ScreencopyManagerState::new::<State, _>(dh, |client| {
-            // Same idea as security context state.
-            client
-                .get_data::<ClientState>()
-                .is_none_or(|data| data.security_context.is_none())
-        });
+        // From: https://wayland.app/protocols/security-context-v1
+        // "Compositors should forbid nesting multiple security contexts"
+        SecurityContextState::new::<State, _>(dh, client_is_unrestricted);
+        ScreencopyManagerState::new::<State, _>(dh, client_is_unrestricted);
         XdgDialogState::new::<State>(dh);
         XdgDecorationState::new::<State>(dh);
         FractionalScaleManagerState::new::<State>(dh);
@@ -772,6 +940,12 @@ impl Fht {
         SinglePixelBufferState::new::<State>(dh);
         AlphaModifierState::new::<State>(dh);
         RelativePointerManagerState::new::<State>(dh);
+        // Gestures are forwarded to the pointer focus since we don't have any compositor gestures.
+        PointerGesturesState::new::<State>(dh);
+        TearingControlManagerState::new::<State>(dh);
+        KdeBlurManagerState::new::<State>(dh);
+        FifoManagerState::new::<State>(dh);
+        CommitTimingManagerState::new::<State>(dh);
 
         // Initialize a seat and immediatly attach a keyboard and pointer to it.
         // If clients try to connect and do not find any of them they will try to initialize them
@@ -786,22 +960,32 @@ impl Fht {
             keyboard_config.repeat_delay,
             keyboard_config.repeat_rate,
         );
-        let keyboard = match res {
-            Ok(k) => k,
-            Err(err) => {
-                error!(
-                    ?err,
-                    "Failed to add keyboard with user xkb config! Falling back to defaults"
-                );
-                seat.add_keyboard(
-                    XkbConfig::default(),
-                    keyboard_config.repeat_delay,
-                    keyboard_config.repeat_rate,
-                )
-                .expect("The keyboard is not keyboarding")
-            }
-        };
-        let pointer = seat.add_pointer();
+        if let Err(err) = res {
+            error!(
+                ?err,
+                "Failed to add keyboard with user xkb config! Falling back to defaults"
+            );
+            seat.add_keyboard(
+                XkbConfig::default(),
+                keyboard_config.repeat_delay,
+                keyboard_config.repeat_rate,
+            )
+            .expect("The keyboard is not keyboarding");
+        }
+        seat.add_pointer();
+        seat.add_touch();
+
+        let mut extra_seats = vec![];
+        if config.debug.extra_test_seat {
+            // No input device drives this seat, it only exists so that multi-seat code paths get
+            // exercised, both in clients and in the compositor.
+            let mut test_seat = seat_state.new_wl_seat(dh, "fht-test-seat");
+            test_seat
+                .add_keyboard(XkbConfig::default(), 200, 25)
+                .expect("The keyboard is not keyboarding");
+            test_seat.add_pointer();
+            extra_seats.push(test_seat);
+        }
         let cursor_theme_manager = CursorThemeManager::new(config.cursor.clone());
         let keyboard_shortcuts_inhibit_state = KeyboardShortcutsInhibitState::new::<State>(dh);
 
@@ -815,8 +999,15 @@ impl Fht {
                 .inspect_err(|err| error!(?err, "Failed to connect to session D-Bus"))
                 .ok()
         };
+        #[cfg(feature = "accessibility")]
+        if let Some(cnx) = dbus_connection.as_ref() {
+            if let Err(err) = crate::a11y::A11yBridge::start(cnx, &loop_handle) {
+                warn!(?err, "Failed to start accessibility bridge");
+            }
+        }
 
         let space = Space::new(&config);
+        let session = Session::new(&loop_handle, socket_name);
 
         Self {
             display_handle: dh.clone(),
@@ -827,20 +1018,38 @@ impl Fht {
             clock,
             suppressed_keys: HashSet::new(),
             repeated_keyaction_timer: None,
+            virtual_keyboards: HashMap::new(),
             seat,
             devices: vec![],
+            last_pointer_motion_time: None,
+            discrete_scroll_remainder: [0.0; 2],
+            mouse_keys: None,
+            tablet_output: None,
+            keyboard_layout_to_restore: None,
+            debug_overlay: config.debug.debug_overlay,
+            frame_timings_capture: None,
             seat_state,
-            keyboard,
-            pointer,
+            extra_seats,
             lock_state: LockState::Unlocked,
+            region_selection: None,
+            workspace_drop_strip: None,
 
             dnd_icon: None,
+            dnd_source: None,
+            dragged_window: None,
             cursor_theme_manager,
             space,
             unmapped_windows: vec![],
+            launch_snapshots: VecDeque::new(),
+            session,
             popups: PopupManager::default(),
             root_surfaces: HashMap::default(),
             idle_inhibiting_surfaces: Vec::new(),
+            text_input_focused: false,
+            osk_panned_window: None,
+            focus_restore_stack: FocusRestoreStack::default(),
+            ipc_event_streams: vec![],
+            ipc_workspace_occupancy: vec![],
 
             output_state: HashMap::new(),
             has_transient_output_changes: false,
@@ -853,6 +1062,8 @@ impl Fht {
 
             #[cfg(feature = "dbus")]
             dbus_connection,
+            #[cfg(feature = "accessibility")]
+            a11y: None,
 
             #[cfg(feature = "xdg-screencast-portal")]
             pipewire_initialised: std::sync::Once::new(),
@@ -861,6 +1072,7 @@ impl Fht {
 
             compositor_state,
             data_control_state,
+            ext_data_control_state,
             data_device_state,
             dmabuf_state,
             foreign_toplevel_list_state,
@@ -874,6 +1086,19 @@ impl Fht {
             xdg_activation_state,
             xdg_shell_state,
             xdg_foreign_state,
+            toplevel_drag_state,
+        }
+    }
+
+    /// Get the window with the keyboard focus of `seat`, directly or through one of its popups.
+    pub fn keyboard_focused_window(&self, seat: &Seat<State>) -> Option<Window> {
+        match seat.get_keyboard()?.current_focus()? {
+            KeyboardFocusTarget::Window(window) => Some(window),
+            KeyboardFocusTarget::Popup(popup) => {
+                let root = find_popup_root_surface(&popup).ok()?;
+                self.space.find_window(&root)
+            }
+            _ => None,
         }
     }
 
@@ -885,7 +1110,24 @@ impl Fht {
 
         info!(name = output.name(), "Adding new output");
         self.space.add_output(output.clone());
+        if let Some(idx) = self
+            .config
+            .outputs
+            .get(&output.name())
+            .and_then(|config| config.default_workspace)
+        {
+            let mon = self.space.monitor_mut_for_output(&output).unwrap();
+            mon.set_active_workspace_idx(idx.clamp(0, 8), false);
+        }
 
+        let last_active_workspace_idx = self
+            .space
+            .monitor_for_output(&output)
+            .unwrap()
+            .active_workspace_idx();
+        // Fade in from black, including when the compositor starts.
+        let mut fade = Fade::new();
+        fade.fade_in(self.screen_fade_animation().as_ref());
         let state = output::OutputState {
             redraw_state: output::RedrawState::Idle,
             frame_clock: FrameClock::new(refresh_interval),
@@ -896,6 +1138,17 @@ impl Fht {
             debug_damage_tracker: None,
             lock_surface: None,
             lock_backdrop: None,
+            osd: Osd::new(),
+            debug_overlay: DebugOverlay::new(),
+            color_filter: self
+                .config
+                .outputs
+                .get(&output.name())
+                .and_then(|config| config.color_filter),
+            zoom: Zoom::new(),
+            offscreen_buffer: OffscreenBuffer::default(),
+            fade,
+            last_active_workspace_idx,
         };
         self.output_state.insert(output.clone(), state);
 
@@ -903,7 +1156,8 @@ impl Fht {
         if self.config.general.cursor_warps {
             let center = output.geometry().center();
             self.loop_handle.insert_idle(move |state| {
-                state.move_pointer(center.to_f64());
+                let seat = state.fht.seat.clone();
+                state.move_pointer(&seat, center.to_f64());
             });
         }
         self.space.set_active_output(&output);
@@ -924,6 +1178,14 @@ impl Fht {
         self.output_management_manager_state.remove_head(&output);
         self.output_management_manager_state.update::<State>();
 
+        // Don't render the output anymore if a redraw was waiting for its deadline.
+        if let Some(output_state) = self.output_state.get_mut(output) {
+            if let RedrawState::WaitingForRenderDeadline { token } = output_state.redraw_state {
+                self.loop_handle.remove(token);
+                output_state.redraw_state = RedrawState::Idle;
+            }
+        }
+
         // Cleanly close [`LayerSurface`] instead of letting them know their demise after noticing
         // the output is gone.
         for layer in layer_map_for_output(output).layers() {
@@ -1007,7 +1269,11 @@ impl Fht {
                 .and_then(|cfg| Some(smithay::output::Scale::Integer(cfg.scale?.clamp(1, 10))))
                 .unwrap_or(smithay::output::Scale::Integer(1));
 
-            output.change_current_state(None, Some(new_transform), Some(new_scale), None);
+            output.change_logical_state(Some(new_transform), Some(new_scale), None);
+
+            if let Some(state) = self.output_state.get_mut(output) {
+                state.color_filter = config.and_then(|cfg| cfg.color_filter);
+            }
         }
 
         // If we had previous output changes, we force re-apply all config.
@@ -1106,6 +1372,15 @@ impl Fht {
         }
     }
 
+    /// Find the unmapped [`Window`] associated with this surface.
+    pub fn unmapped_window(&self, surface: &WlSurface) -> Option<Window> {
+        self.unmapped_windows
+            .iter()
+            .map(UnmappedWindow::window)
+            .find(|window| window.wl_surface().as_deref() == Some(surface))
+            .cloned()
+    }
+
     pub fn output_named(&self, name: &str) -> Option<Output> {
         if name == "active" {
             Some(self.space.active_output().clone())
@@ -1126,6 +1401,43 @@ impl Fht {
         }
     }
 
+    /// Start or stop capturing frame timings.
+    ///
+    /// Returns whether a capture is now running, and the path of the file it writes to.
+    pub fn toggle_frame_timings_capture(&mut self) -> anyhow::Result<(bool, std::path::PathBuf)> {
+        match self.frame_timings_capture.take() {
+            Some(capture) => {
+                let path = capture.stop()?;
+                info!(?path, "Stopped capturing frame timings");
+                Ok((false, path))
+            }
+            None => {
+                let capture = crate::profiling::FrameTimingsCapture::start()?;
+                let path = capture.path().to_path_buf();
+                info!(?path, "Started capturing frame timings");
+                self.frame_timings_capture = Some(capture);
+                Ok((true, path))
+            }
+        }
+    }
+
+    /// Queue a redraw of the outputs the pointer moved from and to.
+    ///
+    /// Other outputs are left alone, the cursor is the only thing that changed on screen.
+    pub fn queue_redraw_for_pointer_motion(
+        &mut self,
+        old_location: Point<f64, Logical>,
+        new_location: Point<f64, Logical>,
+    ) {
+        for output in self.space.outputs() {
+            let geometry = output.geometry().to_f64();
+            if geometry.contains(old_location) || geometry.contains(new_location) {
+                let state = self.output_state.get_mut(output).unwrap();
+                state.redraw_state.queue();
+            }
+        }
+    }
+
     pub fn focus_target_under(
         &self,
         point: Point<f64, Logical>,
@@ -1284,8 +1596,15 @@ impl Fht {
         let output_state = self.output_state.get(output).unwrap();
         let sequence = output_state.current_frame_sequence;
 
+        // Surfaces getting a frame callback for this refresh cycle also get their FIFO barrier
+        // cleared, the clients might have commits blocked on it.
+        let fifo_clients = RefCell::new(Vec::new());
         let should_send_frames = |surface: &WlSurface, states: &SurfaceData| {
-            should_send_frames(output, sequence, surface, states)
+            let output = should_send_frames(output, sequence, surface, states)?;
+            if clear_fifo_barrier(states) {
+                fifo_clients.borrow_mut().extend(surface.client());
+            }
+            Some(output)
         };
 
         if let Some(lock_surface) = output_state.lock_surface.as_ref() {
@@ -1306,14 +1625,35 @@ impl Fht {
             send_frames_surface_tree(surface, output, time, throttle, should_send_frames);
         }
 
-        for window in self.space.visible_windows_for_output(output) {
+        let visible_windows: Vec<_> = self.space.visible_windows_for_output(output).collect();
+        for window in &visible_windows {
             window.send_frame(output, time, throttle, should_send_frames);
         }
 
+        // Windows on inactive workspaces or covered by a fullscreen window only get throttled
+        // frame callbacks, since drawing frames nobody sees is a waste.
+        for window in self.space.windows_on_output(output) {
+            if !visible_windows.contains(&window) {
+                window.send_frame(output, time, throttle, |_, _| None);
+            }
+        }
+
         let map = layer_map_for_output(output);
         for layer_surface in map.layers() {
             layer_surface.send_frame(output, time, throttle, should_send_frames);
         }
+
+        let fifo_clients = fifo_clients.into_inner();
+        if !fifo_clients.is_empty() {
+            self.loop_handle.insert_idle(move |state| {
+                let dh = state.fht.display_handle.clone();
+                for client in fifo_clients {
+                    state
+                        .client_compositor_state(&client)
+                        .blocker_cleared(state, &dh);
+                }
+            });
+        }
     }
 
     pub fn update_primary_scanout_output(
@@ -1489,11 +1829,23 @@ impl Fht {
             );
         }
 
+        let fullscreened_window = self
+            .space
+            .monitor_for_output(output)
+            .and_then(|monitor| monitor.active_workspace().fullscreened_window());
         for window in self.space.visible_windows_for_output(output) {
+            let root_surface = window.wl_surface();
+            let is_fullscreened = fullscreened_window.as_ref() == Some(window);
             window.send_dmabuf_feedback(
                 output,
                 |_, _| Some(output.clone()),
                 |surface, _| {
+                    // A fullscreen window could be directly scanned out on the primary plane, so
+                    // let the client know which buffers would allow this.
+                    if is_fullscreened && root_surface.as_deref() == Some(surface) {
+                        return &feedback.fullscreen_feedback;
+                    }
+
                     select_dmabuf_feedback(
                         surface,
                         render_element_states,
@@ -1610,6 +1962,104 @@ impl Fht {
         }
     }
 
+    /// Apply window rules marked as `retroactive` to existing windows.
+    ///
+    /// Rules such as floating or workspace assignment only apply when a window opens, this applies
+    /// them again after reloading the configuration.
+    pub fn apply_retroactive_window_rules(&mut self) {
+        crate::profile_function!();
+        let retroactive_rules: Vec<_> = self
+            .config
+            .rules
+            .iter()
+            .filter(|rule| rule.retroactive)
+            .cloned()
+            .collect();
+        if retroactive_rules.is_empty() {
+            return;
+        }
+
+        let mut windows = vec![];
+        for monitor in self.space.monitors() {
+            let output_name = monitor.output().name();
+            for (ws_idx, workspace) in monitor.workspaces().enumerate() {
+                let focused_idx = workspace.active_tile_idx();
+                for (window_idx, window) in workspace.windows().enumerate() {
+                    let rules = ResolvedWindowRules::resolve(
+                        window,
+                        &retroactive_rules,
+                        &output_name,
+                        ws_idx,
+                        focused_idx == Some(window_idx),
+                    );
+                    windows.push((window.clone(), monitor.output().clone(), ws_idx, rules));
+                }
+            }
+        }
+
+        let animate = !self.config.animations.disable;
+        for (window, output, ws_idx, rules) in windows {
+            if let Some(floating) = rules.floating {
+                if window.tiled() == floating {
+                    window.request_tiled(!floating);
+                    if let Some(workspace) = window
+                        .wl_surface()
+                        .and_then(|surface| self.space.workspace_mut_for_window_surface(&surface))
+                    {
+                        workspace.arrange_tiles(animate);
+                    }
+                }
+            }
+
+            if let Some(maximized) = rules.maximized {
+                self.space.maximize_window(&window, maximized, animate);
+            }
+
+            match rules.fullscreen {
+                Some(true) => {
+                    self.space.fullscreen_window(&window, animate);
+                }
+                Some(false) => window.request_fullscreen(false),
+                None => (),
+            }
+
+            let target_output = rules
+                .open_on_output
+                .as_ref()
+                .and_then(|name| self.output_named(name))
+                .unwrap_or_else(|| output.clone());
+            let Some(target_idx) =
+                rules
+                    .open_on_workspace
+                    .map(|idx| idx.clamp(0, 8))
+                    .or_else(|| {
+                        // Only the output changed, use its active workspace like when opening.
+                        (target_output != output).then(|| {
+                            let mon = self.space.monitor_for_output(&target_output).unwrap();
+                            mon.active_workspace_idx()
+                        })
+                    })
+            else {
+                window.send_pending_configure();
+                continue;
+            };
+
+            if target_output != output || target_idx != ws_idx {
+                let mon = self.space.monitor_mut_for_output(&output).unwrap();
+                if mon
+                    .workspace_mut_by_index(ws_idx)
+                    .remove_window(&window, animate)
+                {
+                    let mon = self.space.monitor_mut_for_output(&target_output).unwrap();
+                    mon.workspace_mut_by_index(target_idx)
+                        .insert_window(window.clone(), animate);
+                }
+            }
+
+            window.send_pending_configure();
+        }
+    }
+
     pub fn refresh_idle_inhibit(&mut self) {
         self.idle_inhibiting_surfaces.retain(|s| s.alive());
         let is_inhibited = self.idle_inhibiting_surfaces.iter().any(|surface| {
@@ -1621,6 +2071,313 @@ impl Fht {
         self.idle_notifier_state.set_is_inhibited(is_inhibited);
     }
 
+    /// Refresh workspace switch feedback.
+    ///
+    /// When the active workspace of an output changes, this shows the workspace number on its OSD
+    /// and runs the configured workspace switch command.
+    pub fn refresh_workspace_switches(&mut self) {
+        crate::profile_function!();
+        let mut switched = vec![];
+        for monitor in self.space.monitors() {
+            let output = monitor.output();
+            let idx = monitor.active_workspace_idx();
+            let output_state = self.output_state.get_mut(output).unwrap();
+            if output_state.last_active_workspace_idx != idx {
+                output_state.last_active_workspace_idx = idx;
+                switched.push((output.clone(), idx));
+            }
+        }
+
+        for (output, idx) in switched {
+            self.send_ipc_event(&crate::ipc::Event::ActiveWorkspace {
+                output: output.name(),
+                workspace: idx,
+            });
+
+            // Key actions and IPC use 0-based workspace indices, but the numbers shown to the
+            // user start from 1, like the default Super-1 to Super-9 binds.
+            let number = (idx + 1).to_string();
+            if self.config.general.workspace_osd {
+                let output_state = self.output_state.get_mut(&output).unwrap();
+                output_state.osd.show(number.clone());
+                self.queue_redraw(&output);
+            }
+
+            if let Some(cmd) = &self.config.general.workspace_switch_command {
+                let output_name = output.name();
+                let environment = [
+                    ("FHT_OUTPUT", output_name.as_str()),
+                    ("FHT_WORKSPACE", number.as_str()),
+                ];
+                crate::utils::spawn(cmd, None, &environment);
+            }
+        }
+    }
+
+    /// Refresh on-screen keyboard hints.
+    ///
+    /// This runs the configured show/hide hooks when text input focus changes, and moves the
+    /// focused floating window above the on-screen keyboard if needed.
+    pub fn refresh_on_screen_keyboard(&mut self) {
+        crate::profile_function!();
+        let focused_window = match self.seat.get_keyboard().unwrap().current_focus() {
+            Some(KeyboardFocusTarget::Window(window)) => Some(window),
+            _ => None,
+        };
+
+        let mut text_input_focused = false;
+        if focused_window.is_some() {
+            self.seat
+                .text_input()
+                .with_focused_text_input(|_, _| text_input_focused = true);
+        }
+
+        if text_input_focused != self.text_input_focused {
+            self.text_input_focused = text_input_focused;
+            let osk_config = &self.config.input.on_screen_keyboard;
+            let hook = if text_input_focused {
+                osk_config.show_command.as_ref()
+            } else {
+                osk_config.hide_command.as_ref()
+            };
+            if let Some(cmd) = hook {
+                // Don't give the keyboard an activation token, it should not steal focus.
+                crate::utils::spawn(cmd, None, &[]);
+            }
+        }
+
+        if !self.config.input.on_screen_keyboard.pan_floating_windows {
+            return;
+        }
+
+        let focused_window = focused_window.filter(|_| text_input_focused);
+        if let Some((window, offset)) = self.osk_panned_window.take() {
+            if focused_window.as_ref() == Some(&window) {
+                self.osk_panned_window = Some((window, offset));
+            } else if let Some(workspace) = window
+                .wl_surface()
+                .and_then(|surface| self.space.workspace_mut_for_window_surface(&surface))
+            {
+                workspace.unpan_floating_window(&window, offset);
+            }
+        }
+
+        if let Some(window) = focused_window {
+            let Some(workspace) = window
+                .wl_surface()
+                .and_then(|surface| self.space.workspace_mut_for_window_surface(&surface))
+            else {
+                return;
+            };
+            if let Some(offset) = workspace.pan_floating_window_into_work_area(&window) {
+                let total_offset = self
+                    .osk_panned_window
+                    .take()
+                    .map_or(0, |(_, previous)| previous);
+                self.osk_panned_window = Some((window, total_offset + offset));
+            }
+        }
+    }
+
+    /// Spawn a command, recording how it got launched.
+    ///
+    /// The command gets its own XDG activation token, which lets us find back the windows it
+    /// opens, see [`Fht::attach_launch_snapshot`].
+    pub fn spawn(&mut self, cmd: &str) {
+        let context = ActivationTokenContext {
+            workspace_id: self.space.active_workspace_id(),
+        };
+        let (token, data) = self.xdg_activation_state.create_external_token(None);
+        data.user_data.insert_if_missing(|| context);
+        let token = String::from(token.clone());
+        let extra_environment = if self.config.decorations.prefer_no_csd {
+            crate::utils::NO_CSD_ENVIRONMENT
+        } else {
+            &[]
+        };
+        crate::utils::spawn(cmd, Some(&token), extra_environment);
+
+        let mut environment: HashMap<String, String> = std::env::vars().collect();
+        environment.extend(
+            extra_environment
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string())),
+        );
+        environment.insert("XDG_ACTIVATION_TOKEN".to_string(), token.clone());
+        environment.insert("DESKTOP_STARTUP_ID".to_string(), token.clone());
+        self.launch_snapshots.push_back(Arc::new(LaunchSnapshot {
+            command: cmd.to_string(),
+            activation_token: token,
+            environment,
+        }));
+
+        while self.launch_snapshots.len() > MAX_LAUNCH_SNAPSHOTS {
+            let snapshot = self.launch_snapshots.pop_front().unwrap();
+            self.xdg_activation_state
+                .remove_token(&XdgActivationToken::from(snapshot.activation_token.clone()));
+        }
+    }
+
+    /// Get the launch snapshot associated with this XDG activation token.
+    pub fn launch_snapshot_for_token(&self, token: &str) -> Option<Arc<LaunchSnapshot>> {
+        self.launch_snapshots
+            .iter()
+            .find(|snapshot| snapshot.activation_token == token)
+            .cloned()
+    }
+
+    /// Get the activation token the client process of this window got launched with.
+    fn activation_token_from_environment(&self, window: &Window) -> Option<String> {
+        let pid = window
+            .wl_surface()
+            .and_then(|surface| surface.client())
+            .and_then(|client| client.get_credentials(&self.display_handle).ok())
+            .map(|credentials| credentials.pid)?;
+        let environ = std::fs::read(format!("/proc/{pid}/environ")).ok()?;
+
+        environ
+            .split(|b| *b == b'\0')
+            .filter_map(|var| std::str::from_utf8(var).ok())
+            .find_map(|var| var.strip_prefix("XDG_ACTIVATION_TOKEN="))
+            .map(str::to_string)
+    }
+
+    /// Try to find how this window got launched.
+    ///
+    /// We check the environment of the client process for the activation token we gave out when
+    /// spawning commands. Windows can also be matched later on when they request activation.
+    pub fn attach_launch_snapshot(&self, window: &Window) {
+        if window.launch_snapshot().is_some() {
+            return;
+        }
+
+        let snapshot = self
+            .activation_token_from_environment(window)
+            .and_then(|token| self.launch_snapshot_for_token(&token));
+        if let Some(snapshot) = snapshot {
+            window.set_launch_snapshot(snapshot);
+        }
+    }
+
+    /// Get the workspace where the activation token this window got launched with was created.
+    pub fn activation_token_workspace(&self, window: &Window) -> Option<WorkspaceId> {
+        let token = window
+            .launch_snapshot()
+            .map(|snapshot| snapshot.activation_token.clone())
+            .or_else(|| self.activation_token_from_environment(window))?;
+        let data = self.xdg_activation_state.data_for_token(&token)?;
+        if data.timestamp.elapsed() >= ACTIVATION_TIMEOUT {
+            return None;
+        }
+
+        data.user_data
+            .get::<ActivationTokenContext>()
+            .map(|context| context.workspace_id)
+    }
+
+    /// Returns whether the content displayed on this output allows for tearing.
+    ///
+    /// We only allow tearing when the active workspace has a fullscreen window that either opted
+    /// in using `wp-tearing-control-v1`, or has a window rule with `allow-tearing` set. A rule
+    /// with `allow-tearing = false` always takes precedence over the client hint.
+    pub fn output_allows_tearing(&self, output: &Output) -> bool {
+        let Some(monitor) = self.space.monitor_for_output(output) else {
+            return false;
+        };
+        let Some(window) = monitor.active_workspace().fullscreened_window() else {
+            return false;
+        };
+
+        let allow_tearing = window.rules().allow_tearing;
+        allow_tearing.unwrap_or_else(|| {
+            window
+                .wl_surface()
+                .is_some_and(|surface| surface_prefers_tearing(&surface))
+        })
+    }
+
+    /// Returns the content type of the fullscreen window on the active workspace of this output.
+    ///
+    /// Clients advertise their content type using `wp-content-type-v1`.
+    pub fn output_fullscreen_content_type(&self, output: &Output) -> Option<ContentType> {
+        let monitor = self.space.monitor_for_output(output)?;
+        let window = monitor.active_workspace().fullscreened_window()?;
+        Some(window.content_type())
+    }
+
+    /// Delay the queued redraw of this output until its render deadline, if it has one.
+    ///
+    /// With the `max-render-time` output option, we start rendering at most `max-render-time`
+    /// milliseconds before the next presentation time, instead of right away. Returns whether the
+    /// redraw got delayed.
+    pub fn delay_redraw_until_render_deadline(&mut self, output: &Output) -> bool {
+        let Some(max_render_time) = self
+            .config
+            .outputs
+            .get(&output.name())
+            .and_then(|config| config.max_render_time)
+        else {
+            return false;
+        };
+        // Tearing presents as soon as possible anyway.
+        if self.output_allows_tearing(output) {
+            return false;
+        }
+
+        let output_state = self.output_state.get_mut(output).unwrap();
+        if !matches!(output_state.redraw_state, RedrawState::Queued)
+            || output_state.frame_clock.refresh_interval().is_none()
+        {
+            return false;
+        }
+
+        let deadline = output_state
+            .frame_clock
+            .next_presentation_time()
+            .saturating_sub(Duration::from_millis(max_render_time.into()));
+        let duration = deadline.saturating_sub(get_monotonic_time());
+        if duration.is_zero() {
+            return false;
+        }
+
+        let output = output.clone();
+        let timer = Timer::from_duration(duration);
+        let token = self
+            .loop_handle
+            .insert_source(timer, move |_, _, state| {
+                // The output could have been removed in the meantime.
+                if let Some(output_state) = state.fht.output_state.get_mut(&output) {
+                    if let RedrawState::WaitingForRenderDeadline { .. } = output_state.redraw_state
+                    {
+                        output_state.redraw_state = RedrawState::Queued;
+                    }
+                }
+                TimeoutAction::Drop
+            })
+            .unwrap();
+        output_state.redraw_state = RedrawState::WaitingForRenderDeadline { token };
+
+        true
+    }
+
+    /// Returns whether this output should enable variable refresh rate.
+    pub fn output_wants_vrr(&self, output: &Output) -> bool {
+        let vrr = self
+            .config
+            .outputs
+            .get(&output.name())
+            .map(|config| config.vrr)
+            .unwrap_or_default();
+        match vrr {
+            VrrMode::Off => false,
+            VrrMode::On => true,
+            VrrMode::OnDemand => matches!(
+                self.output_fullscreen_content_type(output),
+                Some(ContentType::Game | ContentType::Video)
+            ),
+        }
+    }
+
     pub fn resolve_rules_for_all_windows_if_needed(&self) {
         crate::profile_function!();
         for monitor in self.space.monitors() {
@@ -1654,7 +2411,8 @@ impl Fht {
             .get(device.name())
             .or_else(|| input_config.per_device.get(device.sysname()));
 
-        self.keyboard.change_repeat_info(
+        // Our input devices drive the primary seat.
+        self.seat.get_keyboard().unwrap().change_repeat_info(
             input_config.keyboard.repeat_rate,
             input_config.keyboard.repeat_delay,
         );
@@ -1749,7 +2507,10 @@ impl Fht {
                     }
                 }
 
-                if let Some(speed) = mouse_config.acceleration_speed {
+                if mouse_config.acceleration_curve.is_some() {
+                    // We apply the acceleration curve ourselves, libinput should not do anything.
+                    let _ = device.config_accel_set_speed(0.0);
+                } else if let Some(speed) = mouse_config.acceleration_speed {
                     let speed = speed.clamp(-1.0, 1.0);
                     let _ = device.config_accel_set_speed(speed);
                 } else {
@@ -1757,7 +2518,9 @@ impl Fht {
                     let _ = device.config_accel_set_speed(default);
                 }
 
-                if let Some(profile) = mouse_config.acceleration_profile {
+                if mouse_config.acceleration_curve.is_some() {
+                    let _ = device.config_accel_set_profile(input::AccelProfile::Flat);
+                } else if let Some(profile) = mouse_config.acceleration_profile {
                     let _ = device.config_accel_set_profile(profile.into());
                 } else if let Some(default) = device.config_accel_default_profile() {
                     let _ = device.config_accel_set_profile(default);
@@ -1785,6 +2548,27 @@ impl Fht {
         self.devices.push(device);
     }
 
+    /// Get the mouse configuration for this input device.
+    ///
+    /// Like [`Fht::add_libinput_device`], this looks up the device by name, then by sysname.
+    pub fn mouse_config_for_device(
+        &self,
+        device: &impl smithay::backend::input::Device,
+    ) -> &fht_compositor_config::Mouse {
+        let input_config = &self.config.input;
+        // The sysname of a libinput device is the last component of its syspath.
+        let syspath = device.syspath();
+        let sysname = syspath
+            .as_deref()
+            .and_then(|path| path.file_name())
+            .and_then(|name| name.to_str());
+        input_config
+            .per_device
+            .get(&device.name())
+            .or_else(|| sysname.and_then(|sysname| input_config.per_device.get(sysname)))
+            .map_or(&input_config.mouse, |c| &c.mouse)
+    }
+
     #[cfg(feature = "xdg-screencast-portal")]
     pub fn stop_cast(&mut self, id: CastId) {
         crate::profile_function!();
@@ -1942,6 +2726,8 @@ fn should_send_frames(
 pub struct SurfaceDmabufFeedback {
     pub render_feedback: DmabufFeedback,
     pub scanout_feedback: DmabufFeedback,
+    // Feedback for fullscreen windows, preferring formats that the primary plane can scan out.
+    pub fullscreen_feedback: DmabufFeedback,
 }
 
 #[derive(Default, Debug)]
@@ -1950,6 +2736,16 @@ pub struct ClientState {
     pub security_context: Option<SecurityContext>,
 }
 
+/// Whether this client can access privileged protocols.
+///
+/// Clients that connected through a security context (for example Flatpak apps) are sandboxed
+/// and are denied them.
+pub fn client_is_unrestricted(client: &Client) -> bool {
+    client
+        .get_data::<ClientState>()
+        .is_none_or(|data| data.security_context.is_none())
+}
+
 impl ClientData for ClientState {
     fn initialized(&self, _client_id: smithay::reexports::wayland_server::backend::ClientId) {}
     fn disconnected(
@@ -2000,6 +2796,7 @@ pub struct ResolvedWindowRules {
     pub open_on_output: Option<String>,
     pub open_on_workspace: Option<usize>,
     pub opacity: Option<f32>,
+    pub inactive_opacity: Option<f32>,
     pub proportion: Option<f64>,
     pub decoration_mode: Option<DecorationMode>,
     pub maximized: Option<bool>,
@@ -2007,6 +2804,9 @@ pub struct ResolvedWindowRules {
     pub floating: Option<bool>,
     pub centered: Option<bool>,
     pub centered_in_parent: Option<bool>,
+    pub allow_tearing: Option<bool>,
+    pub keyboard_layout: Option<String>,
+    pub size_increments: Option<[i32; 2]>,
 }
 
 impl ResolvedWindowRules {
@@ -2054,6 +2854,10 @@ impl ResolvedWindowRules {
                 resolved_rules.opacity = Some(opacity)
             }
 
+            if let Some(inactive_opacity) = rule.inactive_opacity {
+                resolved_rules.inactive_opacity = Some(inactive_opacity)
+            }
+
             if let Some(proportion) = rule.proportion {
                 resolved_rules.proportion = Some(proportion)
             }
@@ -2077,6 +2881,18 @@ impl ResolvedWindowRules {
             if let Some(centered) = rule.centered {
                 resolved_rules.centered = Some(centered);
             }
+
+            if let Some(allow_tearing) = rule.allow_tearing {
+                resolved_rules.allow_tearing = Some(allow_tearing);
+            }
+
+            if let Some(keyboard_layout) = &rule.keyboard_layout {
+                resolved_rules.keyboard_layout = Some(keyboard_layout.clone());
+            }
+
+            if let Some(size_increments) = rule.size_increments {
+                resolved_rules.size_increments = Some(size_increments);
+            }
         }
 
         resolved_rules
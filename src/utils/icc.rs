@@ -0,0 +1,161 @@
+//! Minimal ICC profile support.
+//!
+//! We only read the `vcgt` (video card gamma table) tag of the profile, that holds the
+//! calibration curves meant to be loaded into the display gamma ramps, like `dispwin` or `xcalib`
+//! do on X11.
+//!
+//! This is calibration, not color management: the primaries and tone curves of the profile are
+//! ignored, no 3D LUT or CTM gets derived from them, so content is not converted to the color
+//! space of the display.
+
+use std::path::Path;
+
+use anyhow::Context;
+
+/// Gamma ramps for the red, green and blue channels.
+pub type GammaRamps = [Vec<u16>; 3];
+
+/// Load the calibration curves of the ICC profile at `path`, sampled to `size` entries.
+///
+/// Profiles without calibration curves give linear gamma ramps.
+pub fn load_calibration_curves(path: &Path, size: usize) -> anyhow::Result<GammaRamps> {
+    let data = std::fs::read(path).context("Failed to read ICC profile")?;
+    let Some(vcgt) = find_tag(&data, b"vcgt")? else {
+        warn!(?path, "ICC profile has no calibration curves, it will have no effect");
+        return Ok(linear_ramps(size));
+    };
+    anyhow::ensure!(
+        vcgt.len() >= 12 && &vcgt[..4] == b"vcgt",
+        "Invalid vcgt tag"
+    );
+
+    let curves: [Box<dyn Fn(f64) -> f64>; 3] = match read_u32(vcgt, 8)? {
+        // Table: the curves are given as a list of values.
+        0 => {
+            let channels = read_u16(vcgt, 12)? as usize;
+            let entry_count = read_u16(vcgt, 14)? as usize;
+            let entry_size = read_u16(vcgt, 16)? as usize;
+            anyhow::ensure!(channels == 1 || channels == 3, "Invalid vcgt channel count");
+            anyhow::ensure!(
+                entry_size == 1 || entry_size == 2,
+                "Invalid vcgt entry size"
+            );
+            anyhow::ensure!(entry_count >= 2, "Invalid vcgt entry count");
+
+            let max = if entry_size == 1 {
+                u8::MAX as f64
+            } else {
+                u16::MAX as f64
+            };
+            let mut tables = vec![];
+            for channel in 0..channels {
+                let table = (0..entry_count)
+                    .map(|idx| {
+                        let offset = 18 + (channel * entry_count + idx) * entry_size;
+                        let value = if entry_size == 1 {
+                            *vcgt.get(offset).context("Truncated vcgt table")? as f64
+                        } else {
+                            read_u16(vcgt, offset)? as f64
+                        };
+                        Ok(value / max)
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                tables.push(table);
+            }
+
+            let table_curve = |table: Vec<f64>| -> Box<dyn Fn(f64) -> f64> {
+                Box::new(move |x| {
+                    let position = x * (table.len() - 1) as f64;
+                    let idx = (position.floor() as usize).min(table.len() - 2);
+                    let t = position - idx as f64;
+                    table[idx] * (1.0 - t) + table[idx + 1] * t
+                })
+            };
+            if channels == 1 {
+                let table = tables.remove(0);
+                [
+                    table_curve(table.clone()),
+                    table_curve(table.clone()),
+                    table_curve(table),
+                ]
+            } else {
+                let blue = tables.pop().unwrap();
+                let green = tables.pop().unwrap();
+                let red = tables.pop().unwrap();
+                [table_curve(red), table_curve(green), table_curve(blue)]
+            }
+        }
+        // Formula: value = min + (max - min) * x^gamma, for each channel.
+        1 => {
+            let formula_curve = |channel: usize| -> anyhow::Result<Box<dyn Fn(f64) -> f64>> {
+                let offset = 12 + channel * 12;
+                let gamma = read_s15_fixed16(vcgt, offset)?;
+                let min = read_s15_fixed16(vcgt, offset + 4)?;
+                let max = read_s15_fixed16(vcgt, offset + 8)?;
+                Ok(Box::new(move |x| min + (max - min) * x.powf(gamma)))
+            };
+            [formula_curve(0)?, formula_curve(1)?, formula_curve(2)?]
+        }
+        ty => anyhow::bail!("Unknown vcgt type: {ty}"),
+    };
+
+    Ok(curves.map(|curve| sample(curve, size)))
+}
+
+/// Linear gamma ramps, to reset the calibration curves.
+pub fn linear_ramps(size: usize) -> GammaRamps {
+    std::array::from_fn(|_| sample(|x| x, size))
+}
+
+fn sample(curve: impl Fn(f64) -> f64, size: usize) -> Vec<u16> {
+    let last = size.saturating_sub(1).max(1) as f64;
+    (0..size)
+        .map(|idx| {
+            let value = curve(idx as f64 / last).clamp(0.0, 1.0);
+            (value * u16::MAX as f64).round() as u16
+        })
+        .collect()
+}
+
+/// Find the data of a tag in the ICC profile tag table.
+fn find_tag<'a>(data: &'a [u8], signature: &[u8; 4]) -> anyhow::Result<Option<&'a [u8]>> {
+    anyhow::ensure!(
+        data.len() >= 132 && &data[36..40] == b"acsp",
+        "Not an ICC profile"
+    );
+
+    let tag_count = read_u32(data, 128)? as usize;
+    for idx in 0..tag_count {
+        let entry = 132 + idx * 12;
+        if data.get(entry..entry + 4) != Some(signature.as_slice()) {
+            continue;
+        }
+
+        let offset = read_u32(data, entry + 4)? as usize;
+        let size = read_u32(data, entry + 8)? as usize;
+        let tag = data
+            .get(offset..offset.saturating_add(size))
+            .context("Truncated ICC profile")?;
+        return Ok(Some(tag));
+    }
+
+    Ok(None)
+}
+
+fn read_u16(data: &[u8], offset: usize) -> anyhow::Result<u16> {
+    let bytes = data
+        .get(offset..offset + 2)
+        .context("Truncated ICC profile")?;
+    Ok(u16::from_be_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> anyhow::Result<u32> {
+    let bytes = data
+        .get(offset..offset + 4)
+        .context("Truncated ICC profile")?;
+    Ok(u32::from_be_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_s15_fixed16(data: &[u8], offset: usize) -> anyhow::Result<f64> {
+    Ok(read_u32(data, offset)? as i32 as f64 / 65536.0)
+}
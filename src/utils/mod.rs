@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::mem::MaybeUninit;
 use std::os::unix::process::CommandExt;
 use std::process::Stdio;
@@ -6,6 +7,8 @@ use std::time::Duration;
 use smithay::reexports::rustix;
 use smithay::utils::{Coordinate, Point, Rectangle};
 
+#[cfg(feature = "udev-backend")]
+pub mod icc;
 #[cfg(feature = "xdg-screencast-portal")]
 pub mod pipewire;
 
@@ -19,14 +22,48 @@ pub fn get_monotonic_time() -> Duration {
     Duration::new(timespec.tv_sec as u64, timespec.tv_nsec as u32)
 }
 
-pub fn spawn(cmd: &str) {
+/// A snapshot of how a command got launched by the compositor.
+#[derive(Debug)]
+pub struct LaunchSnapshot {
+    /// The command that got launched.
+    pub command: String,
+    /// The XDG activation token given to the command.
+    pub activation_token: String,
+    /// The environment the command got launched with.
+    pub environment: HashMap<String, String>,
+}
+
+/// Environment hints asking toolkits to not draw client-side decorations.
+///
+/// `GTK_CSD` is only honored by GTK3 applications, libadwaita ignores it.
+pub const NO_CSD_ENVIRONMENT: &[(&str, &str)] = &[
+    ("GTK_CSD", "0"),
+    ("QT_WAYLAND_DISABLE_WINDOWDECORATION", "1"),
+];
+
+/// Spawn a command.
+///
+/// If given, the `activation_token` will be exported to the command environment, as both
+/// `XDG_ACTIVATION_TOKEN` and `DESKTOP_STARTUP_ID`. `environment` is exported as-is.
+pub fn spawn(cmd: &str, activation_token: Option<&str>, environment: &[(&str, &str)]) {
     let cmd = cmd.to_string();
+    let activation_token = activation_token.map(str::to_string);
+    let environment: Vec<(String, String)> = environment
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
     crate::profile_function!();
     let res = std::thread::Builder::new()
         .name("Command spawner".to_string())
         .spawn(move || {
             let mut command = std::process::Command::new("/bin/sh");
             command.args(["-c", &cmd]);
+            command.envs(environment);
+            if let Some(token) = &activation_token {
+                command
+                    .env("XDG_ACTIVATION_TOKEN", token)
+                    .env("DESKTOP_STARTUP_ID", token);
+            }
             // Disable all IO.
             command
                 .stdin(Stdio::null())
@@ -825,13 +825,10 @@ impl Cast {
     {
         crate::profile_function!();
 
-        let elements = if self.cursor_mode.contains(CursorMode::EMBEDDED) {
-            &output_elements_result.elements
-        } else {
-            &output_elements_result.elements[output_elements_result.cursor_elements_len..]
-        };
+        let elements = output_elements_result
+            .capture_elements(self.cursor_mode.contains(CursorMode::EMBEDDED));
 
-        self.render(renderer, elements, size, scale)
+        self.render(renderer, &elements, size, scale)
     }
 
     /// Dequeue the latest stream buffer and render inside of it.
@@ -1,14 +1,15 @@
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use owning_ref::MutexGuardRef;
-use smithay::backend::renderer::element;
 use smithay::backend::renderer::element::surface::{
     render_elements_from_surface_tree, WaylandSurfaceRenderElement,
 };
+use smithay::backend::renderer::utils::RendererSurfaceStateUserData;
+use smithay::backend::renderer::{buffer_dimensions, buffer_type, element, BufferType};
 // use smithay::desktop::Window;
 use smithay::desktop::utils::{
     bbox_from_surface_tree, output_update, send_dmabuf_feedback_surface_tree,
@@ -17,19 +18,25 @@ use smithay::desktop::utils::{
 };
 use smithay::desktop::{PopupManager, WindowSurfaceType};
 use smithay::output::{Output, WeakOutput};
+use smithay::reexports::wayland_protocols::wp::content_type::v1::server::wp_content_type_v1::Type as ContentType;
 use smithay::reexports::wayland_protocols::wp::presentation_time::server::wp_presentation_feedback;
+use smithay::reexports::wayland_protocols::xdg::decoration::zv1::server::zxdg_toplevel_decoration_v1::Mode as DecorationMode;
 use smithay::reexports::wayland_protocols::xdg::shell::server::xdg_toplevel::State;
 use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
 use smithay::utils::{IsAlive, Logical, Physical, Point, Rectangle, Scale, Serial, Size};
 use smithay::wayland::compositor::{send_surface_state, with_states, HookId, SurfaceData};
+use smithay::wayland::content_type::ContentTypeSurfaceCachedState;
+use smithay::wayland::dmabuf::get_dmabuf;
 use smithay::wayland::dmabuf::DmabufFeedback;
 use smithay::wayland::foreign_toplevel_list::ForeignToplevelHandle;
 use smithay::wayland::fractional_scale::with_fractional_scale;
 use smithay::wayland::seat::WaylandFocus;
 use smithay::wayland::shell::xdg::{SurfaceCachedState, ToplevelSurface, XdgToplevelSurfaceData};
+use smithay::wayland::shm;
 
 use crate::renderer::FhtRenderer;
 use crate::state::ResolvedWindowRules;
+use crate::utils::{get_monotonic_time, LaunchSnapshot};
 
 #[derive(Debug, Clone)]
 pub struct Window {
@@ -61,6 +68,10 @@ impl WindowId {
     pub fn unique() -> Self {
         Self(WINDOW_IDS.fetch_add(1, Ordering::SeqCst))
     }
+
+    pub fn get(self) -> usize {
+        self.0
+    }
 }
 
 #[derive(Debug)]
@@ -83,6 +94,38 @@ pub struct WindowData {
     rules: ResolvedWindowRules,
     need_to_resolve_rules: bool,
     foreign_toplevel_handle: Option<ForeignToplevelHandle>,
+    // How the window got launched, if we know.
+    launch_snapshot: Option<Arc<LaunchSnapshot>>,
+    // The times of the commits during the last second, for render statistics.
+    commit_times: VecDeque<Duration>,
+    // The window requested activation without being allowed to take focus.
+    urgent: bool,
+    // The decoration mode the client asked for with xdg-decoration.
+    requested_decoration_mode: Option<DecorationMode>,
+    // The window is attached to an ongoing drag-and-drop operation with xdg-toplevel-drag.
+    dragged: bool,
+    // The output the window asked to be fullscreened on before being mapped.
+    requested_fullscreen_output: Option<WeakOutput>,
+    // The geometry of the window while it was floating, before getting fullscreened, maximized or
+    // tiled.
+    saved_floating_geometry: Option<Rectangle<i32, Logical>>,
+    // The window follows the active workspace of its output when switching workspaces.
+    pinned: bool,
+}
+
+/// Rendering statistics of a [`Window`].
+///
+/// Useful to find out which client is responsible for rendering load.
+#[derive(Debug, Clone, Default)]
+pub struct RenderStats {
+    /// The number of commits during the last second.
+    pub commits_per_second: usize,
+    /// The formats of the buffers attached to the window surfaces.
+    pub buffer_formats: Vec<String>,
+    /// An estimate of the GPU memory used by the window surface textures, in bytes.
+    ///
+    /// This assumes every buffer uses 4 bytes per pixel.
+    pub texture_memory: usize,
 }
 
 impl Window {
@@ -99,6 +142,14 @@ impl Window {
                     rules: ResolvedWindowRules::default(),
                     need_to_resolve_rules: false,
                     foreign_toplevel_handle: None,
+                    launch_snapshot: None,
+                    commit_times: VecDeque::new(),
+                    urgent: false,
+                    requested_decoration_mode: None,
+                    dragged: false,
+                    requested_fullscreen_output: None,
+                    saved_floating_geometry: None,
+                    pinned: false,
                 }),
             }),
         }
@@ -132,7 +183,29 @@ impl Window {
         MutexGuardRef::new(guard).map(|data| &data.rules)
     }
 
-    fn set_need_to_resolve_rules(&self) {
+    /// Get the size increments of this window, if any.
+    ///
+    /// The window size should be snapped to multiples of these when resizing or tiling it.
+    pub fn size_increments(&self) -> Option<Size<i32, Logical>> {
+        let [w, h] = self.rules().size_increments?;
+        (w > 0 && h > 0).then(|| Size::from((w, h)))
+    }
+
+    /// Get the min and max size requested by the client for this window.
+    ///
+    /// A zero width or height means that axis is not constrained.
+    pub fn size_constraints(&self) -> (Size<i32, Logical>, Size<i32, Logical>) {
+        let Some(surface) = self.wl_surface() else {
+            return Default::default();
+        };
+        with_states(&surface, |states| {
+            let mut cached_state = states.cached_state.get::<SurfaceCachedState>();
+            let current = cached_state.current();
+            (current.min_size, current.max_size)
+        })
+    }
+
+    pub fn set_need_to_resolve_rules(&self) {
         let mut guard = self.inner.data.lock().unwrap();
         guard.need_to_resolve_rules = true;
     }
@@ -162,6 +235,79 @@ impl Window {
         guard.foreign_toplevel_handle.take()
     }
 
+    pub fn set_launch_snapshot(&self, snapshot: Arc<LaunchSnapshot>) {
+        let mut guard = self.inner.data.lock().unwrap();
+        guard.launch_snapshot = Some(snapshot);
+    }
+
+    pub fn launch_snapshot(&self) -> Option<Arc<LaunchSnapshot>> {
+        let guard = self.inner.data.lock().unwrap();
+        guard.launch_snapshot.clone()
+    }
+
+    pub fn set_urgent(&self, urgent: bool) {
+        let mut guard = self.inner.data.lock().unwrap();
+        guard.urgent = urgent;
+    }
+
+    pub fn urgent(&self) -> bool {
+        let guard = self.inner.data.lock().unwrap();
+        guard.urgent
+    }
+
+    pub fn set_requested_decoration_mode(&self, mode: Option<DecorationMode>) {
+        let mut guard = self.inner.data.lock().unwrap();
+        guard.requested_decoration_mode = mode;
+    }
+
+    pub fn requested_decoration_mode(&self) -> Option<DecorationMode> {
+        let guard = self.inner.data.lock().unwrap();
+        guard.requested_decoration_mode
+    }
+
+    pub fn set_dragged(&self, dragged: bool) {
+        let mut guard = self.inner.data.lock().unwrap();
+        guard.dragged = dragged;
+    }
+
+    pub fn dragged(&self) -> bool {
+        let guard = self.inner.data.lock().unwrap();
+        guard.dragged
+    }
+
+    pub fn set_requested_fullscreen_output(&self, output: Option<Output>) {
+        let mut guard = self.inner.data.lock().unwrap();
+        guard.requested_fullscreen_output = output.as_ref().map(Output::downgrade);
+    }
+
+    pub fn requested_fullscreen_output(&self) -> Option<Output> {
+        let guard = self.inner.data.lock().unwrap();
+        guard
+            .requested_fullscreen_output
+            .as_ref()
+            .and_then(WeakOutput::upgrade)
+    }
+
+    pub fn set_saved_floating_geometry(&self, geometry: Option<Rectangle<i32, Logical>>) {
+        let mut guard = self.inner.data.lock().unwrap();
+        guard.saved_floating_geometry = geometry;
+    }
+
+    pub fn saved_floating_geometry(&self) -> Option<Rectangle<i32, Logical>> {
+        let guard = self.inner.data.lock().unwrap();
+        guard.saved_floating_geometry
+    }
+
+    pub fn set_pinned(&self, pinned: bool) {
+        let mut guard = self.inner.data.lock().unwrap();
+        guard.pinned = pinned;
+    }
+
+    pub fn pinned(&self) -> bool {
+        let guard = self.inner.data.lock().unwrap();
+        guard.pinned
+    }
+
     pub fn request_size(&self, new_size: Size<i32, Logical>) {
         self.toplevel().with_pending_state(|state| {
             state.size = Some(new_size);
@@ -335,6 +481,41 @@ impl Window {
         })
     }
 
+    /// Whether this window is a modal dialog, as advertised with `xdg-dialog-v1`.
+    pub fn is_modal(&self) -> bool {
+        let Some(surface) = self.wl_surface() else {
+            return false;
+        };
+        with_states(&surface, |states| {
+            let data = states
+                .data_map
+                .get::<XdgToplevelSurfaceData>()
+                .unwrap()
+                .lock()
+                .unwrap();
+            data.modal
+        })
+    }
+
+    /// Whether this window is a modal dialog of `parent`.
+    pub fn is_modal_dialog_of(&self, parent: &Self) -> bool {
+        let Some(parent_surface) = parent.wl_surface() else {
+            return false;
+        };
+        self.toplevel().parent().as_ref() == Some(&*parent_surface) && self.is_modal()
+    }
+
+    /// Get the content type of this window, as advertised with `wp-content-type-v1`.
+    pub fn content_type(&self) -> ContentType {
+        let Some(surface) = self.wl_surface() else {
+            return ContentType::None;
+        };
+        with_states(&surface, |states| {
+            let mut guard = states.cached_state.get::<ContentTypeSurfaceCachedState>();
+            guard.current().content_type()
+        })
+    }
+
     pub fn set_offscreen_element_id(&self, id: Option<element::Id>) {
         let mut guard = self.inner.data.lock().unwrap();
         guard.offscreen_element_id = id
@@ -472,7 +653,69 @@ impl Window {
 
     pub fn on_commit(&self) {
         if let Some(surface) = self.wl_surface() {
-            self.inner.data.lock().unwrap().bbox = bbox_from_surface_tree(&surface, (0, 0));
+            let mut guard = self.inner.data.lock().unwrap();
+            guard.bbox = bbox_from_surface_tree(&surface, (0, 0));
+
+            let now = get_monotonic_time();
+            guard.commit_times.push_back(now);
+            while guard
+                .commit_times
+                .front()
+                .is_some_and(|time| now.saturating_sub(*time) > Duration::from_secs(1))
+            {
+                guard.commit_times.pop_front();
+            }
+        }
+    }
+
+    /// Get the rendering statistics of this window.
+    pub fn render_stats(&self) -> RenderStats {
+        let now = get_monotonic_time();
+        let commits_per_second = {
+            let guard = self.inner.data.lock().unwrap();
+            guard
+                .commit_times
+                .iter()
+                .filter(|time| now.saturating_sub(**time) <= Duration::from_secs(1))
+                .count()
+        };
+
+        let mut buffer_formats = vec![];
+        let mut texture_memory = 0;
+        self.with_surfaces(|_, states| {
+            let Some(data) = states.data_map.get::<RendererSurfaceStateUserData>() else {
+                return;
+            };
+            let data = data.lock().unwrap();
+            let Some(buffer) = data.buffer() else {
+                return;
+            };
+
+            if let Some(size) = buffer_dimensions(buffer) {
+                texture_memory += (size.w * size.h) as usize * 4;
+            }
+
+            let format = match buffer_type(buffer) {
+                Some(BufferType::Shm) => {
+                    shm::with_buffer_contents(buffer, |_, _, data| format!("shm {:?}", data.format))
+                        .ok()
+                }
+                Some(BufferType::Dma) => get_dmabuf(buffer).ok().map(|dmabuf| {
+                    let format = dmabuf.format();
+                    format!("dmabuf {:?} ({:?})", format.code, format.modifier)
+                }),
+                Some(BufferType::SinglePixel) => Some("single-pixel".to_string()),
+                _ => None,
+            };
+            if let Some(format) = format.filter(|format| !buffer_formats.contains(format)) {
+                buffer_formats.push(format);
+            }
+        });
+
+        RenderStats {
+            commits_per_second,
+            buffer_formats,
+            texture_memory,
         }
     }
 
@@ -531,14 +774,50 @@ impl Window {
         };
 
         location -= self.render_offset().to_physical_precise_round(scale);
-        render_elements_from_surface_tree(
-            renderer,
-            &surface,
-            location,
-            scale,
-            alpha,
-            element::Kind::Unspecified,
-        )
+        // Fullscreen windows usually cover the whole output, let the DRM compositor try to put
+        // their buffer directly on the primary plane. The DRM compositor falls back to
+        // composition by itself.
+        let kind = if self.fullscreen() {
+            element::Kind::ScanoutCandidate
+        } else {
+            element::Kind::Unspecified
+        };
+        let mut elements =
+            render_elements_from_surface_tree(renderer, &surface, location, scale, alpha, kind);
+
+        if !self.fullscreen() && self.content_type() == ContentType::Video {
+            // Videos can be put on overlay planes, but only the main surface. Subsurfaces on top
+            // of it (subtitles, player controls) must stay composited.
+            let main_id = element::Id::from_wayland_resource(&surface);
+            if let Some(main) = elements
+                .iter_mut()
+                .find(|e| *element::Element::id(*e) == main_id)
+            {
+                let scanout_candidate = with_states(&surface, |states| {
+                    // Same location as render_elements_from_surface_tree gives the main surface.
+                    let offset = states
+                        .data_map
+                        .get::<RendererSurfaceStateUserData>()
+                        .and_then(|data| Some(data.lock().unwrap().view()?.offset))
+                        .unwrap_or_default();
+                    WaylandSurfaceRenderElement::from_surface(
+                        renderer,
+                        &surface,
+                        states,
+                        location.to_f64() + offset.to_f64().to_physical(scale),
+                        alpha,
+                        element::Kind::ScanoutCandidate,
+                    )
+                });
+                match scanout_candidate {
+                    Ok(Some(element)) => *main = element,
+                    Ok(None) => (),
+                    Err(err) => warn!(?err, "Failed to render video surface"),
+                }
+            }
+        }
+
+        elements
     }
 
     pub fn render_popup_elements<R: FhtRenderer>(
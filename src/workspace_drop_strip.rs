@@ -0,0 +1,121 @@
+//! The workspace drop strip.
+//!
+//! While moving a window with the pointer, hovering at the top edge of another output shows a
+//! strip with that output's workspaces. Releasing the pointer over one of them sends the window
+//! there, without switching to that workspace.
+
+use smithay::backend::renderer::element::solid::SolidColorRenderElement;
+use smithay::backend::renderer::element::{Id, Kind};
+use smithay::backend::renderer::utils::CommitCounter;
+use smithay::backend::renderer::Color32F;
+use smithay::output::Output;
+use smithay::utils::{Logical, Point, Rectangle};
+
+use crate::output::OutputExt;
+
+/// The height of the strip, and of the area at the top of the output that activates it.
+pub const STRIP_HEIGHT: i32 = 40;
+const CELL_GAP: i32 = 4;
+const CELL_COLOR: Color32F = Color32F::new(0.15, 0.15, 0.15, 0.8);
+const HOVERED_CELL_COLOR: Color32F = Color32F::new(0.4, 0.4, 0.4, 0.9);
+
+crate::fht_render_elements! {
+    WorkspaceDropStripRenderElement => {
+        Solid = SolidColorRenderElement,
+    }
+}
+
+/// A strip of workspace drop targets at the top of an output.
+pub struct WorkspaceDropStrip {
+    /// The output the strip is shown on.
+    output: Output,
+    /// The index of the workspace under the pointer.
+    hovered: Option<usize>,
+    /// The [`Id`]s of the render elements, one per workspace.
+    ids: Vec<Id>,
+    /// The commit counter of our render elements.
+    commit: CommitCounter,
+}
+
+impl WorkspaceDropStrip {
+    /// Create a new strip on this output, for `workspace_count` workspaces.
+    pub fn new(output: Output, workspace_count: usize) -> Self {
+        Self {
+            output,
+            hovered: None,
+            ids: (0..workspace_count).map(|_| Id::new()).collect(),
+            commit: CommitCounter::default(),
+        }
+    }
+
+    /// Get the output the strip is shown on.
+    pub fn output(&self) -> &Output {
+        &self.output
+    }
+
+    /// Get the index of the workspace under the pointer.
+    pub fn hovered(&self) -> Option<usize> {
+        self.hovered
+    }
+
+    /// Whether `location` is inside the activation area of this output.
+    ///
+    /// `location` is in global compositor coordinates.
+    pub fn contains(output: &Output, location: Point<f64, Logical>) -> bool {
+        let geometry = output.geometry().to_f64();
+        geometry.contains(location) && location.y < geometry.loc.y + STRIP_HEIGHT as f64
+    }
+
+    /// Update the hovered workspace.
+    ///
+    /// `location` is in global compositor coordinates.
+    pub fn update(&mut self, location: Point<f64, Logical>) {
+        let hovered = Self::contains(&self.output, location)
+            .then(|| {
+                let location = location.to_i32_round() - self.output.geometry().loc;
+                self.cells().position(|cell| cell.contains(location))
+            })
+            .flatten();
+
+        if hovered != self.hovered {
+            self.hovered = hovered;
+            self.commit.increment();
+        }
+    }
+
+    /// The rectangles of the workspace cells, relative to the output.
+    fn cells(&self) -> impl Iterator<Item = Rectangle<i32, Logical>> {
+        let output_width = self.output.geometry().size.w;
+        let count = self.ids.len() as i32;
+        let cell_width = (output_width - CELL_GAP * (count + 1)) / count.max(1);
+        (0..count).map(move |idx| {
+            Rectangle::new(
+                (CELL_GAP + idx * (cell_width + CELL_GAP), CELL_GAP).into(),
+                (cell_width, STRIP_HEIGHT - 2 * CELL_GAP).into(),
+            )
+        })
+    }
+
+    /// Render the strip.
+    pub fn render(&self, scale: i32) -> Vec<WorkspaceDropStripRenderElement> {
+        self.cells()
+            .zip(&self.ids)
+            .enumerate()
+            .map(|(idx, (rect, id))| {
+                let color = if self.hovered == Some(idx) {
+                    HOVERED_CELL_COLOR
+                } else {
+                    CELL_COLOR
+                };
+                SolidColorRenderElement::new(
+                    id.clone(),
+                    rect.to_physical(scale),
+                    self.commit,
+                    color,
+                    Kind::Unspecified,
+                )
+                .into()
+            })
+            .collect()
+    }
+}
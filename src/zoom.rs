@@ -0,0 +1,253 @@
+//! Screen magnifier.
+//!
+//! Each output has its own [`Zoom`], stored inside its [`OutputState`](crate::output::OutputState).
+//! When zoomed, the output contents (including the cursor) get rendered offscreen, then the area
+//! around the zoom focus is stretched to fill the whole output.
+//!
+//! The zoomed area follows the pointer proportionally: a point at the edge of the output stays at
+//! the edge once zoomed, so the cursor is always displayed where it really is, and every part of
+//! the output can be reached by moving it. With `zoom.follow-focus`, the zoomed area also moves to
+//! the focused window when the keyboard focus changes, until the pointer moves again.
+
+use std::time::Duration;
+
+use fht_animation::curve::Easing;
+use fht_animation::{Animation, AnimationCurve};
+use smithay::input::Seat;
+use smithay::output::Output;
+use smithay::utils::{Logical, Point, Rectangle, Size};
+
+use crate::input::WHEEL_STEP_AMOUNT;
+use crate::output::OutputExt;
+use crate::state::{Fht, State};
+use crate::window::Window;
+
+const ZOOM_CURVE: AnimationCurve = AnimationCurve::Simple(Easing::EaseOutCubic);
+
+#[derive(Debug)]
+pub struct Zoom {
+    /// The zoom level, or the level we are animating to.
+    level: f64,
+    /// The animation of the zoom level, if any.
+    animation: Option<Animation<f64>>,
+    /// The point the zoom is centered on, relative to the output.
+    ///
+    /// [`None`] means we follow the pointer.
+    focus: Option<Point<f64, Logical>>,
+    /// Scroll that did not make a full zoom step yet.
+    scroll_remainder: f64,
+}
+
+impl Zoom {
+    /// Create a new [`Zoom`], not zoomed in.
+    pub fn new() -> Self {
+        Self {
+            level: 1.0,
+            animation: None,
+            focus: None,
+            scroll_remainder: 0.0,
+        }
+    }
+
+    /// Get the current zoom level, accounting for the animation.
+    pub fn level(&self) -> f64 {
+        self.animation
+            .as_ref()
+            .map_or(self.level, |animation| *animation.value())
+    }
+
+    /// Set the zoom level, animating the change if `duration` is not zero.
+    pub fn set_level(&mut self, level: f64, duration: Duration) {
+        let level = level.max(1.0);
+        if level == self.level {
+            return;
+        }
+
+        let current = self.level();
+        self.level = level;
+        if level == 1.0 {
+            let _ = self.focus.take();
+        }
+
+        self.animation = (!duration.is_zero())
+            .then(|| Animation::new(current, level, duration).with_curve(ZOOM_CURVE));
+    }
+
+    /// Center the zoomed area on `point`, relative to the output, until the pointer moves.
+    pub fn set_focus(&mut self, point: Point<f64, Logical>) {
+        self.focus = Some(point);
+    }
+
+    /// Follow the pointer again.
+    pub fn follow_pointer(&mut self) {
+        let _ = self.focus.take();
+    }
+
+    /// Accumulate scroll until it makes full zoom steps of `step_amount`.
+    ///
+    /// Returns the number of zoom steps, negative when scrolling up.
+    pub fn accumulate_scroll(&mut self, amount: f64, step_amount: f64) -> f64 {
+        if self.scroll_remainder.signum() != amount.signum() {
+            // The user changed the scroll direction, start again.
+            self.scroll_remainder = 0.0;
+        }
+        self.scroll_remainder += amount;
+        let steps = (self.scroll_remainder / step_amount).trunc();
+        self.scroll_remainder -= steps * step_amount;
+        steps
+    }
+
+    /// Advance the animations for this [`Zoom`].
+    pub fn advance_animations(&mut self, target_presentation_time: Duration) -> bool {
+        let _ = self.animation.take_if(|a| a.is_finished());
+        if let Some(animation) = &mut self.animation {
+            animation.tick(target_presentation_time);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Get the area of the output that gets displayed, relative to the output.
+    ///
+    /// `pointer` is the pointer location relative to the output. Returns [`None`] when we are not
+    /// zoomed in.
+    pub fn visible_area(
+        &self,
+        output_size: Size<f64, Logical>,
+        pointer: Point<f64, Logical>,
+    ) -> Option<Rectangle<f64, Logical>> {
+        let level = self.level();
+        if level <= 1.0 {
+            return None;
+        }
+
+        let focus = self.focus.unwrap_or(pointer);
+        let focus = Point::<f64, Logical>::from((
+            focus.x.clamp(0.0, output_size.w),
+            focus.y.clamp(0.0, output_size.h),
+        ));
+        let size = output_size.downscale(level);
+        let loc = focus - focus.downscale(level);
+        Some(Rectangle::new(loc, size))
+    }
+}
+
+impl Fht {
+    /// Get the area of `output` displayed when zoomed in, relative to the output.
+    pub fn zoom_visible_area(&self, output: &Output) -> Option<Rectangle<f64, Logical>> {
+        let output_state = self.output_state.get(output)?;
+        let output_geometry = output.geometry().to_f64();
+        // The cursor we draw is the one of the primary seat.
+        let pointer = self.seat.get_pointer().unwrap().current_location() - output_geometry.loc;
+        output_state
+            .zoom
+            .visible_area(output_geometry.size, pointer)
+    }
+
+    /// Change the zoom level of `output` by `delta`.
+    pub fn change_zoom(&mut self, output: &Output, delta: f64) {
+        let (max_level, duration) = (self.config.zoom.max_level, self.zoom_animation_duration());
+        let Some(output_state) = self.output_state.get_mut(output) else {
+            return;
+        };
+        let level = (output_state.zoom.level + delta).clamp(1.0, max_level.max(1.0));
+        output_state.zoom.set_level(level, duration);
+        self.queue_redraw(output);
+    }
+
+    /// Zoom out of `output`.
+    pub fn reset_zoom(&mut self, output: &Output) {
+        let duration = self.zoom_animation_duration();
+        let Some(output_state) = self.output_state.get_mut(output) else {
+            return;
+        };
+        output_state.zoom.set_level(1.0, duration);
+        self.queue_redraw(output);
+    }
+
+    fn zoom_animation_duration(&self) -> Duration {
+        if self.config.animations.disable {
+            Duration::ZERO
+        } else {
+            self.config.zoom.duration
+        }
+    }
+
+    /// Make the zoom follow the pointer of `seat` again on the output it's on.
+    pub fn zoom_follow_pointer(&mut self, seat: &Seat<State>) {
+        let pointer_location = seat.get_pointer().unwrap().current_location();
+        let Some(output) = self
+            .space
+            .outputs()
+            .find(|output| output.geometry().to_f64().contains(pointer_location))
+            .cloned()
+        else {
+            return;
+        };
+        if let Some(output_state) = self.output_state.get_mut(&output) {
+            output_state.zoom.follow_pointer();
+        }
+    }
+
+    /// Move the zoomed area to `window`, if `zoom.follow-focus` is enabled.
+    pub fn zoom_follow_window(&mut self, window: &Window) {
+        if !self.config.zoom.follow_focus {
+            return;
+        }
+        let Some(output) = self
+            .space
+            .workspace_for_window(window)
+            .map(|workspace| workspace.output().clone())
+        else {
+            return;
+        };
+        let Some(location) = self.space.window_location(window) else {
+            return;
+        };
+
+        let output_state = self.output_state.get_mut(&output).unwrap();
+        if output_state.zoom.level() <= 1.0 {
+            return;
+        }
+
+        let center = (location - output.current_location()).to_f64()
+            + window.size().to_f64().downscale(2.0).to_point();
+        output_state.zoom.set_focus(center);
+        self.queue_redraw(&output);
+    }
+}
+
+impl State {
+    /// Zoom with a scroll event if the configured zoom modifiers are held.
+    ///
+    /// Returns whether the scroll event got consumed.
+    pub fn zoom_with_scroll(&mut self, seat: &Seat<State>, vertical_amount: f64) -> bool {
+        let Some(modifiers) = self.fht.config.zoom.scroll_modifiers else {
+            return false;
+        };
+        if vertical_amount == 0.0 {
+            return false;
+        }
+        let current_modifiers: fht_compositor_config::ModifiersState =
+            seat.get_keyboard().unwrap().modifier_state().into();
+        if modifiers != current_modifiers {
+            return false;
+        }
+
+        let output = self.fht.space.active_output().clone();
+        let Some(output_state) = self.fht.output_state.get_mut(&output) else {
+            return true;
+        };
+        // Each wheel step worth of scroll makes a zoom step, smooth scrolling gets accumulated.
+        let steps = output_state
+            .zoom
+            .accumulate_scroll(vertical_amount, WHEEL_STEP_AMOUNT);
+        if steps != 0.0 {
+            // Scrolling up zooms in.
+            let delta = -steps * self.fht.config.zoom.scroll_step;
+            self.fht.change_zoom(&output, delta);
+        }
+        true
+    }
+}